@@ -38,7 +38,10 @@ use std::{
 use clap::{IntoApp, Parser};
 use clap_complete::generate;
 
-use crate::{cmd::Cmd, utils::read_secret};
+use crate::{
+    cmd::Cmd,
+    utils::{read_secret, SecretKind},
+};
 use eyre::WrapErr;
 
 #[tokio::main]
@@ -588,7 +591,8 @@ async fn main() -> eyre::Result<()> {
 
                 match path {
                     Some(path) => {
-                        let password = read_secret(password, unsafe_password)?;
+                        let password =
+                            read_secret(password, unsafe_password, SecretKind::Secret("secret"))?;
                         let (key, uuid) =
                             LocalWallet::new_keystore(&path, &mut rng, password, None)?;
                         let address = SimpleCast::checksum_address(&key.address())?;
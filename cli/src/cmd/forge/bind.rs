@@ -11,8 +11,12 @@ use foundry_config::{
     },
     impl_figment_convert, Config,
 };
+use globset::Glob;
 use serde::Serialize;
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 impl_figment_convert!(BindArgs);
 
@@ -64,9 +68,33 @@ pub struct BindArgs {
     #[serde(skip)]
     overwrite: bool,
 
-    #[clap(long = "single-file", help = "Generate bindings as a single file.")]
+    #[clap(
+        long = "single-file",
+        help = "Generate bindings as a single file.",
+        long_help = "Generate the bindings of all contracts as a single file with a module per contract, instead of a file per contract. Every module has its own imports, so the modules don't conflict."
+    )]
     #[serde(skip)]
     single_file: bool,
+
+    #[clap(
+        long,
+        multiple_occurrences = true,
+        value_name = "GLOB",
+        help = "Only generate bindings for the contracts whose name matches the glob.",
+        long_help = "Only generate bindings for the contracts whose name matches the glob, e.g. `Vault*`. Can be passed multiple times, bindings are generated for all contracts by default."
+    )]
+    #[serde(skip)]
+    select: Vec<Glob>,
+
+    #[clap(
+        long,
+        multiple_occurrences = true,
+        value_name = "GLOB",
+        help = "Don't generate bindings for the contracts whose name matches the glob.",
+        long_help = "Don't generate bindings for the contracts whose name matches the glob, e.g. `*Test`. Can be passed multiple times, takes precedence over --select."
+    )]
+    #[serde(skip)]
+    skip: Vec<Glob>,
 }
 
 impl BindArgs {
@@ -86,17 +114,30 @@ impl BindArgs {
         self.bindings_root().is_dir()
     }
 
+    /// Returns `true` if bindings are generated for the contract, see `--select` and `--skip`
+    fn is_selected(&self, contract: &str) -> bool {
+        let matches =
+            |globs: &[Glob]| globs.iter().any(|glob| glob.compile_matcher().is_match(contract));
+        (self.select.is_empty() || matches(&self.select)) && !matches(&self.skip)
+    }
+
     /// Instantiate the multi-abigen
     fn get_multi(&self) -> eyre::Result<MultiAbigen> {
-        let multi = MultiAbigen::from_json_files(self.artifacts())?;
-
+        let artifacts = artifact_files(&self.artifacts());
         eyre::ensure!(
-            !multi.is_empty(),
+            !artifacts.is_empty(),
             r#"
 No contract artifacts found. Hint: Have you built your contracts yet? `forge bind` does not currently invoke `forge build`, although this is planned for future versions.
             "#
         );
-        Ok(multi)
+
+        let selected = artifacts
+            .into_iter()
+            .filter(|(name, _)| self.is_selected(name))
+            .map(|(name, path)| (name, path.to_string_lossy().to_string()))
+            .collect::<Vec<_>>();
+        eyre::ensure!(!selected.is_empty(), "No contract matches --select and --skip");
+        Ok(MultiAbigen::new(selected)?)
     }
 
     /// Check that the existing bindings match the expected abigen output
@@ -135,6 +176,19 @@ No contract artifacts found. Hint: Have you built your contracts yet? `forge bin
     }
 }
 
+/// Returns the contract name and path of every JSON artifact in the artifacts directory
+fn artifact_files(artifacts: &Path) -> Vec<(String, PathBuf)> {
+    walkdir::WalkDir::new(artifacts)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().map(|ext| ext == "json").unwrap_or_default())
+        .filter_map(|path| Some((path.file_stem()?.to_str()?.to_string(), path)))
+        .collect()
+}
+
 impl Cmd for BindArgs {
     type Output = ();
 
@@ -1,13 +1,23 @@
 //! build command
 
-use ethers::solc::{Project, ProjectCompileOutput};
-use std::path::PathBuf;
+use ethers::solc::{Graph, Project, ProjectCompileOutput, ProjectPathsConfig};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
 use crate::{
-    cmd::{forge::watch::WatchArgs, Cmd},
-    compile,
-    opts::forge::CompilerArgs,
+    cmd::{
+        forge::{
+            install::Lockfile,
+            watch::{self, WatchArgs},
+        },
+        Cmd,
+    },
+    compile::{self, ProjectCompiler},
+    opts::forge::{parse_solc, CompilerArgs},
 };
+use ansi_term::Colour;
 use clap::{Parser, ValueHint};
 use ethers::solc::{remappings::Remapping, utils::canonicalized};
 use foundry_config::{
@@ -19,6 +29,7 @@ use foundry_config::{
     },
     find_project_root_path, impl_figment_convert, remappings_from_env_var, Config,
 };
+use globset::{Glob, GlobSetBuilder};
 use serde::Serialize;
 use watchexec::config::{InitConfig, RuntimeConfig};
 
@@ -71,15 +82,6 @@ pub struct CoreBuildArgs {
     #[serde(skip)]
     pub force: bool,
 
-    #[clap(
-        help_heading = "LINKER OPTIONS",
-        help = "Set pre-linked libraries.",
-        long,
-        env = "DAPP_LIBRARIES"
-    )]
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub libraries: Vec<String>,
-
     #[clap(flatten, next_help_heading = "COMPILER OPTIONS")]
     #[serde(flatten)]
     pub compiler: CompilerArgs,
@@ -95,26 +97,31 @@ pub struct CoreBuildArgs {
     /// Specify the solc version, or a path to a local solc, to build with.
     ///
     /// Valid values are in the format `x.y.z`, `solc:x.y.z` or `path/to/solc`.
-    #[clap(help_heading = "COMPILER OPTIONS", value_name = "use", long = "use")]
+    #[clap(
+        help_heading = "COMPILER OPTIONS",
+        value_name = "use",
+        long = "use",
+        parse(try_from_str = parse_solc)
+    )]
     #[serde(skip)]
     pub use_solc: Option<String>,
 
     #[clap(
         help_heading = "COMPILER OPTIONS",
         help = "Do not access the network.",
-        long_help = "Do not access the network. Missing solc versions will not be installed.",
+        long_help = "Do not access the network. Missing solc versions, including the one set with --use, will not be installed and the build fails instead.",
         long
     )]
     #[serde(skip)]
     pub offline: bool,
 
     #[clap(
-        help_heading = "COMPILER OPTIONS",
-        help = "Use the Yul intermediate representation compilation pipeline.",
+        help_heading = "PROJECT OPTIONS",
+        help = "Fail if the installed dependencies don't match the revisions in foundry.lock.",
         long
     )]
     #[serde(skip)]
-    pub via_ir: bool,
+    pub locked: bool,
 
     #[clap(flatten, next_help_heading = "PROJECT OPTIONS")]
     #[serde(flatten)]
@@ -148,9 +155,19 @@ impl CoreBuildArgs {
     /// [`foundry_config::Config::project()`]
     pub fn project(&self) -> eyre::Result<Project> {
         let config: Config = self.into();
+        self.ensure_locked(&config)?;
+        warn_missing_paths(&config);
         Ok(config.project()?)
     }
 
+    /// Ensures the installed dependencies match the lockfile if `--locked` is set
+    pub fn ensure_locked(&self, config: &Config) -> eyre::Result<()> {
+        if self.locked {
+            Lockfile::read(&config.__root.0)?.ensure_locked(&config.__root.0)?;
+        }
+        Ok(())
+    }
+
     /// Returns the remappings to add to the config
     #[deprecated(note = "Use ProjectPathsArgs::get_remappings() instead")]
     pub fn get_remappings(&self) -> Vec<Remapping> {
@@ -158,6 +175,19 @@ impl CoreBuildArgs {
     }
 }
 
+/// Warns about configured allow and include paths that don't exist
+fn warn_missing_paths(config: &Config) {
+    for path in config.allow_paths.iter().chain(&config.include_paths) {
+        if !path.exists() {
+            eprintln!(
+                "{} configured path {} does not exist",
+                Colour::Yellow.bold().paint("Warning:"),
+                path.display()
+            );
+        }
+    }
+}
+
 impl Provider for CoreBuildArgs {
     fn metadata(&self) -> Metadata {
         Metadata::named("Core Build Args Provider")
@@ -173,17 +203,13 @@ impl Provider for CoreBuildArgs {
         }
 
         if let Some(ref solc) = self.use_solc {
-            dict.insert("solc".to_string(), solc.trim_start_matches("solc:").into());
+            dict.insert("solc".to_string(), solc.as_str().into());
         }
 
         if self.offline {
             dict.insert("offline".to_string(), true.into());
         }
 
-        if self.via_ir {
-            dict.insert("via_ir".to_string(), true.into());
-        }
-
         if self.force {
             dict.insert("force".to_string(), self.force.into());
         }
@@ -197,6 +223,10 @@ impl Provider for CoreBuildArgs {
             dict.insert("extra_output".to_string(), selection.into());
         }
 
+        if let Some(ref libraries) = self.compiler.libraries {
+            dict.insert("libraries".to_string(), libraries.concat().into());
+        }
+
         if let Some(ref extra) = self.compiler.extra_output_files {
             let selection: Vec<_> = extra.iter().map(|s| s.to_string()).collect();
             dict.insert("extra_output_files".to_string(), selection.into());
@@ -236,10 +266,24 @@ pub struct BuildArgs {
     #[serde(skip)]
     pub names: bool,
 
-    #[clap(help = "Print compiled contract sizes.", long = "sizes")]
+    #[clap(
+        help = "Print the runtime and init code sizes of the compiled contracts, fails if a contract exceeds the EIP-170 size limit.",
+        long = "sizes"
+    )]
     #[serde(skip)]
     pub sizes: bool,
 
+    #[clap(
+        help_heading = "PROJECT OPTIONS",
+        long,
+        multiple_occurrences = true,
+        value_name = "GLOB",
+        help = "Don't compile the files whose path matches the glob.",
+        long_help = "Don't compile the files whose path relative to the project root matches the glob, e.g. `src/mocks/**`. Can be passed multiple times. Fails if a compiled file imports an excluded file."
+    )]
+    #[serde(skip)]
+    pub exclude: Vec<Glob>,
+
     #[clap(flatten, next_help_heading = "WATCH OPTIONS")]
     #[serde(skip)]
     pub watch: WatchArgs,
@@ -249,10 +293,59 @@ impl Cmd for BuildArgs {
     type Output = ProjectCompileOutput;
     fn run(self) -> eyre::Result<Self::Output> {
         let project = self.project()?;
-        compile::compile(&project, self.names, self.sizes)
+        if self.exclude.is_empty() {
+            return compile::compile(&project, self.names, self.sizes)
+        }
+        let files = included_files(&project.paths, &self.exclude)?;
+        ProjectCompiler::new(self.names, self.sizes)
+            .compile_with(&project, |prj| Ok(prj.compile_files(files)?))
     }
 }
 
+/// Returns the input files of the project whose path relative to the root matches none of the
+/// `exclude` globs, sorted by path
+///
+/// Fails if any of the returned files imports an excluded file, directly or through a library
+fn included_files(paths: &ProjectPathsConfig, exclude: &[Glob]) -> eyre::Result<Vec<PathBuf>> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in exclude {
+        builder.add(glob.clone());
+    }
+    let excluded = builder.build()?;
+    let relative = |path: &Path| path.strip_prefix(&paths.root).unwrap_or(path).to_path_buf();
+    let is_excluded = |path: &Path| excluded.is_match(relative(path));
+
+    let graph = Graph::resolve(paths)?;
+    let mut files = graph
+        .files()
+        .iter()
+        .filter(|(path, _)| !is_excluded(path))
+        .map(|(path, id)| (path.clone(), *id))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut visited = HashSet::new();
+    let mut queue = files.iter().map(|(_, id)| *id).collect::<Vec<_>>();
+    while let Some(id) = queue.pop() {
+        if !visited.insert(id) {
+            continue
+        }
+        for import in graph.imported_nodes(id) {
+            let import_path = graph.node(*import).path();
+            if is_excluded(import_path) {
+                eyre::bail!(
+                    "`{}` imports `{}`, which is excluded by --exclude",
+                    relative(graph.node(id).path()).display(),
+                    relative(import_path).display()
+                )
+            }
+            queue.push(*import);
+        }
+    }
+
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
 impl BuildArgs {
     /// Returns the `Project` for the current workspace
     ///
@@ -271,8 +364,8 @@ impl BuildArgs {
     /// Returns the [`watchexec::InitConfig`] and [`watchexec::RuntimeConfig`] necessary to
     /// bootstrap a new [`watchexe::Watchexec`] loop.
     pub(crate) fn watchexec_config(&self) -> eyre::Result<(InitConfig, RuntimeConfig)> {
-        // use the path arguments or if none where provided the `src` dir
-        self.watch.watchexec_config(|| Config::from(self).src)
+        // use the path arguments or if none where provided the project's dirs
+        self.watch.watchexec_config(|| watch::watch_paths(&Config::from(self)))
     }
 }
 
@@ -405,3 +498,165 @@ impl Provider for ProjectPathsArgs {
 }
 
 impl_figment_convert!(ProjectPathsArgs);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::solc::artifacts::{BytecodeHash, RevertStrings};
+    use foundry_config::SolcReq;
+
+    #[test]
+    fn can_set_via_ir() {
+        let args = CoreBuildArgs::parse_from(["foundry-cli", "--via-ir"]);
+        assert!(args.compiler.via_ir);
+        let config = Config::from(&args);
+        assert!(config.via_ir);
+
+        let args = CoreBuildArgs::parse_from(["foundry-cli"]);
+        let dict = Value::serialize(&args.compiler).unwrap().into_dict().unwrap();
+        assert!(!dict.contains_key("via_ir"));
+    }
+
+    #[test]
+    fn can_set_revert_strings() {
+        [
+            ("default", RevertStrings::Default),
+            ("strip", RevertStrings::Strip),
+            ("debug", RevertStrings::Debug),
+            ("verboseDebug", RevertStrings::VerboseDebug),
+        ]
+        .iter()
+        .for_each(|(input, expected)| {
+            let args = CoreBuildArgs::parse_from(["foundry-cli", "--revert-strings", input]);
+            assert_eq!(args.compiler.revert_strings, Some(*expected));
+            let config = Config::from(&args);
+            assert_eq!(config.revert_strings, Some(*expected));
+            let settings = config.solc_settings().unwrap();
+            assert_eq!(settings.debug.unwrap().revert_strings, Some(*expected));
+        });
+
+        let args = CoreBuildArgs::parse_from(["foundry-cli"]);
+        assert_eq!(Config::from(&args).revert_strings, None);
+        assert!(CoreBuildArgs::try_parse_from(["foundry-cli", "--revert-strings", "x"]).is_err());
+    }
+
+    #[test]
+    fn can_set_metadata_hash() {
+        [
+            ("none", BytecodeHash::None),
+            ("ipfs", BytecodeHash::Ipfs),
+            ("bzzr1", BytecodeHash::Bzzr1),
+        ]
+        .iter()
+        .for_each(|(input, expected)| {
+            let args = CoreBuildArgs::parse_from(["foundry-cli", "--metadata-hash", input]);
+            let config = Config::from(&args);
+            assert_eq!(config.bytecode_hash, *expected);
+            let settings = config.solc_settings().unwrap();
+            assert_eq!(settings.metadata.unwrap().bytecode_hash, Some(*expected));
+        });
+
+        assert!(CoreBuildArgs::try_parse_from(["foundry-cli", "--metadata-hash", "x"]).is_err());
+    }
+
+    #[test]
+    fn can_set_allow_and_include_paths() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let args = CoreBuildArgs::parse_from([
+            "foundry-cli",
+            "--root",
+            root.to_str().unwrap(),
+            "--allow-paths",
+            "../config",
+            "--include-paths",
+            "src",
+        ]);
+        let config = Config::from(&args);
+        assert_eq!(config.allow_paths, vec![canonicalized(root.join("../config"))]);
+        let src = canonicalized(root.join("src"));
+        assert_eq!(config.include_paths, vec![src.clone()]);
+        assert!(!config.project_paths().libraries.contains(&src));
+        // include paths can't be passed to auto detected solc versions
+        assert!(config.project().is_err());
+    }
+
+    #[test]
+    fn passes_include_paths_to_solc() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let solc = tempfile::NamedTempFile::new().unwrap();
+        let args = CoreBuildArgs::parse_from([
+            "foundry-cli",
+            "--root",
+            root.to_str().unwrap(),
+            "--include-paths",
+            "src",
+        ]);
+        let mut config = Config::from(&args);
+        config.solc = Some(SolcReq::Local(solc.path().to_path_buf()));
+        let project = config.project().unwrap();
+        let include = canonicalized(root.join("src")).display().to_string();
+        assert_eq!(
+            project.solc.args,
+            vec![
+                "--base-path".to_string(),
+                canonicalized(root).display().to_string(),
+                "--include-path".to_string(),
+                include
+            ]
+        );
+    }
+
+    #[test]
+    fn can_parse_use_solc() {
+        let args = CoreBuildArgs::parse_from(["foundry-cli", "--use", "0.8.17"]);
+        assert_eq!(args.use_solc, Some("0.8.17".to_string()));
+        let config = Config::from(&args);
+        assert_eq!(config.solc, Some(SolcReq::Version("0.8.17".parse().unwrap())));
+
+        let args = CoreBuildArgs::parse_from(["foundry-cli", "--use", "solc:0.8.17"]);
+        assert_eq!(args.use_solc, Some("0.8.17".to_string()));
+
+        assert!(CoreBuildArgs::try_parse_from(["foundry-cli", "--use", "0.8"]).is_err());
+        assert!(CoreBuildArgs::try_parse_from(["foundry-cli", "--use", "path/to/solc"]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn can_parse_use_solc_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("foundry-use-solc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let solc = dir.join("solc");
+        std::fs::write(&solc, "#!/bin/sh").unwrap();
+
+        std::fs::set_permissions(&solc, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let err = CoreBuildArgs::try_parse_from(["foundry-cli", "--use", solc.to_str().unwrap()])
+            .unwrap_err();
+        assert!(err.to_string().contains("is not executable"), "{}", err);
+
+        std::fs::set_permissions(&solc, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let args = CoreBuildArgs::parse_from(["foundry-cli", "--use", solc.to_str().unwrap()]);
+        let config = Config::from(&args);
+        assert_eq!(config.solc, Some(SolcReq::Local(solc)));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn can_set_libraries() {
+        let lib = "src/DssSpell.sol:DssExecLib:0xfD88CeE74f7D78697775aBDAE53f9Da1559728E4";
+        let args = CoreBuildArgs::parse_from(["foundry-cli", "--libraries", lib]);
+        let config = Config::from(&args);
+        assert_eq!(config.libraries, vec![lib.to_string()]);
+        let args = CoreBuildArgs::try_parse_from(["foundry-cli", "--libraries", "src/A.sol:A"]);
+        assert!(args.is_err());
+
+        let other = "src/Other.sol:Other:0x8De6DDbCd5053d32292AAA0D2105A32d108484a6";
+        let libs = format!("{},{}", lib, other);
+        let args =
+            CoreBuildArgs::parse_from(["foundry-cli", "--libraries", &libs, "--libraries", lib]);
+        let config = Config::from(&args);
+        assert_eq!(config.libraries, vec![lib, other, lib]);
+    }
+}
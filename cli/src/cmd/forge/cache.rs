@@ -0,0 +1,319 @@
+//! cache command
+
+use crate::cmd::Cmd;
+use clap::{Parser, Subcommand};
+use eyre::Context;
+use forge::executor::fork::{cache_objects, prune_objects};
+use foundry_config::{Chain, Config};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Command to manage the storage caches of forks in `~/.foundry/cache`
+#[derive(Debug, Clone, Parser)]
+pub struct CacheArgs {
+    #[clap(subcommand)]
+    pub sub: CacheSubcommands,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum CacheSubcommands {
+    #[clap(about = "List the cached storage of every forked chain and block.")]
+    Ls(LsArgs),
+    /// Remove cached fork storage.
+    ///
+    /// Without arguments, the cached storage of all chains is removed.
+    Clean(CleanArgs),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct LsArgs {
+    #[clap(help = "Only list the cache of this chain.", value_name = "CHAIN")]
+    chain: Option<Chain>,
+    #[clap(help = "Print the cache entries as json.", long)]
+    json: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct CleanArgs {
+    #[clap(help = "Only remove the cache of this chain.", value_name = "CHAIN")]
+    chain: Option<Chain>,
+    /// Only remove the cache of these blocks, e.g. `--blocks 14000000,14100000`.
+    #[clap(long, requires = "chain", use_delimiter = true, value_name = "BLOCKS")]
+    blocks: Vec<u64>,
+    /// Remove the least recently used blocks until the cache dir is smaller than the size.
+    ///
+    /// Sizes are given in bytes or with a unit, e.g. `500MB` or `2GB`. Units are powers of 1024.
+    #[clap(long, parse(try_from_str = parse_size), value_name = "SIZE")]
+    max_size: Option<u64>,
+    #[clap(help = "Print the removed cache entries as json.", long)]
+    json: bool,
+}
+
+impl Cmd for CacheArgs {
+    type Output = ();
+
+    fn run(self) -> eyre::Result<Self::Output> {
+        let cache_dir = Config::foundry_cache_dir()
+            .ok_or_else(|| eyre::eyre!("Failed to find the home directory"))?;
+        match self.sub {
+            CacheSubcommands::Ls(args) => {
+                let entries = filter_chain(read_cache(&cache_dir)?, args.chain);
+                if args.json {
+                    println!("{}", serde_json::to_string_pretty(&entries)?);
+                } else {
+                    print_entries(&entries);
+                }
+            }
+            CacheSubcommands::Clean(args) => {
+                let mut entries = filter_chain(read_cache(&cache_dir)?, args.chain);
+                if !args.blocks.is_empty() {
+                    entries.retain(|entry| args.blocks.contains(&entry.block));
+                }
+
+                let size_before = dir_size(&cache_dir);
+                let removed = if let Some(max_size) = args.max_size {
+                    // blocks share objects, so the freed size is only known after removing a block
+                    entries.sort_by_key(|entry| entry.last_used);
+                    let mut removed = Vec::new();
+                    for entry in entries {
+                        if dir_size(&cache_dir) <= max_size {
+                            break
+                        }
+                        remove_entry(&entry)?;
+                        removed.push(entry);
+                    }
+                    removed
+                } else {
+                    for entry in &entries {
+                        remove_entry(entry)?;
+                    }
+                    entries
+                };
+
+                if args.json {
+                    println!("{}", serde_json::to_string_pretty(&removed)?);
+                } else {
+                    println!(
+                        "Removed the cache of {} block(s), {} freed",
+                        removed.len(),
+                        format_size(size_before.saturating_sub(dir_size(&cache_dir)))
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The cached storage of a forked block, `~/.foundry/cache/<chain>/<block>`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheEntry {
+    /// The name or id of the chain
+    pub chain: String,
+    pub block: u64,
+    pub path: PathBuf,
+    /// The size of the cache of the block in bytes, including the objects it shares with the
+    /// caches of other blocks of the chain
+    pub size: u64,
+    /// When the cache was last written, in seconds since the unix epoch
+    pub last_used: u64,
+}
+
+/// Returns the cache entries of all blocks, ordered by chain and block
+///
+/// Everything in the cache dir that isn't a `<chain>/<block>` directory, like the etherscan cache,
+/// is ignored.
+pub fn read_cache(cache_dir: &Path) -> eyre::Result<Vec<CacheEntry>> {
+    let mut entries = Vec::new();
+    if !cache_dir.exists() {
+        return Ok(entries)
+    }
+
+    for chain in read_dirs(cache_dir)? {
+        let chain_name = chain.file_name().unwrap_or_default().to_string_lossy().to_string();
+        for block_dir in read_dirs(&chain)? {
+            let block = match block_dir.file_name().and_then(|name| name.to_str()?.parse().ok()) {
+                Some(block) => block,
+                None => continue,
+            };
+
+            let mut size = 0;
+            let mut last_used = 0;
+            for file in walkdir::WalkDir::new(&block_dir).into_iter().filter_map(Result::ok) {
+                let metadata = file.metadata()?;
+                if metadata.is_file() {
+                    size += metadata.len();
+                    let modified = metadata
+                        .modified()?
+                        .duration_since(UNIX_EPOCH)
+                        .map(|time| time.as_secs())
+                        .unwrap_or_default();
+                    last_used = last_used.max(modified);
+                    size += cache_objects(file.path())
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|object| fs::metadata(object).ok())
+                        .map(|metadata| metadata.len())
+                        .sum::<u64>();
+                }
+            }
+
+            entries.push(CacheEntry {
+                chain: chain_name.clone(),
+                block,
+                path: block_dir,
+                size,
+                last_used,
+            });
+        }
+    }
+
+    entries.sort_by(|a, b| (&a.chain, a.block).cmp(&(&b.chain, b.block)));
+    Ok(entries)
+}
+
+/// Returns all directories in the directory
+fn read_dirs(dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(dir).wrap_err_with(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+fn filter_chain(mut entries: Vec<CacheEntry>, chain: Option<Chain>) -> Vec<CacheEntry> {
+    if let Some(chain) = chain {
+        let chain = chain.to_string();
+        entries.retain(|entry| entry.chain == chain);
+    }
+    entries
+}
+
+/// Returns the size of all files in the directory
+fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Removes the block directory of the entry and the objects no other block of the chain refers to
+///
+/// The chain directory is removed as well if nothing else is left in it.
+fn remove_entry(entry: &CacheEntry) -> eyre::Result<()> {
+    fs::remove_dir_all(&entry.path)
+        .wrap_err_with(|| format!("Failed to remove {}", entry.path.display()))?;
+    if let Some(chain_dir) = entry.path.parent() {
+        prune_objects(chain_dir)?;
+        if fs::read_dir(chain_dir)?.next().is_none() {
+            fs::remove_dir(chain_dir)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_entries(entries: &[CacheEntry]) {
+    if entries.is_empty() {
+        println!("No cached fork storage found");
+        return
+    }
+
+    let mut chains: BTreeMap<&str, Vec<&CacheEntry>> = BTreeMap::new();
+    for entry in entries {
+        chains.entry(&entry.chain).or_default().push(entry);
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+    for (chain, entries) in chains {
+        // the blocks of a chain share objects, so their sizes don't add up to the size on disk
+        let size = entries[0].path.parent().map(dir_size).unwrap_or_default();
+        println!("{} ({} on disk)", chain, format_size(size));
+        for entry in entries {
+            println!(
+                "  block {:<12} {:>10}  last used {}",
+                entry.block,
+                format_size(entry.size),
+                format_age(now.saturating_sub(entry.last_used))
+            );
+        }
+    }
+}
+
+const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+/// Formats a number of bytes with the largest unit that keeps it above 1, e.g. `1.5 GB`
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Parses a size like `2GB`, `500 MB`, `1.5GiB` or `1000` (bytes)
+fn parse_size(s: &str) -> eyre::Result<u64> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 =
+        number.parse().wrap_err_with(|| format!("Invalid size `{}`, expected e.g. 2GB", s))?;
+    let exponent = match unit.trim().to_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        _ => eyre::bail!("Invalid size unit `{}`, expected one of B, KB, MB, GB or TB", unit),
+    };
+    Ok((number * 1024f64.powi(exponent)) as u64)
+}
+
+/// Formats a duration in seconds as the largest whole unit, e.g. `3 days ago`
+fn format_age(secs: u64) -> String {
+    let (value, unit) = match secs {
+        s if s < 60 => return "just now".to_string(),
+        s if s < 60 * 60 => (s / 60, "minute"),
+        s if s < 24 * 60 * 60 => (s / (60 * 60), "hour"),
+        s => (s / (24 * 60 * 60), "day"),
+    };
+    format!("{} {}{} ago", value, unit, if value == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_size() {
+        assert_eq!(parse_size("1000").unwrap(), 1000);
+        assert_eq!(parse_size("2GB").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size("500 MB").unwrap(), 500 * 1024 * 1024);
+        assert_eq!(parse_size("1.5kib").unwrap(), 1536);
+        assert!(parse_size("2PB").is_err());
+        assert!(parse_size("GB").is_err());
+    }
+
+    #[test]
+    fn can_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(2 * 1024 * 1024 * 1024), "2.0 GB");
+    }
+}
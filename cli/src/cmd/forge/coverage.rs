@@ -0,0 +1,641 @@
+//! Coverage command
+use crate::{
+    cmd::{
+        forge::{build::CoreBuildArgs, test},
+        Cmd,
+    },
+    compile::ProjectCompiler,
+};
+use clap::{Parser, ValueHint};
+use ethers::solc::{
+    artifacts::{BytecodeObject, CompactBytecode, CompactContractBytecode, Offsets},
+    sourcemap::{self, SourceMap},
+};
+use eyre::Context;
+use forge::coverage::{HitMap, HitMaps};
+use foundry_config::Config;
+use solang_parser::pt::{
+    CodeLocation, ContractPart, FunctionDefinition, FunctionTy, SourceUnitPart,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+const PUSH1: u8 = 0x60;
+const PUSH32: u8 = 0x7f;
+const JUMPI: u8 = 0x57;
+
+#[derive(Debug, Clone, Parser)]
+pub struct CoverageArgs {
+    /// All test arguments are supported
+    #[clap(flatten, next_help_heading = "TEST OPTIONS")]
+    test: test::TestArgs,
+
+    /// Include the files of the library directories in the report.
+    ///
+    /// By default, only the project's own sources are reported, test files are never reported.
+    #[clap(long)]
+    include_libs: bool,
+
+    /// The file to write the LCOV report to, relative to the project root.
+    #[clap(long, default_value = "lcov.info", value_hint = ValueHint::FilePath, value_name = "FILE")]
+    lcov: PathBuf,
+}
+
+impl CoverageArgs {
+    /// Returns the nested [`CoreBuildArgs`]
+    pub fn build_args(&self) -> &CoreBuildArgs {
+        self.test.build_args()
+    }
+
+    /// Whether the coverage of the file is reported
+    fn is_reported(&self, path: &Path, config: &Config) -> bool {
+        let root = &config.__root.0;
+        let is_test =
+            path.starts_with(root.join(&config.test)) || path.to_string_lossy().ends_with(".t.sol");
+        let is_lib = config.libs.iter().any(|lib| path.starts_with(root.join(lib)));
+        !is_test && (self.include_libs || !is_lib)
+    }
+}
+
+impl Cmd for CoverageArgs {
+    type Output = ();
+
+    fn run(self) -> eyre::Result<Self::Output> {
+        let (mut config, evm_opts) = self.test.config_and_evm_opts()?;
+        // the optimizer moves and deduplicates code, which makes the source maps imprecise
+        config.optimizer = false;
+        let root = config.__root.0.clone();
+        let filter = self.test.filter().clone().with_root(&root);
+
+        self.build_args().ensure_locked(&config)?;
+        let project = config.ephemeral_no_artifacts_project()?;
+        let output = ProjectCompiler::default().compile(&project)?;
+        let (artifacts, sources) = output.clone().into_artifacts_with_sources();
+
+        // Run the tests and collect the program counters they hit
+        let mut runner = self
+            .test
+            .runner_builder(&config, &evm_opts)?
+            .coverage(true)
+            .build(&root, output, evm_opts)?;
+        let results = runner.test(&filter, None, true)?;
+        let failed = results
+            .values()
+            .flat_map(|suite| suite.test_results.values())
+            .filter(|result| !result.success)
+            .count();
+        if failed > 0 {
+            eprintln!(
+                "Warning: {} test(s) failed, the coverage of their calls is still reported",
+                failed
+            );
+        }
+        let mut hit_maps = HitMaps::default();
+        for coverage in results
+            .into_values()
+            .flat_map(|suite| suite.test_results.into_values())
+            .filter_map(|result| result.coverage)
+        {
+            hit_maps.merge(coverage);
+        }
+
+        // Map the hits back to the reported source files
+        let mut files = BTreeMap::new();
+        for (id, path) in sources.into_ids() {
+            let path = root.join(path);
+            if !self.is_reported(&path, &config) {
+                continue
+            }
+            let content = std::fs::read_to_string(&path)
+                .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+            let name = path.strip_prefix(&root).unwrap_or(&path).display().to_string();
+            files.insert(id, SourceFile::new(name, content));
+        }
+        let bytecodes = artifacts
+            .into_iter()
+            .flat_map(|(_, artifact)| {
+                let CompactContractBytecode { bytecode, deployed_bytecode, .. } = artifact.into();
+                let creation = bytecode.and_then(|code| CoverableBytecode::new(&code, None));
+                let runtime = deployed_bytecode.and_then(|deployed| {
+                    CoverableBytecode::new(
+                        deployed.bytecode.as_ref()?,
+                        Some(&deployed.immutable_references),
+                    )
+                });
+                creation.into_iter().chain(runtime)
+            })
+            .collect::<Vec<_>>();
+        let report = coverage_report(&files, &bytecodes, &hit_maps);
+
+        print!("{}", summary_table(&report));
+        let lcov_path = root.join(&self.lcov);
+        std::fs::write(&lcov_path, lcov_report(&report)?)
+            .wrap_err_with(|| format!("Failed to write {}", lcov_path.display()))?;
+        println!("Wrote LCOV report to {}", lcov_path.display());
+
+        Ok(())
+    }
+}
+
+/// A reported source file
+#[derive(Debug, Clone)]
+struct SourceFile {
+    /// The path relative to the project root
+    name: String,
+    /// The byte offset of every newline
+    newlines: Vec<usize>,
+    /// The functions with a body, with the byte range from their header to the end of the body
+    functions: Vec<(String, usize, usize)>,
+}
+
+impl SourceFile {
+    fn new(name: String, content: String) -> Self {
+        let newlines = content.match_indices('\n').map(|(pos, _)| pos).collect();
+        Self { name, newlines, functions: functions(&content) }
+    }
+
+    /// Returns the 1-based line of the byte offset
+    fn line(&self, offset: usize) -> usize {
+        self.newlines.partition_point(|pos| *pos < offset) + 1
+    }
+}
+
+/// Returns the name and byte range of every function with a body in the source, functions in
+/// contracts are named `<contract>.<function>`
+fn functions(source: &str) -> Vec<(String, usize, usize)> {
+    fn range(func: &FunctionDefinition, contract: Option<&str>) -> Option<(String, usize, usize)> {
+        let body = func.body.as_ref()?;
+        let name = match (&func.ty, &func.name) {
+            (FunctionTy::Constructor, _) => "constructor".to_string(),
+            (FunctionTy::Fallback, _) => "fallback".to_string(),
+            (FunctionTy::Receive, _) => "receive".to_string(),
+            (_, Some(name)) => name.name.clone(),
+            (_, None) => return None,
+        };
+        let name = match contract {
+            Some(contract) => format!("{}.{}", contract, name),
+            None => name,
+        };
+        Some((name, func.loc.start(), body.loc().end()))
+    }
+
+    let source_unit = match solang_parser::parse(source, 0) {
+        Ok((source_unit, _)) => source_unit,
+        Err(_) => return Vec::new(),
+    };
+    source_unit
+        .0
+        .iter()
+        .flat_map(|part| match part {
+            SourceUnitPart::ContractDefinition(contract) => contract
+                .parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContractPart::FunctionDefinition(func) => {
+                        range(func, Some(&contract.name.name))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            SourceUnitPart::FunctionDefinition(func) => range(func, None).into_iter().collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// The compiled bytecode of a contract, either the creation or the runtime bytecode
+#[derive(Debug, Clone)]
+struct CoverableBytecode {
+    /// The bytes of the bytecode, `None` marks the bytes that are only known once the contract
+    /// is deployed: the addresses of linked libraries and the values of immutables
+    pattern: Vec<Option<u8>>,
+    source_map: SourceMap,
+}
+
+impl CoverableBytecode {
+    fn new(
+        bytecode: &CompactBytecode,
+        immutables: Option<&BTreeMap<String, Vec<Offsets>>>,
+    ) -> Option<Self> {
+        let source_map = sourcemap::parse(bytecode.source_map.as_ref()?).ok()?;
+        let hex = match bytecode.object {
+            BytecodeObject::Bytecode(ref bytes) => hex::encode(bytes),
+            BytecodeObject::Unlinked(ref hex) => hex.trim_start_matches("0x").to_string(),
+        };
+        let mut pattern = hex
+            .as_bytes()
+            .chunks(2)
+            .map(|byte| u8::from_str_radix(std::str::from_utf8(byte).ok()?, 16).ok())
+            .collect::<Vec<_>>();
+        let placeholders = bytecode
+            .link_references
+            .values()
+            .flat_map(|libraries| libraries.values())
+            .chain(immutables.into_iter().flat_map(|immutables| immutables.values()))
+            .flatten();
+        for offsets in placeholders {
+            for byte in
+                pattern.iter_mut().skip(offsets.start as usize).take(offsets.length as usize)
+            {
+                *byte = None;
+            }
+        }
+
+        (!pattern.is_empty()).then(|| Self { pattern, source_map })
+    }
+
+    /// Whether the executed code is this bytecode
+    ///
+    /// The executed code can be longer, e.g. creation code is followed by the constructor
+    /// arguments.
+    fn matches(&self, code: &[u8]) -> bool {
+        code.len() >= self.pattern.len() &&
+            self.pattern
+                .iter()
+                .zip(code)
+                .all(|(expected, byte)| expected.map_or(true, |b| b == *byte))
+    }
+
+    /// Returns the program counter of every instruction, source maps are indexed by the
+    /// instruction counter which skips push data
+    fn program_counters(&self) -> Vec<usize> {
+        let mut pcs = Vec::with_capacity(self.pattern.len());
+        let mut pc = 0;
+        while pc < self.pattern.len() {
+            pcs.push(pc);
+            let op = self.pattern[pc].unwrap_or_default();
+            if (PUSH1..=PUSH32).contains(&op) {
+                pc += (op - PUSH1 + 1) as usize;
+            }
+            pc += 1;
+        }
+        pcs
+    }
+}
+
+/// The coverage of a source file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileCoverage {
+    /// The number of times every line with instructions was executed, by line number
+    pub lines: BTreeMap<usize, u64>,
+    /// The line of every `JUMPI` and how often it jumped and didn't jump, `None` if it was never
+    /// executed
+    pub branches: Vec<(usize, Option<(u64, u64)>)>,
+    /// The name, line and number of calls of every function
+    pub functions: Vec<(String, usize, u64)>,
+}
+
+impl FileCoverage {
+    /// Returns the number of lines that were hit and the number of lines with instructions
+    pub fn lines_hit(&self) -> (usize, usize) {
+        (self.lines.values().filter(|hits| **hits > 0).count(), self.lines.len())
+    }
+
+    /// Returns the number of branches that were taken and the number of branches, every `JUMPI`
+    /// has two branches
+    pub fn branches_hit(&self) -> (usize, usize) {
+        let hit = self
+            .branches
+            .iter()
+            .map(|(_, taken)| match taken {
+                Some((jumped, not_jumped)) => (*jumped > 0) as usize + (*not_jumped > 0) as usize,
+                None => 0,
+            })
+            .sum();
+        (hit, self.branches.len() * 2)
+    }
+
+    /// Returns the number of functions that were called and the number of functions
+    pub fn functions_hit(&self) -> (usize, usize) {
+        (self.functions.iter().filter(|(_, _, hits)| *hits > 0).count(), self.functions.len())
+    }
+}
+
+/// Maps the hits of the executed code back to the lines, branches and functions of the files,
+/// keyed by the file name
+///
+/// Hits of an instruction count for the line of the start of its source range. A line that is
+/// part of multiple contracts, e.g. of an inherited contract, counts the hits in all of them.
+fn coverage_report(
+    files: &BTreeMap<u32, SourceFile>,
+    bytecodes: &[CoverableBytecode],
+    hit_maps: &HitMaps,
+) -> BTreeMap<String, FileCoverage> {
+    let mut lines: BTreeMap<(u32, usize), u64> = BTreeMap::new();
+    let mut functions: BTreeMap<(u32, usize), u64> = BTreeMap::new();
+    // `JUMPI`s are identified by their source range and their position among the `JUMPI`s of
+    // that range, so the same branch in different contracts is reported once
+    let mut branches: BTreeMap<(u32, usize, usize, usize), (usize, Option<(u64, u64)>)> =
+        BTreeMap::new();
+
+    // Every executed code is attributed to the longest bytecode it matches
+    let mut executions: Vec<Option<HitMap>> = vec![None; bytecodes.len()];
+    for hit_map in hit_maps.0.values() {
+        let best = bytecodes
+            .iter()
+            .enumerate()
+            .filter(|(_, bytecode)| bytecode.matches(&hit_map.bytecode))
+            .max_by_key(|(_, bytecode)| bytecode.pattern.len());
+        if let Some((idx, _)) = best {
+            match executions[idx] {
+                Some(ref mut hits) => hits.merge(hit_map.clone()),
+                None => executions[idx] = Some(hit_map.clone()),
+            }
+        }
+    }
+
+    for (bytecode, hit_map) in bytecodes.iter().zip(executions) {
+        let pc_hits = |pc: usize| {
+            hit_map.as_ref().and_then(|hit_map| hit_map.hits.get(&pc)).copied().unwrap_or_default()
+        };
+
+        let mut bytecode_lines: BTreeMap<(u32, usize), u64> = BTreeMap::new();
+        let mut bytecode_functions: BTreeMap<(u32, usize), u64> = BTreeMap::new();
+        let mut jumpis: BTreeMap<(u32, usize, usize), usize> = BTreeMap::new();
+        for (pc, element) in bytecode.program_counters().into_iter().zip(&bytecode.source_map) {
+            let (index, file) =
+                match element.index.and_then(|index| Some((index, files.get(&index)?))) {
+                    Some(file) => file,
+                    None => continue,
+                };
+            let hits = pc_hits(pc);
+
+            let line = bytecode_lines.entry((index, file.line(element.offset))).or_default();
+            *line = (*line).max(hits);
+
+            let end = element.offset + element.length;
+            for (idx, (_, start, func_end)) in file.functions.iter().enumerate() {
+                if *start <= element.offset && end <= *func_end {
+                    let func = bytecode_functions.entry((index, idx)).or_default();
+                    *func = (*func).max(hits);
+                }
+            }
+
+            if bytecode.pattern[pc] == Some(JUMPI) {
+                let occurrence = jumpis.entry((index, element.offset, element.length)).or_default();
+                let key = (index, element.offset, element.length, *occurrence);
+                *occurrence += 1;
+
+                let taken = hit_map
+                    .as_ref()
+                    .filter(|_| hits > 0)
+                    .map(|hit_map| hit_map.branches.get(&pc).copied().unwrap_or_default());
+                let (_, total) =
+                    branches.entry(key).or_insert_with(|| (file.line(element.offset), None));
+                *total = match (*total, taken) {
+                    (Some(total), Some(taken)) => Some((total.0 + taken.0, total.1 + taken.1)),
+                    (total, taken) => total.or(taken),
+                };
+            }
+        }
+
+        for (key, hits) in bytecode_lines {
+            *lines.entry(key).or_default() += hits;
+        }
+        for (key, hits) in bytecode_functions {
+            *functions.entry(key).or_default() += hits;
+        }
+    }
+
+    files
+        .iter()
+        .map(|(index, file)| {
+            let coverage = FileCoverage {
+                lines: lines
+                    .range((*index, 0)..=(*index, usize::MAX))
+                    .map(|((_, line), hits)| (*line, *hits))
+                    .collect(),
+                branches: branches
+                    .range((*index, 0, 0, 0)..=(*index, usize::MAX, usize::MAX, usize::MAX))
+                    .map(|(_, branch)| *branch)
+                    .collect(),
+                functions: file
+                    .functions
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, (name, start, _))| {
+                        let hits = functions.get(&(*index, idx)).copied().unwrap_or_default();
+                        (name.clone(), file.line(*start), hits)
+                    })
+                    .collect(),
+            };
+            (file.name.clone(), coverage)
+        })
+        .filter(|(_, coverage)| !coverage.lines.is_empty() || !coverage.functions.is_empty())
+        .collect()
+}
+
+/// Formats the hit and total count, e.g. `75.00% (3/4)`
+fn percentage((hit, total): (usize, usize)) -> String {
+    if total == 0 {
+        return "-".to_string()
+    }
+    format!("{:.2}% ({}/{})", hit as f64 * 100.0 / total as f64, hit, total)
+}
+
+/// Formats the coverage of every file and their total as a table
+fn summary_table(report: &BTreeMap<String, FileCoverage>) -> String {
+    let total = |count: fn(&FileCoverage) -> (usize, usize)| {
+        percentage(
+            report.values().map(count).fold((0, 0), |(hit, total), (h, t)| (hit + h, total + t)),
+        )
+    };
+    let total = [
+        "Total".to_string(),
+        total(FileCoverage::lines_hit),
+        total(FileCoverage::branches_hit),
+        total(FileCoverage::functions_hit),
+    ];
+    let rows = report
+        .iter()
+        .map(|(name, coverage)| {
+            [
+                name.clone(),
+                percentage(coverage.lines_hit()),
+                percentage(coverage.branches_hit()),
+                percentage(coverage.functions_hit()),
+            ]
+        })
+        .chain(std::iter::once(total))
+        .collect::<Vec<_>>();
+    let header = ["File", "% Lines", "% Branches", "% Funcs"].map(String::from);
+    let mut widths = header.clone().map(|cell| cell.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<1$}", cell, width))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Formats the coverage as an LCOV tracefile, see `man geninfo`
+fn lcov_report(report: &BTreeMap<String, FileCoverage>) -> Result<String, std::fmt::Error> {
+    let mut lcov = String::new();
+    for (name, coverage) in report {
+        writeln!(lcov, "TN:")?;
+        writeln!(lcov, "SF:{}", name)?;
+
+        for (func, line, _) in &coverage.functions {
+            writeln!(lcov, "FN:{},{}", line, func)?;
+        }
+        for (func, _, hits) in &coverage.functions {
+            writeln!(lcov, "FNDA:{},{}", hits, func)?;
+        }
+        let (hit, found) = coverage.functions_hit();
+        writeln!(lcov, "FNF:{}\nFNH:{}", found, hit)?;
+
+        for (block, (line, taken)) in coverage.branches.iter().enumerate() {
+            let (jumped, not_jumped) = match taken {
+                Some((jumped, not_jumped)) => (jumped.to_string(), not_jumped.to_string()),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            writeln!(lcov, "BRDA:{},{},0,{}", line, block, jumped)?;
+            writeln!(lcov, "BRDA:{},{},1,{}", line, block, not_jumped)?;
+        }
+        let (hit, found) = coverage.branches_hit();
+        writeln!(lcov, "BRF:{}\nBRH:{}", found, hit)?;
+
+        for (line, hits) in &coverage.lines {
+            writeln!(lcov, "DA:{},{}", line, hits)?;
+        }
+        let (hit, found) = coverage.lines_hit();
+        writeln!(lcov, "LF:{}\nLH:{}", found, hit)?;
+        writeln!(lcov, "end_of_record")?;
+    }
+    Ok(lcov)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{types::H256, utils::keccak256};
+
+    const SOURCE: &str = r#"contract A {
+    function f(uint256 x) public returns (uint256) {
+        if (x > 1) {
+            return 1;
+        }
+        return 2;
+    }
+}
+"#;
+
+    /// Returns the bytecode `PUSH1 1 JUMPI JUMPDEST STOP`, the instructions are mapped to the
+    /// condition, the condition, `return 1` and `return 2`
+    fn bytecode() -> CoverableBytecode {
+        let element =
+            |snippet: &str| format!("{}:{}:0:-", SOURCE.find(snippet).unwrap(), snippet.len());
+        let source_map = ["x > 1", "x > 1", "return 1", "return 2"].map(element).join(";");
+        let bytecode: CompactBytecode = serde_json::from_value(serde_json::json!({
+            "object": "0x6001575b00",
+            "sourceMap": source_map,
+            "linkReferences": {},
+        }))
+        .unwrap();
+        CoverableBytecode::new(&bytecode, None).unwrap()
+    }
+
+    fn report() -> BTreeMap<String, FileCoverage> {
+        let code = vec![0x60, 0x01, 0x57, 0x5b, 0x00];
+        let mut hit_map = HitMap::new(code.clone().into());
+        for pc in [0, 0, 2, 2, 3] {
+            hit_map.hit(pc);
+        }
+        hit_map.branch(2, true);
+        hit_map.branch(2, false);
+        let hit_maps = HitMaps(BTreeMap::from([(H256(keccak256(&code)), hit_map)]));
+
+        let files =
+            BTreeMap::from([(0, SourceFile::new("src/A.sol".to_string(), SOURCE.to_string()))]);
+        coverage_report(&files, &[bytecode()], &hit_maps)
+    }
+
+    #[test]
+    fn matches_executed_code() {
+        let bytecode: CompactBytecode = serde_json::from_value(serde_json::json!({
+            "object": "0x73__$2b07d1e3b4cb1e0ac6bf27e8a6ef09bd46$__3014",
+            "sourceMap": "0:1:0:-;0:1:0:-;0:1:0:-",
+            "linkReferences": {
+                "src/Lib.sol": { "Lib": [{ "start": 1, "length": 20 }] }
+            },
+        }))
+        .unwrap();
+        let bytecode = CoverableBytecode::new(&bytecode, None).unwrap();
+        assert_eq!(bytecode.program_counters(), [0, 21, 22]);
+
+        let mut code = vec![0x73];
+        code.extend([0xab; 20]);
+        code.extend([0x30, 0x14]);
+        assert!(bytecode.matches(&code));
+        // trailing bytes, e.g. constructor arguments
+        code.extend([0x00; 32]);
+        assert!(bytecode.matches(&code));
+        code[21] = 0x31;
+        assert!(!bytecode.matches(&code));
+        assert!(!bytecode.matches(&[0x73]));
+    }
+
+    #[test]
+    fn finds_functions() {
+        let source = "function free() pure {}\ninterface I { function g() external; }\ncontract C {\n    constructor() {}\n    function h() public {}\n}\n";
+        let functions = functions(source);
+        let names = functions.iter().map(|(name, _, _)| name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["free", "C.constructor", "C.h"]);
+        let (_, start, end) = &functions[2];
+        assert_eq!(&source[*start..*end], "function h() public {}");
+    }
+
+    #[test]
+    fn maps_hits_to_lines_branches_and_functions() {
+        let report = report();
+        let coverage = &report["src/A.sol"];
+        assert_eq!(coverage.lines, BTreeMap::from([(3, 2), (4, 1), (6, 0)]));
+        assert_eq!(coverage.branches, [(3, Some((1, 1)))]);
+        assert_eq!(coverage.functions, [("A.f".to_string(), 2, 2)]);
+
+        assert_eq!(coverage.lines_hit(), (2, 3));
+        assert_eq!(coverage.branches_hit(), (2, 2));
+        assert_eq!(coverage.functions_hit(), (1, 1));
+    }
+
+    #[test]
+    fn reports_unexecuted_code() {
+        let files =
+            BTreeMap::from([(0, SourceFile::new("src/A.sol".to_string(), SOURCE.to_string()))]);
+        let report = coverage_report(&files, &[bytecode()], &HitMaps::default());
+        let coverage = &report["src/A.sol"];
+        assert_eq!(coverage.lines, BTreeMap::from([(3, 0), (4, 0), (6, 0)]));
+        assert_eq!(coverage.branches, [(3, None)]);
+        assert_eq!(coverage.functions_hit(), (0, 1));
+    }
+
+    #[test]
+    fn writes_lcov_and_summary() {
+        let report = report();
+        assert_eq!(
+            lcov_report(&report).unwrap(),
+            "TN:\nSF:src/A.sol\nFN:2,A.f\nFNDA:2,A.f\nFNF:1\nFNH:1\nBRDA:3,0,0,1\nBRDA:3,0,1,1\nBRF:2\nBRH:2\nDA:3,2\nDA:4,1\nDA:6,0\nLF:3\nLH:2\nend_of_record\n"
+        );
+        assert_eq!(
+            summary_table(&report),
+            "File      | % Lines      | % Branches    | % Funcs\n\
+             src/A.sol | 66.67% (2/3) | 100.00% (2/2) | 100.00% (1/1)\n\
+             Total     | 66.67% (2/3) | 100.00% (2/2) | 100.00% (1/1)\n"
+        );
+    }
+}
@@ -1,20 +1,37 @@
 //! Create command
 use crate::{
-    cmd::{forge::build::CoreBuildArgs, Cmd},
+    cmd::{
+        forge::{
+            build::CoreBuildArgs,
+            verify::{VerificationMode, VerifierArgs, VerifyArgs},
+        },
+        Cmd,
+    },
     compile,
     opts::{forge::ContractInfo, EthereumOpts, WalletType},
     utils::{parse_ether_value, parse_u256},
 };
 use clap::{Parser, ValueHint};
 use ethers::{
-    abi::{Abi, Constructor, Token},
-    prelude::{artifacts::BytecodeObject, ContractFactory, Http, Middleware, Provider},
-    types::{transaction::eip2718::TypedTransaction, Chain, U256},
+    abi::{
+        token::{LenientTokenizer, Tokenizer},
+        Abi, Address, Constructor, ParamType, Token,
+    },
+    prelude::{artifacts::BytecodeObject, ArtifactId, ContractFactory, Http, Middleware, Provider},
+    types::{transaction::eip2718::TypedTransaction, Chain, H256, U256, U64},
+    utils::{get_contract_address, get_create2_address},
 };
 use eyre::{Context, Result};
+use foundry_config::Config;
 use foundry_utils::parse_tokens;
+use semver::Version;
 use serde_json::json;
-use std::{fs, path::PathBuf, sync::Arc};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 #[derive(Debug, Clone, Parser)]
 pub struct CreateArgs {
@@ -33,12 +50,85 @@ pub struct CreateArgs {
     #[clap(
         long,
         help = "The path to a file containing the constructor arguments.",
+        long_help = "The path to a file containing the constructor arguments. The format is picked by the file extension: a `.json` file contains a JSON array with an element per argument, where arrays and tuples are nested arrays, a `.hex` file contains the ABI-encoded arguments as a single hex string, any other file contains the arguments separated by whitespace or newlines.",
         value_hint = ValueHint::FilePath,
         name = "constructor_args_path",
         conflicts_with = "constructor_args",
     )]
     constructor_args_path: Option<PathBuf>,
 
+    #[clap(
+        long,
+        help = "Verify the contract after it was deployed.",
+        long_help = "Verify the contract after it was deployed, with the compiler version and settings it was compiled with and the same encoded constructor arguments it was deployed with. The verification is submitted once the deployment has `--confirmations` confirmations and checked until it succeeded or failed."
+    )]
+    verify: bool,
+
+    #[clap(
+        long,
+        help = "The compiler version of the contract, used to verify it.",
+        long_help = "The compiler version of the contract, used to verify it, e.g. `v0.8.10+commit.fc410830`. Defaults to the version the contract was compiled with.",
+        value_name = "VERSION",
+        requires = "verify"
+    )]
+    compiler_version: Option<String>,
+
+    #[clap(
+        long,
+        help = "The number of confirmations to wait for before the contract is verified.",
+        long_help = "The number of confirmations to wait for before the contract is verified. Etherscan doesn't find contracts in blocks that are too recent.",
+        default_value = "5",
+        value_name = "CONFIRMATIONS"
+    )]
+    confirmations: usize,
+
+    #[clap(flatten, next_help_heading = "VERIFIER OPTIONS")]
+    verifier: VerifierArgs,
+
+    #[clap(
+        long,
+        help = "Print the deployment address and estimated gas without deploying the contract.",
+        long_help = "Print the deployment address and estimated gas without deploying the contract. The address is derived from the deployer and its current nonce, or with `--salt` from the CREATE2 factory, the salt and the init code.",
+        conflicts_with = "verify"
+    )]
+    predict_address: bool,
+
+    #[clap(
+        long,
+        help = "Deploy the contract with CREATE2 with the given 32 byte salt.",
+        long_help = "Deploy the contract with CREATE2 with the given 32 byte salt. The contract is deployed through the CREATE2 factory, its address only depends on the factory, the salt and the init code. The address is printed before the deployment is sent, nothing is sent if there already is a contract at the address unless `--force-deploy` is passed.",
+        value_name = "SALT",
+        parse(try_from_str = parse_salt)
+    )]
+    salt: Option<H256>,
+
+    #[clap(
+        long,
+        help = "Send the CREATE2 deployment even if there already is a contract at the address.",
+        requires = "salt"
+    )]
+    force_deploy: bool,
+
+    #[clap(
+        long,
+        visible_alias = "deployer",
+        alias = "create2-deployer",
+        help = "The address of the CREATE2 factory used with `--salt`.",
+        long_help = "The address of the CREATE2 factory used with `--salt`, defaults to the deterministic deployment proxy. The factory is called with the salt followed by the init code.",
+        value_name = "ADDRESS",
+        default_value = "0x4e59b44847b379578588920cA78FbF26c0B4956C"
+    )]
+    create2_factory: Address,
+
+    #[clap(
+        long,
+        help = "Print the CREATE2 address of the contract and exit.",
+        long_help = "Print the CREATE2 address of the contract and exit. The address is computed from the CREATE2 factory, the salt and the init code including the constructor arguments, neither a signer nor an RPC endpoint is needed.",
+        requires = "salt",
+        conflicts_with_all = &["verify", "predict-address"]
+    )]
+    compute_address: bool,
+
     #[clap(
         long,
         help_heading = "TRANSACTION OPTIONS",
@@ -112,6 +202,18 @@ impl Cmd for CreateArgs {
             compile::compile(&project, false, false)?
         };
 
+        // the source file and the compiler version of the contract, the contract is verified with
+        // them
+        let artifact_id = if self.verify {
+            compiled
+                .clone()
+                .into_artifacts()
+                .find(|(id, _)| id.name == self.contract.name)
+                .map(|(id, _)| id)
+        } else {
+            None
+        };
+
         // Get ABI and BIN
         let (abi, bin, _) =
             crate::cmd::utils::read_artifact(&project, compiled, self.contract.clone())?;
@@ -122,41 +224,60 @@ impl Cmd for CreateArgs {
         };
 
         // Add arguments to constructor
-        let provider = Provider::<Http>::try_from(self.eth.rpc_url()?)?;
         let params = match abi.constructor {
             Some(ref v) => {
-                let constructor_args =
-                    if let Some(ref constructor_args_path) = self.constructor_args_path {
-                        if !std::path::Path::new(&constructor_args_path).exists() {
-                            eyre::bail!("constructor args path not found");
-                        }
-                        let file = fs::read_to_string(constructor_args_path)?;
-                        file.split(' ').map(|s| s.to_string()).collect::<Vec<String>>()
-                    } else {
-                        self.constructor_args.clone()
-                    };
-                self.parse_constructor_args(v, &constructor_args)?
+                if let Some(ref constructor_args_path) = self.constructor_args_path {
+                    ConstructorArgsFile::read(constructor_args_path)?.tokens(v)?
+                } else {
+                    parse_constructor_args(v, &self.constructor_args)?
+                }
             }
             None => vec![],
         };
 
+        if self.compute_address {
+            let salt = self.salt.expect("required by --compute-address");
+            let code = bin.as_bytes().expect("checked above");
+            let address = deployment_address(
+                Address::zero(),
+                U256::zero(),
+                Some((self.create2_factory, salt)),
+                &init_code(code.as_ref(), &params),
+            );
+            if self.json {
+                println!("{}", json!({ "computedAddress": address }));
+            } else {
+                println!("Computed address: {:?}", address);
+            }
+            return Ok(())
+        }
+
         // Deploy with signer
+        let provider = Provider::<Http>::try_from(self.eth.rpc_url()?)?;
         let rt = tokio::runtime::Runtime::new().expect("could not start tokio rt");
         let chain_id = rt.block_on(provider.get_chainid())?;
-        if let Some(signer) = rt.block_on(self.eth.signer_with(chain_id, provider))? {
+        let constructor_args = ethers::abi::encode(&params);
+        let address = if let Some(signer) = rt.block_on(self.eth.signer_with(chain_id, provider))? {
             match signer {
                 WalletType::Ledger(signer) => {
-                    rt.block_on(self.deploy(abi, bin, params, signer))?;
+                    rt.block_on(self.clone().deploy(abi, bin, params, signer))?
                 }
                 WalletType::Local(signer) => {
-                    rt.block_on(self.deploy(abi, bin, params, signer))?;
+                    rt.block_on(self.clone().deploy(abi, bin, params, signer))?
                 }
                 WalletType::Trezor(signer) => {
-                    rt.block_on(self.deploy(abi, bin, params, signer))?;
+                    rt.block_on(self.clone().deploy(abi, bin, params, signer))?
                 }
             }
         } else {
             eyre::bail!("could not find artifact")
+        };
+
+        if self.verify && !self.predict_address {
+            let verify =
+                self.verify_args(address, chain_id.as_u64(), &constructor_args, artifact_id)?;
+            println!("Starting contract verification...");
+            rt.block_on(verify.run_and_wait(VERIFY_CHECK_RETRIES, VERIFY_CHECK_INTERVAL))?;
         }
 
         Ok(())
@@ -164,16 +285,23 @@ impl Cmd for CreateArgs {
 }
 
 impl CreateArgs {
+    /// Deploys the contract and returns its address
+    ///
+    /// With `--predict-address` nothing is sent, the address the contract would be deployed to is
+    /// returned instead.
     async fn deploy<M: Middleware + 'static>(
         self,
         abi: Abi,
         bin: BytecodeObject,
         args: Vec<Token>,
         provider: M,
-    ) -> Result<()> {
+    ) -> Result<Address> {
         let chain = provider.get_chainid().await?.as_u64();
         let deployer_address =
             provider.default_sender().expect("no sender address set for provider");
+        if !self.json && !self.predict_address {
+            println!("Deployer: {:?}", deployer_address);
+        }
         let bin = bin.into_bytes().unwrap_or_else(|| {
             panic!("no bytecode found in bin object for {}", self.contract.name)
         });
@@ -192,6 +320,42 @@ impl CreateArgs {
         let is_legacy =
             self.legacy || Chain::try_from(chain).map(|x| Chain::is_legacy(&x)).unwrap_or_default();
         let mut deployer = if is_legacy { deployer.legacy() } else { deployer };
+        let init_code = deployer.tx.data().map(|data| data.to_vec()).unwrap_or_default();
+
+        // CREATE2 deployments are calls to the factory
+        if let Some(salt) = self.salt {
+            let code = provider.get_code(self.create2_factory, None).await?;
+            if code.as_ref().is_empty() {
+                eyre::bail!(
+                    "There is no CREATE2 factory at {:?} on chain {}, deploy it first or pass another factory with --create2-factory",
+                    self.create2_factory,
+                    chain
+                )
+            }
+            deployer.tx.set_to(self.create2_factory);
+            deployer.tx.set_data(create2_calldata(salt, &init_code).into());
+
+            if !self.predict_address {
+                let address = deployment_address(
+                    deployer_address,
+                    U256::zero(),
+                    Some((self.create2_factory, salt)),
+                    &init_code,
+                );
+                if !self.json {
+                    println!("Deploying to: {:?}", address);
+                }
+                // the factory reverts if the address is taken
+                if !self.force_deploy &&
+                    !provider.get_code(address, None).await?.as_ref().is_empty()
+                {
+                    eyre::bail!(
+                        "There already is a contract at {:?}, use another salt or pass --force-deploy to send the deployment anyway",
+                        address
+                    )
+                }
+            }
+        }
 
         // fill tx first because if you target a lower gas than current base, eth_estimateGas
         // will fail and create will fail
@@ -227,35 +391,702 @@ impl CreateArgs {
             deployer.tx.set_value(value);
         }
 
-        let (deployed_contract, receipt) = deployer.send_with_receipt().await?;
+        if self.predict_address {
+            let nonce = provider.get_transaction_count(deployer_address, None).await?;
+            let gas = provider.estimate_gas(&deployer.tx).await?;
+            let address = deployment_address(
+                deployer_address,
+                nonce,
+                self.salt.map(|salt| (self.create2_factory, salt)),
+                &init_code,
+            );
+            if self.json {
+                let output = json!({
+                    "deployer": deployer_address,
+                    "predictedAddress": address,
+                    "estimatedGas": gas,
+                });
+                println!("{}", output);
+            } else {
+                println!("Deployer: {:?}", deployer_address);
+                println!("Predicted address: {:?}", address);
+                println!("Estimated gas: {}", gas);
+            }
+            return Ok(address)
+        }
+
+        let (address, receipt) = if let Some(salt) = self.salt {
+            let receipt = provider
+                .send_transaction(deployer.tx, None)
+                .await?
+                .await?
+                .ok_or_else(|| eyre::eyre!("The CREATE2 deployment transaction was dropped"))?;
+            let address = deployment_address(
+                deployer_address,
+                U256::zero(),
+                Some((self.create2_factory, salt)),
+                &init_code,
+            );
+            // the factory doesn't revert if the deployment fails
+            if provider.get_code(address, None).await?.as_ref().is_empty() {
+                eyre::bail!(
+                    "The CREATE2 deployment to {:?} failed, transaction hash: {:?}",
+                    address,
+                    receipt.transaction_hash
+                )
+            }
+            (address, receipt)
+        } else {
+            let (deployed_contract, receipt) = deployer.send_with_receipt().await?;
+            (deployed_contract.address(), receipt)
+        };
+        let transaction_hash = receipt.transaction_hash;
         if self.json {
             let output = json!({
                 "deployer": deployer_address,
-                "deployedTo": deployed_contract.address(),
-                "transactionHash": receipt.transaction_hash
+                "deployedTo": address,
+                "transactionHash": transaction_hash
             });
-            println!("{output}");
+            println!("{}", output);
         } else {
-            println!("Deployer: {deployer_address:?}");
-            println!("Deployed to: {:?}", deployed_contract.address());
-            println!("Transaction hash: {:?}", receipt.transaction_hash);
+            println!("Deployed to: {:?}", address);
+            println!("Transaction hash: {:?}", transaction_hash);
         }
 
-        Ok(())
+        if self.verify {
+            if let Some(block) = receipt.block_number {
+                wait_for_confirmations(provider.as_ref(), block, self.confirmations).await?;
+            }
+        }
+
+        Ok(address)
     }
 
-    fn parse_constructor_args(
+    /// Returns the arguments to verify the deployed contract
+    ///
+    /// `constructor_args` are the ABI-encoded arguments the contract was deployed with, the
+    /// artifact id provides the compiler version and the file of the contract if the contract
+    /// was given without a path.
+    fn verify_args(
         &self,
-        constructor: &Constructor,
-        constructor_args: &[String],
-    ) -> Result<Vec<Token>> {
-        let params = constructor
-            .inputs
-            .iter()
-            .zip(constructor_args)
-            .map(|(input, arg)| (&input.kind, arg.as_str()))
-            .collect::<Vec<_>>();
-
-        parse_tokens(params, true)
+        address: Address,
+        chain: u64,
+        constructor_args: &[u8],
+        artifact_id: Option<ArtifactId>,
+    ) -> Result<VerifyArgs> {
+        let config = Config::from(&self.opts);
+        let project = config.project()?;
+        let optimizer = &project.solc_config.settings.optimizer;
+        let mut contract = self.contract.clone();
+        if contract.path.is_none() {
+            contract.path = artifact_id.as_ref().map(|id| id.source.to_string_lossy().to_string());
+        }
+        let compiler_version = match (&self.compiler_version, &artifact_id) {
+            (Some(version), _) => version.clone(),
+            (None, Some(id)) => etherscan_compiler_version(&id.version),
+            (None, None) => eyre::bail!(
+                "Could not find the compiler version of `{}`, pass it with --compiler-version",
+                self.contract.name
+            ),
+        };
+
+        Ok(VerifyArgs {
+            address,
+            contract,
+            constructor_args: (!constructor_args.is_empty()).then(|| hex::encode(constructor_args)),
+            constructor_args_path: None,
+            compiler_version,
+            num_of_optimizations: optimizer
+                .enabled
+                .unwrap_or_default()
+                .then(|| optimizer.runs.unwrap_or_default() as u32),
+            chain: chain.into(),
+            etherscan_key: self
+                .eth
+                .etherscan_api_key
+                .clone()
+                .or_else(|| config.etherscan_api_key.clone()),
+            via: VerificationMode::StandardJson,
+            flatten: false,
+            force: false,
+            project_paths: self.opts.project_paths.clone(),
+            verifier: self.verifier.clone(),
+        })
+    }
+}
+
+/// How often the block number is polled while waiting for confirmations
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the status of the verification is checked after it was submitted, and the time
+/// between the checks
+const VERIFY_CHECK_RETRIES: u32 = 10;
+const VERIFY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Waits until the block has the given number of confirmations, the block itself is the first
+/// confirmation
+async fn wait_for_confirmations<M: Middleware>(
+    provider: &M,
+    block: U64,
+    confirmations: usize,
+) -> Result<()> {
+    let target = block + confirmations.saturating_sub(1) as u64;
+    let mut current = provider.get_block_number().await?;
+    if current < target {
+        println!("Waiting for {} confirmations before verifying...", confirmations);
+    }
+    while current < target {
+        tokio::time::sleep(CONFIRMATION_POLL_INTERVAL).await;
+        current = provider.get_block_number().await?;
+    }
+    Ok(())
+}
+
+/// Returns the compiler version in the format etherscan expects, e.g. `v0.8.10+commit.fc410830`
+///
+/// The version of a compiler binary may contain the platform as well, e.g.
+/// `0.8.10+commit.fc410830.Linux.g++`.
+fn etherscan_compiler_version(version: &Version) -> String {
+    let mut etherscan = format!("v{}.{}.{}", version.major, version.minor, version.patch);
+    let mut build = version.build.as_str().split('.');
+    if let (Some("commit"), Some(commit)) = (build.next(), build.next()) {
+        etherscan.push_str(&format!("+commit.{}", commit));
+    }
+    etherscan
+}
+
+/// Returns the address a contract is deployed to
+///
+/// Without a salt this is the CREATE address of the deployer at its nonce, with a salt the CREATE2
+/// address of the contract deployed by the CREATE2 factory with the salt.
+fn deployment_address(
+    deployer: Address,
+    nonce: U256,
+    create2: Option<(Address, H256)>,
+    init_code: &[u8],
+) -> Address {
+    match create2 {
+        Some((create2_factory, salt)) => {
+            get_create2_address(create2_factory, salt.to_fixed_bytes(), init_code.to_vec())
+        }
+        None => get_contract_address(deployer, nonce),
+    }
+}
+
+/// Returns the init code of the contract, the creation code followed by the ABI-encoded
+/// constructor arguments
+fn init_code(code: &[u8], args: &[Token]) -> Vec<u8> {
+    [code, &ethers::abi::encode(args)].concat()
+}
+
+/// Returns the calldata of a deployment through the CREATE2 factory, the salt followed by the
+/// init code
+fn create2_calldata(salt: H256, init_code: &[u8]) -> Vec<u8> {
+    [salt.as_bytes(), init_code].concat()
+}
+
+/// Parses a CREATE2 salt, the salt must be exactly 32 bytes
+fn parse_salt(salt: &str) -> Result<H256> {
+    let bytes = hex::decode(salt.trim_start_matches("0x"))
+        .wrap_err_with(|| format!("Invalid salt `{}`, expected hex", salt))?;
+    if bytes.len() != 32 {
+        eyre::bail!("Invalid salt `{}`, expected 32 bytes but got {}", salt, bytes.len())
+    }
+    Ok(H256::from_slice(&bytes))
+}
+
+/// The constructor arguments of a `--constructor-args-path` file
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstructorArgsFile {
+    /// The ABI-encoded arguments as a single hex string
+    Encoded(Vec<u8>),
+    /// A JSON array with an element per argument, arrays and tuples are nested arrays
+    Json(Vec<serde_json::Value>),
+    /// The arguments separated by whitespace or newlines
+    Plain(Vec<String>),
+}
+
+impl ConstructorArgsFile {
+    pub fn read(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            eyre::bail!("Constructor args file `{}` not found", path.display());
+        }
+        let content = fs::read_to_string(path).wrap_err_with(|| {
+            format!("Failed to read constructor args file `{}`", path.display())
+        })?;
+        Self::parse(&content, path.extension().and_then(|ext| ext.to_str()))
+            .wrap_err_with(|| format!("Invalid constructor args file `{}`", path.display()))
+    }
+
+    /// Parses the content of a file with the given extension, `json` and `hex` files are JSON
+    /// arrays and encoded arguments, any other file contains plain arguments
+    fn parse(content: &str, extension: Option<&str>) -> Result<Self> {
+        let content = content.trim();
+        match extension {
+            Some("json") => Ok(Self::Json(serde_json::from_str(content)?)),
+            Some("hex") => {
+                let encoded = hex::decode(content.trim_start_matches("0x"))
+                    .wrap_err("Expected the ABI-encoded arguments as a single hex string")?;
+                Ok(Self::Encoded(encoded))
+            }
+            _ => Ok(Self::Plain(content.split_whitespace().map(str::to_string).collect())),
+        }
+    }
+
+    /// Returns the arguments as tokens of the constructor's input types
+    pub fn tokens(&self, constructor: &Constructor) -> Result<Vec<Token>> {
+        match self {
+            Self::Encoded(encoded) => {
+                let kinds =
+                    constructor.inputs.iter().map(|input| input.kind.clone()).collect::<Vec<_>>();
+                ethers::abi::decode(&kinds, encoded).wrap_err_with(|| {
+                    format!(
+                        "The encoded constructor arguments don't match the constructor's input types ({})",
+                        param_types(constructor)
+                    )
+                })
+            }
+            Self::Json(values) => {
+                check_constructor_args_len(constructor, values.len())?;
+                constructor
+                    .inputs
+                    .iter()
+                    .zip(values)
+                    .enumerate()
+                    .map(|(idx, (input, value))| {
+                        json_token(&input.kind, value, &format!("[{}]", idx)).map_err(|err| {
+                            eyre::eyre!(
+                                "Failed to tokenize constructor argument `{}`: {}",
+                                input.name,
+                                err
+                            )
+                        })
+                    })
+                    .collect()
+            }
+            Self::Plain(args) => parse_constructor_args(constructor, args),
+        }
+    }
+}
+
+/// Tokenizes the JSON value of a constructor argument, values are parsed like the arguments of
+/// `--constructor-args`
+///
+/// `path` locates the value in the JSON array for errors, e.g. `[1][0]`.
+fn json_token(kind: &ParamType, value: &serde_json::Value, path: &str) -> Result<Token> {
+    use serde_json::Value;
+
+    let check_len = |expected: usize, values: &[Value]| {
+        if values.len() != expected {
+            eyre::bail!(
+                "{} must have {} element(s) for `{}`, but has {}",
+                path,
+                expected,
+                kind,
+                values.len()
+            )
+        }
+        Ok(())
+    };
+
+    match (kind, value) {
+        (ParamType::Array(inner), Value::Array(values)) => {
+            Ok(Token::Array(json_tokens(std::iter::repeat(inner.as_ref()), values, path)?))
+        }
+        (ParamType::FixedArray(inner, len), Value::Array(values)) => {
+            check_len(*len, values)?;
+            Ok(Token::FixedArray(json_tokens(std::iter::repeat(inner.as_ref()), values, path)?))
+        }
+        (ParamType::Tuple(kinds), Value::Array(values)) => {
+            check_len(kinds.len(), values)?;
+            Ok(Token::Tuple(json_tokens(kinds.iter(), values, path)?))
+        }
+        (ParamType::Array(_) | ParamType::FixedArray(..) | ParamType::Tuple(_), _) => {
+            eyre::bail!("{} must be an array for `{}`, but is `{}`", path, kind, value)
+        }
+        (_, Value::String(s)) => tokenize(kind, s, path),
+        (_, Value::Number(_) | Value::Bool(_)) => tokenize(kind, &value.to_string(), path),
+        _ => eyre::bail!("{} must be a string for `{}`, but is `{}`", path, kind, value),
+    }
+}
+
+/// Tokenizes the elements of a JSON array, see [json_token()]
+fn json_tokens<'a>(
+    kinds: impl Iterator<Item = &'a ParamType>,
+    values: &[serde_json::Value],
+    path: &str,
+) -> Result<Vec<Token>> {
+    kinds
+        .zip(values)
+        .enumerate()
+        .map(|(idx, (kind, value))| json_token(kind, value, &format!("{}[{}]", path, idx)))
+        .collect()
+}
+
+fn tokenize(kind: &ParamType, value: &str, path: &str) -> Result<Token> {
+    LenientTokenizer::tokenize(kind, value)
+        .map_err(|_| eyre::eyre!("{} `{}` is not a valid `{}`", path, value, kind))
+}
+
+/// Returns the comma separated input types of the constructor
+fn param_types(constructor: &Constructor) -> String {
+    constructor.inputs.iter().map(|input| input.kind.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn check_constructor_args_len(constructor: &Constructor, len: usize) -> Result<()> {
+    if len != constructor.inputs.len() {
+        eyre::bail!(
+            "The constructor takes {} argument(s) ({}), but {} were provided",
+            constructor.inputs.len(),
+            param_types(constructor),
+            len
+        )
+    }
+    Ok(())
+}
+
+/// Parses the constructor arguments into tokens of the constructor's input types
+fn parse_constructor_args(
+    constructor: &Constructor,
+    constructor_args: &[String],
+) -> Result<Vec<Token>> {
+    check_constructor_args_len(constructor, constructor_args.len())?;
+
+    let params = constructor
+        .inputs
+        .iter()
+        .zip(constructor_args)
+        .map(|(input, arg)| (&input.kind, arg.as_str()))
+        .collect::<Vec<_>>();
+
+    parse_tokens(params, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::Param;
+
+    fn constructor() -> Constructor {
+        let param = |name: &str, kind| Param { name: name.to_string(), kind, internal_type: None };
+        Constructor {
+            inputs: vec![
+                param("owner", ParamType::Address),
+                param("supply", ParamType::Uint(256)),
+                param("name", ParamType::String),
+                param("paused", ParamType::Bool),
+            ],
+        }
+    }
+
+    #[test]
+    fn reads_constructor_args_from_file() {
+        let path = std::env::temp_dir().join("forge-create-constructor-args.txt");
+        fs::write(
+            &path,
+            "0x8ba1f109551bd432803012645ac136ddd64dba72 1000000
+  Token
+	true
+",
+        )
+        .unwrap();
+
+        let file = ConstructorArgsFile::read(&path).unwrap();
+        let args = ["0x8ba1f109551bd432803012645ac136ddd64dba72", "1000000", "Token", "true"]
+            .map(str::to_string);
+        assert_eq!(file, ConstructorArgsFile::Plain(args.to_vec()));
+
+        // the values are encoded exactly like the inline `--constructor-args`
+        let inline = ["0x8ba1f109551bd432803012645ac136ddd64dba72", "1000000", "Token", "true"]
+            .map(str::to_string);
+        let tokens = file.tokens(&constructor()).unwrap();
+        assert_eq!(tokens, parse_constructor_args(&constructor(), &inline).unwrap());
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Address("0x8ba1f109551bd432803012645ac136ddd64dba72".parse().unwrap()),
+                Token::Uint(1_000_000u64.into()),
+                Token::String("Token".to_string()),
+                Token::Bool(true),
+            ]
+        );
+
+        fs::write(
+            &path,
+            "0x8ba1f109551bd432803012645ac136ddd64dba72
+1000000
+",
+        )
+        .unwrap();
+        let err = ConstructorArgsFile::read(&path).unwrap().tokens(&constructor()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The constructor takes 4 argument(s) (address, uint256, string, bool), but 2 were provided"
+        );
+    }
+
+    #[test]
+    fn reads_json_constructor_args() {
+        let tokens = vec![
+            Token::Address("0x8ba1f109551bd432803012645ac136ddd64dba72".parse().unwrap()),
+            Token::Uint(U256::exp10(18)),
+            Token::String("Token".to_string()),
+            Token::Bool(true),
+        ];
+        let file = ConstructorArgsFile::parse(
+            r#"["0x8ba1f109551bd432803012645ac136ddd64dba72", "1000000000000000000", "Token", true]"#,
+            Some("json"),
+        )
+        .unwrap();
+        assert_eq!(file.tokens(&constructor()).unwrap(), tokens);
+
+        // nested arrays and tuples
+        let param = |name: &str, kind| Param { name: name.to_string(), kind, internal_type: None };
+        let constructor = Constructor {
+            inputs: vec![
+                param("owners", ParamType::Array(Box::new(ParamType::Address))),
+                param(
+                    "config",
+                    ParamType::Tuple(vec![
+                        ParamType::Uint(8),
+                        ParamType::FixedArray(Box::new(ParamType::Bool), 2),
+                    ]),
+                ),
+            ],
+        };
+        let file = ConstructorArgsFile::parse(
+            r#"[
+                ["0x8ba1f109551bd432803012645ac136ddd64dba72", "0x0000000000000000000000000000000000000001"],
+                [3, [true, false]]
+            ]"#,
+            Some("json"),
+        )
+        .unwrap();
+        assert_eq!(
+            file.tokens(&constructor).unwrap(),
+            vec![
+                Token::Array(vec![
+                    Token::Address("0x8ba1f109551bd432803012645ac136ddd64dba72".parse().unwrap()),
+                    Token::Address(Address::from_low_u64_be(1)),
+                ]),
+                Token::Tuple(vec![
+                    Token::Uint(3u64.into()),
+                    Token::FixedArray(vec![Token::Bool(true), Token::Bool(false)]),
+                ]),
+            ]
+        );
+
+        // errors point at the value that failed
+        let err = ConstructorArgsFile::parse(
+            r#"[["0x8ba1f109551bd432803012645ac136ddd64dba72", "0xzz"], [3, [true, false]]]"#,
+            Some("json"),
+        )
+        .unwrap()
+        .tokens(&constructor)
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to tokenize constructor argument `owners`: [0][1] `0xzz` is not a valid `address`"
+        );
+        let err = ConstructorArgsFile::parse(r#"[[], [3, [true]]]"#, Some("json"))
+            .unwrap()
+            .tokens(&constructor)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to tokenize constructor argument `config`: [1][1] must have 2 element(s) for `bool[2]`, but has 1"
+        );
+    }
+
+    #[test]
+    fn reads_encoded_constructor_args() {
+        let tokens = vec![
+            Token::Address("0x8ba1f109551bd432803012645ac136ddd64dba72".parse().unwrap()),
+            Token::Uint(1_000_000u64.into()),
+            Token::String("Token".to_string()),
+            Token::Bool(true),
+        ];
+        let encoded = ethers::abi::encode(&tokens);
+        let file =
+            ConstructorArgsFile::parse(&format!("0x{}\n", hex::encode(&encoded)), Some("hex"))
+                .unwrap();
+        assert_eq!(file, ConstructorArgsFile::Encoded(encoded));
+        assert_eq!(file.tokens(&constructor()).unwrap(), tokens);
+        assert!(ConstructorArgsFile::parse("0xzz", Some("hex")).is_err());
+
+        // the format isn't guessed from the content of other files
+        let word = format!("0x{}", "11".repeat(32));
+        let file = ConstructorArgsFile::parse(&word, Some("txt")).unwrap();
+        assert_eq!(file, ConstructorArgsFile::Plain(vec![word]));
+        let file = ConstructorArgsFile::parse("[1,2] 0x01", None).unwrap();
+        assert_eq!(file, ConstructorArgsFile::Plain(vec!["[1,2]".to_string(), "0x01".to_string()]));
+    }
+
+    #[test]
+    fn predicts_create_address() {
+        let deployer: Address = "0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0".parse().unwrap();
+        let expected = [
+            "0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d",
+            "0x343c43a37d37dff08ae8c4a11544c718abb4fcf8",
+            "0xf778b86fa74e846c4f0a1fbd1335fe81c00a0c91",
+            "0xfffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c",
+        ];
+        for (nonce, expected) in expected.iter().enumerate() {
+            assert_eq!(
+                deployment_address(deployer, nonce.into(), None, &[]),
+                expected.parse::<Address>().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn predicts_create2_address() {
+        // the examples of EIP-1014, the deployer's nonce is irrelevant
+        let vectors = [
+            (
+                "0x0000000000000000000000000000000000000000",
+                "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "00",
+                "0x4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38",
+            ),
+            (
+                "0xdeadbeef00000000000000000000000000000000",
+                "0x000000000000000000000000feed000000000000000000000000000000000000",
+                "00",
+                "0xD04116cDd17beBE565EB2422F2497E06cC1C9833",
+            ),
+            (
+                "0x00000000000000000000000000000000deadbeef",
+                "0x00000000000000000000000000000000000000000000000000000000cafebabe",
+                "deadbeef",
+                "0x60f3f640a8508fC6a86d45DF051962668E1e8AC7",
+            ),
+            (
+                "0x0000000000000000000000000000000000000000",
+                "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "",
+                "0xE33C0C7F7df4809055C3ebA6c09CFe4BaF1BD9e0",
+            ),
+        ];
+        let deployer: Address = "0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0".parse().unwrap();
+        for (create2_deployer, salt, init_code, expected) in vectors {
+            let create2 = Some((create2_deployer.parse().unwrap(), salt.parse().unwrap()));
+            for nonce in [0u64, 7] {
+                assert_eq!(
+                    deployment_address(
+                        deployer,
+                        nonce.into(),
+                        create2,
+                        &hex::decode(init_code).unwrap()
+                    ),
+                    expected.parse::<Address>().unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn computes_create2_address_from_init_code_hash() {
+        let factory: Address = "0x4e59b44847b379578588920cA78FbF26c0B4956C".parse().unwrap();
+        let salt = parse_salt("0x0000000000000000000000000000000000000000000000000000000000000001")
+            .unwrap();
+        let init_code =
+            hex::decode("6080604052348015600f57600080fd5b50603f80601d6000396000f3fe").unwrap();
+
+        // keccak256(0xff ++ factory ++ salt ++ keccak256(init_code))[12..]
+        let preimage = [
+            &[0xff][..],
+            factory.as_bytes(),
+            salt.as_bytes(),
+            &ethers::utils::keccak256(&init_code),
+        ]
+        .concat();
+        let expected = Address::from_slice(&ethers::utils::keccak256(preimage)[12..]);
+        assert_eq!(
+            deployment_address(Address::zero(), U256::zero(), Some((factory, salt)), &init_code),
+            expected
+        );
+
+        let calldata = create2_calldata(salt, &init_code);
+        assert_eq!(&calldata[..32], salt.as_bytes());
+        assert_eq!(&calldata[32..], init_code.as_slice());
+    }
+
+    #[test]
+    fn appends_constructor_args_to_init_code() {
+        let code = hex::decode("6080604052").unwrap();
+        let args = [Token::Uint(1u64.into())];
+        let with_args = init_code(&code, &args);
+        assert_eq!(&with_args[..5], code.as_slice());
+        assert_eq!(&with_args[5..], ethers::abi::encode(&args).as_slice());
+        assert_eq!(init_code(&code, &[]), code);
+
+        // the constructor arguments change the CREATE2 address
+        let factory: Address = "0x4e59b44847b379578588920cA78FbF26c0B4956C".parse().unwrap();
+        let create2 = Some((factory, H256::zero()));
+        assert_ne!(
+            deployment_address(Address::zero(), U256::zero(), create2, &with_args),
+            deployment_address(Address::zero(), U256::zero(), create2, &code)
+        );
+    }
+
+    #[test]
+    fn formats_etherscan_compiler_version() {
+        let version = |v: &str| etherscan_compiler_version(&v.parse().unwrap());
+        assert_eq!(version("0.8.10+commit.fc410830"), "v0.8.10+commit.fc410830");
+        assert_eq!(version("0.8.10+commit.fc410830.Linux.g++"), "v0.8.10+commit.fc410830");
+        assert_eq!(version("0.8.10"), "v0.8.10");
+    }
+
+    #[test]
+    fn parses_salt() {
+        let mut salt = H256::zero();
+        salt.0[..4].copy_from_slice(&[0xca, 0xfe, 0xba, 0xbe]);
+        assert_eq!(
+            parse_salt("cafebabe00000000000000000000000000000000000000000000000000000000").unwrap(),
+            salt
+        );
+        assert_eq!(
+            parse_salt("0x00000000000000000000000000000000000000000000000000000000cafebabe")
+                .unwrap(),
+            H256::from_low_u64_be(0xcafebabe)
+        );
+        assert_eq!(
+            parse_salt("0xcafebabe").unwrap_err().to_string(),
+            "Invalid salt `0xcafebabe`, expected 32 bytes but got 4"
+        );
+        assert!(parse_salt("0xzz").is_err());
+    }
+
+    #[test]
+    fn force_deploy_is_separate_from_build_force() {
+        let salt = format!("{:?}", H256::zero());
+        let args: CreateArgs = CreateArgs::parse_from([
+            "foundry-cli",
+            "src/Counter.sol:Counter",
+            "--salt",
+            &salt,
+            "--force-deploy",
+        ]);
+        assert!(args.force_deploy);
+        assert!(!args.opts.force);
+
+        let args: CreateArgs = CreateArgs::parse_from([
+            "foundry-cli",
+            "src/Counter.sol:Counter",
+            "--salt",
+            &salt,
+            "--force",
+        ]);
+        assert!(!args.force_deploy);
+        assert!(args.opts.force);
+
+        // only meaningful for CREATE2 deployments
+        assert!(CreateArgs::try_parse_from([
+            "foundry-cli",
+            "src/Counter.sol:Counter",
+            "--force-deploy"
+        ])
+        .is_err());
     }
 }
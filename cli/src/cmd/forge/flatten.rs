@@ -1,23 +1,54 @@
 use super::build::{CoreBuildArgs, ProjectPathsArgs};
-use crate::cmd::Cmd;
+use crate::{cmd::Cmd, utils::FoundryPathExt};
 use clap::{Parser, ValueHint};
+use ethers::solc::{artifacts::Source, Graph, ProjectPathsConfig};
 use foundry_config::Config;
-use std::path::PathBuf;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use semver::Version;
+use std::path::{Path, PathBuf};
+
+/// Matches a single comparator of a solidity version pragma, like `^0.8.0` or `>= 0.8.4`
+static VERSION_COMPARATOR_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(\^|~|>=|<=|>|<|=)?[ \t]*([0-9]+(\.[0-9]+){0,2})").unwrap());
+
+/// Matches import directives, including multi-line `import {A, B} from "..";` directives
+static IMPORT_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?m)^[ \t]*import[ \t\r\n{*"'][^;]*;[ \t]*\r?\n?"#).unwrap());
 
 #[derive(Debug, Clone, Parser)]
 pub struct FlattenArgs {
-    #[clap(help = "The path to the contract to flatten.", value_hint = ValueHint::FilePath)]
-    pub target_path: PathBuf,
+    #[clap(
+        help = "The paths to the contracts to flatten.",
+        long_help = "The paths to the contracts to flatten. Directories and glob patterns like `src/**/*.sol` are expanded to all solidity files they contain.",
+        value_hint = ValueHint::FilePath,
+        required = true
+    )]
+    pub target_path: Vec<PathBuf>,
 
     #[clap(
         long,
         short,
         help = "The path to output the flattened contract.",
-        long_help = "The path to output the flattened contract. If not specified, the flattened contract will be output to stdout.",
-        value_hint = ValueHint::FilePath
+        long_help = "The path to output the flattened contract. If not specified, the flattened contract will be output to stdout. When flattening multiple contracts this is the directory the flattened contracts are written to, each named after its source.",
+        value_hint = ValueHint::AnyPath
     )]
     pub output: Option<PathBuf>,
 
+    #[clap(
+        long,
+        help = "Combine conflicting SPDX license identifiers instead of failing.",
+        long_help = "Combine conflicting SPDX license identifiers of the flattened files into a single `AND` expression instead of failing."
+    )]
+    pub force: bool,
+
+    #[clap(
+        long,
+        help = "Remove all comments except the SPDX license identifier.",
+        long_help = "Remove all comments except the SPDX license identifier, this keeps the flattened file small, e.g. to stay below Etherscan's size limit."
+    )]
+    pub strip_comments: bool,
+
     #[clap(flatten, next_help_heading = "PROJECT OPTIONS")]
     project_paths: ProjectPathsArgs,
 }
@@ -25,7 +56,7 @@ pub struct FlattenArgs {
 impl Cmd for FlattenArgs {
     type Output = ();
     fn run(self) -> eyre::Result<Self::Output> {
-        let FlattenArgs { target_path, output, project_paths } = self;
+        let FlattenArgs { target_path, output, force, strip_comments, project_paths } = self;
 
         // flatten is a subset of `BuildArgs` so we can reuse that to get the config
         let build_args = CoreBuildArgs {
@@ -37,28 +68,477 @@ impl Cmd for FlattenArgs {
             use_solc: None,
             offline: false,
             force: false,
-            libraries: vec![],
-            via_ir: false,
+            locked: false,
             config_path: None,
         };
 
         let config = Config::from(&build_args);
 
         let paths = config.project_paths();
-        let target_path = dunce::canonicalize(target_path)?;
-        let flattened = paths
-            .flatten(&target_path)
-            .map_err(|err| eyre::Error::msg(format!("Failed to flatten the file: {}", err)))?;
-
-        match output {
-            Some(output) => {
-                std::fs::create_dir_all(&output.parent().unwrap())?;
-                std::fs::write(&output, flattened)?;
-                println!("Flattened file written at {}", output.display());
-            }
-            None => println!("{}", flattened),
-        };
+
+        let is_single = target_path.len() == 1 &&
+            !target_path[0].is_dir() &&
+            !is_glob(&target_path[0].to_string_lossy());
+        if is_single {
+            let target_path = dunce::canonicalize(&target_path[0])?;
+            let flattened = flatten(&paths, &target_path, force, strip_comments)
+                .map_err(|err| eyre::Error::msg(format!("Failed to flatten the file: {}", err)))?;
+
+            match output {
+                Some(output) => {
+                    std::fs::create_dir_all(&output.parent().unwrap())?;
+                    std::fs::write(&output, flattened)?;
+                    println!("Flattened file written at {}", output.display());
+                }
+                None => println!("{}", flattened),
+            };
+            return Ok(())
+        }
+
+        let output = output.ok_or_else(|| {
+            eyre::eyre!("`--output <DIR>` is required when flattening multiple contracts")
+        })?;
+        let targets = expand_targets(&target_path)?;
+
+        let mut written = Vec::with_capacity(targets.len());
+        for target in targets {
+            let flattened = flatten(&paths, &target, force, strip_comments)
+                .map_err(|err| eyre::eyre!("Failed to flatten {}: {}", target.display(), err))?;
+            // preserve the directory structure within the sources dir to avoid name clashes
+            let name = target
+                .strip_prefix(&paths.sources)
+                .or_else(|_| target.strip_prefix(&paths.root))
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|_| target.file_name().unwrap().into());
+            let out = output.join(name);
+            std::fs::create_dir_all(&out.parent().unwrap())?;
+            std::fs::write(&out, flattened)?;
+            written.push((target, out));
+        }
+
+        println!("Flattened {} files:", written.len());
+        for (target, out) in written {
+            let target = target.strip_prefix(&paths.root).unwrap_or(&target);
+            println!("  {} -> {}", target.display(), out.display());
+        }
 
         Ok(())
     }
 }
+
+/// Flattens the target and all of its imports into a single file
+///
+/// Imported files are ordered so that every file comes after all the files it imports, which
+/// guarantees that base contracts are declared before the contracts that inherit from them.
+fn flatten(
+    paths: &ProjectPathsConfig,
+    target: &Path,
+    force: bool,
+    strip_comments: bool,
+) -> eyre::Result<String> {
+    let graph = Graph::resolve_sources(paths, Source::read_all_files(vec![target.to_path_buf()])?)?;
+    let target = *graph
+        .files()
+        .get(target)
+        .ok_or_else(|| eyre::eyre!("{} is not part of the import graph", target.display()))?;
+
+    let mut sources = Vec::new();
+    for id in sorted_imports(&graph, target, paths)? {
+        let content = IMPORT_REGEX.replace_all(graph.node(id).content(), "");
+        let content = if strip_comments { remove_comments(&content) } else { content.into_owned() };
+        sources.push(content.trim().to_string());
+    }
+
+    dedup_header(&sources.join("\n\n"), force)
+}
+
+/// Returns the target and all of its imports so that every file comes after all of its imports
+///
+/// Fails if the imports are cyclic, since there's no order in which the flattened file compiles
+fn sorted_imports(
+    graph: &Graph,
+    target: usize,
+    paths: &ProjectPathsConfig,
+) -> eyre::Result<Vec<usize>> {
+    fn visit(
+        graph: &Graph,
+        id: usize,
+        stack: &mut Vec<usize>,
+        sorted: &mut Vec<usize>,
+        paths: &ProjectPathsConfig,
+    ) -> eyre::Result<()> {
+        if sorted.contains(&id) {
+            return Ok(())
+        }
+        if let Some(pos) = stack.iter().position(|other| *other == id) {
+            let cycle = stack[pos..]
+                .iter()
+                .chain(std::iter::once(&id))
+                .map(|id| {
+                    let path = graph.node(*id).path();
+                    path.strip_prefix(&paths.root).unwrap_or(path).display().to_string()
+                })
+                .collect::<Vec<_>>();
+            eyre::bail!("cyclic imports: {}", cycle.join(" -> "))
+        }
+        stack.push(id);
+        for import in graph.imported_nodes(id) {
+            visit(graph, *import, stack, sorted, paths)?;
+        }
+        stack.pop();
+        sorted.push(id);
+        Ok(())
+    }
+
+    let mut sorted = Vec::new();
+    visit(graph, target, &mut Vec::new(), &mut sorted, paths)?;
+    Ok(sorted)
+}
+
+/// Removes all comments from the solidity source, except SPDX license identifiers
+fn remove_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    // whether a comment was removed from the line, per line of `out`
+    let mut commented = vec![false];
+    let mut chars = source.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                // copy string literals verbatim
+                out.push(c);
+                while let Some((_, next)) = chars.next() {
+                    out.push(next);
+                    if next == '\\' {
+                        if let Some((_, escaped)) = chars.next() {
+                            out.push(escaped);
+                        }
+                    } else if next == c {
+                        break
+                    }
+                }
+            }
+            '/' if source[idx..].starts_with("//") || source[idx..].starts_with("/*") => {
+                let end = if source[idx..].starts_with("//") {
+                    source[idx..].find('\n').map(|end| idx + end).unwrap_or(source.len())
+                } else {
+                    source[idx + 2..].find("*/").map(|end| idx + end + 4).unwrap_or(source.len())
+                };
+                if source[idx..end].starts_with("// SPDX-License-Identifier:") {
+                    out.push_str(&source[idx..end]);
+                } else {
+                    *commented.last_mut().unwrap() = true;
+                }
+                while chars.peek().map(|(next, _)| *next < end).unwrap_or_default() {
+                    chars.next();
+                }
+            }
+            '\n' => {
+                out.push(c);
+                commented.push(false);
+            }
+            _ => out.push(c),
+        }
+    }
+
+    // drop the lines that only contained comments
+    out.split('\n')
+        .zip(commented)
+        .filter(|(line, commented)| !(*commented && line.trim().is_empty()))
+        .map(|(line, _)| line.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_string()
+}
+
+/// Replaces the SPDX license identifiers and pragmas of all flattened files with a single header
+///
+/// Conflicting license identifiers are an error unless `force` is set, in which case they're
+/// combined into an `AND` expression. The `pragma solidity` requirements are combined into the
+/// range of versions that satisfies all of them.
+fn dedup_header(flattened: &str, force: bool) -> eyre::Result<String> {
+    let mut licenses: Vec<String> = Vec::new();
+    let mut version_reqs: Vec<String> = Vec::new();
+    let mut pragmas: Vec<String> = Vec::new();
+    let mut body = Vec::new();
+
+    for line in flattened.lines() {
+        let trimmed = line.trim();
+        if let Some(license) = trimmed.strip_prefix("// SPDX-License-Identifier:") {
+            let license = license.trim().to_string();
+            if !licenses.contains(&license) {
+                licenses.push(license);
+            }
+        } else if let Some(req) = parse_pragma(trimmed, "solidity") {
+            if !version_reqs.contains(&req) {
+                version_reqs.push(req);
+            }
+        } else if parse_pragma(trimmed, "abicoder")
+            .or_else(|| parse_pragma(trimmed, "experimental"))
+            .is_some()
+        {
+            let pragma = trimmed.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !pragmas.contains(&pragma) {
+                pragmas.push(pragma);
+            }
+        } else {
+            body.push(line);
+        }
+    }
+
+    if licenses.len() > 1 && !force {
+        eyre::bail!(
+            "conflicting SPDX license identifiers: {}, use `--force` to combine them",
+            licenses.join(", ")
+        )
+    }
+
+    let mut header = Vec::new();
+    if !licenses.is_empty() {
+        header.push(format!("// SPDX-License-Identifier: {}", licenses.join(" AND ")));
+    }
+    if let Some(req) = combine_version_reqs(&version_reqs)? {
+        header.push(format!("pragma solidity {};", req));
+    }
+    header.extend(pragmas);
+
+    Ok(format!("{}\n\n{}", header.join("\n"), body.join("\n").trim_start()))
+}
+
+/// Returns the value of a `pragma <name> <value>;` line
+fn parse_pragma(line: &str, name: &str) -> Option<String> {
+    let value = line.strip_prefix("pragma")?.trim_start().strip_prefix(name)?;
+    if !value.starts_with(char::is_whitespace) {
+        return None
+    }
+    Some(value.trim().strip_suffix(';')?.trim().to_string())
+}
+
+/// A bound of a version range, the `bool` is true if the bound is inclusive
+type Bound = Option<(Version, bool)>;
+
+/// Combines all solidity version requirements into a single requirement that's satisfied by all
+/// versions that satisfy each of them
+fn combine_version_reqs(reqs: &[String]) -> eyre::Result<Option<String>> {
+    match reqs {
+        [] => return Ok(None),
+        [req] => return Ok(Some(req.clone())),
+        _ => {}
+    }
+
+    let mut lower: Bound = None;
+    let mut upper: Bound = None;
+    for req in reqs {
+        if req.contains("||") {
+            eyre::bail!("can't combine version requirement `{}` containing `||`", req)
+        }
+        let mut matched = false;
+        for cap in VERSION_COMPARATOR_REGEX.captures_iter(req) {
+            matched = true;
+            let version = parse_partial_version(&cap[2])?;
+            match cap.get(1).map(|op| op.as_str()).unwrap_or("=") {
+                "=" => {
+                    raise(&mut lower, version.clone(), true);
+                    lower_upper(&mut upper, version, true);
+                }
+                ">=" => raise(&mut lower, version, true),
+                ">" => raise(&mut lower, version, false),
+                "<=" => lower_upper(&mut upper, version, true),
+                "<" => lower_upper(&mut upper, version, false),
+                "^" => {
+                    let next = if version.major > 0 {
+                        Version::new(version.major + 1, 0, 0)
+                    } else if version.minor > 0 {
+                        Version::new(0, version.minor + 1, 0)
+                    } else {
+                        Version::new(0, 0, version.patch + 1)
+                    };
+                    raise(&mut lower, version, true);
+                    lower_upper(&mut upper, next, false);
+                }
+                _ => {
+                    let next = Version::new(version.major, version.minor + 1, 0);
+                    raise(&mut lower, version, true);
+                    lower_upper(&mut upper, next, false);
+                }
+            }
+        }
+        if !matched {
+            eyre::bail!("invalid version requirement `{}`", req)
+        }
+    }
+
+    let is_empty = match (&lower, &upper) {
+        (Some((lower, lower_inclusive)), Some((upper, upper_inclusive))) => {
+            lower > upper || (lower == upper && !(*lower_inclusive && *upper_inclusive))
+        }
+        _ => false,
+    };
+    if is_empty {
+        eyre::bail!("the version requirements {} have no version in common", reqs.join(", "))
+    }
+
+    if let (Some((lower, true)), Some((upper, true))) = (&lower, &upper) {
+        if lower == upper {
+            return Ok(Some(lower.to_string()))
+        }
+    }
+    let lower = lower.map(|(v, inclusive)| format!("{}{}", if inclusive { ">=" } else { ">" }, v));
+    let upper = upper.map(|(v, inclusive)| format!("{}{}", if inclusive { "<=" } else { "<" }, v));
+    Ok(Some(lower.into_iter().chain(upper).collect::<Vec<_>>().join(" ")))
+}
+
+/// Raises the lower bound to the given version if it's stricter
+fn raise(bound: &mut Bound, version: Version, inclusive: bool) {
+    let stricter = match bound {
+        Some((current, current_inclusive)) => {
+            version > *current || (version == *current && *current_inclusive && !inclusive)
+        }
+        None => true,
+    };
+    if stricter {
+        *bound = Some((version, inclusive));
+    }
+}
+
+/// Lowers the upper bound to the given version if it's stricter
+fn lower_upper(bound: &mut Bound, version: Version, inclusive: bool) {
+    let stricter = match bound {
+        Some((current, current_inclusive)) => {
+            version < *current || (version == *current && *current_inclusive && !inclusive)
+        }
+        None => true,
+    };
+    if stricter {
+        *bound = Some((version, inclusive));
+    }
+}
+
+/// Parses versions like `0.8` as `0.8.0`
+fn parse_partial_version(version: &str) -> eyre::Result<Version> {
+    let mut parts = version.split('.').map(str::parse::<u64>);
+    let mut next = || parts.next().transpose().map(Option::unwrap_or_default);
+    Ok(Version::new(next()?, next()?, next()?))
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(|c| matches!(c, '*' | '?' | '['))
+}
+
+/// Expands all directories and glob patterns to the solidity files they contain
+fn expand_targets(targets: &[PathBuf]) -> eyre::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for target in targets {
+        let pattern = target.to_string_lossy();
+        if is_glob(&pattern) {
+            let matches = glob::glob(&pattern)?
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .filter(|path| path.is_sol())
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                eyre::bail!("no solidity files match `{}`", pattern)
+            }
+            files.extend(matches);
+        } else if target.is_dir() {
+            for entry in walkdir::WalkDir::new(target) {
+                let entry = entry?;
+                if entry.file_type().is_file() && entry.path().is_sol() {
+                    files.push(entry.into_path());
+                }
+            }
+        } else {
+            files.push(target.clone());
+        }
+    }
+
+    let mut files = files.into_iter().map(dunce::canonicalize).collect::<Result<Vec<_>, _>>()?;
+    files.sort_unstable();
+    files.dedup();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combines_version_reqs() {
+        [
+            (vec![], None),
+            (vec!["^0.8.0"], Some("^0.8.0")),
+            (vec!["^0.8.0", ">=0.8.4 <0.9.0"], Some(">=0.8.4 <0.9.0")),
+            (vec![">=0.6.2 <0.9.0", "^0.8.0", ">= 0.8.10"], Some(">=0.8.10 <0.9.0")),
+            (vec!["0.8.10", "^0.8.0"], Some("0.8.10")),
+            (vec!["~0.8.4", "<=0.8.10"], Some(">=0.8.4 <=0.8.10")),
+            (vec![">0.8.0", ">=0.8.0"], Some(">0.8.0")),
+        ]
+        .into_iter()
+        .for_each(|(reqs, expected)| {
+            let reqs = reqs.into_iter().map(str::to_string).collect::<Vec<_>>();
+            assert_eq!(combine_version_reqs(&reqs).unwrap().as_deref(), expected);
+        });
+
+        [vec!["^0.7.0", "^0.8.0"], vec!["0.8.10", ">0.8.10"], vec!["^0.8.0", "^0.7.0 || ^0.8.0"]]
+            .into_iter()
+            .for_each(|reqs| {
+                let reqs = reqs.into_iter().map(str::to_string).collect::<Vec<_>>();
+                assert!(combine_version_reqs(&reqs).is_err());
+            });
+    }
+
+    #[test]
+    fn removes_comments() {
+        let source = r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+/// @notice natspec
+contract A {
+    /* block
+       comment */
+    string s = "not // a comment"; // trailing
+    string t = 'not /* a comment */ either';
+}
+"#;
+        let expected = r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+contract A {
+    string s = "not // a comment";
+    string t = 'not /* a comment */ either';
+}"#;
+        assert_eq!(remove_comments(source), expected);
+    }
+
+    #[test]
+    fn dedups_header() {
+        let flattened = r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+pragma abicoder v2;
+
+contract A {}
+
+// SPDX-License-Identifier: MIT
+pragma solidity >=0.8.4 <0.9.0;
+pragma experimental ABIEncoderV2;
+pragma abicoder   v2;
+
+contract B is A {}
+"#;
+        let expected = r#"// SPDX-License-Identifier: MIT
+pragma solidity >=0.8.4 <0.9.0;
+pragma abicoder v2;
+pragma experimental ABIEncoderV2;
+
+contract A {}
+
+
+contract B is A {}"#;
+        assert_eq!(dedup_header(flattened, false).unwrap(), expected);
+
+        let flattened = flattened.replacen("MIT", "GPL-3.0", 1);
+        assert!(dedup_header(&flattened, false).is_err());
+        let combined = dedup_header(&flattened, true).unwrap();
+        assert!(combined.starts_with("// SPDX-License-Identifier: GPL-3.0 AND MIT\n"));
+    }
+}
@@ -1,27 +1,45 @@
-use std::{fmt::Write, path::PathBuf};
+use std::{
+    fmt::Write,
+    io::Read,
+    path::{Path, PathBuf},
+};
 
-use clap::Parser;
+use clap::{Parser, ValueHint};
 use console::{style, Style};
-use ethers::solc::ProjectPathsConfig;
+use foundry_config::{load_config_with_root, Config};
 
 use rayon::prelude::*;
 use similar::{ChangeTag, TextDiff};
 
 use forge_fmt::{Formatter, FormatterConfig, Visitable};
 
-use crate::cmd::Cmd;
+use crate::{cmd::Cmd, utils::FoundryPathExt};
 
 #[derive(Debug, Clone, Parser)]
 pub struct FmtArgs {
-    #[clap(help = "path to the file or directory", conflicts_with = "root")]
+    #[clap(
+        help = "path to the file or directory, or `-` to read the source from stdin",
+        conflicts_with = "root",
+        value_hint = ValueHint::FilePath
+    )]
     path: Option<PathBuf>,
-    #[clap(help = "project's root path, default being the current working directory", long)]
+    #[clap(
+        help = "project's root path, default being the current working directory",
+        long,
+        value_hint = ValueHint::DirPath
+    )]
     root: Option<PathBuf>,
     #[clap(
         help = "run in 'check' mode. Exits with 0 if input is formatted correctly. Exits with 1 if formatting is required.",
         long
     )]
     check: bool,
+    #[clap(
+        help = "print the formatted source to stdout instead of writing it to the files",
+        long,
+        conflicts_with = "check"
+    )]
+    raw: bool,
 }
 
 struct Line(Option<usize>);
@@ -35,117 +53,231 @@ impl std::fmt::Display for Line {
     }
 }
 
+/// The source to format
+enum Input {
+    Stdin(String),
+    Paths(Vec<PathBuf>),
+}
+
+impl FmtArgs {
+    /// Returns the source to format
+    ///
+    /// Without a path, all solidity files of the project's `src`, `test` and `script`
+    /// directories are formatted.
+    fn input(&self, config: &Config) -> eyre::Result<Input> {
+        let path = match self.path {
+            Some(ref path) if path == Path::new("-") => {
+                let mut source = String::new();
+                std::io::stdin().read_to_string(&mut source)?;
+                return Ok(Input::Stdin(source))
+            }
+            Some(ref path) => path.clone(),
+            None => {
+                let dirs =
+                    [config.src.clone(), config.test.clone(), config.__root.0.join("script")];
+                let mut paths = dirs
+                    .iter()
+                    .filter(|dir| dir.is_dir())
+                    .flat_map(ethers::solc::utils::source_files)
+                    .collect::<Vec<_>>();
+                paths.sort();
+                paths.dedup();
+                return Ok(Input::Paths(paths))
+            }
+        };
+
+        if path.is_dir() {
+            Ok(Input::Paths(ethers::solc::utils::source_files(path)))
+        } else if path.is_sol() {
+            Ok(Input::Paths(vec![path]))
+        } else {
+            eyre::bail!("{} is not a solidity file or directory", path.display())
+        }
+    }
+}
+
 impl Cmd for FmtArgs {
     type Output = ();
 
     fn run(self) -> eyre::Result<Self::Output> {
-        let root = if let Some(path) = self.path {
-            path
-        } else {
-            let root = self.root.unwrap_or_else(|| {
-                std::env::current_dir().expect("failed to get current directory")
-            });
+        if let Some(ref root) = self.root {
             if !root.is_dir() {
                 return Err(eyre::eyre!("Root path should be a directory"))
             }
-
-            ProjectPathsConfig::find_source_dir(&root)
+        }
+        let config = load_config_with_root(self.root.clone());
+        let fmt_config = FormatterConfig {
+            line_length: config.fmt.line_length,
+            tab_width: config.fmt.tab_width,
+            bracket_spacing: config.fmt.bracket_spacing,
         };
 
-        let paths = if root.is_dir() {
-            ethers::solc::utils::source_files(root)
-        } else if root.file_name().unwrap().to_string_lossy().ends_with(".sol") {
-            vec![root]
-        } else {
-            vec![]
+        let paths = match self.input(&config)? {
+            Input::Stdin(source) => {
+                let output = format(&source, fmt_config).map_err(|err| {
+                    eyre::eyre!("Failed to format Solidity code from stdin.\n{}", err)
+                })?;
+                if self.check {
+                    if let Some(diff) = diff_summary("stdin", &source, &output)? {
+                        print!("{}", diff);
+                        std::process::exit(1);
+                    }
+                } else {
+                    print!("{}", output);
+                }
+                return Ok(())
+            }
+            Input::Paths(paths) => paths,
         };
 
-        let diffs = paths
+        let outputs = paths
             .par_iter()
-            .enumerate()
-            .map(|(i, path)| {
+            .map(|path| {
                 let source = std::fs::read_to_string(&path)?;
-                let (mut source_unit, _comments) = solang_parser::parse(&source, i)
-                    .map_err(|diags| eyre::eyre!(
-                            "Failed to parse Solidity code for {}. Leaving source unchanged.\nDebug info: {:?}",
-                            path.to_string_lossy(),
-                            diags
-                        ))?;
-
-                let mut output = String::new();
-                let mut formatter =
-                    Formatter::new(&mut output, &source, FormatterConfig::default());
-
-                source_unit.visit(&mut formatter).unwrap();
-
-                solang_parser::parse(&output, 0).map_err(|diags| {
+                let output = format(&source, fmt_config.clone()).map_err(|err| {
                     eyre::eyre!(
-                            "Failed to construct valid Solidity code for {}. Leaving source unchanged.\nDebug info: {:?}",
-                            path.to_string_lossy(),
-                            diags
-                        )
+                        "Failed to format {}. Leaving source unchanged.\n{}",
+                        path.to_string_lossy(),
+                        err
+                    )
                 })?;
 
                 if self.check {
-                    let diff = TextDiff::from_lines(&source, &output);
-
-                    if diff.ratio() < 1.0 {
-                        let mut diff_summary = String::new();
-
-                        writeln!(diff_summary, "Diff in {}:", path.to_string_lossy())?;
-                        for (j, group) in diff.grouped_ops(3).iter().enumerate() {
-                            if j > 0 {
-                                writeln!(diff_summary, "{:-^1$}", "-", 80)?;
-                            }
-                            for op in group {
-                                for change in diff.iter_inline_changes(op) {
-                                    let (sign, s) = match change.tag() {
-                                        ChangeTag::Delete => ("-", Style::new().red()),
-                                        ChangeTag::Insert => ("+", Style::new().green()),
-                                        ChangeTag::Equal => (" ", Style::new().dim()),
-                                    };
-                                    write!(
-                                        diff_summary,
-                                        "{}{} |{}",
-                                        style(Line(change.old_index())).dim(),
-                                        style(Line(change.new_index())).dim(),
-                                        s.apply_to(sign).bold(),
-                                    )?;
-                                    for (emphasized, value) in change.iter_strings_lossy() {
-                                        if emphasized {
-                                            write!(diff_summary, "{}", s.apply_to(value).underlined().on_black())?;
-                                        } else {
-                                            write!(diff_summary, "{}", s.apply_to(value))?;
-                                        }
-                                    }
-                                    if change.missing_newline() {
-                                        writeln!(diff_summary)?;
-                                    }
-                                }
-                            }
-                        }
-
-                        return Ok(Some(diff_summary))
-                    }
-                } else {
+                    return diff_summary(&path.to_string_lossy(), &source, &output)
+                }
+                if self.raw {
+                    return Ok(Some(output))
+                }
+                if source != output {
                     std::fs::write(path, output)?;
+                    return Ok(Some(format!("Formatted {}", display_path(path, &config))))
                 }
 
                 Ok(None)
             })
             .collect::<eyre::Result<Vec<Option<String>>>>()?;
 
-        if !diffs.is_empty() {
-            for (i, diff) in diffs.iter().flatten().enumerate() {
-                if i > 0 {
-                    println!();
+        if self.check {
+            let diffs = outputs.into_iter().flatten().collect::<Vec<_>>();
+            if !diffs.is_empty() {
+                for (i, diff) in diffs.iter().enumerate() {
+                    if i > 0 {
+                        println!();
+                    }
+                    print!("{}", diff);
                 }
-                print!("{}", diff);
-            }
 
-            std::process::exit(1);
+                std::process::exit(1);
+            }
+        } else if self.raw {
+            for output in outputs.into_iter().flatten() {
+                print!("{}", output);
+            }
+        } else {
+            for formatted in outputs.into_iter().flatten() {
+                println!("{}", formatted);
+            }
         }
 
         Ok(())
     }
 }
+
+/// Formats the solidity source
+///
+/// Returns an error if the source can't be parsed or if formatting doesn't produce valid
+/// solidity.
+pub fn format(source: &str, config: FormatterConfig) -> eyre::Result<String> {
+    let (mut source_unit, _comments) = solang_parser::parse(source, 0)
+        .map_err(|diags| eyre::eyre!("Failed to parse Solidity code.\nDebug info: {:?}", diags))?;
+
+    let mut output = String::new();
+    let mut formatter = Formatter::new(&mut output, source, config);
+    source_unit.visit(&mut formatter).map_err(|err| eyre::eyre!("{}", err))?;
+
+    solang_parser::parse(&output, 0).map_err(|diags| {
+        eyre::eyre!("Failed to construct valid Solidity code.\nDebug info: {:?}", diags)
+    })?;
+
+    Ok(output)
+}
+
+fn display_path(path: &Path, config: &Config) -> String {
+    path.strip_prefix(&config.__root.0).unwrap_or(path).display().to_string()
+}
+
+/// Returns the colored diff between the source and its formatted output, if they differ
+fn diff_summary(name: &str, source: &str, output: &str) -> eyre::Result<Option<String>> {
+    let diff = TextDiff::from_lines(source, output);
+    if diff.ratio() >= 1.0 {
+        return Ok(None)
+    }
+
+    let mut diff_summary = String::new();
+    writeln!(diff_summary, "Diff in {}:", name)?;
+    for (j, group) in diff.grouped_ops(3).iter().enumerate() {
+        if j > 0 {
+            writeln!(diff_summary, "{:-^1$}", "-", 80)?;
+        }
+        for op in group {
+            for change in diff.iter_inline_changes(op) {
+                let (sign, s) = match change.tag() {
+                    ChangeTag::Delete => ("-", Style::new().red()),
+                    ChangeTag::Insert => ("+", Style::new().green()),
+                    ChangeTag::Equal => (" ", Style::new().dim()),
+                };
+                write!(
+                    diff_summary,
+                    "{}{} |{}",
+                    style(Line(change.old_index())).dim(),
+                    style(Line(change.new_index())).dim(),
+                    s.apply_to(sign).bold(),
+                )?;
+                for (emphasized, value) in change.iter_strings_lossy() {
+                    if emphasized {
+                        write!(diff_summary, "{}", s.apply_to(value).underlined().on_black())?;
+                    } else {
+                        write!(diff_summary, "{}", s.apply_to(value))?;
+                    }
+                }
+                if change.missing_newline() {
+                    writeln!(diff_summary)?;
+                }
+            }
+        }
+    }
+
+    Ok(Some(diff_summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures() -> Vec<PathBuf> {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../testdata/fixtures/Fmt");
+        let mut fixtures = ethers::solc::utils::source_files(dir);
+        fixtures.sort();
+        fixtures
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        let fixtures = fixtures();
+        assert!(!fixtures.is_empty());
+        for fixture in fixtures {
+            let source = std::fs::read_to_string(&fixture).unwrap();
+            let formatted = format(&source, FormatterConfig::default()).unwrap();
+            let reformatted = format(&formatted, FormatterConfig::default()).unwrap();
+            pretty_assertions::assert_eq!(formatted, reformatted, "{}", fixture.display());
+        }
+    }
+
+    #[test]
+    fn respects_tab_width() {
+        let source = "contract A {\n    enum E {\n        X\n    }\n}\n";
+        let config = FormatterConfig { tab_width: 2, ..Default::default() };
+        let formatted = format(source, config).unwrap();
+        assert!(formatted.contains("\n  enum E {\n    X\n  }\n"), "{}", formatted);
+    }
+}
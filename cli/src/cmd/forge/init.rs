@@ -1,7 +1,10 @@
 //! init command
 
 use crate::{
-    cmd::{forge::install::install, Cmd},
+    cmd::{
+        forge::install::{install, install_as_folder},
+        Cmd,
+    },
     opts::forge::Dependency,
     utils::p_println,
 };
@@ -25,11 +28,16 @@ pub struct InitArgs {
         value_hint = ValueHint::DirPath
     )]
     root: Option<PathBuf>,
-    #[clap(help = "The template to start from.", long, short)]
+    /// The template to start from.
+    ///
+    /// The template is a git repository in any format that `forge install` accepts, e.g.
+    /// `org/repo`, `org/repo@tag` or a url. Its files are copied into the new project without its
+    /// git history, its submodules are copied as plain folders.
+    #[clap(long, short, value_name = "TEMPLATE")]
     template: Option<String>,
-    #[clap(help = "Do not create a git repository.", conflicts_with = "template", long)]
+    #[clap(help = "Do not create a git repository.", long)]
     no_git: bool,
-    #[clap(help = "Do not create an initial commit.", conflicts_with = "template", long)]
+    #[clap(help = "Do not create an initial commit.", long)]
     no_commit: bool,
     #[clap(help = "Do not print any messages.", short, long)]
     quiet: bool,
@@ -40,15 +48,10 @@ pub struct InitArgs {
         alias = "no-deps"
     )]
     offline: bool,
-    #[clap(
-        help = "Create the project even if the specified root directory is not empty.",
-        conflicts_with = "template",
-        long
-    )]
+    #[clap(help = "Create the project even if the specified root directory is not empty.", long)]
     force: bool,
     #[clap(
         help = "Create a .vscode/settings.json file with Solidity settings, and generate a remappings.txt file.",
-        conflicts_with = "template",
         long
     )]
     vscode: bool,
@@ -67,32 +70,23 @@ impl Cmd for InitArgs {
         }
         let root = dunce::canonicalize(root)?;
 
-        // if a template is provided, then this command is just an alias to `git clone <url>
-        // <path>`
-        if let Some(template) = template {
-            let template = if template.starts_with("https://") {
-                template
-            } else {
-                "https://github.com/".to_string() + &template
-            };
-            p_println!(!quiet => "Initializing {} from {}...", root.display(), template);
-            Command::new("git")
-                .args(&["clone", "--recursive", &template, &root.display().to_string()])
-                .stdout(Stdio::piped())
-                .spawn()?
-                .wait()?;
-        } else {
-            // check if target is empty
-            if !force && root.read_dir().map(|mut i| i.next().is_some()).unwrap_or(false) {
-                eprintln!(
-                    r#"{}: `forge init` cannot be run on a non-empty directory.
+        // check if target is empty
+        if !force && root.read_dir().map(|mut i| i.next().is_some()).unwrap_or(false) {
+            eprintln!(
+                r#"{}: `forge init` cannot be run on a non-empty directory.
 
         run `forge init --force` to initialize regardless."#,
-                    Colour::Red.paint("error")
-                );
-                std::process::exit(1);
-            }
+                Colour::Red.paint("error")
+            );
+            std::process::exit(1);
+        }
 
+        let from_template = template.is_some();
+        if let Some(template) = template {
+            let template = Dependency::from_str(&template)?;
+            p_println!(!quiet => "Initializing {} from {}...", root.display(), template.url);
+            init_from_template(&root, template, quiet)?;
+        } else {
             p_println!(!quiet => "Initializing {}...", root.display());
 
             // make the dirs
@@ -109,35 +103,36 @@ impl Cmd for InitArgs {
                 contract_path,
                 include_str!("../../../../assets/ContractTemplate.t.sol"),
             )?;
+        }
 
-            let dest = root.join(Config::FILE_NAME);
-            if !dest.exists() {
-                // write foundry.toml
-                let config = Config::load_with_root(&root).into_basic();
-                std::fs::write(dest, config.to_string_pretty()?)?;
-            }
+        let dest = root.join(Config::FILE_NAME);
+        if !dest.exists() {
+            // write foundry.toml
+            let config = Config::load_with_root(&root).into_basic();
+            std::fs::write(dest, config.to_string_pretty()?)?;
+        }
 
-            // sets up git
-            if !no_git {
-                init_git_repo(&root, no_commit)?;
-            }
+        // sets up git
+        if !no_git {
+            init_git_repo(&root, no_commit)?;
+        }
 
-            if !offline {
-                let opts = DependencyInstallOpts { no_git, no_commit, quiet };
+        // templates bring their own dependencies
+        if !offline && !from_template {
+            let opts = DependencyInstallOpts { no_git, no_commit, quiet, ..Default::default() };
 
-                if root.join("lib/ds-test").exists() {
-                    println!("\"lib/ds-test\" already exists, skipping install....");
-                    install(&root, vec![], opts)?;
-                } else {
-                    Dependency::from_str("https://github.com/dapphub/ds-test")
-                        .and_then(|dependency| install(&root, vec![dependency], opts))?;
-                }
-            }
-            // vscode init
-            if vscode {
-                init_vscode(&root)?;
+            if root.join("lib/ds-test").exists() {
+                println!("\"lib/ds-test\" already exists, skipping install....");
+                install(&root, vec![], opts, None)?;
+            } else {
+                Dependency::from_str("https://github.com/dapphub/ds-test")
+                    .and_then(|dependency| install(&root, vec![dependency], opts, None))?;
             }
         }
+        // vscode init
+        if vscode {
+            init_vscode(&root)?;
+        }
 
         p_println!(!quiet => "    {} forge project.",   Colour::Green.paint("Initialized"));
         Ok(())
@@ -155,8 +150,11 @@ fn init_git_repo(root: &Path, no_commit: bool) -> eyre::Result<()> {
         .wait()?;
 
     if !is_git.success() {
+        // keep the `.gitignore` of a template
         let gitignore_path = root.join(".gitignore");
-        std::fs::write(gitignore_path, include_str!("../../../../assets/.gitignoreTemplate"))?;
+        if !gitignore_path.exists() {
+            std::fs::write(gitignore_path, include_str!("../../../../assets/.gitignoreTemplate"))?;
+        }
 
         Command::new("git")
             .arg("init")
@@ -180,18 +178,60 @@ fn init_git_repo(root: &Path, no_commit: bool) -> eyre::Result<()> {
     Ok(())
 }
 
+/// Copies the files of the template repository into the root dir, without its git history
+///
+/// The template is cloned into a temporary directory first, so the root dir may already contain
+/// files if `--force` is used.
+fn init_from_template(root: &Path, template: Dependency, quiet: bool) -> eyre::Result<()> {
+    if template.path.is_none() && template.is_archive() {
+        eyre::bail!("The template {} has to be a git repository", template.url)
+    }
+
+    let tmp = std::env::temp_dir().join(format!("forge-template-{}", std::process::id()));
+    if tmp.exists() {
+        std::fs::remove_dir_all(&tmp)?;
+    }
+    std::fs::create_dir_all(&tmp)?;
+
+    let subdir = template.subdir.clone();
+    let template = Dependency { name: "template".to_string(), ..template };
+    let res = install_as_folder(&template, &tmp, true, quiet).and_then(|_| {
+        let mut src = tmp.join(&template.name);
+        if let Some(subdir) = subdir {
+            src = src.join(subdir);
+        }
+        copy_dir(&src, root)
+    });
+    let _ = std::fs::remove_dir_all(&tmp);
+    res
+}
+
+/// Copies all files in `src` into `dest`, existing files are overwritten
+fn copy_dir(src: &Path, dest: &Path) -> eyre::Result<()> {
+    for entry in walkdir::WalkDir::new(src).min_depth(1) {
+        let entry = entry?;
+        let target = dest.join(entry.path().strip_prefix(src)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
 /// initializes the `.vscode/settings.json` file
+///
+/// Settings that already exist in the file are kept, `.vscode` is added to the `.gitignore`.
 fn init_vscode(root: &Path) -> eyre::Result<()> {
+    let remappings = remappings::relative_remappings(&root.join("lib"), root)
+        .into_iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>();
     let remappings_file = root.join("remappings.txt");
-    if !remappings_file.exists() {
-        let remappings = remappings::relative_remappings(&root.join("lib"), root)
-            .into_iter()
-            .map(|r| r.to_string())
-            .collect::<Vec<_>>();
-        if !remappings.is_empty() {
-            let content = remappings.join("\n");
-            std::fs::write(remappings_file, content)?;
-        }
+    if !remappings_file.exists() && !remappings.is_empty() {
+        let content = remappings.join("\n");
+        std::fs::write(remappings_file, content)?;
     }
 
     let vscode_dir = root.join(".vscode");
@@ -215,9 +255,20 @@ fn init_vscode(root: &Path) -> eyre::Result<()> {
     if !obj.contains_key(lib_key) {
         obj.insert(lib_key.to_string(), serde_json::Value::String("lib".to_string()));
     }
+    let remappings_key = "solidity.remappings";
+    if !obj.contains_key(remappings_key) && !remappings.is_empty() {
+        obj.insert(remappings_key.to_string(), remappings.into());
+    }
 
     let content = serde_json::to_string_pretty(&settings)?;
     std::fs::write(settings_file, content)?;
 
+    let gitignore_path = root.join(".gitignore");
+    let gitignore = std::fs::read_to_string(&gitignore_path).unwrap_or_default();
+    if !gitignore.lines().any(|line| matches!(line.trim(), ".vscode" | ".vscode/" | "/.vscode")) {
+        let separator = if gitignore.is_empty() || gitignore.ends_with('\n') { "" } else { "\n" };
+        std::fs::write(gitignore_path, format!("{}{}.vscode/\n", gitignore, separator))?;
+    }
+
     Ok(())
 }
@@ -7,21 +7,27 @@ use crate::{
     opts::forge::CompilerArgs,
 };
 use clap::Parser;
-use ethers::prelude::artifacts::output_selection::{
-    ContractOutputSelection, EvmOutputSelection, EwasmOutputSelection,
+use ethers::{
+    abi::{AbiError, Event},
+    prelude::artifacts::output_selection::{
+        ContractOutputSelection, EvmOutputSelection, EwasmOutputSelection,
+    },
 };
 use serde_json::{to_value, Value};
-use std::{fmt, str::FromStr};
+use std::{collections::BTreeMap, fmt, str::FromStr};
 
 /// Contract level output selection
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum ContractArtifactFields {
     Abi,
+    Events,
+    Errors,
     Bytecode,
     DeployedBytecode,
     Assembly,
     AssemblyOptimized,
     MethodIdentifiers,
+    Selectors,
     GasEstimates,
     StorageLayout,
     DevDoc,
@@ -36,11 +42,14 @@ impl fmt::Display for ContractArtifactFields {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ContractArtifactFields::Abi => f.write_str("abi"),
+            ContractArtifactFields::Events => f.write_str("events"),
+            ContractArtifactFields::Errors => f.write_str("errors"),
             ContractArtifactFields::Bytecode => f.write_str("bytecode"),
             ContractArtifactFields::DeployedBytecode => f.write_str("deployedBytecode"),
             ContractArtifactFields::Assembly => f.write_str("assembly"),
             ContractArtifactFields::AssemblyOptimized => f.write_str("assemblyOptimized"),
             ContractArtifactFields::MethodIdentifiers => f.write_str("methodIdentifiers"),
+            ContractArtifactFields::Selectors => f.write_str("selectors"),
             ContractArtifactFields::GasEstimates => f.write_str("gasEstimates"),
             ContractArtifactFields::StorageLayout => f.write_str("storageLayout"),
             ContractArtifactFields::DevDoc => f.write_str("devdoc"),
@@ -59,6 +68,8 @@ impl FromStr for ContractArtifactFields {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "abi" => Ok(ContractArtifactFields::Abi),
+            "events" | "ev" => Ok(ContractArtifactFields::Events),
+            "errors" | "er" => Ok(ContractArtifactFields::Errors),
             "b" | "bytes" | "bytecode" => Ok(ContractArtifactFields::Bytecode),
             "deployedBytecode" | "deployed_bytecode" | "deployed-bytecode" | "deployed" |
             "deployedbytecode" => Ok(ContractArtifactFields::DeployedBytecode),
@@ -68,6 +79,7 @@ impl FromStr for ContractArtifactFields {
             "asm_optimized" => Ok(ContractArtifactFields::AssemblyOptimized),
             "methods" | "methodidentifiers" | "methodIdentifiers" | "method_identifiers" |
             "method-identifiers" | "mi" => Ok(ContractArtifactFields::MethodIdentifiers),
+            "selectors" | "sigs" => Ok(ContractArtifactFields::Selectors),
             "gasEstimates" | "gas" | "gas_estimates" | "gas-estimates" | "gasestimates" => {
                 Ok(ContractArtifactFields::GasEstimates)
             }
@@ -87,6 +99,87 @@ impl FromStr for ContractArtifactFields {
     }
 }
 
+impl ContractArtifactFields {
+    /// Returns the compiler output that needs to be requested for the field, if it isn't part of
+    /// every artifact
+    pub fn output_selection(&self) -> Option<ContractOutputSelection> {
+        let selection = match self {
+            ContractArtifactFields::Abi |
+            ContractArtifactFields::Events |
+            ContractArtifactFields::Errors => ContractOutputSelection::Abi,
+            ContractArtifactFields::Bytecode => return None,
+            ContractArtifactFields::DeployedBytecode => return None,
+            ContractArtifactFields::Assembly | ContractArtifactFields::AssemblyOptimized => {
+                ContractOutputSelection::Evm(EvmOutputSelection::Assembly)
+            }
+            ContractArtifactFields::MethodIdentifiers | ContractArtifactFields::Selectors => {
+                ContractOutputSelection::Evm(EvmOutputSelection::MethodIdentifiers)
+            }
+            ContractArtifactFields::GasEstimates => {
+                ContractOutputSelection::Evm(EvmOutputSelection::GasEstimates)
+            }
+            ContractArtifactFields::StorageLayout => ContractOutputSelection::StorageLayout,
+            ContractArtifactFields::DevDoc => ContractOutputSelection::DevDoc,
+            ContractArtifactFields::Ir => ContractOutputSelection::Ir,
+            ContractArtifactFields::IrOptimized => ContractOutputSelection::IrOptimized,
+            ContractArtifactFields::Metadata => ContractOutputSelection::Metadata,
+            ContractArtifactFields::UserDoc => ContractOutputSelection::UserDoc,
+            ContractArtifactFields::Ewasm => {
+                ContractOutputSelection::Ewasm(EwasmOutputSelection::All)
+            }
+        };
+        Some(selection)
+    }
+}
+
+/// Maps every `0x` prefixed 4-byte selector to its function signature, the inverse of solc's
+/// method identifiers
+fn selectors(method_identifiers: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    method_identifiers
+        .iter()
+        .map(|(signature, selector)| (format!("0x{}", selector), signature.clone()))
+        .collect()
+}
+
+/// Returns the event entries of the ABI, each with the `topic0` hash of its signature
+fn events(abi: &Value) -> eyre::Result<Vec<Value>> {
+    abi_entries(abi, "event", |entry| {
+        let event: Event = serde_json::from_value(entry.clone())?;
+        Ok(("topic0", format!("{:?}", event.signature())))
+    })
+}
+
+/// Returns the custom error entries of the ABI, each with the 4-byte `selector` of its signature
+fn errors(abi: &Value) -> eyre::Result<Vec<Value>> {
+    abi_entries(abi, "error", |entry| {
+        let error: AbiError = serde_json::from_value(entry.clone())?;
+        Ok(("selector", format!("0x{}", hex::encode(&error.signature()[..4]))))
+    })
+}
+
+/// Returns the entries of the ABI of the given type, with the key and value returned by `hash`
+/// added to every entry
+fn abi_entries(
+    abi: &Value,
+    kind: &str,
+    hash: impl Fn(&Value) -> eyre::Result<(&'static str, String)>,
+) -> eyre::Result<Vec<Value>> {
+    let entries =
+        abi.as_array().ok_or_else(|| eyre::eyre!("Failed to extract the artifact abi"))?;
+    entries
+        .iter()
+        .filter(|entry| entry["type"] == kind)
+        .map(|entry| {
+            let (key, value) = hash(entry)?;
+            let mut entry = entry.clone();
+            if let Some(entry) = entry.as_object_mut() {
+                entry.insert(key.to_string(), Value::String(value));
+            }
+            Ok(entry)
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, Parser)]
 pub struct InspectArgs {
     #[clap(help = "The name of the contract to inspect.")]
@@ -107,33 +200,9 @@ impl Cmd for InspectArgs {
 
         // Map field to ContractOutputSelection
         let mut cos = build.compiler.extra_output.unwrap_or_default();
-        if !cos.iter().any(|&i| i.to_string() == field.to_string()) {
-            match field {
-                ContractArtifactFields::Abi => cos.push(ContractOutputSelection::Abi),
-                ContractArtifactFields::Bytecode => { /* Auto Generated */ }
-                ContractArtifactFields::DeployedBytecode => { /* Auto Generated */ }
-                ContractArtifactFields::Assembly | ContractArtifactFields::AssemblyOptimized => {
-                    cos.push(ContractOutputSelection::Evm(EvmOutputSelection::Assembly))
-                }
-                ContractArtifactFields::MethodIdentifiers => {
-                    cos.push(ContractOutputSelection::Evm(EvmOutputSelection::MethodIdentifiers))
-                }
-                ContractArtifactFields::GasEstimates => {
-                    cos.push(ContractOutputSelection::Evm(EvmOutputSelection::GasEstimates))
-                }
-                ContractArtifactFields::StorageLayout => {
-                    cos.push(ContractOutputSelection::StorageLayout)
-                }
-                ContractArtifactFields::DevDoc => cos.push(ContractOutputSelection::DevDoc),
-                ContractArtifactFields::Ir => cos.push(ContractOutputSelection::Ir),
-                ContractArtifactFields::IrOptimized => {
-                    cos.push(ContractOutputSelection::IrOptimized)
-                }
-                ContractArtifactFields::Metadata => cos.push(ContractOutputSelection::Metadata),
-                ContractArtifactFields::UserDoc => cos.push(ContractOutputSelection::UserDoc),
-                ContractArtifactFields::Ewasm => {
-                    cos.push(ContractOutputSelection::Ewasm(EwasmOutputSelection::All))
-                }
+        if let Some(selection) = field.output_selection() {
+            if !cos.contains(&selection) {
+                cos.push(selection);
             }
         }
 
@@ -171,6 +240,14 @@ impl Cmd for InspectArgs {
             ContractArtifactFields::Abi => {
                 println!("{}", serde_json::to_string_pretty(&to_value(&artifact.abi)?)?);
             }
+            ContractArtifactFields::Events | ContractArtifactFields::Errors => {
+                let abi = to_value(&artifact.abi)?;
+                let entries = match field {
+                    ContractArtifactFields::Events => events(&abi)?,
+                    _ => errors(&abi)?,
+                };
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+            }
             ContractArtifactFields::Bytecode => {
                 let tval: Value = to_value(&artifact.bytecode)?;
                 println!(
@@ -203,6 +280,12 @@ impl Cmd for InspectArgs {
                     serde_json::to_string_pretty(&to_value(&artifact.method_identifiers)?)?
                 );
             }
+            ContractArtifactFields::Selectors => {
+                let method_identifiers = artifact.method_identifiers.as_ref().ok_or_else(|| {
+                    eyre::eyre!("Failed to extract the method identifiers of the artifact")
+                })?;
+                println!("{}", serde_json::to_string_pretty(&selectors(method_identifiers))?);
+            }
             ContractArtifactFields::GasEstimates => {
                 println!("{}", serde_json::to_string_pretty(&to_value(&artifact.gas_estimates)?)?);
             }
@@ -247,3 +330,83 @@ impl Cmd for InspectArgs {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_method_identifiers_for_selectors() {
+        for field in ["selectors", "methodIdentifiers"] {
+            let field = ContractArtifactFields::from_str(field).unwrap();
+            assert_eq!(
+                field.output_selection(),
+                Some(ContractOutputSelection::Evm(EvmOutputSelection::MethodIdentifiers))
+            );
+        }
+        assert_eq!(
+            ContractArtifactFields::from_str("storageLayout").unwrap().output_selection(),
+            Some(ContractOutputSelection::StorageLayout)
+        );
+        assert_eq!(ContractArtifactFields::Bytecode.output_selection(), None);
+    }
+
+    #[test]
+    fn filters_events_and_errors() {
+        let abi = serde_json::json!([
+            {
+                "type": "function",
+                "name": "balanceOf",
+                "inputs": [],
+                "outputs": [],
+                "stateMutability": "view"
+            },
+            {
+                "type": "event",
+                "name": "Transfer",
+                "anonymous": false,
+                "inputs": [
+                    { "name": "from", "type": "address", "indexed": true },
+                    { "name": "to", "type": "address", "indexed": true },
+                    { "name": "amount", "type": "uint256", "indexed": false }
+                ]
+            },
+            {
+                "type": "error",
+                "name": "InsufficientBalance",
+                "inputs": [
+                    { "name": "available", "type": "uint256" },
+                    { "name": "required", "type": "uint256" }
+                ]
+            },
+            { "type": "error", "name": "Unauthorized", "inputs": [] }
+        ]);
+
+        let events = events(&abi).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["name"], "Transfer");
+        assert_eq!(
+            events[0]["topic0"],
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        );
+
+        let errors = errors(&abi).unwrap();
+        let selectors = errors.iter().map(|error| error["selector"].clone()).collect::<Vec<_>>();
+        assert_eq!(selectors, vec!["0xcf479181", "0x82b42900"]);
+    }
+
+    #[test]
+    fn maps_selectors_to_signatures() {
+        let method_identifiers = BTreeMap::from([
+            ("transfer(address,uint256)".to_string(), "a9059cbb".to_string()),
+            ("balanceOf(address)".to_string(), "70a08231".to_string()),
+        ]);
+        assert_eq!(
+            selectors(&method_identifiers),
+            BTreeMap::from([
+                ("0x70a08231".to_string(), "balanceOf(address)".to_string()),
+                ("0xa9059cbb".to_string(), "transfer(address,uint256)".to_string()),
+            ])
+        );
+    }
+}
@@ -1,22 +1,35 @@
 //! Create command
 use std::{path::PathBuf, str};
 
-use crate::{cmd::Cmd, opts::forge::Dependency, utils::p_println};
+use crate::{
+    cmd::{forge::remappings, Cmd},
+    opts::forge::Dependency,
+    utils::{self, p_println},
+};
 use ansi_term::Colour;
 use clap::{Parser, ValueHint};
-use foundry_config::find_project_root_path;
+use ethers::solc::remappings::Remapping;
+use foundry_config::{find_project_root_path, Config};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use std::{
+    collections::BTreeMap,
     path::Path,
     process::{Command, Stdio},
 };
 
+/// The file in the project root that records the installed revision of every dependency
+pub const LOCKFILE: &str = "foundry.lock";
+
 /// Command to install dependencies
 #[derive(Debug, Clone, Parser)]
 pub struct InstallArgs {
     /// The dependencies to install.
     ///
-    /// A dependency can be a raw URL, or the path to a GitHub repository.
+    /// A dependency can be a raw URL, the path to a GitHub repository, a local path (e.g.
+    /// `../shared-contracts` or `file:../shared-contracts`), or the URL of a `.tar.gz` or `.zip`
+    /// release archive.
     ///
     /// Additionally, a ref can be provided by adding @ to the dependency path.
     ///
@@ -27,6 +40,18 @@ pub struct InstallArgs {
     dependencies: Vec<Dependency>,
     #[clap(flatten)]
     opts: DependencyInstallOpts,
+    #[clap(
+        help = "The name of the dependency in `lib`.",
+        long_help = "The name of the dependency in `lib`. By default, the name is derived from the repository or archive name.",
+        long
+    )]
+    name: Option<String>,
+    #[clap(
+        help = "The expected sha256 checksum of a release archive.",
+        long,
+        value_name = "SHA256"
+    )]
+    checksum: Option<String>,
     #[clap(
         help = "The project's root path.",
         long_help = "The project's root path. By default, this is the root directory of the current Git repository, or the current working directory.",
@@ -40,66 +65,538 @@ impl Cmd for InstallArgs {
     type Output = ();
 
     fn run(self) -> eyre::Result<Self::Output> {
-        let InstallArgs { root, .. } = self;
+        let InstallArgs { mut dependencies, mut opts, name, checksum, root } = self;
         let root = root.unwrap_or_else(|| find_project_root_path().unwrap());
-        install(root, self.dependencies, self.opts)
+        opts.offline |= Config::load_with_root(&root).offline;
+        if name.is_some() || checksum.is_some() {
+            if dependencies.len() != 1 {
+                eyre::bail!("`--name` and `--checksum` can only be used with a single dependency")
+            }
+            if checksum.is_some() && !dependencies[0].is_archive() {
+                eyre::bail!("`--checksum` can only be used with a release archive")
+            }
+        }
+        if let Some(name) = name {
+            dependencies[0].name = name;
+        }
+        install(root, dependencies, opts, checksum.as_deref())
     }
 }
 
 #[derive(Debug, Clone, Copy, Default, Parser)]
 pub struct DependencyInstallOpts {
-    #[clap(help = "Install without adding the dependency as a submodule.", long)]
+    #[clap(
+        help = "Install without adding the dependency as a submodule.",
+        long_help = "Install without adding the dependency as a submodule. The dependency is vendored as a plain copy into `lib`, its revision is recorded in `foundry.lock`.",
+        long,
+        alias = "vendor"
+    )]
     pub no_git: bool,
     #[clap(help = "Do not create a commit.", long)]
     pub no_commit: bool,
-    #[clap(help = "Do not print any messages.", short, long)]
+    #[clap(help = "Do not print any messages except errors.", short, long)]
     pub quiet: bool,
+    #[clap(
+        help = "Do not install the dependencies of the dependency.",
+        long_help = "Do not install the dependencies of the dependency. By default, the nested submodules of a dependency are installed as well, dependencies that are already installed further up the tree are skipped.",
+        long
+    )]
+    pub no_recursive: bool,
+    #[clap(
+        help = "Do not access the network.",
+        long_help = "Do not access the network. Fails if a dependency has to be downloaded, only local dependencies and already cloned submodules can be installed. Also enabled by `offline = true` in the config.",
+        long
+    )]
+    pub offline: bool,
 }
 
 /// Installs all dependencies
+///
+/// The `checksum` is the expected sha256 checksum of release archive dependencies, if any.
 pub(crate) fn install(
     root: impl AsRef<Path>,
     dependencies: Vec<Dependency>,
     opts: DependencyInstallOpts,
+    checksum: Option<&str>,
 ) -> eyre::Result<()> {
     let root = root.as_ref();
     let libs = root.join("lib");
 
     if dependencies.is_empty() {
+        if opts.offline {
+            let missing = submodules(root)?
+                .into_iter()
+                .filter(|(path, _)| !root.join(path).join(".git").exists())
+                .map(|(path, url)| format!("{} ({})", path, url))
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                eyre::bail!(
+                    "Can't install submodules that aren't cloned yet in offline mode: {}",
+                    missing.join(", ")
+                )
+            }
+        }
+
         let mut cmd = Command::new("git");
-        cmd.args(&[
-            "submodule",
-            "update",
-            "--init",
-            "--recursive",
-            libs.display().to_string().as_str(),
-        ]);
+        cmd.args(&["submodule", "update", "--init"]);
+        if opts.offline {
+            cmd.arg("--no-fetch");
+        }
+        if !opts.no_recursive {
+            cmd.arg("--recursive");
+        }
+        if opts.quiet {
+            cmd.arg("--quiet");
+        }
+        cmd.arg(libs.display().to_string());
         cmd.spawn()?.wait()?;
+
+        // pin all dependencies to the revisions recorded in the lockfile
+        Lockfile::read(root)?.checkout(root, opts.quiet, opts.offline)?;
     }
 
     std::fs::create_dir_all(&libs)?;
 
+    let mut installed = Vec::with_capacity(dependencies.len());
     for dep in dependencies {
-        let DependencyInstallOpts { no_git, no_commit, quiet } = opts;
+        let DependencyInstallOpts { no_git, quiet, no_recursive, .. } = opts;
         p_println!(!quiet => "Installing {} in {:?}, (url: {}, tag: {:?})", dep.name, &libs.join(&dep.name), dep.url, dep.tag);
-        if no_git {
-            install_as_folder(&dep, &libs)?;
-        } else {
-            install_as_submodule(&dep, &libs, no_commit)?;
+        if opts.offline && dep.path.is_none() {
+            eyre::bail!(
+                "Can't install {} in offline mode, it has to be downloaded from {}",
+                dep.name,
+                dep.url
+            )
+        }
+        if libs.join(&dep.name).exists() {
+            eyre::bail!(
+                "\"lib/{}\" already exists, use `forge update` to update it or install the dependency under a different name with `forge install <name>=<dependency>`",
+                dep.name
+            )
         }
+        let rev = if let Some(ref path) = dep.path {
+            install_as_link(&dep.name, path, &libs)?;
+            path.display().to_string()
+        } else if dep.is_archive() {
+            p_println!(!quiet => "    {} {}", Colour::Cyan.paint("Downloading"), dep.url);
+            let checksum = install_as_archive(&dep, &libs, checksum)?;
+            lock_dependency(root, &dep, checksum.clone(), true)?;
+            checksum
+        } else if no_git {
+            p_println!(!quiet => "    {} {}", Colour::Cyan.paint("Cloning"), dep.url);
+            let rev = install_as_folder(&dep, &libs, !no_recursive, quiet)?;
+            remap_subdir(&dep, root, quiet)?;
+            if !no_recursive {
+                remap_nested(root, &libs.join(&dep.name), quiet)?;
+            }
+            lock_dependency(root, &dep, rev.clone(), true)?;
+            rev
+        } else {
+            install_as_submodule(&dep, root, &libs, opts)?
+        };
 
         p_println!(!quiet => "    {} {}",    Colour::Green.paint("Installed"), dep.name);
+        installed.push((dep.name, rev));
+    }
+
+    if installed.len() > 1 && !opts.quiet {
+        println!("Installed {} dependencies:", installed.len());
+        let width = installed.iter().map(|(name, _)| name.len()).max().unwrap_or_default();
+        for (name, rev) in installed {
+            println!("    {:width$} {}", name, rev, width = width);
+        }
     }
     Ok(())
 }
 
-/// installs the dependency as an ordinary folder instead of a submodule
-fn install_as_folder(dep: &Dependency, libs: &Path) -> eyre::Result<()> {
+/// The contents of the `foundry.lock` file, `dependency name -> LockEntry`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Lockfile(BTreeMap<String, LockEntry>);
+
+/// A dependency recorded in the [Lockfile]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockEntry {
+    /// The url the dependency was installed from
+    pub url: String,
+    /// The commit the dependency was installed at, or the sha256 checksum of a release archive
+    pub rev: String,
+    /// The tag, branch or commit that was requested, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Whether the dependency was vendored as a plain folder instead of a submodule
+    #[serde(default)]
+    pub vendored: bool,
+}
+
+impl Lockfile {
+    /// Reads the lockfile in the `root` directory, an empty lockfile is returned if it doesn't
+    /// exist yet
+    pub fn read(root: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = root.as_ref().join(LOCKFILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .map_err(|err| eyre::eyre!("Failed to parse \"{}\": {}", path.display(), err))
+    }
+
+    /// Writes the lockfile to the `root` directory
+    pub fn write(&self, root: impl AsRef<Path>) -> eyre::Result<()> {
+        let path = root.as_ref().join(LOCKFILE);
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    /// Checks out every dependency at the revision recorded in the lockfile
+    ///
+    /// Vendored dependencies that are missing in the `lib` folder are installed again, unless
+    /// `offline` is set.
+    pub fn checkout(&self, root: impl AsRef<Path>, quiet: bool, offline: bool) -> eyre::Result<()> {
+        let libs = root.as_ref().join("lib");
+        for (name, entry) in self.0.iter() {
+            let dep_dir = libs.join(name);
+            if entry.vendored {
+                if !dep_dir.exists() {
+                    if offline {
+                        eyre::bail!(
+                            "Can't install {} in offline mode, it has to be downloaded from {}",
+                            name,
+                            entry.url
+                        )
+                    }
+                    p_println!(!quiet => "Installing {} at {}", name, entry.rev);
+                    std::fs::create_dir_all(&libs)?;
+                    let mut dep: Dependency = entry.url.parse()?;
+                    dep.name = name.clone();
+                    if dep.is_archive() {
+                        install_as_archive(&dep, &libs, Some(&entry.rev))?;
+                    } else {
+                        dep.tag = Some(entry.rev.clone());
+                        install_as_folder(&dep, &libs, true, quiet)?;
+                    }
+                }
+                continue
+            }
+
+            if git_head(&dep_dir)? != entry.rev {
+                p_println!(!quiet => "Checking out {} at {}", name, entry.rev);
+                let output = Command::new("git")
+                    .args(&["checkout", "--recurse-submodules", &entry.rev])
+                    .current_dir(&dep_dir)
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .output()?;
+                if !output.status.success() {
+                    eyre::bail!(
+                        "Failed to check out {} at {}: {}",
+                        name,
+                        entry.rev,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Ensures that all installed dependencies match the revisions recorded in the lockfile
+    pub fn ensure_locked(&self, root: impl AsRef<Path>) -> eyre::Result<()> {
+        let libs = root.as_ref().join("lib");
+        let mismatches = self
+            .0
+            .iter()
+            .filter_map(|(name, entry)| {
+                let dep_dir = libs.join(name);
+                if entry.vendored {
+                    return (!dep_dir.exists()).then(|| format!("{}: not installed", name))
+                }
+                match git_head(&dep_dir) {
+                    Ok(rev) if rev == entry.rev => None,
+                    Ok(rev) => Some(format!("{}: expected {}, found {}", name, entry.rev, rev)),
+                    Err(_) => Some(format!("{}: not installed", name)),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if !mismatches.is_empty() {
+            eyre::bail!(
+                "The installed dependencies do not match {}:\n{}",
+                LOCKFILE,
+                mismatches.join("\n")
+            )
+        }
+        Ok(())
+    }
+
+    /// Updates the recorded revisions of the submodule dependencies to the commits they're
+    /// currently checked out at.
+    ///
+    /// If a `name` is provided, only the entry of that dependency is updated.
+    pub fn update(&mut self, root: impl AsRef<Path>, name: Option<&str>) -> eyre::Result<()> {
+        let libs = root.as_ref().join("lib");
+        for (dep_name, entry) in self.0.iter_mut() {
+            if entry.vendored || name.map(|name| name != dep_name.as_str()).unwrap_or_default() {
+                continue
+            }
+            entry.rev = git_head(&libs.join(dep_name))?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if no dependencies are recorded
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the entry for the given dependency
+    pub fn get(&self, name: &str) -> Option<&LockEntry> {
+        self.0.get(name)
+    }
+
+    /// Adds or replaces the entry for the given dependency
+    pub fn insert(&mut self, name: String, entry: LockEntry) -> Option<LockEntry> {
+        self.0.insert(name, entry)
+    }
+
+    /// Removes the entry of the given dependency
+    pub fn remove(&mut self, name: &str) -> Option<LockEntry> {
+        self.0.remove(name)
+    }
+}
+
+/// Checks out an installed dependency at the ref of the given dependency and commits the new
+/// revision
+///
+/// Vendored dependencies are installed again at the requested ref.
+pub(crate) fn update_to_ref(
+    root: impl AsRef<Path>,
+    dep: &Dependency,
+    quiet: bool,
+) -> eyre::Result<()> {
+    let root = root.as_ref();
+    let libs = root.join("lib");
+    let dep_dir = libs.join(&dep.name);
+    let tag = dep.tag.as_deref().ok_or_else(|| eyre::eyre!("no ref provided for {}", dep.name))?;
+    if !dep_dir.exists() {
+        eyre::bail!("Dependency \"{}\" is not installed in \"{}\"", dep.name, libs.display())
+    }
+
+    let mut lockfile = Lockfile::read(root)?;
+    if let Some(entry) = lockfile.get(&dep.name).filter(|entry| entry.vendored).cloned() {
+        let mut vendored = dep.clone();
+        vendored.url = entry.url;
+        if vendored.is_archive() {
+            eyre::bail!(
+                "\"{}\" was installed from a release archive, install the archive of the new release instead",
+                dep.name
+            )
+        }
+        std::fs::remove_dir_all(&dep_dir)?;
+        let rev = install_as_folder(&vendored, &libs, true, quiet)?;
+        return lock_dependency(root, &vendored, rev, true)
+    }
+
+    p_println!(!quiet => "    {} {}", Colour::Cyan.paint("Fetching"), dep.name);
     let output = Command::new("git")
-        .args(&["clone", &dep.url, &dep.name])
-        .current_dir(&libs)
+        .args(&["fetch", "--tags", "origin"])
+        .current_dir(&dep_dir)
         .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
         .output()?;
+    if !output.status.success() {
+        eyre::bail!(
+            "Failed to fetch {}: {}",
+            dep.name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+
+    // prefer the remote branch so that branches are fast-forwarded to the latest commit
+    let rev = [format!("origin/{}", tag), tag.to_string()]
+        .iter()
+        .find_map(|candidate| resolve_commit(&dep_dir, candidate))
+        .ok_or_else(|| {
+            eyre::eyre!("Ref \"{}\" of dependency \"{}\" not found on the remote", tag, dep.name)
+        })?;
+
+    p_println!(!quiet => "    {} {} at {}", Colour::Cyan.paint("Checking out"), tag, rev);
+    let output = Command::new("git")
+        .args(&["checkout", "--recurse-submodules", &rev])
+        .current_dir(&dep_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()?;
+    if !output.status.success() {
+        eyre::bail!(
+            "Failed to check out {} at {}: {}",
+            dep.name,
+            tag,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+
+    let mut paths = vec![Path::new("lib").join(&dep.name).display().to_string()];
+    if let Some(entry) = lockfile.get(&dep.name).cloned() {
+        lockfile.insert(dep.name.clone(), LockEntry { rev, tag: dep.tag.clone(), ..entry });
+        lockfile.write(root)?;
+        paths.push(LOCKFILE.to_string());
+    }
+
+    Command::new("git").arg("add").args(&paths).current_dir(root).spawn()?.wait()?;
+    Command::new("git")
+        .args(&["commit", "-m", &format!("forge update: {}\n\n{}", dep.name, tag), "--"])
+        .args(&paths)
+        .current_dir(root)
+        .stdout(Stdio::piped())
+        .spawn()?
+        .wait()?;
+
+    Ok(())
+}
+
+/// Returns the commit the given ref points to in the git repository in `dir`, if it exists
+fn resolve_commit(dir: &Path, rev: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(&["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev)])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// installs a local dependency by linking its directory into `lib`
+fn install_as_link(name: &str, path: &Path, libs: &Path) -> eyre::Result<()> {
+    let source = dunce::canonicalize(path)
+        .map_err(|err| eyre::eyre!("Local dependency \"{}\" not found: {}", path.display(), err))?;
+    if !source.is_dir() {
+        eyre::bail!("Local dependency \"{}\" is not a directory", path.display())
+    }
+
+    let target = libs.join(name);
+    if target.exists() {
+        eyre::bail!("Destination path \"{}\" already exists.", target.display())
+    }
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&source, &target)?;
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_dir(&source, &target)?;
+
+    Ok(())
+}
+
+/// Records the installed revision of the dependency in the lockfile
+fn lock_dependency(root: &Path, dep: &Dependency, rev: String, vendored: bool) -> eyre::Result<()> {
+    let mut lockfile = Lockfile::read(root)?;
+    lockfile.insert(
+        dep.name.clone(),
+        LockEntry { url: dep.url.clone(), rev, tag: dep.tag.clone(), vendored },
+    );
+    lockfile.write(root)
+}
+
+/// Remaps the dependency to its folder inside of the repository, if it has one
+///
+/// The remapping is added to the `remappings.txt` file in `root`, the remappings that are detected
+/// for the dependency only cover the root of its repository.
+///
+/// Returns the added remappings.
+fn remap_subdir(dep: &Dependency, root: &Path, quiet: bool) -> eyre::Result<Vec<String>> {
+    let subdir = match dep.subdir {
+        Some(ref subdir) => subdir,
+        None => return Ok(Vec::new()),
+    };
+    let dir = Path::new("lib").join(&dep.name).join(subdir);
+    if !root.join(&dir).is_dir() {
+        eyre::bail!("Folder \"{}\" not found in {}", subdir, dep.url)
+    }
+    // point to the sources of the package, like the detected remappings do
+    let src = dir.join("src");
+    let path = if root.join(&src).is_dir() { src } else { dir };
+    let remapping = format!("{}/={}/", dep.name, path.display());
+    let added = remappings::add_remappings(root, &[remapping])?;
+    for remapping in &added {
+        p_println!(!quiet => "    {} {}", Colour::Cyan.paint("Remapped"), remapping);
+    }
+    Ok(added)
+}
+
+/// Flattens the remappings of the nested dependencies of the dependency in `dir` into the
+/// `remappings.txt` file in `root`, so their imports resolve in the project
+///
+/// Dependencies of the project take precedence over nested dependencies of the same name. Returns
+/// the added remappings.
+fn remap_nested(root: &Path, dir: &Path, quiet: bool) -> eyre::Result<Vec<String>> {
+    let installed = std::fs::read_dir(root.join("lib"))?
+        .filter_map(Result::ok)
+        .map(|entry| format!("{}/", entry.file_name().to_string_lossy()))
+        .collect::<Vec<_>>();
+    let remappings = nested_remappings(root, dir)?
+        .into_iter()
+        .filter(|r| !installed.contains(&r.name))
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>();
+    let added = remappings::add_remappings(root, &remappings)?;
+    for remapping in &added {
+        p_println!(!quiet => "    {} {}", Colour::Cyan.paint("Remapped"), remapping);
+    }
+    Ok(added)
+}
+
+/// Returns the remappings of all nested dependencies of the dependency in `dir`, relative to
+/// `root`
+///
+/// Submodules that weren't installed, like the ones that were skipped because of a cycle, are
+/// empty and have no remappings.
+fn nested_remappings(root: &Path, dir: &Path) -> eyre::Result<Vec<Remapping>> {
+    let mut remappings = Vec::new();
+    for (path, _) in submodules(dir)? {
+        let nested = dir.join(&path);
+        remappings.extend(remappings::dependency_remappings(&nested, root));
+        remappings.extend(nested_remappings(root, &nested)?);
+    }
+    Ok(remappings)
+}
+
+/// Returns the commit the git repository in `dir` is checked out at
+fn git_head(dir: &Path) -> eyre::Result<String> {
+    let output = Command::new("git").args(&["rev-parse", "HEAD"]).current_dir(dir).output()?;
+    if !output.status.success() {
+        eyre::bail!(
+            "Failed to get the commit of \"{}\": {}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// installs the dependency as an ordinary folder instead of a submodule
+///
+/// If `recursive` is set, the nested submodules of the dependency are vendored as well.
+///
+/// Returns the commit hash the dependency was installed at.
+pub(crate) fn install_as_folder(
+    dep: &Dependency,
+    libs: &Path,
+    recursive: bool,
+    quiet: bool,
+) -> eyre::Result<String> {
+    let mut args = vec!["clone"];
+    // a commit can't be fetched via `--branch`, so that requires a full clone
+    let is_commit = dep.tag.as_deref().map(is_commit_hash).unwrap_or_default();
+    if !is_commit {
+        args.extend(["--depth", "1"]);
+        if let Some(ref tag) = dep.tag {
+            args.extend(["--branch", tag.as_str()]);
+        }
+    }
+    args.extend([dep.url.as_str(), dep.name.as_str()]);
+
+    let output =
+        Command::new("git").args(&args).current_dir(&libs).stdout(Stdio::piped()).output()?;
 
     let stderr = str::from_utf8(&output.stderr).unwrap();
 
@@ -114,25 +611,230 @@ fn install_as_folder(dep: &Dependency, libs: &Path) -> eyre::Result<()> {
         eyre::bail!("{}", stderr.trim())
     }
 
-    if let Some(ref tag) = dep.tag {
-        Command::new("git")
+    let dep_dir = libs.join(&dep.name);
+    if is_commit {
+        let tag = dep.tag.as_deref().unwrap();
+        let output = Command::new("git")
             .args(&["checkout", tag])
-            .current_dir(&libs.join(&dep.name))
+            .current_dir(&dep_dir)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .spawn()?
-            .wait()?;
+            .output()?;
+        if !output.status.success() {
+            eyre::bail!("{}", String::from_utf8_lossy(&output.stderr).trim())
+        }
     }
 
-    // rm git artifacts
-    std::fs::remove_dir_all(libs.join(&dep.name).join(".git"))?;
+    let rev = git_head(&dep_dir)?;
 
+    if recursive {
+        install_nested(&dep_dir, &mut vec![normalize_url(&dep.url)], quiet)?;
+    }
+
+    // rm git artifacts, nested submodules have a `.git` file that points into the parent's `.git`
+    for entry in walkdir::WalkDir::new(&dep_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name() == ".git")
+        .collect::<Vec<_>>()
+    {
+        if !entry.path().exists() {
+            // already removed with its parent
+            continue
+        }
+        if entry.file_type().is_dir() {
+            std::fs::remove_dir_all(entry.path())?;
+        } else {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+
+    Ok(rev)
+}
+
+/// Initializes the nested submodules of the repository in `dir`, and their submodules
+///
+/// `ancestors` are the normalized urls of the repositories further up the tree, a submodule that
+/// points to one of them is a dependency cycle and skipped.
+fn install_nested(dir: &Path, ancestors: &mut Vec<String>, quiet: bool) -> eyre::Result<()> {
+    for (path, url) in submodules(dir)? {
+        let normalized = normalize_url(&url);
+        if ancestors.contains(&normalized) {
+            eprintln!(
+                "{} skipping \"{}\" in \"{}\", {} depends on itself",
+                Colour::Yellow.bold().paint("Warning:"),
+                path,
+                dir.display(),
+                url
+            );
+            continue
+        }
+
+        p_println!(!quiet => "    {} {}", Colour::Cyan.paint("Installing nested"), path);
+        let output = Command::new("git")
+            .args(&["submodule", "update", "--init", "--", &path])
+            .current_dir(dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()?;
+        if !output.status.success() {
+            eyre::bail!(
+                "Failed to install \"{}\" in \"{}\": {}",
+                path,
+                dir.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+
+        ancestors.push(normalized);
+        install_nested(&dir.join(&path), ancestors, quiet)?;
+        ancestors.pop();
+    }
     Ok(())
 }
 
+/// Returns the `(path, url)` of all submodules declared in the `.gitmodules` file in `dir`
+fn submodules(dir: &Path) -> eyre::Result<Vec<(String, String)>> {
+    if !dir.join(".gitmodules").is_file() {
+        return Ok(Vec::new())
+    }
+    let output = Command::new("git")
+        .args(&["config", "-f", ".gitmodules", "--get-regexp", r"^submodule\..*\.(path|url)$"])
+        .current_dir(dir)
+        .output()?;
+
+    // lines are `submodule.<name>.<key> <value>`
+    let mut paths = BTreeMap::new();
+    let mut urls = BTreeMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let (key, value) = match line.split_once(' ') {
+            Some(item) => item,
+            None => continue,
+        };
+        if let Some(name) = key.strip_suffix(".path") {
+            paths.insert(name.to_string(), value.to_string());
+        } else if let Some(name) = key.strip_suffix(".url") {
+            urls.insert(name.to_string(), value.to_string());
+        }
+    }
+    Ok(paths.into_iter().filter_map(|(name, path)| Some((path, urls.remove(&name)?))).collect())
+}
+
+/// Normalizes a git url so that different spellings of the same repository compare equal
+fn normalize_url(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    // only compare `org/repo`, the host and transport may differ
+    let mut segments = url.rsplit(|c| c == '/' || c == ':').take(2).collect::<Vec<_>>();
+    segments.reverse();
+    segments.join("/").to_lowercase()
+}
+
+/// installs the dependency by downloading and unpacking its release archive
+///
+/// Returns the sha256 checksum of the archive, which must match `checksum` if provided.
+fn install_as_archive(
+    dep: &Dependency,
+    libs: &Path,
+    checksum: Option<&str>,
+) -> eyre::Result<String> {
+    let target = libs.join(&dep.name);
+    if target.exists() {
+        eyre::bail!("Destination path \"{}\" already exists.", target.display())
+    }
+
+    let is_zip = dep.url.ends_with(".zip");
+    let archive = libs.join(format!(".{}.{}", dep.name, if is_zip { "zip" } else { "tar.gz" }));
+    let staging = libs.join(format!(".{}", dep.name));
+    let result = download_archive(dep, &archive, &staging, checksum, is_zip).and_then(|sum| {
+        // release archives usually contain a single top level folder
+        let entries = std::fs::read_dir(&staging)?.collect::<Result<Vec<_>, _>>()?;
+        let content = match entries.as_slice() {
+            [entry] if entry.path().is_dir() => entry.path(),
+            _ => staging.clone(),
+        };
+        std::fs::rename(content, &target)?;
+        Ok(sum)
+    });
+
+    let _ = std::fs::remove_file(&archive);
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+/// Downloads the archive of the dependency, verifies its checksum and unpacks it into `staging`
+fn download_archive(
+    dep: &Dependency,
+    archive: &Path,
+    staging: &Path,
+    checksum: Option<&str>,
+    is_zip: bool,
+) -> eyre::Result<String> {
+    let bytes =
+        utils::block_on(async { reqwest::get(&dep.url).await?.error_for_status()?.bytes().await })
+            .map_err(|err| eyre::eyre!("Failed to download \"{}\": {}", dep.url, err))?;
+    std::fs::write(archive, &bytes)?;
+
+    let sum = hex::encode(Sha256::digest(&bytes));
+    if let Some(expected) = checksum {
+        let expected = expected.trim().trim_start_matches("sha256:").to_lowercase();
+        if expected != sum {
+            eyre::bail!("Checksum mismatch for \"{}\": expected {}, got {}", dep.url, expected, sum)
+        }
+    }
+
+    std::fs::create_dir_all(staging)?;
+    let (program, args) = if is_zip {
+        ("unzip", vec!["-q".to_string(), archive.display().to_string(), "-d".to_string()])
+    } else {
+        ("tar", vec!["-xzf".to_string(), archive.display().to_string(), "-C".to_string()])
+    };
+    let output = Command::new(program)
+        .args(&args)
+        .arg(staging)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|err| {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                eyre::eyre!("`{}` is required to unpack \"{}\" but was not found", program, dep.url)
+            } else {
+                err.into()
+            }
+        })?;
+    if !output.status.success() {
+        eyre::bail!(
+            "Failed to unpack \"{}\": {}",
+            dep.url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+    Ok(sum)
+}
+
+/// Returns `true` if the given ref looks like an (abbreviated) commit hash
+fn is_commit_hash(tag: &str) -> bool {
+    tag.len() >= 7 && tag.len() <= 40 && tag.chars().all(|c| c.is_ascii_hexdigit())
+}
+
 /// installs the dependency as new submodule
-fn install_as_submodule(dep: &Dependency, libs: &Path, no_commit: bool) -> eyre::Result<()> {
+///
+/// Returns the commit hash the dependency was installed at.
+fn install_as_submodule(
+    dep: &Dependency,
+    root: &Path,
+    libs: &Path,
+    opts: DependencyInstallOpts,
+) -> eyre::Result<String> {
+    let DependencyInstallOpts { no_commit, quiet, no_recursive, .. } = opts;
+    // fail before touching the working tree if the requested ref doesn't exist
+    if let Some(ref tag) = dep.tag {
+        p_println!(!quiet => "    {} {}", Colour::Cyan.paint("Resolving"), tag);
+    }
+    ensure_ref_exists(dep)?;
+
     // install the dep
+    p_println!(!quiet => "    {} {}", Colour::Cyan.paint("Adding submodule"), dep.url);
     let output = Command::new("git")
         .args(&["submodule", "add", &dep.url, &dep.name])
         .current_dir(&libs)
@@ -159,25 +861,24 @@ fn install_as_submodule(dep: &Dependency, libs: &Path, no_commit: bool) -> eyre:
         eyre::bail!("{}", stderr.trim())
     }
 
-    // call update on it
-    Command::new("git")
-        .args(&["submodule", "update", "--init", "--recursive", &dep.name])
-        .current_dir(&libs)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?
-        .wait()?;
+    // roll back the submodule addition if it can't be checked out at the requested ref
+    let remapped = checkout_submodule(dep, libs, !no_recursive, quiet).and_then(|_| {
+        let mut remapped = remap_subdir(dep, root, quiet)?;
+        if !no_recursive {
+            remapped.extend(remap_nested(root, &libs.join(&dep.name), quiet)?);
+        }
+        Ok(remapped)
+    });
+    let remapped = match remapped {
+        Ok(remapped) => remapped,
+        Err(err) => {
+            remove_submodule(root, &dep.name)?;
+            return Err(err)
+        }
+    };
 
-    // checkout the tag if necessary
+    // stage the checked out tag
     let message = if let Some(ref tag) = dep.tag {
-        Command::new("git")
-            .args(&["checkout", "--recurse-submodules", tag])
-            .current_dir(&libs.join(&dep.name))
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?
-            .wait()?;
-
         if !no_commit {
             Command::new("git").args(&["add", &libs.display().to_string()]).spawn()?.wait()?;
         }
@@ -186,7 +887,18 @@ fn install_as_submodule(dep: &Dependency, libs: &Path, no_commit: bool) -> eyre:
         format!("forge install: {}", dep.name)
     };
 
+    let rev = git_head(&libs.join(&dep.name))?;
+    lock_dependency(root, dep, rev.clone(), false)?;
+
     if !no_commit {
+        Command::new("git").args(&["add", LOCKFILE]).current_dir(root).spawn()?.wait()?;
+        if !remapped.is_empty() {
+            Command::new("git")
+                .args(&["add", "remappings.txt"])
+                .current_dir(root)
+                .spawn()?
+                .wait()?;
+        }
         Command::new("git")
             .args(&["commit", "-m", &message])
             .current_dir(&libs)
@@ -195,5 +907,228 @@ fn install_as_submodule(dep: &Dependency, libs: &Path, no_commit: bool) -> eyre:
             .wait()?;
     }
 
+    Ok(rev)
+}
+
+/// Ensures that the tag or branch of the dependency exists on its remote
+///
+/// Commits can't be looked up on the remote, they're verified when the submodule is checked out.
+fn ensure_ref_exists(dep: &Dependency) -> eyre::Result<()> {
+    let tag = match dep.tag {
+        Some(ref tag) if !is_commit_hash(tag) => tag,
+        _ => return Ok(()),
+    };
+
+    let output = Command::new("git")
+        .args(&["ls-remote", "--heads", "--tags", &dep.url, tag])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("Repository not found") {
+        eyre::bail!("Repo: \"{}\" not found!", &dep.url)
+    } else if !output.status.success() {
+        eyre::bail!("{}", stderr.trim())
+    }
+
+    // `ls-remote` matches the pattern against the end of the ref, so `v1` also matches `a/v1`
+    let heads = format!("refs/heads/{}", tag);
+    let tags = format!("refs/tags/{}", tag);
+    let exists = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|r| r.trim_end_matches("^{}"))
+        .any(|r| r == heads || r == tags);
+    if !exists {
+        eyre::bail!("tag/branch/commit `{}` not found in {}", tag, dep.url)
+    }
+    Ok(())
+}
+
+/// Initializes the newly added submodule and checks out the tag if necessary
+///
+/// If `recursive` is set, the nested submodules at the checked out tag are initialized as well.
+fn checkout_submodule(
+    dep: &Dependency,
+    libs: &Path,
+    recursive: bool,
+    quiet: bool,
+) -> eyre::Result<()> {
+    p_println!(!quiet => "    {} {}", Colour::Cyan.paint("Fetching"), dep.name);
+    let output = Command::new("git")
+        .args(&["submodule", "update", "--init", &dep.name])
+        .current_dir(&libs)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()?;
+    if !output.status.success() {
+        eyre::bail!("{}", String::from_utf8_lossy(&output.stderr).trim())
+    }
+
+    if let Some(ref tag) = dep.tag {
+        p_println!(!quiet => "    {} {}", Colour::Cyan.paint("Checking out"), tag);
+        let output = Command::new("git")
+            .args(&["checkout", "--recurse-submodules", tag])
+            .current_dir(&libs.join(&dep.name))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()?;
+        if !output.status.success() {
+            eyre::bail!("tag/branch/commit `{}` not found in {}", tag, dep.url)
+        }
+    }
+
+    if recursive {
+        install_nested(&libs.join(&dep.name), &mut vec![normalize_url(&dep.url)], quiet)?;
+    }
     Ok(())
 }
+
+/// Removes a submodule that was just added to `lib`, this restores `.gitmodules` and the index
+fn remove_submodule(root: &Path, name: &str) -> eyre::Result<()> {
+    let path = Path::new("lib").join(name).display().to_string();
+    let gitmodules = root.join(".gitmodules");
+    Command::new("git")
+        .args(&["submodule", "deinit", "-f", "-q", &path])
+        .current_dir(root)
+        .stderr(Stdio::piped())
+        .output()?;
+    Command::new("git")
+        .args(&["rm", "-f", "-q", &path])
+        .current_dir(root)
+        .stderr(Stdio::piped())
+        .output()?;
+
+    // `git rm` leaves an empty `.gitmodules` behind if this was the first submodule
+    if std::fs::read_to_string(&gitmodules).map(|s| s.trim().is_empty()).unwrap_or_default() {
+        Command::new("git")
+            .args(&["rm", "-f", "-q", ".gitmodules"])
+            .current_dir(root)
+            .stderr(Stdio::piped())
+            .output()?;
+    }
+
+    let git_mod_path = root.join(".git/modules/lib").join(name);
+    if git_mod_path.exists() {
+        std::fs::remove_dir_all(git_mod_path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn remaps_dependency_subdir() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        let dep = Dependency::from_str("gakonst/lootloose/packages/contracts@v1").unwrap();
+        let package = root.join("lib/contracts/packages/contracts");
+
+        // the folder has to exist in the repository
+        let err = remap_subdir(&dep, root, true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Folder \"packages/contracts\" not found in https://github.com/gakonst/lootloose"
+        );
+
+        std::fs::create_dir_all(&package).unwrap();
+        remap_subdir(&dep, root, true).unwrap();
+        let remappings = std::fs::read_to_string(root.join("remappings.txt")).unwrap();
+        assert_eq!(remappings, "contracts/=lib/contracts/packages/contracts/\n");
+
+        // the sources of the package are preferred, existing remappings are kept
+        std::fs::create_dir_all(package.join("src")).unwrap();
+        std::fs::write(root.join("remappings.txt"), "ds-test/=lib/ds-test/src/").unwrap();
+        remap_subdir(&dep, root, true).unwrap();
+        remap_subdir(&dep, root, true).unwrap();
+        let remappings = std::fs::read_to_string(root.join("remappings.txt")).unwrap();
+        assert_eq!(
+            remappings,
+            "ds-test/=lib/ds-test/src/\ncontracts/=lib/contracts/packages/contracts/src/\n"
+        );
+
+        // dependencies in the root of their repository are remapped by detection
+        let dep = Dependency::from_str("gakonst/lootloose@v1").unwrap();
+        remap_subdir(&dep, root, true).unwrap();
+        assert_eq!(std::fs::read_to_string(root.join("remappings.txt")).unwrap(), remappings);
+    }
+
+    #[test]
+    fn normalizes_urls() {
+        for url in [
+            "https://github.com/transmissions11/solmate",
+            "https://github.com/transmissions11/solmate.git",
+            "https://github.com/Transmissions11/Solmate/",
+            "git@github.com:transmissions11/solmate.git",
+            "ssh://git@github.com/transmissions11/solmate",
+            "https://gitlab.com/transmissions11/solmate",
+        ] {
+            assert_eq!(normalize_url(url), "transmissions11/solmate", "{}", url);
+        }
+        assert_ne!(
+            normalize_url("https://github.com/transmissions11/solmate"),
+            normalize_url("https://github.com/dapphub/ds-test")
+        );
+    }
+
+    /// Runs git in `dir` and panics if it fails
+    fn git(dir: &Path, args: &[&str]) {
+        let output = Command::new("git")
+            .args(&["-c", "protocol.file.allow=always", "-c", "user.name=forge"])
+            .args(&["-c", "user.email=forge@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    #[test]
+    fn breaks_dependency_cycles() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (a, b) = (tmp.path().join("a"), tmp.path().join("b"));
+        for (repo, source) in [(&a, "src/A.sol"), (&b, "src/B.sol")] {
+            std::fs::create_dir_all(repo.join("src")).unwrap();
+            std::fs::write(repo.join(source), "").unwrap();
+            git(repo, &["init", "-q"]);
+            git(repo, &["add", "."]);
+            git(repo, &["commit", "-q", "-m", "init"]);
+        }
+        let url = |repo: &Path| repo.display().to_string();
+
+        // `a` depends on `b`, which depends on `a` again
+        git(&b, &["submodule", "add", "-q", &url(&a), "lib/a"]);
+        git(&b, &["commit", "-q", "-m", "add a"]);
+        git(&a, &["submodule", "add", "-q", &url(&b), "lib/b"]);
+        git(&a, &["commit", "-q", "-m", "add b"]);
+
+        let root = tmp.path().join("project");
+        std::fs::create_dir_all(root.join("lib")).unwrap();
+        git(&root.join("lib"), &["clone", "-q", &url(&a), "a"]);
+        let dep = root.join("lib/a");
+        // git refuses to clone submodules from local paths unless allowed, which only the `git`
+        // helper does. Fetch `b` up front, so installing it only checks out the fetched module
+        git(&dep, &["submodule", "update", "-q", "--init", "--", "lib/b"]);
+        git(&dep, &["submodule", "deinit", "-q", "-f", "--", "lib/b"]);
+        install_nested(&dep, &mut vec![normalize_url(&url(&a))], true).unwrap();
+
+        // `b` is installed, `a` inside of it is skipped
+        assert!(dep.join("lib/b/src/B.sol").exists());
+        assert!(!dep.join("lib/b/lib/a/src").exists());
+
+        // the nested dependency is remapped in the project
+        remap_nested(&root, &dep, true).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join("remappings.txt")).unwrap(),
+            "b/=lib/a/lib/b/src/\n"
+        );
+
+        // dependencies of the project take precedence
+        std::fs::remove_file(root.join("remappings.txt")).unwrap();
+        std::fs::create_dir_all(root.join("lib/b/src")).unwrap();
+        assert!(remap_nested(&root, &dep, true).unwrap().is_empty());
+    }
+}
@@ -3,7 +3,17 @@
 use crate::cmd::Cmd;
 use clap::{Parser, ValueHint};
 use ethers::solc::{remappings::Remapping, ProjectPathsConfig};
-use std::path::{Path, PathBuf};
+use foundry_config::{
+    figment::{
+        providers::{Format, Toml},
+        Figment,
+    },
+    Config,
+};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 /// Command to list remappings
 #[derive(Debug, Clone, Parser)]
@@ -49,3 +59,85 @@ pub fn relative_remappings(lib: &Path, root: &Path) -> Vec<Remapping> {
         .map(Into::into)
         .collect()
 }
+
+/// Returns the remappings detected for the dependency in `dir`, relative to `root`
+pub fn dependency_remappings(dir: &Path, root: &Path) -> Vec<Remapping> {
+    let lib = match dir.parent() {
+        Some(lib) => lib,
+        None => return Vec::new(),
+    };
+    let rel = dir.strip_prefix(root).unwrap_or(dir);
+    relative_remappings(lib, root).into_iter().filter(|r| remaps_into(r, root, rel)).collect()
+}
+
+/// Returns `true` if the remapping points into `dir`, which is relative to `root`
+fn remaps_into(remapping: &Remapping, root: &Path, dir: &Path) -> bool {
+    let path = Path::new(&remapping.path);
+    let path = path.strip_prefix(root).unwrap_or(path);
+    path.strip_prefix(".").unwrap_or(path).starts_with(dir)
+}
+
+/// Removes all remappings from the `remappings.txt` file in `root` that point into `dir`
+///
+/// Returns the removed remappings, if `dry_run` is set the file is left untouched.
+pub fn remove_remappings(root: &Path, dir: &Path, dry_run: bool) -> eyre::Result<Vec<String>> {
+    let file = root.join("remappings.txt");
+    if !file.is_file() {
+        return Ok(Vec::new())
+    }
+    let content = std::fs::read_to_string(&file)?;
+    let (removed, kept): (Vec<_>, Vec<_>) = content.lines().partition(|line| {
+        Remapping::from_str(line.trim()).map(|r| remaps_into(&r, root, dir)).unwrap_or_default()
+    });
+
+    if !removed.is_empty() && !dry_run {
+        std::fs::write(&file, kept.iter().map(|line| format!("{}\n", line)).collect::<String>())?;
+    }
+    Ok(removed.into_iter().map(|line| line.trim().to_string()).collect())
+}
+
+/// Appends the remappings to the `remappings.txt` file in `root`, the file is created if it doesn't
+/// exist yet
+///
+/// Remappings of names that are already remapped in the file are skipped. Returns the added
+/// remappings.
+pub fn add_remappings(root: &Path, remappings: &[String]) -> eyre::Result<Vec<String>> {
+    let file = root.join("remappings.txt");
+    let mut content = if file.is_file() { std::fs::read_to_string(&file)? } else { String::new() };
+    let mut names = content
+        .lines()
+        .filter_map(|line| Remapping::from_str(line.trim()).ok())
+        .map(|r| r.name)
+        .collect::<Vec<_>>();
+
+    let mut added = Vec::new();
+    for remapping in remappings {
+        let name = Remapping::from_str(remapping)?.name;
+        if names.contains(&name) {
+            continue
+        }
+        names.push(name);
+        added.push(remapping.clone());
+    }
+
+    if !added.is_empty() {
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.extend(added.iter().map(|r| format!("{}\n", r)));
+        std::fs::write(&file, content)?;
+    }
+    Ok(added)
+}
+
+/// Returns all remappings of the selected profile in the `foundry.toml` file in `root` that point
+/// into `dir`
+pub fn find_config_remappings(root: &Path, dir: &Path) -> Vec<String> {
+    Figment::from(Toml::file(root.join(Config::FILE_NAME)).nested())
+        .select(Config::selected_profile())
+        .extract_inner::<Vec<String>>("remappings")
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|r| Remapping::from_str(r).map(|r| remaps_into(&r, root, dir)).unwrap_or_default())
+        .collect()
+}
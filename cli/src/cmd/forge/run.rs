@@ -1,32 +1,51 @@
 use crate::{
     cmd::{forge::build::CoreBuildArgs, Cmd},
-    compile, utils,
+    compile,
+    opts::LocalWalletOpts,
+    utils,
 };
 use ansi_term::Colour;
 use clap::{Parser, ValueHint};
 use ethers::{
-    abi::{Abi, RawLog},
-    prelude::ArtifactId,
+    abi::{
+        token::{LenientTokenizer, Tokenizer},
+        Abi, Function, ParamType, RawLog, Token,
+    },
+    prelude::{ArtifactId, Http, LocalWallet, Middleware, Provider, Signer, SignerMiddleware},
     solc::{
         artifacts::{CompactContractBytecode, ContractBytecode, ContractBytecodeSome},
         Project,
     },
-    types::{Address, Bytes, U256},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Bytes, TransactionReceipt,
+        H256, U256, U64,
+    },
+    utils::get_contract_address,
 };
+use eyre::Context;
 use forge::{
     debug::DebugArena,
     decode::decode_console_logs,
     executor::{
         builder::Backend, opts::EvmOpts, CallResult, DatabaseRef, DeployResult, EvmError, Executor,
-        ExecutorBuilder, RawCallResult,
+        ExecutorBuilder, RawCallResult, StateChangeset,
+    },
+    trace::{
+        identifier::LocalTraceIdentifier, CallTraceArena, CallTraceDecoder, RawOrDecodedLog,
+        TraceKind,
     },
-    trace::{identifier::LocalTraceIdentifier, CallTraceArena, CallTraceDecoder, TraceKind},
     CALLER,
 };
 use foundry_common::evm::EvmArgs;
 use foundry_config::{figment::Figment, Config};
-use foundry_utils::{encode_args, IntoFunction, PostLinkInput, RuntimeOrHandle};
-use std::{collections::BTreeMap, path::PathBuf};
+use foundry_utils::{format_token, IntoFunction, PostLinkInput, RuntimeOrHandle};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 use ui::{TUIExitReason, Tui, Ui};
 
 // Loads project's figment and merges the build cli arguments into it
@@ -41,7 +60,9 @@ pub struct RunArgs {
     #[clap(value_hint = ValueHint::FilePath)]
     pub path: PathBuf,
 
-    /// Arguments to pass to the script function.
+    /// Arguments to pass to the script function, see --sig.
+    ///
+    /// Unsigned integers can be tagged with a unit, e.g. `1ether` or `10gwei`.
     pub args: Vec<String>,
 
     /// The name of the contract you want to run.
@@ -49,6 +70,9 @@ pub struct RunArgs {
     pub target_contract: Option<String>,
 
     /// The signature of the function you want to call in the contract, or raw calldata.
+    ///
+    /// The arguments of the function are passed after the path, e.g.
+    /// `forge run script/Deploy.s.sol --sig "run(address,uint256)" 0xabc... 1ether`.
     #[clap(long, short, default_value = "run()", value_name = "SIGNATURE")]
     pub sig: String,
 
@@ -56,21 +80,69 @@ pub struct RunArgs {
     #[clap(long)]
     pub debug: bool,
 
+    /// Send the transactions of the script to `--rpc-url`.
+    ///
+    /// Every call and contract creation the script makes is sent as a transaction of the wallet,
+    /// in order. Without it, the transactions are only written to
+    /// `broadcast/<script>/run-latest.json`.
+    #[clap(long, requires = "fork-url", conflicts_with = "debug")]
+    pub broadcast: bool,
+
+    /// Send the remaining transactions of the last broadcast of the script.
+    ///
+    /// The transactions in `broadcast/<script>/run-latest.json` that were already sent are
+    /// checked against the chain, the script is not run again.
+    #[clap(long, requires = "fork-url", conflicts_with_all = &["broadcast", "debug"])]
+    pub resume: bool,
+
+    /// Print the result of the script as JSON.
+    ///
+    /// The result contains the decoded return values, the emitted events and the state changes of
+    /// the script, the traces are included with `-vvv` or higher.
+    #[clap(long, conflicts_with_all = &["broadcast", "resume", "debug"])]
+    pub json: bool,
+
     #[clap(flatten, next_help_heading = "BUILD OPTIONS")]
     pub opts: CoreBuildArgs,
 
     #[clap(flatten, next_help_heading = "EVM OPTIONS")]
     pub evm_opts: EvmArgs,
+
+    #[clap(flatten, next_help_heading = "WALLET OPTIONS")]
+    pub wallet: LocalWalletOpts,
 }
 
 impl Cmd for RunArgs {
     type Output = ();
     fn run(self) -> eyre::Result<Self::Output> {
         let figment: Figment = From::from(&self);
-        let evm_opts = figment.extract::<EvmOpts>()?;
+        let mut evm_opts = figment.extract::<EvmOpts>()?;
+        evm_opts.resolve_fork_url()?;
+        utils::resolve_fork_block(&mut evm_opts, self.evm_opts.fork_block())?;
         let verbosity = evm_opts.verbosity;
         let config = Config::from_provider(figment).sanitized();
 
+        // the transactions of the script are sent by the wallet
+        let signer = self.wallet.signer()?;
+        if let Some(signer) = &signer {
+            evm_opts.sender = signer.address();
+        } else if self.broadcast || self.resume {
+            eyre::bail!("Broadcasting requires a wallet, set a private key, mnemonic or keystore")
+        }
+
+        if self.resume {
+            let path = ScriptSequence::path(&config.__root.0, &self.path);
+            if !path.exists() {
+                eyre::bail!("No transactions to resume, {} does not exist", path.display())
+            }
+            let mut sequence = ScriptSequence::load(&path)?;
+            if let (Some(signer), Some(fork_url)) = (signer, evm_opts.fork_url.as_deref()) {
+                RuntimeOrHandle::new().block_on(sequence.send(&path, signer, fork_url))?;
+                println!("{}", Colour::Green.paint("Transactions sent."));
+            }
+            return Ok(())
+        }
+
         let BuildOutput {
             project,
             contract,
@@ -101,7 +173,7 @@ impl Cmd for RunArgs {
         let needs_setup = setup_fns.len() == 1 && setup_fns[0].name == "setUp";
 
         for setup_fn in setup_fns.iter() {
-            if setup_fn.name != "setUp" {
+            if setup_fn.name != "setUp" && !self.json {
                 println!(
                     "{} Found invalid setup function \"{}\" did you mean \"setUp()\"?",
                     Colour::Yellow.bold().paint("Warning:"),
@@ -110,8 +182,13 @@ impl Cmd for RunArgs {
             }
         }
 
+        if self.broadcast && !predeploy_libraries.is_empty() {
+            eyre::bail!("Scripts that deploy libraries can't be broadcast, deploy the libraries first and link them with `--libraries`")
+        }
+
         let runtime = RuntimeOrHandle::new();
         let env = runtime.block_on(evm_opts.evm_env());
+        let chain_id = evm_opts.fork_url.as_ref().map(|_| env.cfg.chain_id.as_u64());
         // the db backend that serves all the data
         let db = runtime
             .block_on(Backend::new(utils::get_fork(&evm_opts, &config.rpc_storage_caching), &env));
@@ -119,32 +196,58 @@ impl Cmd for RunArgs {
         let mut builder = ExecutorBuilder::new()
             .with_cheatcodes(evm_opts.ffi)
             .with_config(env)
-            .with_spec(crate::utils::evm_spec(&config.evm_version))
-            .with_gas_limit(evm_opts.gas_limit());
+            .with_spec(crate::utils::evm_spec(&config.evm_version)?)
+            .with_gas_limit(evm_opts.gas_limit())
+            .with_forks(utils::fork_config(&evm_opts, &config.rpc_storage_caching));
 
-        if verbosity >= 3 {
+        // the events of the JSON result are collected from the traces
+        if verbosity >= 3 || self.json {
             builder = builder.with_tracing();
         }
         if self.debug {
             builder = builder.with_tracing().with_debugger();
         }
 
-        let mut result = {
+        let calldata: Bytes = if let Some(calldata) = self.sig.strip_prefix("0x") {
+            if !self.args.is_empty() {
+                eyre::bail!("Arguments can't be passed along with raw calldata as --sig")
+            }
+            hex::decode(calldata)?.into()
+        } else {
+            encode_script_args(&IntoFunction::into(self.sig.clone()), &self.args)?.into()
+        };
+
+        let (mut result, transactions, state_diffs) = {
             let mut runner =
                 Runner::new(builder.build(db), evm_opts.initial_balance, evm_opts.sender);
             let (address, mut result) =
                 runner.setup(&predeploy_libraries, bytecode, needs_setup)?;
 
+            // the transactions of the script must follow the pending transactions of the sender,
+            // the simulated libraries already took the nonces otherwise
+            if let Some(fork_url) = evm_opts.fork_url.as_deref() {
+                if predeploy_libraries.is_empty() {
+                    let provider = Provider::<Http>::try_from(fork_url)?;
+                    let nonce = runtime.block_on(provider.get_transaction_count(
+                        evm_opts.sender,
+                        Some(BlockNumber::Pending.into()),
+                    ))?;
+                    runner.executor.set_nonce(evm_opts.sender, nonce.as_u64());
+                }
+            }
+            runner.executor.broadcast(address, evm_opts.sender);
+
+            let state_before = self.json.then(|| runner.executor.db.clone());
             let RunResult {
-                success, gas, logs, traces, debug: run_debug, labeled_addresses, ..
-            } = runner.run(
-                address,
-                if let Some(calldata) = self.sig.strip_prefix("0x") {
-                    hex::decode(calldata)?.into()
-                } else {
-                    encode_args(&IntoFunction::into(self.sig), &self.args)?.into()
-                },
-            )?;
+                success,
+                gas,
+                logs,
+                traces,
+                debug: run_debug,
+                labeled_addresses,
+                returned,
+                state_changeset,
+            } = runner.run(address, calldata.clone())?;
 
             result.success &= success;
 
@@ -153,8 +256,16 @@ impl Cmd for RunArgs {
             result.traces.extend(traces);
             result.debug = run_debug;
             result.labeled_addresses.extend(labeled_addresses);
+            result.returned = returned;
+
+            let state_diffs = match (state_before, state_changeset) {
+                (Some(before), Some(changeset)) => {
+                    diff_states(&before, &runner.executor.db, &changeset)
+                }
+                _ => BTreeMap::new(),
+            };
 
-            result
+            (result, runner.executor.take_broadcastable_transactions(), state_diffs)
         };
 
         // Identify addresses in each trace
@@ -200,6 +311,12 @@ impl Cmd for RunArgs {
             match tui.start().expect("Failed to start tui") {
                 TUIExitReason::CharExit => return Ok(()),
             }
+        } else if self.json {
+            for (_, trace) in &mut result.traces {
+                decoder.decode(trace);
+            }
+            let json = JsonResult::new(&result, &abi, &calldata, state_diffs, verbosity >= 3);
+            println!("{}", serde_json::to_string_pretty(&json)?);
         } else {
             if verbosity >= 3 {
                 if result.traces.is_empty() {
@@ -241,6 +358,256 @@ impl Cmd for RunArgs {
                 }
             }
         }
+
+        if !result.success {
+            if self.broadcast {
+                eyre::bail!("The script failed, no transactions were sent")
+            }
+            return Ok(())
+        }
+        if transactions.is_empty() {
+            if self.broadcast {
+                println!("The script made no transactions to broadcast.");
+            }
+            return Ok(())
+        }
+
+        let mut sequence = ScriptSequence::new(transactions, chain_id);
+        let path = ScriptSequence::path(&config.__root.0, &self.path);
+        sequence.save(&path)?;
+        if let (true, Some(signer), Some(fork_url)) =
+            (self.broadcast, signer, evm_opts.fork_url.as_deref())
+        {
+            runtime.block_on(sequence.send(&path, signer, fork_url))?;
+            println!("{}", Colour::Green.paint("Transactions sent."));
+        } else if !self.json {
+            println!(
+                "{} transaction(s) written to {}, run the script with `--broadcast` to send them.",
+                sequence.transactions.len(),
+                path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The transactions of a script, written to `broadcast/<script>/run-latest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptSequence {
+    pub transactions: Vec<ScriptTransaction>,
+    /// The chain of the fork the script ran against, if any
+    pub chain: Option<u64>,
+    /// When the script ran, in seconds since the unix epoch
+    pub timestamp: u64,
+}
+
+/// A transaction of a script and, once it was sent, its hash and receipt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptTransaction {
+    pub transaction: TypedTransaction,
+    /// The address of the contract the transaction creates
+    pub contract_address: Option<Address>,
+    pub hash: Option<H256>,
+    pub receipt: Option<TransactionReceipt>,
+}
+
+impl ScriptTransaction {
+    /// Returns how the receipt of the sent transaction on chain differs from the recorded one
+    ///
+    /// Without a recorded receipt, only the status and the contract address are compared.
+    fn diff(&self, on_chain: Option<&TransactionReceipt>) -> Option<String> {
+        let on_chain = match (on_chain, &self.receipt) {
+            (Some(on_chain), _) => on_chain,
+            (None, Some(receipt)) => {
+                return Some(format!(
+                    "recorded in block {}, not found on chain",
+                    fmt_block(receipt.block_number)
+                ))
+            }
+            (None, None) => return None,
+        };
+
+        if let Some(receipt) = &self.receipt {
+            if receipt.block_hash != on_chain.block_hash {
+                return Some(format!(
+                    "recorded in block {}, on chain in block {}",
+                    fmt_block(receipt.block_number),
+                    fmt_block(on_chain.block_number)
+                ))
+            }
+        }
+        if on_chain.status == Some(0u64.into()) {
+            return Some("reverted on chain".to_string())
+        }
+        if on_chain.contract_address != self.contract_address {
+            return Some(format!(
+                "recorded contract address {:?}, on chain {:?}",
+                self.contract_address, on_chain.contract_address
+            ))
+        }
+        None
+    }
+}
+
+fn fmt_block(number: Option<U64>) -> String {
+    number.map(|number| number.to_string()).unwrap_or_else(|| "<pending>".to_string())
+}
+
+impl ScriptSequence {
+    pub fn new(transactions: Vec<TypedTransaction>, chain: Option<u64>) -> Self {
+        let transactions = transactions
+            .into_iter()
+            .map(|transaction| {
+                let contract_address =
+                    match (transaction.to(), transaction.from(), transaction.nonce()) {
+                        (None, Some(from), Some(nonce)) => {
+                            Some(get_contract_address(*from, *nonce))
+                        }
+                        _ => None,
+                    };
+                ScriptTransaction { transaction, contract_address, hash: None, receipt: None }
+            })
+            .collect();
+        let timestamp =
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        Self { transactions, chain, timestamp }
+    }
+
+    /// Returns the path of the latest transactions of the script, relative to the project root
+    pub fn path(root: &Path, script: &Path) -> PathBuf {
+        let name = script.file_name().map(|name| name.to_string_lossy().to_string());
+        root.join("broadcast").join(name.unwrap_or_default()).join("run-latest.json")
+    }
+
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .wrap_err_with(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> eyre::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Sends the transactions that were not sent yet, in order, and waits for the receipt of each
+    /// before sending the next
+    ///
+    /// The transactions that were already sent must still be on chain as recorded, their hashes
+    /// and receipts are saved as soon as they are known.
+    pub async fn send(&mut self, path: &Path, signer: LocalWallet, url: &str) -> eyre::Result<()> {
+        let provider = Provider::<Http>::try_from(url)?;
+        let chain_id = provider.get_chainid().await?.as_u64();
+        if let Some(chain) = self.chain.filter(|chain| *chain != chain_id) {
+            eyre::bail!(
+                "The transactions are for chain {} but the RPC endpoint is on chain {}",
+                chain,
+                chain_id
+            )
+        }
+        let signer = signer.with_chain_id(chain_id);
+        let sender = signer.address();
+        println!("Sending transactions from {:?}", sender);
+        let client = SignerMiddleware::new(provider, signer);
+
+        self.check_sent(&client).await?;
+        self.save(path)?;
+
+        let first = match self.transactions.iter().position(|tx| tx.hash.is_none()) {
+            Some(first) => first,
+            None => {
+                println!("All transactions were sent already.");
+                return Ok(())
+            }
+        };
+        if first > 0 {
+            println!("Resuming from transaction {} of {}", first + 1, self.transactions.len());
+        }
+
+        // transactions of the sender that are still pending would take the nonces of the script
+        let nonce = client.get_transaction_count(sender, Some(BlockNumber::Pending.into())).await?;
+        if let Some(expected) = self.transactions[first].transaction.nonce() {
+            if *expected != nonce {
+                eyre::bail!(
+                    "The next nonce of {:?} is {} but the next transaction of the script has nonce {}, run the script again",
+                    sender,
+                    nonce,
+                    expected
+                )
+            }
+        }
+
+        for i in first..self.transactions.len() {
+            let mut transaction = self.transactions[i].transaction.clone();
+            client.fill_transaction(&mut transaction, None).await?;
+            let pending = client.send_transaction(transaction, None).await?;
+            let hash = *pending;
+            self.transactions[i].hash = Some(hash);
+            self.save(path)?;
+            println!("Sent transaction {:?}", hash);
+
+            let receipt =
+                pending.await?.ok_or_else(|| eyre::eyre!("Transaction {:?} was dropped", hash))?;
+            if let Some(diff) = self.transactions[i].diff(Some(&receipt)) {
+                eyre::bail!("Transaction {:?} {}", hash, diff)
+            }
+            if let Some(address) = receipt.contract_address {
+                println!("Deployed contract at {:?}", address);
+            }
+            self.transactions[i].receipt = Some(receipt);
+            self.save(path)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the transactions that were sent are on chain as recorded
+    ///
+    /// Receipts of transactions that were mined after they were recorded are added, transactions
+    /// that the endpoint doesn't know anymore are considered unsent.
+    async fn check_sent<M: Middleware>(&mut self, client: &M) -> eyre::Result<()> {
+        let mut diffs = Vec::new();
+        for (i, tx) in self.transactions.iter_mut().enumerate() {
+            let hash = match tx.hash {
+                Some(hash) => hash,
+                None => continue,
+            };
+            let receipt = client
+                .get_transaction_receipt(hash)
+                .await
+                .map_err(|err| eyre::eyre!("Failed to get the receipt of {:?}: {}", hash, err))?;
+
+            if let Some(diff) = tx.diff(receipt.as_ref()) {
+                diffs.push(format!("  transaction {} ({:?}): {}", i + 1, hash, diff));
+                continue
+            }
+            match receipt {
+                Some(receipt) => tx.receipt = Some(receipt),
+                None => {
+                    let known = client
+                        .get_transaction(hash)
+                        .await
+                        .map_err(|err| eyre::eyre!("Failed to get {:?}: {}", hash, err))?;
+                    if known.is_some() {
+                        eyre::bail!(
+                            "Transaction {:?} is still pending, resume once it was mined",
+                            hash
+                        )
+                    }
+                    tx.hash = None;
+                }
+            }
+        }
+
+        if !diffs.is_empty() {
+            eyre::bail!(
+                "The sent transactions don't match the chain, nothing was sent:\n{}",
+                diffs.join("\n")
+            )
+        }
         Ok(())
     }
 }
@@ -266,7 +633,11 @@ impl RunArgs {
     pub fn build(&self, config: &Config, evm_opts: &EvmOpts) -> eyre::Result<BuildOutput> {
         let target_contract = dunce::canonicalize(&self.path)?;
         let project = config.ephemeral_no_artifacts_project()?;
-        let output = compile::compile_files(&project, vec![target_contract])?;
+        let output = if self.json {
+            compile::suppress_compile_files(&project, vec![target_contract])?
+        } else {
+            compile::compile_files(&project, vec![target_contract])?
+        };
 
         let (contracts, sources) = output.into_artifacts_with_sources();
         let contracts: BTreeMap<ArtifactId, CompactContractBytecode> =
@@ -355,6 +726,188 @@ struct RunResult {
     pub debug: Option<Vec<DebugArena>>,
     pub gas: u64,
     pub labeled_addresses: BTreeMap<Address, String>,
+    pub returned: Bytes,
+    pub state_changeset: Option<StateChangeset>,
+}
+
+/// The result of a script printed with `--json`
+#[derive(Debug, Serialize)]
+struct JsonResult {
+    success: bool,
+    gas: u64,
+    /// The raw return data of the script function
+    returned: Bytes,
+    /// The decoded return values, if the function is in the ABI of the script
+    returns: Option<Vec<JsonParam>>,
+    /// The events in the order they were emitted, decoded with the ABIs of the project
+    events: Vec<JsonEvent>,
+    logs: Vec<JsonLog>,
+    created_contracts: Vec<CreatedContract>,
+    /// The changed balances, nonces and storage slots of the accounts the script touched
+    state_diffs: BTreeMap<Address, AccountDiff>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    traces: Option<Vec<(TraceKind, CallTraceArena)>>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonParam {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonEvent {
+    address: Address,
+    /// The name of the event, if it was decoded
+    name: Option<String>,
+    args: Vec<JsonParam>,
+    /// The raw log of events that are not in any ABI of the project
+    raw: Option<JsonLog>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonLog {
+    topics: Vec<H256>,
+    data: Bytes,
+}
+
+impl From<&RawLog> for JsonLog {
+    fn from(log: &RawLog) -> Self {
+        Self { topics: log.topics.clone(), data: log.data.clone().into() }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CreatedContract {
+    address: Address,
+    /// The name of the contract, if it is a contract of the project
+    name: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct AccountDiff {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    balance: Option<Change<U256>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<Change<u64>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    storage: BTreeMap<U256, Change<U256>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Change<T> {
+    from: T,
+    to: T,
+}
+
+impl<T: PartialEq> Change<T> {
+    fn new(from: T, to: T) -> Option<Self> {
+        (from != to).then(|| Self { from, to })
+    }
+}
+
+impl JsonResult {
+    fn new(
+        result: &RunResult,
+        abi: &Abi,
+        calldata: &[u8],
+        state_diffs: BTreeMap<Address, AccountDiff>,
+        with_traces: bool,
+    ) -> Self {
+        let returns = abi
+            .functions()
+            .find(|func| calldata.len() >= 4 && func.short_signature() == calldata[..4])
+            .and_then(|func| {
+                let tokens = func.decode_output(&result.returned).ok()?;
+                Some(
+                    func.outputs
+                        .iter()
+                        .zip(tokens.iter())
+                        .map(|(param, token)| JsonParam {
+                            name: param.name.clone(),
+                            value: format_token(token),
+                        })
+                        .collect(),
+                )
+            });
+
+        let events = result
+            .traces
+            .iter()
+            .flat_map(|(_, trace)| trace.logs())
+            .map(|(address, log)| match log {
+                RawOrDecodedLog::Decoded(name, args) => JsonEvent {
+                    address,
+                    name: Some(name.clone()),
+                    args: args
+                        .iter()
+                        .map(|(name, value)| JsonParam { name: name.clone(), value: value.clone() })
+                        .collect(),
+                    raw: None,
+                },
+                RawOrDecodedLog::Raw(log) => {
+                    JsonEvent { address, name: None, args: Vec::new(), raw: Some(log.into()) }
+                }
+            })
+            .collect();
+
+        // the contracts created by the script, not the script and its libraries
+        let created_contracts = result
+            .traces
+            .iter()
+            .filter(|(kind, _)| *kind != TraceKind::Deployment)
+            .flat_map(|(_, trace)| trace.arena.iter())
+            .filter(|node| node.trace.created() && node.trace.success)
+            .map(|node| CreatedContract {
+                address: node.trace.address,
+                name: node.trace.contract.clone(),
+            })
+            .collect();
+
+        Self {
+            success: result.success,
+            gas: result.gas,
+            returned: result.returned.clone(),
+            returns,
+            events,
+            logs: result.logs.iter().map(Into::into).collect(),
+            created_contracts,
+            state_diffs,
+            traces: with_traces.then(|| result.traces.clone()),
+        }
+    }
+}
+
+/// Returns the changes of the accounts in the changeset between the two states
+fn diff_states(
+    before: &impl DatabaseRef,
+    after: &impl DatabaseRef,
+    changeset: &StateChangeset,
+) -> BTreeMap<Address, AccountDiff> {
+    changeset
+        .iter()
+        .filter_map(|(address, account)| {
+            let (from, to) = (before.basic(*address), after.basic(*address));
+            let diff = AccountDiff {
+                balance: Change::new(from.balance, to.balance),
+                nonce: Change::new(from.nonce, to.nonce),
+                storage: account
+                    .storage
+                    .keys()
+                    .filter_map(|slot| {
+                        let change = Change::new(
+                            before.storage(*address, *slot),
+                            after.storage(*address, *slot),
+                        )?;
+                        Some((*slot, change))
+                    })
+                    .collect(),
+            };
+            let changed =
+                diff.balance.is_some() || diff.nonce.is_some() || !diff.storage.is_empty();
+            changed.then(|| (*address, diff))
+        })
+        .collect()
 }
 
 struct Runner<DB: DatabaseRef> {
@@ -395,14 +948,15 @@ impl<DB: DatabaseRef> Runner<DB> {
             .map(|traces| (TraceKind::Deployment, traces))
             .collect();
 
-        // Deploy an instance of the contract
+        // Deploy an instance of the contract, not from the sender so the contract doesn't take
+        // one of the nonces of its transactions
         let DeployResult {
             address,
             mut logs,
             traces: constructor_traces,
             debug: constructor_debug,
             ..
-        } = self.executor.deploy(self.sender, code.0, 0u32.into()).expect("couldn't deploy");
+        } = self.executor.deploy(*CALLER, code.0, 0u32.into()).expect("couldn't deploy");
         traces.extend(constructor_traces.map(|traces| (TraceKind::Deployment, traces)).into_iter());
         self.executor.set_balance(address, self.initial_balance);
 
@@ -440,6 +994,8 @@ impl<DB: DatabaseRef> Runner<DB> {
                             success: !reverted,
                             debug: vec![constructor_debug, debug].into_iter().collect(),
                             gas,
+                            returned: Bytes::new(),
+                            state_changeset: None,
                         },
                     )
                 }
@@ -455,14 +1011,26 @@ impl<DB: DatabaseRef> Runner<DB> {
                     debug: vec![constructor_debug].into_iter().collect(),
                     gas: 0,
                     labeled_addresses: Default::default(),
+                    returned: Bytes::new(),
+                    state_changeset: None,
                 },
             )
         })
     }
 
     pub fn run(&mut self, address: Address, calldata: Bytes) -> eyre::Result<RunResult> {
-        let RawCallResult { reverted, gas, stipend, logs, traces, labels, debug, .. } =
-            self.executor.call_raw(self.sender, address, calldata.0, 0.into())?;
+        let RawCallResult {
+            reverted,
+            result,
+            gas,
+            stipend,
+            logs,
+            traces,
+            labels,
+            debug,
+            state_changeset,
+            ..
+        } = self.executor.call_raw_committing(self.sender, address, calldata.0, 0.into())?;
         Ok(RunResult {
             success: !reverted,
             gas: gas.overflowing_sub(stipend).0,
@@ -470,6 +1038,121 @@ impl<DB: DatabaseRef> Runner<DB> {
             traces: traces.map(|traces| vec![(TraceKind::Execution, traces)]).unwrap_or_default(),
             debug: vec![debug].into_iter().collect(),
             labeled_addresses: labels,
+            returned: result.into(),
+            state_changeset,
         })
     }
 }
+
+/// ABI-encodes the call of the script function with the arguments
+///
+/// Unsigned integers are parsed like ether values, so they can be tagged with a unit.
+fn encode_script_args(func: &Function, args: &[String]) -> eyre::Result<Vec<u8>> {
+    if func.inputs.len() != args.len() {
+        eyre::bail!(
+            "`{}` expects {} argument(s), got {}",
+            func.signature(),
+            func.inputs.len(),
+            args.len()
+        )
+    }
+
+    let tokens = func
+        .inputs
+        .iter()
+        .zip(args)
+        .enumerate()
+        .map(|(idx, (param, arg))| {
+            let token = match param.kind {
+                ParamType::Uint(_) => utils::parse_ether_value(arg).map(Token::Uint),
+                _ => LenientTokenizer::tokenize(&param.kind, arg).map_err(Into::into),
+            };
+            token.wrap_err_with(|| {
+                let name = if param.name.is_empty() {
+                    String::new()
+                } else {
+                    format!(" `{}`", param.name)
+                };
+                format!(
+                    "Failed to parse argument {}{} of `{}`, `{}` is not a valid {}",
+                    idx + 1,
+                    name,
+                    func.signature(),
+                    arg,
+                    param.kind
+                )
+            })
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+    Ok(func.encode_input(&tokens)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::TransactionRequest;
+
+    #[test]
+    fn can_encode_script_args() {
+        let func = IntoFunction::into("run(address,uint256)");
+        let address = "0x00000000000000000000000000000000000000aa";
+        let encoded =
+            encode_script_args(&func, &[address.to_string(), "1ether".to_string()]).unwrap();
+        let expected = func
+            .encode_input(&[Token::Address(address.parse().unwrap()), Token::Uint(U256::exp10(18))])
+            .unwrap();
+        assert_eq!(encoded, expected);
+
+        let err = encode_script_args(&func, &[address.to_string()]).unwrap_err();
+        assert_eq!(err.to_string(), "`run(address,uint256)` expects 2 argument(s), got 1");
+
+        let err = encode_script_args(&func, &["0xabc".to_string(), "1".to_string()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse argument 1 of `run(address,uint256)`, `0xabc` is not a valid address"
+        );
+        let err =
+            encode_script_args(&func, &[address.to_string(), "1foo".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("argument 2"));
+        assert!(err.to_string().contains("uint256"));
+    }
+
+    #[test]
+    fn can_diff_sent_transactions() {
+        let contract_address = Some(Address::random());
+        let receipt = TransactionReceipt {
+            block_hash: Some(H256::random()),
+            block_number: Some(10u64.into()),
+            status: Some(1u64.into()),
+            contract_address,
+            ..Default::default()
+        };
+        let mut tx = ScriptTransaction {
+            transaction: TransactionRequest::default().into(),
+            contract_address,
+            hash: Some(H256::random()),
+            receipt: None,
+        };
+
+        // mined after the receipt was recorded
+        assert_eq!(tx.diff(None), None);
+        assert_eq!(tx.diff(Some(&receipt)), None);
+
+        tx.receipt = Some(receipt.clone());
+        assert_eq!(tx.diff(Some(&receipt)), None);
+        assert_eq!(tx.diff(None).unwrap(), "recorded in block 10, not found on chain");
+
+        let reorged = TransactionReceipt {
+            block_hash: Some(H256::random()),
+            block_number: Some(11u64.into()),
+            ..receipt.clone()
+        };
+        assert_eq!(tx.diff(Some(&reorged)).unwrap(), "recorded in block 10, on chain in block 11");
+
+        tx.receipt = None;
+        let reverted = TransactionReceipt { status: Some(0u64.into()), ..receipt.clone() };
+        assert_eq!(tx.diff(Some(&reverted)).unwrap(), "reverted on chain");
+        let other = TransactionReceipt { contract_address: Some(Address::random()), ..receipt };
+        assert!(tx.diff(Some(&other)).unwrap().starts_with("recorded contract address"));
+    }
+}
@@ -7,12 +7,13 @@ use crate::cmd::{
     },
     Cmd,
 };
-use ansi_term::Colour;
-use clap::{Parser, ValueHint};
+use ansi_term::{Colour, Style};
+use clap::{ArgEnum, Parser, ValueHint};
 use eyre::Context;
 use forge::TestKindGas;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
     collections::HashMap,
@@ -64,8 +65,23 @@ pub struct SnapshotArgs {
     )]
     check: Option<Option<PathBuf>>,
 
-    // Hidden because there is only one option
-    #[clap(help = "How to format the output.", long, hide(true))]
+    /// Only flag tests in `--check` and `--diff` whose gas changed by more than the given
+    /// percentage.
+    ///
+    /// Smaller changes are still printed, dimmed. With a tolerance set, only increases beyond it
+    /// fail `--check`, improvements don't. Without one, any change fails.
+    #[clap(long, default_value = "0", value_name = "PERCENT")]
+    tolerance: f64,
+
+    /// Only flag tests in `--check` and `--diff` whose gas changed by more than the given amount
+    /// of gas.
+    #[clap(long, default_value = "0", value_name = "GAS")]
+    tolerance_gas: u64,
+
+    /// How to format the output of `--diff`: `json` or `markdown`.
+    ///
+    /// By default, the diff is printed in a human readable format.
+    #[clap(long, requires = "diff", arg_enum, value_name = "FORMAT")]
     format: Option<Format>,
 
     #[clap(
@@ -106,49 +122,57 @@ impl Cmd for SnapshotArgs {
         let outcome = custom_run(self.test, self.include_fuzz_tests)?;
         outcome.ensure_ok()?;
         let tests = self.config.apply(outcome);
+        let tolerance = Tolerance { percent: self.tolerance, gas: self.tolerance_gas };
 
         if let Some(path) = self.diff {
             let snap = path.as_ref().unwrap_or(&self.snap);
             let snaps = read_snapshot(snap)?;
-            diff(tests, snaps)?;
+            diff(tests, snaps, self.format, tolerance)?;
         } else if let Some(path) = self.check {
             let snap = path.as_ref().unwrap_or(&self.snap);
             let snaps = read_snapshot(snap)?;
-            if check(tests, snaps) {
+            if check(tests, snaps, tolerance) {
                 std::process::exit(0)
             } else {
                 std::process::exit(1)
             }
         } else {
-            write_to_snapshot_file(&tests, self.snap, self.format)?;
+            write_to_snapshot_file(&tests, self.snap)?;
         }
         Ok(())
     }
 }
 
-// TODO implement pretty tables
-#[derive(Debug, Clone)]
+/// The machine readable formats of a snapshot diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
 pub enum Format {
-    Table,
+    /// A JSON array of [JsonSnapshotDiff]
+    Json,
+    /// A markdown table, e.g. for PR comments
+    #[clap(alias = "md")]
+    Markdown,
 }
 
-impl FromStr for Format {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "t" | "table" => Ok(Format::Table),
-            _ => Err(format!("Unrecognized format `{}`", s)),
-        }
-    }
+/// The key the tests of a snapshot are sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum SnapshotSort {
+    /// By contract name and test signature
+    Name,
+    /// By gas used, tests with the same gas are sorted by name
+    Gas,
 }
 
 /// Additional filters that can be applied on the test results
 #[derive(Debug, Clone, Parser, Default)]
 struct SnapshotConfig {
-    #[clap(help = "Sort results by gas used (ascending).", long)]
+    /// Sort results by `name` or `gas`.
+    ///
+    /// Defaults to `gas` if `--asc` or `--desc` is set, and to `name` otherwise.
+    #[clap(long, arg_enum, value_name = "KEY")]
+    sort: Option<SnapshotSort>,
+    #[clap(help = "Sort results in ascending order.", long)]
     asc: bool,
-    #[clap(help = "Sort results by gas used (descending).", conflicts_with = "asc", long)]
+    #[clap(help = "Sort results in descending order.", conflicts_with = "asc", long)]
     desc: bool,
     #[clap(help = "Only include tests that used more gas that the given amount.", long)]
     min: Option<u64>,
@@ -174,17 +198,40 @@ impl SnapshotConfig {
     fn apply(&self, outcome: TestOutcome) -> Vec<Test> {
         let mut tests = outcome
             .into_tests()
+            // invariants span many transactions, so they have no gas to snapshot
+            .filter(|test| !test.result.is_invariant())
             .filter(|test| self.is_in_gas_range(test.gas_used()))
             .collect::<Vec<_>>();
-
-        if self.asc {
-            tests.sort_by_key(|a| a.gas_used());
-        } else if self.desc {
-            tests.sort_by_key(|b| std::cmp::Reverse(b.gas_used()))
-        }
-
+        self.sort(&mut tests);
         tests
     }
+
+    /// Sorts the tests, so the same tests always result in the same snapshot
+    fn sort(&self, tests: &mut [Test]) {
+        let key = self.sort.unwrap_or(if self.asc || self.desc {
+            SnapshotSort::Gas
+        } else {
+            SnapshotSort::Name
+        });
+        let by_name = |a: &Test, b: &Test| {
+            (a.contract_name(), &a.signature, &a.artifact_id).cmp(&(
+                b.contract_name(),
+                &b.signature,
+                &b.artifact_id,
+            ))
+        };
+        tests.sort_by(|a, b| {
+            let ordering = match key {
+                SnapshotSort::Name => by_name(a, b),
+                SnapshotSort::Gas => a.gas_used().cmp(&b.gas_used()).then_with(|| by_name(a, b)),
+            };
+            if self.desc {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
 }
 
 /// A general entry in a snapshot file
@@ -252,11 +299,7 @@ fn read_snapshot(path: impl AsRef<Path>) -> eyre::Result<Vec<SnapshotEntry>> {
 }
 
 /// Writes a series of tests to a snapshot file
-fn write_to_snapshot_file(
-    tests: &[Test],
-    path: impl AsRef<Path>,
-    _format: Option<Format>,
-) -> eyre::Result<()> {
+fn write_to_snapshot_file(tests: &[Test], path: impl AsRef<Path>) -> eyre::Result<()> {
     let mut out = String::new();
     for test in tests {
         writeln!(
@@ -270,9 +313,72 @@ fn write_to_snapshot_file(
     Ok(fs::write(path, out)?)
 }
 
+/// How much the gas of a test may change before `--check` and `--diff` flag it
+///
+/// A change is within the tolerance if it is within either of the thresholds, the default
+/// tolerance only allows tests whose gas didn't change at all.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Tolerance {
+    /// The change in percent of the snapshot's gas
+    pub percent: f64,
+    /// The absolute change in gas
+    pub gas: u64,
+}
+
+impl Tolerance {
+    fn is_zero(&self) -> bool {
+        self.percent <= 0.0 && self.gas == 0
+    }
+}
+
+impl std::fmt::Display for Tolerance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.percent > 0.0, self.gas > 0) {
+            (true, true) => write!(f, "{}% or {} gas", self.percent, self.gas),
+            (false, true) => write!(f, "{} gas", self.gas),
+            _ => write!(f, "{}%", self.percent),
+        }
+    }
+}
+
+/// The number of tests whose gas regressed, improved, or stayed within the tolerance
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SnapshotSummary {
+    pub regressed: usize,
+    pub improved: usize,
+    pub within_tolerance: usize,
+}
+
+impl SnapshotSummary {
+    fn new(diffs: &[SnapshotDiff], tolerance: &Tolerance) -> Self {
+        let mut summary = Self::default();
+        for diff in diffs {
+            if diff.is_within_tolerance(tolerance) {
+                summary.within_tolerance += 1;
+            } else if diff.gas_change() > 0 {
+                summary.regressed += 1;
+            } else {
+                summary.improved += 1;
+            }
+        }
+        summary
+    }
+}
+
+impl std::fmt::Display for SnapshotSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} regressed, {} improved, {} within tolerance",
+            self.regressed, self.improved, self.within_tolerance
+        )
+    }
+}
+
 /// A Snapshot entry diff
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SnapshotDiff {
+    pub contract_name: String,
     pub signature: String,
     pub source_gas_used: TestKindGas,
     pub target_gas_used: TestKindGas,
@@ -291,32 +397,43 @@ impl SnapshotDiff {
     fn gas_diff(&self) -> f64 {
         self.gas_change() as f64 / self.target_gas_used.gas() as f64
     }
+
+    /// Whether the gas changed by no more than the tolerance, in either direction
+    fn is_within_tolerance(&self, tolerance: &Tolerance) -> bool {
+        let change = self.gas_change();
+        change == 0 ||
+            change.unsigned_abs() <= tolerance.gas as u128 ||
+            self.gas_diff().abs() * 100.0 <= tolerance.percent
+    }
+
+    /// Whether the source used more gas than the target, by more than the tolerance
+    fn exceeds_tolerance(&self, tolerance: &Tolerance) -> bool {
+        self.gas_change() > 0 && !self.is_within_tolerance(tolerance)
+    }
 }
 
 /// Compares the set of tests with an existing snapshot
 ///
-/// Returns true all tests match
-fn check(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> bool {
+/// Returns true if no test used more gas than in the snapshot, beyond the tolerance. Changes
+/// within the tolerance are printed dimmed, improvements beyond it are printed but don't fail the
+/// check. Without a tolerance, any change fails the check.
+fn check(tests: Vec<Test>, snaps: Vec<SnapshotEntry>, tolerance: Tolerance) -> bool {
     let snaps = snaps
         .into_iter()
         .map(|s| ((s.contract_name, s.signature), s.gas_used))
         .collect::<HashMap<_, _>>();
     let mut has_diff = false;
+    let mut diffs = Vec::new();
     for test in tests {
         if let Some(target_gas) =
             snaps.get(&(test.contract_name().to_string(), test.signature.clone())).cloned()
         {
-            let source_gas = test.result.kind.gas_used();
-            if source_gas.gas() != target_gas.gas() {
-                eprintln!(
-                    "Diff in \"{}::{}\": consumed \"{}\" gas, expected \"{}\" gas ",
-                    test.contract_name(),
-                    test.signature,
-                    source_gas,
-                    target_gas
-                );
-                has_diff = true;
-            }
+            diffs.push(SnapshotDiff {
+                contract_name: test.contract_name().to_string(),
+                signature: test.signature.clone(),
+                source_gas_used: test.result.kind.gas_used(),
+                target_gas_used: target_gas,
+            });
         } else {
             eprintln!(
                 "No matching snapshot entry found for \"{}::{}\" in snapshot file",
@@ -326,11 +443,116 @@ fn check(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> bool {
             has_diff = true;
         }
     }
+
+    let (within, flagged): (Vec<_>, Vec<_>) =
+        diffs.iter().cloned().partition(|diff| diff.is_within_tolerance(&tolerance));
+    for diff in within.iter().filter(|diff| diff.gas_change() != 0) {
+        eprintln!(
+            "{}",
+            Style::new().dimmed().paint(format!(
+                "Diff in \"{}::{}\" within tolerance: consumed \"{}\" gas, expected \"{}\" gas",
+                diff.contract_name, diff.signature, diff.source_gas_used, diff.target_gas_used
+            ))
+        );
+    }
+    let (regressions, improvements): (Vec<_>, Vec<_>) =
+        flagged.into_iter().partition(|diff| diff.exceeds_tolerance(&tolerance));
+    if !improvements.is_empty() {
+        if tolerance.is_zero() {
+            // without a tolerance every change fails the check, like it always did
+            eprintln!("Gas usage decreased compared to the snapshot:");
+            has_diff = true;
+        } else {
+            eprintln!("Gas usage decreased by more than {}:", tolerance);
+        }
+        eprint!("{}", changes_table(&improvements));
+    }
+    if !regressions.is_empty() {
+        if tolerance.is_zero() {
+            eprintln!("Gas usage increased compared to the snapshot:");
+        } else {
+            eprintln!("Gas usage increased by more than {}:", tolerance);
+        }
+        eprint!("{}", changes_table(&regressions));
+        has_diff = true;
+    }
+    if !diffs.is_empty() {
+        eprintln!("{}", SnapshotSummary::new(&diffs, &tolerance));
+    }
     !has_diff
 }
 
+/// Formats the changed tests as a table with their old and new gas usage
+fn changes_table(changes: &[SnapshotDiff]) -> String {
+    let rows = changes
+        .iter()
+        .map(|diff| {
+            [
+                format!("{}::{}", diff.contract_name, diff.signature),
+                diff.target_gas_used.gas().to_string(),
+                diff.source_gas_used.gas().to_string(),
+                format!("{:+} ({:.2}%)", diff.gas_change(), diff.gas_diff() * 100.0),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let header = ["Test", "Old", "New", "Delta"].map(String::from);
+    let mut widths = header.clone().map(|cell| cell.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<1$}", cell, width))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// A snapshot entry diff as printed with `--format json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonSnapshotDiff {
+    /// The name of the test contract
+    pub contract: String,
+    /// The signature of the test
+    pub test: String,
+    /// The gas used according to the snapshot file
+    pub previous_gas: u64,
+    /// The gas used by this run
+    pub new_gas: u64,
+    /// The change in gas, negative if this run used less gas
+    pub delta: i128,
+}
+
+impl From<&SnapshotDiff> for JsonSnapshotDiff {
+    fn from(diff: &SnapshotDiff) -> Self {
+        Self {
+            contract: diff.contract_name.clone(),
+            test: diff.signature.clone(),
+            previous_gas: diff.target_gas_used.gas(),
+            new_gas: diff.source_gas_used.gas(),
+            delta: diff.gas_change(),
+        }
+    }
+}
+
 /// Compare the set of tests with an existing snapshot
-fn diff(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> eyre::Result<()> {
+///
+/// Changes within the tolerance are printed dimmed.
+fn diff(
+    tests: Vec<Test>,
+    snaps: Vec<SnapshotEntry>,
+    format: Option<Format>,
+    tolerance: Tolerance,
+) -> eyre::Result<()> {
     let snaps = snaps
         .into_iter()
         .map(|s| ((s.contract_name, s.signature), s.gas_used))
@@ -348,6 +570,7 @@ fn diff(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> eyre::Result<()> {
             })?;
 
         diffs.push(SnapshotDiff {
+            contract_name: test.contract_name().to_string(),
             source_gas_used: test.result.kind.gas_used(),
             signature: test.signature,
             target_gas_used,
@@ -360,17 +583,35 @@ fn diff(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> eyre::Result<()> {
         a.gas_diff().abs().partial_cmp(&b.gas_diff().abs()).unwrap_or(Ordering::Equal)
     });
 
-    for diff in diffs {
+    match format {
+        Some(Format::Json) => {
+            let diffs = diffs.iter().map(JsonSnapshotDiff::from).collect::<Vec<_>>();
+            println!("{}", serde_json::to_string(&diffs)?);
+            return Ok(())
+        }
+        Some(Format::Markdown) => {
+            print!("{}", markdown_diff(&diffs));
+            return Ok(())
+        }
+        None => {}
+    }
+
+    for diff in &diffs {
         let gas_change = diff.gas_change();
         overall_gas_change += gas_change;
         let gas_diff = diff.gas_diff();
         overall_gas_diff += gas_diff;
-        println!(
-            "{} (gas: {} ({})) ",
-            diff.signature,
-            fmt_change(gas_change),
-            fmt_pct_change(gas_diff)
-        );
+        if gas_change != 0 && diff.is_within_tolerance(&tolerance) {
+            let line = format!("{} (gas: {} ({:.3}%)) ", diff.signature, gas_change, gas_diff);
+            println!("{}", Style::new().dimmed().paint(line));
+        } else {
+            println!(
+                "{} (gas: {} ({})) ",
+                diff.signature,
+                fmt_change(gas_change),
+                fmt_pct_change(gas_diff)
+            );
+        }
     }
 
     println!(
@@ -378,9 +619,28 @@ fn diff(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> eyre::Result<()> {
         fmt_change(overall_gas_change),
         fmt_pct_change(overall_gas_diff)
     );
+    println!("{}", SnapshotSummary::new(&diffs, &tolerance));
     Ok(())
 }
 
+/// Formats the diffs as a markdown table
+fn markdown_diff(diffs: &[SnapshotDiff]) -> String {
+    let mut table = String::from("| Test | Previous gas | New gas | Delta |\n");
+    table.push_str("| --- | ---: | ---: | ---: |\n");
+    for diff in diffs {
+        table.push_str(&format!(
+            "| `{}::{}` | {} | {} | {:+} ({:+.2}%) |\n",
+            diff.contract_name,
+            diff.signature,
+            diff.target_gas_used.gas(),
+            diff.source_gas_used.gas(),
+            diff.gas_change(),
+            diff.gas_diff() * 100.0
+        ));
+    }
+    table
+}
+
 fn fmt_pct_change(change: f64) -> String {
     match change.partial_cmp(&0.0).unwrap_or(Ordering::Equal) {
         Ordering::Less => Colour::Green.paint(format!("{:.3}%", change)).to_string(),
@@ -419,6 +679,195 @@ mod tests {
         );
     }
 
+    fn snapshot_diff(old: u64, new: u64) -> SnapshotDiff {
+        SnapshotDiff {
+            contract_name: "Test".to_string(),
+            signature: "deposit()".to_string(),
+            source_gas_used: TestKindGas::Standard(new),
+            target_gas_used: TestKindGas::Standard(old),
+        }
+    }
+
+    fn percent(percent: f64) -> Tolerance {
+        Tolerance { percent, gas: 0 }
+    }
+
+    #[test]
+    fn checks_gas_increase_against_tolerance() {
+        // regressions over the tolerance
+        assert!(snapshot_diff(1000, 1101).exceeds_tolerance(&percent(10.0)));
+        assert!(snapshot_diff(1000, 1001).exceeds_tolerance(&percent(0.0)));
+        // regressions under the tolerance
+        assert!(!snapshot_diff(1000, 1100).exceeds_tolerance(&percent(10.0)));
+        assert!(!snapshot_diff(1000, 1050).exceeds_tolerance(&percent(10.0)));
+        // improvements are never regressions
+        assert!(!snapshot_diff(1000, 500).exceeds_tolerance(&percent(0.0)));
+        assert!(!snapshot_diff(1000, 1000).exceeds_tolerance(&percent(0.0)));
+    }
+
+    #[test]
+    fn checks_gas_change_against_tolerance() {
+        // the default tolerance only allows unchanged gas
+        assert!(snapshot_diff(1000, 1000).is_within_tolerance(&Tolerance::default()));
+        assert!(!snapshot_diff(1000, 997).is_within_tolerance(&Tolerance::default()));
+        assert!(!snapshot_diff(1000, 1003).is_within_tolerance(&Tolerance::default()));
+
+        let gas = Tolerance { percent: 0.0, gas: 3 };
+        assert!(snapshot_diff(1000, 997).is_within_tolerance(&gas));
+        assert!(snapshot_diff(1000, 1003).is_within_tolerance(&gas));
+        assert!(!snapshot_diff(1000, 1004).is_within_tolerance(&gas));
+
+        // either threshold is enough
+        let both = Tolerance { percent: 1.0, gas: 3 };
+        assert!(snapshot_diff(1000, 1010).is_within_tolerance(&both));
+        assert!(snapshot_diff(100, 103).is_within_tolerance(&both));
+        assert!(!snapshot_diff(1000, 1011).is_within_tolerance(&both));
+    }
+
+    #[test]
+    fn summarizes_snapshot_diffs() {
+        let diffs =
+            [snapshot_diff(1000, 1200), snapshot_diff(1000, 800), snapshot_diff(1000, 1003)];
+        let summary = SnapshotSummary::new(&diffs, &Tolerance { percent: 0.0, gas: 3 });
+        assert_eq!(summary, SnapshotSummary { regressed: 1, improved: 1, within_tolerance: 1 });
+        assert_eq!(summary.to_string(), "1 regressed, 1 improved, 1 within tolerance");
+
+        let summary = SnapshotSummary::new(&diffs, &Tolerance::default());
+        assert_eq!(summary, SnapshotSummary { regressed: 2, improved: 1, within_tolerance: 0 });
+    }
+
+    #[test]
+    fn formats_regressions_table() {
+        let table = changes_table(&[snapshot_diff(1000, 1200), snapshot_diff(1000, 900)]);
+        assert_eq!(
+            table,
+            "Test            | Old  | New  | Delta\n\
+             Test::deposit() | 1000 | 1200 | +200 (20.00%)\n\
+             Test::deposit() | 1000 | 900  | -100 (-10.00%)\n"
+        );
+    }
+
+    #[test]
+    fn json_diff_round_trip() {
+        let diffs = [snapshot_diff(1000, 1200), snapshot_diff(1000, 900)];
+        let json = diffs.iter().map(JsonSnapshotDiff::from).collect::<Vec<_>>();
+        let json = serde_json::to_string(&json).unwrap();
+        let parsed: Vec<JsonSnapshotDiff> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                JsonSnapshotDiff {
+                    contract: "Test".to_string(),
+                    test: "deposit()".to_string(),
+                    previous_gas: 1000,
+                    new_gas: 1200,
+                    delta: 200,
+                },
+                JsonSnapshotDiff {
+                    contract: "Test".to_string(),
+                    test: "deposit()".to_string(),
+                    previous_gas: 1000,
+                    new_gas: 900,
+                    delta: -100,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_markdown_diff() {
+        let table = markdown_diff(&[snapshot_diff(1000, 900)]);
+        assert_eq!(
+            table.lines().last().unwrap(),
+            "| `Test::deposit()` | 1000 | 900 | -100 (-10.00%) |"
+        );
+    }
+
+    fn test(artifact_id: &str, signature: &str, gas: u64) -> Test {
+        Test {
+            artifact_id: artifact_id.to_string(),
+            signature: signature.to_string(),
+            result: forge::TestResult {
+                success: true,
+                reason: None,
+                counterexample: None,
+                fuzz_seed: None,
+                logs: vec![],
+                kind: forge::TestKind::Standard(gas),
+                traces: vec![],
+                labeled_addresses: Default::default(),
+                duration: Default::default(),
+                call_sequence: None,
+                coverage: None,
+            },
+        }
+    }
+
+    fn snapshot_entry(signature: &str, gas: u64) -> SnapshotEntry {
+        SnapshotEntry {
+            contract_name: "ATest".to_string(),
+            signature: signature.to_string(),
+            gas_used: TestKindGas::Standard(gas),
+        }
+    }
+
+    #[test]
+    fn check_only_fails_on_regressions() {
+        let snaps = || vec![snapshot_entry("testA()", 1000), snapshot_entry("testB()", 1000)];
+        let tests = |a, b| {
+            vec![test("src/A.t.sol:ATest", "testA()", a), test("src/A.t.sol:ATest", "testB()", b)]
+        };
+
+        assert!(check(tests(1000, 1000), snaps(), Tolerance::default()));
+        // any change fails without a tolerance
+        assert!(!check(tests(500, 1000), snaps(), Tolerance::default()));
+        // improvements beyond the tolerance
+        assert!(check(tests(500, 1050), snaps(), percent(10.0)));
+        // regressions beyond the tolerance
+        assert!(!check(tests(1000, 1001), snaps(), Tolerance::default()));
+        assert!(!check(tests(500, 1200), snaps(), percent(10.0)));
+    }
+
+    fn sorted(config: SnapshotConfig) -> Vec<String> {
+        let mut tests = vec![
+            test("src/B.t.sol:BTest", "testB()", 200),
+            test("src/A.t.sol:ATest", "testB()", 100),
+            test("src/B.t.sol:BTest", "testA()", 100),
+            test("src/A.t.sol:ATest", "testA()", 300),
+        ];
+        config.sort(&mut tests);
+        tests.iter().map(|test| format!("{}:{}", test.contract_name(), test.signature)).collect()
+    }
+
+    #[test]
+    fn sorts_snapshot_tests() {
+        assert_eq!(
+            sorted(SnapshotConfig::default()),
+            ["ATest:testA()", "ATest:testB()", "BTest:testA()", "BTest:testB()"]
+        );
+        // tests with the same gas are sorted by name
+        assert_eq!(
+            sorted(SnapshotConfig { asc: true, ..Default::default() }),
+            ["ATest:testB()", "BTest:testA()", "BTest:testB()", "ATest:testA()"]
+        );
+        assert_eq!(
+            sorted(SnapshotConfig { sort: Some(SnapshotSort::Gas), ..Default::default() }),
+            sorted(SnapshotConfig { asc: true, ..Default::default() })
+        );
+        assert_eq!(
+            sorted(SnapshotConfig { desc: true, ..Default::default() }),
+            ["ATest:testA()", "BTest:testB()", "BTest:testA()", "ATest:testB()"]
+        );
+        assert_eq!(
+            sorted(SnapshotConfig {
+                sort: Some(SnapshotSort::Name),
+                desc: true,
+                ..Default::default()
+            }),
+            ["BTest:testB()", "BTest:testA()", "ATest:testB()", "ATest:testA()"]
+        );
+    }
+
     #[test]
     fn can_parse_fuzz_snapshot_entry() {
         let s = "Test:deposit() (runs: 256, μ: 100, ~:200)";
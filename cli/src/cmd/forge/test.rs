@@ -1,31 +1,38 @@
 //! Test command
 use crate::{
     cmd::{
-        forge::{build::CoreBuildArgs, run::RunArgs, watch::WatchArgs},
+        forge::{
+            build::CoreBuildArgs,
+            run::RunArgs,
+            watch::{self, WatchArgs},
+        },
         Cmd,
     },
-    compile::ProjectCompiler,
+    compile::{self, ProjectCompiler},
     utils,
     utils::FoundryPathExt,
 };
 use ansi_term::Colour;
-use clap::{AppSettings, Parser};
+use clap::{AppSettings, ArgEnum, Parser, ValueHint};
 use ethers::solc::FileFilter;
 use forge::{
     decode::decode_console_logs,
     executor::opts::EvmOpts,
-    gas_report::GasReport,
+    fuzz::invariant::{InvariantCall, InvariantConfig},
+    gas_report::{read_json_report, GasReport, GasReportDiff, JsonGasReport},
     trace::{
         identifier::{EtherscanIdentifier, LocalTraceIdentifier},
         CallTraceDecoder, TraceKind,
     },
-    MultiContractRunner, MultiContractRunnerBuilder, SuiteResult, TestFilter, TestKind,
+    FuzzCorpus, MultiContractRunner, MultiContractRunnerBuilder, SuiteResult, TestFilter, TestKind,
+    TestKindGas,
 };
 use foundry_common::evm::EvmArgs;
 use foundry_config::{figment::Figment, Config};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     path::{Path, PathBuf},
     sync::mpsc::channel,
     thread,
@@ -46,6 +53,8 @@ pub struct Filter {
     pub test_pattern: Option<regex::Regex>,
 
     /// Only run test functions that do not match the specified regex pattern.
+    ///
+    /// Excludes win: a test function matching both --match-test and --no-match-test is not run.
     #[clap(long = "no-match-test", alias = "nmt", conflicts_with = "pattern")]
     pub test_pattern_inverse: Option<regex::Regex>,
 
@@ -54,14 +63,20 @@ pub struct Filter {
     pub contract_pattern: Option<regex::Regex>,
 
     /// Only run tests in contracts that do not match the specified regex pattern.
+    ///
+    /// Excludes win: a contract matching both --match-contract and --no-match-contract is not run.
     #[clap(long = "no-match-contract", alias = "nmc", conflicts_with = "pattern")]
     pub contract_pattern_inverse: Option<regex::Regex>,
 
     /// Only run tests in source files matching the specified glob pattern.
+    ///
+    /// Paths are matched relative to the project root, e.g. `test/integration/**`.
     #[clap(long = "match-path", alias = "mp", conflicts_with = "pattern")]
     pub path_pattern: Option<globset::Glob>,
 
     /// Only run tests in source files that do not match the specified glob pattern.
+    ///
+    /// Paths are matched relative to the project root, e.g. `test/integration/**`.
     #[clap(
         name = "no-match-path",
         long = "no-match-path",
@@ -69,21 +84,69 @@ pub struct Filter {
         conflicts_with = "pattern"
     )]
     pub path_pattern_inverse: Option<globset::Glob>,
+
+    /// The project root the path patterns are relative to
+    #[clap(skip)]
+    root: Option<PathBuf>,
+
+    /// The contract and test patterns of the recorded failures, see `--rerun`
+    #[clap(skip)]
+    failures: Option<(regex::Regex, regex::Regex)>,
+}
+
+impl Filter {
+    /// Sets the project root, source file paths are matched relative to it
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Only matches the tests that match the contract and test patterns of the recorded failures
+    /// as well, see [rerun_patterns()]
+    pub fn with_failures(mut self, (contracts, tests): (regex::Regex, regex::Regex)) -> Self {
+        self.failures = Some((contracts, tests));
+        self
+    }
+
+    /// Returns true if the path matches the `--match-path` glob and doesn't match the
+    /// `--no-match-path` glob
+    fn is_path_match(&self, path: &Path) -> bool {
+        let path = self.root.as_ref().and_then(|root| path.strip_prefix(root).ok()).unwrap_or(path);
+        let path = normalize_separators(&path.to_string_lossy());
+        let mut ok = true;
+        if let Some(ref glob) = self.path_pattern {
+            ok &= glob_matcher(glob).is_match(&path);
+        }
+        if let Some(ref glob) = self.path_pattern_inverse {
+            ok &= !glob_matcher(glob).is_match(&path);
+        }
+        ok
+    }
+}
+
+/// Paths and globs use `/` as separator, on windows `\` is accepted as well
+fn normalize_separators(path: &str) -> String {
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    }
+}
+
+fn glob_matcher(glob: &globset::Glob) -> globset::GlobMatcher {
+    globset::Glob::new(&normalize_separators(glob.glob()))
+        .unwrap_or_else(|_| glob.clone())
+        .compile_matcher()
 }
 
 impl FileFilter for Filter {
-    /// Returns true if the file regex pattern match the `file`
+    /// Returns true if the file matches the `--match-path` and `--no-match-path` globs
     ///
-    /// If no file regex is set this returns true if the file ends with `.t.sol`, see
+    /// If no glob is set this returns true if the file ends with `.t.sol`, see
     /// [FoundryPathExr::is_sol_test()]
     fn is_match(&self, file: &Path) -> bool {
-        if let Some(file) = file.as_os_str().to_str() {
-            if let Some(ref glob) = self.path_pattern {
-                return glob.compile_matcher().is_match(file)
-            }
-            if let Some(ref glob) = self.path_pattern_inverse {
-                return !glob.compile_matcher().is_match(file)
-            }
+        if self.path_pattern.is_some() || self.path_pattern_inverse.is_some() {
+            return self.is_path_match(file)
         }
         file.is_sol_test()
     }
@@ -103,6 +166,9 @@ impl TestFilter for Filter {
         if let Some(re) = &self.test_pattern_inverse {
             ok &= !re.is_match(test_name);
         }
+        if let Some((_, re)) = &self.failures {
+            ok &= re.is_match(test_name);
+        }
         ok
     }
 
@@ -115,19 +181,14 @@ impl TestFilter for Filter {
         if let Some(re) = &self.contract_pattern_inverse {
             ok &= !re.is_match(contract_name);
         }
+        if let Some((re, _)) = &self.failures {
+            ok &= re.is_match(contract_name);
+        }
         ok
     }
 
     fn matches_path(&self, path: impl AsRef<str>) -> bool {
-        let mut ok = true;
-        let path = path.as_ref();
-        if let Some(ref glob) = self.path_pattern {
-            ok &= glob.compile_matcher().is_match(path);
-        }
-        if let Some(ref glob) = self.path_pattern_inverse {
-            ok &= !glob.compile_matcher().is_match(path);
-        }
-        ok
+        self.is_path_match(Path::new(path.as_ref()))
     }
 }
 
@@ -162,14 +223,113 @@ pub struct TestArgs {
     #[clap(long, env = "FORGE_GAS_REPORT")]
     gas_report: bool,
 
+    /// The format of the gas report.
+    ///
+    /// The JSON report is keyed by the `<path>:<contract>` identifier of each contract.
+    #[clap(long, arg_enum, default_value = "table", value_name = "FORMAT")]
+    gas_report_format: GasReportFormat,
+
+    /// Save the gas report as JSON to the file, to compare later runs against it.
+    #[clap(long, value_hint = ValueHint::FilePath, value_name = "FILE")]
+    gas_report_save: Option<PathBuf>,
+
+    /// Compare the gas report with a report saved by `--gas-report-save`.
+    ///
+    /// Prints the change of the median gas of every function, functions that only exist in one
+    /// of the reports are shown as added or removed.
+    #[clap(long, value_hint = ValueHint::FilePath, value_name = "FILE")]
+    gas_report_diff: Option<PathBuf>,
+
+    /// The increase in percent above which a function's gas is highlighted as a regression.
+    #[clap(long, default_value = "0", value_name = "PERCENT", requires = "gas-report-diff")]
+    gas_report_tolerance: f64,
+
+    /// Exit with a non-zero code if the gas of a function regressed by more than the tolerance.
+    #[clap(long, requires = "gas-report-diff")]
+    gas_report_check: bool,
+
     /// Exit with code 0 even if a test fails.
     #[clap(long, env = "FORGE_ALLOW_FAILURE")]
     allow_failure: bool,
 
+    /// The number of test contracts to run in parallel.
+    ///
+    /// Defaults to the number of logical CPUs, `--jobs 1` runs the test contracts one after
+    /// another.
+    #[clap(long, value_name = "JOBS")]
+    jobs: Option<usize>,
+
+    /// Stop running tests after the first failure.
+    ///
+    /// The tests and test contracts that didn't start yet are skipped, tests that already run in
+    /// parallel are completed and reported.
+    #[clap(long)]
+    fail_fast: bool,
+
+    /// Seed the fuzzer, so fuzz tests generate the same inputs on every run.
+    ///
+    /// The seed of a run is printed with every failed fuzz test, a random seed is used if none
+    /// is set.
+    #[clap(long, value_name = "SEED")]
+    fuzz_seed: Option<u64>,
+
+    /// Don't replay or store the counterexamples of fuzz tests.
+    ///
+    /// By default, the counterexamples of failed fuzz tests are stored in the project's cache
+    /// directory and replayed before the random inputs of later runs.
+    #[clap(long)]
+    no_fuzz_persistence: bool,
+
+    /// Fail every test that runs longer than the given number of seconds.
+    ///
+    /// The remaining tests keep running. Fuzz tests are limited as a whole, not per run.
+    #[clap(long, value_name = "SECONDS")]
+    test_timeout: Option<u64>,
+
+    /// Only run the tests that failed in the previous runs.
+    ///
+    /// The failing tests of every run are recorded in the project's cache directory. All tests
+    /// are run if no failures are recorded. The failures are narrowed down further by the match
+    /// filters.
+    #[clap(long, alias = "rerun-failed", conflicts_with = "debug")]
+    rerun: bool,
+
     /// Output test results in JSON format.
+    ///
+    /// Prints a single JSON array with one entry per test, nothing else is printed to stdout.
     #[clap(long, short, help_heading = "DISPLAY OPTIONS")]
     json: bool,
 
+    /// Output test results as newline delimited JSON, one line per test as soon as it finished.
+    #[clap(long, help_heading = "DISPLAY OPTIONS", conflicts_with = "json")]
+    json_stream: bool,
+
+    /// Highlight tests that run longer than the given number of milliseconds.
+    ///
+    /// The duration of a slow test is printed next to its gas, for fuzz tests together with the
+    /// average duration of a run.
+    #[clap(long, help_heading = "DISPLAY OPTIONS", default_value = "1000", value_name = "MS")]
+    slow: u64,
+
+    /// Print the 10 slowest tests once all tests ran.
+    #[clap(long, help_heading = "DISPLAY OPTIONS")]
+    summary: bool,
+
+    /// Write a test report in the given format, see --report-path.
+    #[clap(long, arg_enum, value_name = "FORMAT", requires = "report-path")]
+    report: Option<TestReport>,
+
+    /// The file to write the test report to, see --report.
+    #[clap(long, value_name = "PATH", requires = "report", value_hint = ValueHint::FilePath)]
+    report_path: Option<PathBuf>,
+
+    /// Write a JUnit XML report to the given file, short for `--report junit --report-path`.
+    ///
+    /// The report has a `testcase` per test with its duration, failed tests have a `failure`
+    /// with the revert reason.
+    #[clap(long, value_name = "PATH", conflicts_with = "report", value_hint = ValueHint::FilePath)]
+    junit: Option<PathBuf>,
+
     #[clap(flatten, next_help_heading = "EVM OPTIONS")]
     evm_opts: EvmArgs,
 
@@ -192,14 +352,24 @@ impl TestArgs {
     }
 
     /// Returns the currently configured [Config] and the extracted [EvmOpts] from that config
+    ///
+    /// The name of an RPC endpoint in `--fork-url` is resolved to its url and a
+    /// `--fork-block-number` tag is resolved to the block number the run is pinned to.
     pub fn config_and_evm_opts(&self) -> eyre::Result<(Config, EvmOpts)> {
         // merge all configs
         let figment: Figment = self.into();
-        let evm_opts = figment.extract()?;
+        let mut evm_opts = figment.extract::<EvmOpts>()?;
+        evm_opts.resolve_fork_url()?;
+        utils::resolve_fork_block(&mut evm_opts, self.evm_opts.fork_block())?;
         let config = Config::from_provider(figment).sanitized();
         Ok((config, evm_opts))
     }
 
+    /// Returns whether the results are printed as JSON
+    pub fn is_json(&self) -> bool {
+        self.json || self.json_stream
+    }
+
     /// Returns whether `BuildArgs` was configured with `--watch`
     pub fn is_watch(&self) -> bool {
         self.watch.watch.is_some()
@@ -208,7 +378,63 @@ impl TestArgs {
     /// Returns the [`watchexec::InitConfig`] and [`watchexec::RuntimeConfig`] necessary to
     /// bootstrap a new [`watchexe::Watchexec`] loop.
     pub(crate) fn watchexec_config(&self) -> eyre::Result<(InitConfig, RuntimeConfig)> {
-        self.watch.watchexec_config(|| Config::from(self).src)
+        self.watch.watchexec_config(|| watch::watch_paths(&Config::from(self)))
+    }
+
+    /// Returns the test runner builder configured by the arguments and the config
+    pub fn runner_builder(
+        &self,
+        config: &Config,
+        evm_opts: &EvmOpts,
+    ) -> eyre::Result<MultiContractRunnerBuilder> {
+        // Setup the fuzzer
+        let cfg = proptest::test_runner::Config {
+            failure_persistence: None,
+            cases: config.fuzz_runs,
+            max_local_rejects: config.fuzz_max_local_rejects,
+            max_global_rejects: config.fuzz_max_global_rejects,
+            ..Default::default()
+        };
+        let fuzz_seed =
+            self.fuzz_seed.or(config.fuzz_seed).unwrap_or_else(ethers::core::rand::random::<u64>);
+
+        let evm_spec = crate::utils::evm_spec(&config.evm_version)?;
+        // the run starts on the first fork, `createFork` pins the other endpoints to their blocks
+        let mut forks =
+            utils::get_forks_of_args(evm_opts, &self.evm_opts, &config.rpc_storage_caching)?
+                .into_iter();
+        let fork = forks.next();
+        let fork_config = forks.fold(
+            utils::fork_config(evm_opts, &config.rpc_storage_caching),
+            |fork_config, fork| match fork.pin_block {
+                Some(block) => fork_config.with_pinned_block(fork.url, block),
+                None => fork_config,
+            },
+        );
+        let mut builder = MultiContractRunnerBuilder::default()
+            .seeded_fuzzer(cfg, fuzz_seed)
+            .initial_balance(evm_opts.initial_balance)
+            .evm_spec(evm_spec)
+            .sender(evm_opts.sender)
+            .with_fork(fork)
+            .with_fork_config(fork_config)
+            .invariant_config(InvariantConfig {
+                runs: config.invariant_runs,
+                depth: config.invariant_depth,
+            });
+        if let Some(jobs) = self.jobs {
+            builder = builder.jobs(jobs);
+        }
+        if self.fail_fast {
+            builder = builder.fail_fast(true);
+        }
+        if let Some(timeout) = self.test_timeout.or(config.test_timeout) {
+            builder = builder.test_timeout(Duration::from_secs(timeout));
+        }
+        if !self.no_fuzz_persistence {
+            builder = builder.fuzz_corpus(read_fuzz_corpus(&fuzz_corpus_file(config)));
+        }
+        Ok(builder)
     }
 }
 
@@ -248,17 +474,134 @@ impl Test {
     }
 }
 
+/// The result of a single test as printed with `--json`
+///
+/// This is the stable output schema of `forge test --json` and `--json-stream`: fields are only
+/// ever added, never renamed or removed. `--json` prints an array of these objects, `--json-stream`
+/// prints one object per line.
+///
+/// ```json
+/// {
+///   "contract": "src/test/Vault.t.sol:VaultTest",
+///   "test": "testDeposit(uint256)",
+///   "success": false,
+///   "reason": "Assertion failed",
+///   "counterexample": "calldata=0x..., args=[1]",
+///   "gas": 42000,
+///   "fuzz": { "runs": 256, "mean_gas": 42100, "median_gas": 42000, "seed": 1337, "mean_run_duration_ms": 0.05 },
+///   "invariant": { "runs": 256, "calls": 3840, "reverts": 12, "call_sequence": [...] },
+///   "logs": ["deposited"],
+///   "duration_ms": 12.5
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonTestResult {
+    /// The identifier of the test contract in the form of `<artifact file name>:<contract name>`
+    pub contract: String,
+    /// The signature of the solidity test
+    pub test: String,
+    /// Whether the test passed
+    pub success: bool,
+    /// The revert reason, if the test reverted
+    pub reason: Option<String>,
+    /// Minimal reproduction test case for failing fuzz tests
+    pub counterexample: Option<String>,
+    /// The gas used by the test, for fuzz tests this is the median
+    pub gas: u64,
+    /// Statistics of the fuzz runs, omitted if this is not a fuzz test
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzz: Option<JsonFuzzStats>,
+    /// Statistics of the invariant campaign, omitted if this is not an invariant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invariant: Option<JsonInvariantStats>,
+    /// The decoded console logs
+    pub logs: Vec<String>,
+    /// How long the test took, in milliseconds
+    pub duration_ms: f64,
+}
+
+/// Gas statistics of a fuzz test as printed with `--json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonFuzzStats {
+    /// The number of fuzz runs
+    pub runs: usize,
+    /// The mean gas used across all runs
+    pub mean_gas: u64,
+    /// The median gas used across all runs
+    pub median_gas: u64,
+    /// The seed of the fuzzer, pass it to `--fuzz-seed` to reproduce the runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// The average duration of a run, in milliseconds
+    #[serde(default)]
+    pub mean_run_duration_ms: f64,
+}
+
+/// Statistics of an invariant campaign as printed with `--json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonInvariantStats {
+    /// The number of call sequences that were run
+    pub runs: usize,
+    /// The number of calls made to the target contracts
+    pub calls: usize,
+    /// The number of calls to the target contracts that reverted
+    pub reverts: usize,
+    /// The shrunk sequence of calls that broke the invariant, omitted if it holds
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub call_sequence: Vec<InvariantCall>,
+}
+
+impl JsonTestResult {
+    fn new(contract: &str, test: &str, result: &forge::TestResult) -> Self {
+        let gas = result.kind.gas_used();
+        let fuzz = match gas {
+            TestKindGas::Fuzz { runs, mean, median } => Some(JsonFuzzStats {
+                runs,
+                mean_gas: mean,
+                median_gas: median,
+                seed: result.fuzz_seed,
+                mean_run_duration_ms: mean_run_duration(result.duration, runs).as_secs_f64() *
+                    1000.0,
+            }),
+            TestKindGas::Standard(_) | TestKindGas::Invariant { .. } => None,
+        };
+        let invariant = match gas {
+            TestKindGas::Invariant { runs, calls, reverts } => Some(JsonInvariantStats {
+                runs,
+                calls,
+                reverts,
+                call_sequence: result.call_sequence.clone().unwrap_or_default(),
+            }),
+            _ => None,
+        };
+        Self {
+            contract: contract.to_string(),
+            test: test.to_string(),
+            success: result.success,
+            reason: result.reason.clone(),
+            counterexample: result.counterexample.as_ref().map(ToString::to_string),
+            gas: gas.gas(),
+            fuzz,
+            invariant,
+            logs: decode_console_logs(&result.logs),
+            duration_ms: result.duration.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
 /// Represents the bundled results of all tests
 pub struct TestOutcome {
     /// Whether failures are allowed
     pub allow_failure: bool,
+    /// Whether to print nothing to stdout, e.g. because the results were printed as JSON
+    pub quiet: bool,
     /// Results for each suite of tests `contract -> SuiteResult`
     pub results: BTreeMap<String, SuiteResult>,
 }
 
 impl TestOutcome {
     fn new(results: BTreeMap<String, SuiteResult>, allow_failure: bool) -> Self {
-        Self { results, allow_failure }
+        Self { results, allow_failure, quiet: false }
     }
 
     /// Iterator over all succeeding tests and their names
@@ -290,11 +633,14 @@ impl TestOutcome {
     pub fn ensure_ok(&self) -> eyre::Result<()> {
         if !self.allow_failure {
             let failures = self.failures().count();
+            if failures > 0 && self.quiet {
+                std::process::exit(1);
+            }
             if failures > 0 {
                 println!();
                 println!("Failed tests:");
                 for (name, result) in self.failures() {
-                    short_test_result(name, result);
+                    short_test_result(name, result, None);
                 }
                 println!();
 
@@ -316,6 +662,20 @@ impl TestOutcome {
             .fold(Duration::ZERO, |acc, SuiteResult { duration, .. }| acc + *duration)
     }
 
+    /// Returns the slowest tests with the name of their contract, the slowest first
+    pub fn slowest(&self, n: usize) -> Vec<(&String, &String, &forge::TestResult)> {
+        let mut tests = self
+            .results
+            .iter()
+            .flat_map(|(contract, suite)| {
+                suite.test_results.iter().map(move |(name, result)| (contract, name, result))
+            })
+            .collect::<Vec<_>>();
+        tests.sort_by(|(_, _, a), (_, _, b)| b.duration.cmp(&a.duration));
+        tests.truncate(n);
+        tests
+    }
+
     pub fn summary(&self) -> String {
         let failed = self.failures().count();
         let result =
@@ -330,7 +690,46 @@ impl TestOutcome {
     }
 }
 
-fn short_test_result(name: &str, result: &forge::TestResult) {
+/// Returns the average duration of a fuzz run
+fn mean_run_duration(duration: Duration, runs: usize) -> Duration {
+    if runs == 0 {
+        return Duration::ZERO
+    }
+    duration / runs as u32
+}
+
+/// Returns how long the test took, for fuzz tests together with the average duration of a run
+fn format_test_duration(result: &forge::TestResult) -> String {
+    match result.kind.gas_used() {
+        TestKindGas::Fuzz { runs, .. } => format!(
+            "[{:.2?}, {:.2?}/run]",
+            result.duration,
+            mean_run_duration(result.duration, runs)
+        ),
+        _ => format!("[{:.2?}]", result.duration),
+    }
+}
+
+/// The number of tests listed by `--summary`
+const SLOWEST_TESTS: usize = 10;
+
+/// Renders the slowest tests, one per line
+fn slowest_tests_summary(outcome: &TestOutcome) -> String {
+    let mut summary = String::from("Slowest tests:\n");
+    for (contract, name, result) in outcome.slowest(SLOWEST_TESTS) {
+        summary.push_str(&format!(
+            "  {} {}::{}\n",
+            format_test_duration(result),
+            utils::get_contract_name(contract),
+            name
+        ));
+    }
+    summary
+}
+
+/// Prints the result of a single test, the duration is printed if the test ran longer than the
+/// `slow` threshold
+fn short_test_result(name: &str, result: &forge::TestResult, slow: Option<Duration>) {
     let status = if result.success {
         Colour::Green.paint("[PASS]")
     } else {
@@ -350,28 +749,61 @@ fn short_test_result(name: &str, result: &forge::TestResult) {
         Colour::Red.paint(txt)
     };
 
-    println!("{} {} {}", status, name, result.kind.gas_used());
+    match slow {
+        Some(threshold) if result.duration > threshold => println!(
+            "{} {} {} {}",
+            status,
+            name,
+            result.kind.gas_used(),
+            Colour::Yellow.paint(format_test_duration(result))
+        ),
+        _ => println!("{} {} {}", status, name, result.kind.gas_used()),
+    }
+    if let (false, Some(seed)) = (result.success, result.fuzz_seed) {
+        println!("  Fuzz seed: {} (rerun with `--fuzz-seed {}`)", seed, seed);
+    }
+    if let Some(ref sequence) = result.call_sequence {
+        println!("  Call sequence:");
+        for call in sequence {
+            println!("    {}", call);
+        }
+    }
 }
 
 pub fn custom_run(mut args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<TestOutcome> {
     // Merge all configs
     let (config, mut evm_opts) = args.config_and_evm_opts()?;
-
-    // Setup the fuzzer
-    // TODO: Add CLI Options to modify the persistence
-    let cfg = proptest::test_runner::Config {
-        failure_persistence: None,
-        cases: config.fuzz_runs,
-        max_local_rejects: config.fuzz_max_local_rejects,
-        max_global_rejects: config.fuzz_max_global_rejects,
-        ..Default::default()
-    };
-    let fuzzer = proptest::test_runner::TestRunner::new(cfg);
+    args.filter = args.filter.with_root(&config.__root.0);
+
+    let failures_file = failures_file(&config);
+    let corpus_file = (!args.no_fuzz_persistence).then(|| fuzz_corpus_file(&config));
+    if args.rerun {
+        let failures = read_failures(&failures_file);
+        if failures.is_empty() {
+            if !args.is_json() {
+                println!(
+                    "No failed tests recorded in {}, running all tests",
+                    failures_file.display()
+                );
+            }
+        } else {
+            args.filter = args.filter.with_failures(rerun_patterns(&failures)?);
+        }
+    }
 
     // Set up the project
+    args.opts.ensure_locked(&config)?;
     let project = config.project()?;
     let compiler = ProjectCompiler::default();
-    let output = if config.sparse_mode {
+    let output = if args.is_json() {
+        // don't print any compiler output to keep stdout parseable
+        let filter = args.filter.clone();
+        if config.sparse_mode {
+            compile::suppress_compile_with(&project, |prj| Ok(prj.compile_sparse(filter)?))
+        } else {
+            compile::suppress_compile(&project)
+        }
+    } else if config.sparse_mode {
         compiler.compile_sparse(&project, args.filter.clone())
     } else {
         compiler.compile(&project)
@@ -379,81 +811,326 @@ pub fn custom_run(mut args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<
 
     // Determine print verbosity and executor verbosity
     let verbosity = evm_opts.verbosity;
-    if args.gas_report && evm_opts.verbosity < 3 {
+    let gas_reporting =
+        args.gas_report || args.gas_report_save.is_some() || args.gas_report_diff.is_some();
+    if gas_reporting && evm_opts.verbosity < 3 {
         evm_opts.verbosity = 3;
     }
 
     // Prepare the test builder
-    let evm_spec = crate::utils::evm_spec(&config.evm_version);
-    let mut runner = MultiContractRunnerBuilder::default()
-        .fuzzer(fuzzer)
-        .initial_balance(evm_opts.initial_balance)
-        .evm_spec(evm_spec)
-        .sender(evm_opts.sender)
-        .with_fork(utils::get_fork(&evm_opts, &config.rpc_storage_caching))
-        .build(project.paths.root, output, evm_opts)?;
+    let mut runner =
+        args.runner_builder(&config, &evm_opts)?.build(project.paths.root, output, evm_opts)?;
 
     if args.debug.is_some() {
         args.filter.test_pattern = args.debug;
-        match runner.count_filtered_tests(&args.filter) {
-                1 => {
-                    // Run the test
-                    let results = runner.test(&args.filter, None, true)?;
-
-                    // Get the result of the single test
-                    let (id, sig, test_kind, counterexample) = results.iter().map(|(id, SuiteResult{ test_results, .. })| {
-                        let (sig, result) = test_results.iter().next().unwrap();
-
-                        (id.clone(), sig.clone(), result.kind.clone(), result.counterexample.clone())
-                    }).next().unwrap();
-
-                    // Build debugger args if this is a fuzz test
-                    let sig = match test_kind {
-                        TestKind::Fuzz(cases) => {
-                            if let Some(counterexample) = counterexample {
-                                counterexample.calldata.to_string()
-                            } else {
-                                cases.cases().first().expect("no fuzz cases run").calldata.to_string()
-                            }
-                        },
-                        _ => sig,
-                    };
-
-                    // Run the debugger
-                    let debugger = RunArgs {
-                        path: PathBuf::from(runner.source_paths.get(&id).unwrap()),
-                        target_contract: Some(utils::get_contract_name(&id).to_string()),
-                        sig,
-                        args: Vec::new(),
-                        debug: true,
-                        opts: args.opts,
-                        evm_opts: args.evm_opts,
-                    };
-                    debugger.run()?;
-
-                    Ok(TestOutcome::new(results, args.allow_failure))
+        let tests = runner.filtered_tests(&args.filter);
+        match tests.len() {
+            1 => {}
+            0 => eyre::bail!("No test matched your criteria, exactly 1 test must match in order to run the debugger."),
+            n => eyre::bail!(
+                "{} tests matched your criteria, but exactly 1 test must match in order to run the debugger:\n    {}\n\nUse --match-contract and --match-path to further limit the search.",
+                n,
+                tests.join("\n    ")
+            ),
+        }
+
+        // Run the test
+        let results = runner.test(&args.filter, None, true)?;
+
+        // Get the result of the single test
+        let (id, sig, test_kind, counterexample) = results
+            .iter()
+            .map(|(id, SuiteResult { test_results, .. })| {
+                let (sig, result) = test_results.iter().next().unwrap();
+
+                (id.clone(), sig.clone(), result.kind.clone(), result.counterexample.clone())
+            })
+            .next()
+            .unwrap();
+
+        // Build debugger args if this is a fuzz test
+        let sig = match test_kind {
+            TestKind::Fuzz(cases) => {
+                if let Some(counterexample) = counterexample {
+                    counterexample.calldata.to_string()
+                } else {
+                    cases.cases().first().expect("no fuzz cases run").calldata.to_string()
                 }
-                n =>
-                    Err(
-                    eyre::eyre!("{} tests matched your criteria, but exactly 1 test must match in order to run the debugger.\n
-                        \n
-                        Use --match-contract and --match-path to further limit the search.", n))
             }
+            _ => sig,
+        };
+
+        // Run the debugger
+        let debugger = RunArgs {
+            path: PathBuf::from(runner.source_paths.get(&id).unwrap()),
+            target_contract: Some(utils::get_contract_name(&id).to_string()),
+            sig,
+            args: Vec::new(),
+            debug: true,
+            opts: args.opts,
+            evm_opts: args.evm_opts,
+        };
+        debugger.run()?;
+
+        Ok(TestOutcome::new(results, args.allow_failure))
     } else {
+        let json = match (args.json, args.json_stream) {
+            (_, true) => Some(JsonOutput::Stream),
+            (true, _) => Some(JsonOutput::Document),
+            _ => None,
+        };
+        let report = args
+            .report
+            .zip(args.report_path.clone())
+            .or_else(|| args.junit.clone().map(|path| (TestReport::Junit, path)));
+        let gas_report = if gas_reporting {
+            Some(GasReportOutput {
+                format: args.gas_report.then(|| args.gas_report_format),
+                save: args.gas_report_save.clone(),
+                baseline: args.gas_report_diff.as_ref().map(read_json_report).transpose()?,
+                tolerance: args.gas_report_tolerance,
+                check: args.gas_report_check,
+            })
+        } else {
+            None
+        };
         let TestArgs { filter, .. } = args;
-        test(
+        let outcome = test(
             config,
             runner,
             verbosity,
             filter,
-            args.json,
+            json,
             args.allow_failure,
             include_fuzz_tests,
-            args.gas_report,
-        )
+            gas_report,
+            Duration::from_millis(args.slow),
+            args.summary,
+        )?;
+
+        update_failures(&failures_file, &outcome.results)?;
+        if let Some(ref path) = corpus_file {
+            let mut corpus = read_fuzz_corpus(path);
+            corpus.record(&outcome.results);
+            corpus.write(path)?;
+        }
+
+        // the report is written before failures are checked, so it's also written if tests fail
+        if let Some((TestReport::Junit, path)) = report {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, junit_report(&outcome.results))?;
+        }
+
+        Ok(outcome)
     }
 }
 
+/// The name of the file in the project's cache directory that records the failing tests
+const TEST_FAILURES_FILE: &str = "test-failures";
+
+/// Returns the file that records the failing tests, one `<artifact>:<contract>:<test>` per line
+fn failures_file(config: &Config) -> PathBuf {
+    config.cache_path.join(TEST_FAILURES_FILE)
+}
+
+/// Reads the recorded failing tests, a missing file has no failures
+fn read_failures(path: &Path) -> BTreeSet<String> {
+    std::fs::read_to_string(path)
+        .map(|content| {
+            content.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Records the failing tests of the run
+///
+/// Tests that failed are added, tests that passed are removed and the failures of tests that
+/// weren't run are kept.
+fn update_failures(path: &Path, results: &BTreeMap<String, SuiteResult>) -> eyre::Result<()> {
+    let mut failures = read_failures(path);
+    for (id, suite) in results {
+        for (signature, result) in &suite.test_results {
+            let test = format!("{}:{}", id, signature);
+            if result.success {
+                failures.remove(&test);
+            } else {
+                failures.insert(test);
+            }
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = failures.into_iter().map(|test| format!("{}\n", test)).collect::<String>();
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// Returns the file that stores the counterexamples of failed fuzz tests
+fn fuzz_corpus_file(config: &Config) -> PathBuf {
+    config.cache_path.join("fuzz").join("corpus.json")
+}
+
+/// Reads the stored counterexamples, an unreadable corpus is replaced with an empty one
+fn read_fuzz_corpus(path: &Path) -> FuzzCorpus {
+    FuzzCorpus::read(path).unwrap_or_else(|err| {
+        eprintln!(
+            "{} Ignoring the fuzz corpus at {}: {}",
+            Colour::Yellow.bold().paint("Warning:"),
+            path.display(),
+            err
+        );
+        FuzzCorpus::default()
+    })
+}
+
+/// Returns the contract and test patterns that match the recorded failing tests
+///
+/// A failing test of one contract also matches tests of the same name in the other contracts
+/// with failures.
+fn rerun_patterns(failures: &BTreeSet<String>) -> eyre::Result<(Regex, Regex)> {
+    let mut contracts = BTreeSet::new();
+    let mut tests = BTreeSet::new();
+    for failure in failures {
+        let mut parts = failure.rsplitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(signature), Some(contract), Some(_)) => {
+                let name = signature.split('(').next().unwrap_or(signature);
+                contracts.insert(regex::escape(contract));
+                tests.insert(regex::escape(name));
+            }
+            _ => eyre::bail!(
+                "invalid failed test `{}`, expected `<artifact>:<contract>:<test>`",
+                failure
+            ),
+        }
+    }
+
+    let contracts = contracts.into_iter().collect::<Vec<_>>().join("|");
+    let tests = tests.into_iter().collect::<Vec<_>>().join("|");
+    // tests are matched by both name and signature
+    let contract_pattern = Regex::new(&format!("^({})$", contracts))?;
+    let test_pattern = Regex::new(&format!("^({})(\\(|$)", tests))?;
+    Ok((contract_pattern, test_pattern))
+}
+
+/// Supported formats of the gas report
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum GasReportFormat {
+    /// A table per contract, for the terminal
+    Table,
+    /// A JSON object with an entry per contract
+    Json,
+    /// A GitHub flavored markdown table per contract
+    Md,
+}
+
+/// What to do with the gas report once all tests ran
+struct GasReportOutput {
+    /// The format to print the report in, `None` if only saved or compared
+    format: Option<GasReportFormat>,
+    /// The file to save the report to as JSON
+    save: Option<PathBuf>,
+    /// The saved report to compare against
+    baseline: Option<JsonGasReport>,
+    /// The increase in percent above which a function's gas is a regression
+    tolerance: f64,
+    /// Whether to fail if any function regressed
+    check: bool,
+}
+
+/// Supported formats of the test report
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum TestReport {
+    /// A JUnit XML report with a `testsuite` per test contract and a `testcase` per test
+    Junit,
+}
+
+/// Renders the results as JUnit XML
+fn junit_report(results: &BTreeMap<String, SuiteResult>) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&apos;")
+    }
+
+    let count = |suite: &SuiteResult| {
+        (suite.test_results.len(), suite.test_results.values().filter(|t| !t.success).count())
+    };
+    let (tests, failures) = results
+        .values()
+        .map(count)
+        .fold((0, 0), |(tests, failures), (t, f)| (tests + t, failures + f));
+    let time: Duration = results.values().map(|suite| suite.duration).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"forge\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+        tests,
+        failures,
+        time.as_secs_f64()
+    ));
+    for (contract, suite) in results {
+        let (tests, failures) = count(suite);
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+            escape(contract),
+            tests,
+            failures,
+            suite.duration.as_secs_f64()
+        ));
+        for (name, result) in &suite.test_results {
+            let testcase = format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape(name),
+                escape(contract),
+                result.duration.as_secs_f64()
+            );
+            if result.success {
+                xml.push_str(&format!("{}/>\n", testcase));
+                continue
+            }
+            let message = result.reason.as_deref().unwrap_or("test failed");
+            let mut details = format!("Reason: {}", message);
+            if let Some(ref counterexample) = result.counterexample {
+                details.push_str(&format!("\nCounterexample: {}", counterexample));
+            }
+            if let Some(seed) = result.fuzz_seed {
+                details.push_str(&format!("\nFuzz seed: {}", seed));
+            }
+            if let Some(ref sequence) = result.call_sequence {
+                details.push_str("\nCall sequence:");
+                for call in sequence {
+                    details.push_str(&format!("\n  {}", call));
+                }
+            }
+            xml.push_str(&format!(
+                "{}>\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                testcase,
+                escape(message),
+                escape(&details)
+            ));
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// How to print the test results as JSON
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JsonOutput {
+    /// A single JSON array once all tests finished
+    Document,
+    /// One JSON object per line as soon as a test suite finished
+    Stream,
+}
+
 /// Runs all the tests
 #[allow(clippy::too_many_arguments)]
 fn test(
@@ -461,15 +1138,44 @@ fn test(
     mut runner: MultiContractRunner,
     verbosity: u8,
     filter: Filter,
-    json: bool,
+    json: Option<JsonOutput>,
     allow_failure: bool,
     include_fuzz_tests: bool,
-    gas_reporting: bool,
+    gas_report_output: Option<GasReportOutput>,
+    slow: Duration,
+    summary: bool,
 ) -> eyre::Result<TestOutcome> {
-    if json {
-        let results = runner.test(&filter, None, include_fuzz_tests)?;
-        println!("{}", serde_json::to_string(&results)?);
-        Ok(TestOutcome::new(results, allow_failure))
+    let gas_reporting = gas_report_output.is_some();
+    if let Some(json) = json {
+        let (tx, rx) = channel::<(String, SuiteResult)>();
+        let handle =
+            thread::spawn(move || runner.test(&filter, Some(tx), include_fuzz_tests).unwrap());
+
+        let mut results: BTreeMap<String, SuiteResult> = BTreeMap::new();
+        let mut tests = Vec::new();
+        for (contract_name, suite_result) in rx {
+            for warning in suite_result.warnings.iter() {
+                eprintln!("{} {}", Colour::Yellow.bold().paint("Warning:"), warning);
+            }
+            for (name, result) in &suite_result.test_results {
+                let test = JsonTestResult::new(&contract_name, name, result);
+                if json == JsonOutput::Stream {
+                    println!("{}", serde_json::to_string(&test)?);
+                } else {
+                    tests.push(test);
+                }
+            }
+            results.insert(contract_name, suite_result);
+        }
+        let _ = handle.join();
+
+        if json == JsonOutput::Document {
+            println!("{}", serde_json::to_string(&tests)?);
+        }
+
+        let mut outcome = TestOutcome::new(results, allow_failure);
+        outcome.quiet = true;
+        Ok(outcome)
     } else {
         // Set up identifiers
         let local_identifier = LocalTraceIdentifier::new(&runner.known_contracts);
@@ -504,7 +1210,7 @@ fn test(
                 println!("Running {} {} for {}", tests.len(), term, contract_name);
             }
             for (name, result) in &mut tests {
-                short_test_result(name, result);
+                short_test_result(name, result, Some(slow));
 
                 // We only display logs at level 2 and above
                 if verbosity >= 2 {
@@ -573,13 +1279,340 @@ fn test(
             results.insert(contract_name, suite_result);
         }
 
-        if gas_reporting {
-            println!("{}", gas_report.finalize());
-        }
-
         // reattach the thread
         let _ = handle.join();
 
-        Ok(TestOutcome::new(results, allow_failure))
+        let invariants = results
+            .iter()
+            .flat_map(|(contract, suite)| {
+                suite
+                    .test_results
+                    .iter()
+                    .filter(|(_, result)| result.is_invariant())
+                    .map(move |(name, result)| (contract, name, result))
+            })
+            .collect::<Vec<_>>();
+        if !invariants.is_empty() {
+            println!();
+            println!("Invariants:");
+            for (contract, name, result) in invariants {
+                let status = if result.success {
+                    Colour::Green.paint("[PASS]")
+                } else {
+                    Colour::Red.paint("[FAIL]")
+                };
+                println!(
+                    "{} {}::{} {}",
+                    status,
+                    utils::get_contract_name(contract),
+                    name,
+                    result.kind.gas_used()
+                );
+            }
+        }
+
+        let outcome = TestOutcome::new(results, allow_failure);
+        if summary {
+            println!();
+            print!("{}", slowest_tests_summary(&outcome));
+        }
+
+        if let Some(output) = gas_report_output {
+            let gas_report = gas_report.finalize();
+            match output.format {
+                Some(GasReportFormat::Table) => println!("{}", gas_report),
+                Some(GasReportFormat::Json) => println!("{}", gas_report.to_json()?),
+                Some(GasReportFormat::Md) => print!("{}", gas_report.to_markdown()),
+                None => {}
+            }
+            if let Some(path) = output.save {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, gas_report.to_json()?)?;
+            }
+            if let Some(baseline) = output.baseline {
+                let diff = GasReportDiff::new(&gas_report, &baseline, output.tolerance);
+                println!("{}", diff);
+                let regressions = diff.regressions().count();
+                if output.check && regressions > 0 {
+                    eyre::bail!(
+                        "the gas of {} function(s) increased by more than {}%",
+                        regressions,
+                        output.tolerance
+                    )
+                }
+            }
+        }
+
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Bytes;
+    use forge::fuzz::{CounterExample, FuzzCase, FuzzedCases};
+
+    fn test_result(success: bool, reason: Option<&str>) -> forge::TestResult {
+        forge::TestResult {
+            success,
+            reason: reason.map(str::to_string),
+            counterexample: None,
+            fuzz_seed: None,
+            logs: vec![],
+            kind: TestKind::Standard(100),
+            traces: vec![],
+            labeled_addresses: Default::default(),
+            duration: Duration::from_millis(5),
+            call_sequence: None,
+            coverage: None,
+        }
+    }
+
+    #[test]
+    fn writes_junit_report() {
+        let mut fuzz_failure = test_result(false, Some("Assertion failed"));
+        fuzz_failure.counterexample =
+            Some(CounterExample { calldata: Bytes::from(vec![0xab, 0xcd]), args: vec![] });
+        let results = BTreeMap::from([
+            (
+                "src/A.t.sol:ATest".to_string(),
+                SuiteResult::new(
+                    Duration::from_millis(20),
+                    BTreeMap::from([
+                        ("testPass()".to_string(), test_result(true, None)),
+                        ("testFuzz(uint256)".to_string(), fuzz_failure),
+                    ]),
+                    vec![],
+                ),
+            ),
+            (
+                "src/B.t.sol:BTest".to_string(),
+                SuiteResult::new(
+                    Duration::from_millis(10),
+                    BTreeMap::from([("testFail<x>()".to_string(), test_result(false, None))]),
+                    vec![],
+                ),
+            ),
+        ]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("junit.xml");
+        std::fs::write(&path, junit_report(&results)).unwrap();
+        let xml = std::fs::read_to_string(&path).unwrap();
+
+        // parse all elements as `(name, attributes)`
+        let element = Regex::new(r#"<([a-z]+)((?: [a-z]+="[^"]*")*)"#).unwrap();
+        let attribute = Regex::new(r#"([a-z]+)="([^"]*)""#).unwrap();
+        let elements = element
+            .captures_iter(&xml)
+            .map(|cap| {
+                let attributes = attribute
+                    .captures_iter(&cap[2])
+                    .map(|attr| (attr[1].to_string(), attr[2].to_string()))
+                    .collect::<BTreeMap<_, _>>();
+                (cap[1].to_string(), attributes)
+            })
+            .collect::<Vec<_>>();
+
+        let names = elements.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "testsuites",
+                "testsuite",
+                "testcase",
+                "failure",
+                "testcase",
+                "testsuite",
+                "testcase",
+                "failure"
+            ]
+        );
+        assert_eq!(elements[0].1["tests"], "3");
+        assert_eq!(elements[0].1["failures"], "2");
+        assert_eq!(elements[0].1["time"], "0.030");
+        assert_eq!(elements[1].1["name"], "src/A.t.sol:ATest");
+        assert_eq!(elements[1].1["failures"], "1");
+        assert_eq!(elements[2].1["name"], "testFuzz(uint256)");
+        assert_eq!(elements[2].1["classname"], "src/A.t.sol:ATest");
+        assert_eq!(elements[2].1["time"], "0.005");
+        assert_eq!(elements[3].1["message"], "Assertion failed");
+        assert!(xml.contains("Counterexample: calldata=0xabcd, args=[]</failure>"));
+        assert_eq!(elements[4].1["name"], "testPass()");
+        assert_eq!(elements[6].1["name"], "testFail&lt;x&gt;()");
+        assert_eq!(elements[7].1["message"], "test failed");
+    }
+
+    #[test]
+    fn builds_rerun_patterns() {
+        let failures = BTreeSet::from([
+            "src/A.t.sol:ATest:testFuzz(uint256)".to_string(),
+            "src/A.t.sol:ATest:testFail()".to_string(),
+            "src/nested/B.t.sol:B$Test:testFail()".to_string(),
+        ]);
+        let (contracts, tests) = rerun_patterns(&failures).unwrap();
+        assert!(contracts.is_match("ATest"));
+        assert!(contracts.is_match("B$Test"));
+        assert!(!contracts.is_match("ATestTwo"));
+        assert!(!contracts.is_match("CTest"));
+        // matches both the name and the signature of the test function
+        assert!(tests.is_match("testFuzz"));
+        assert!(tests.is_match("testFuzz(uint256)"));
+        assert!(tests.is_match("testFail()"));
+        assert!(!tests.is_match("testFuzzTwo(uint256)"));
+        assert!(!tests.is_match("testPass()"));
+
+        assert!(rerun_patterns(&BTreeSet::from(["ATest".to_string()])).is_err());
+    }
+
+    #[test]
+    fn composes_include_and_exclude_filters() {
+        let tests = ["testDeposit", "testDepositFork", "testWithdraw", "testWithdrawFork"];
+        let filter =
+            Filter::parse_from(["forge", "--match-test", "Deposit", "--no-match-test", "Fork$"]);
+        let matched: Vec<_> = tests.iter().filter(|test| filter.matches_test(test)).collect();
+        assert_eq!(matched, [&"testDeposit"]);
+
+        // only excluding runs everything else
+        let filter = Filter::parse_from(["forge", "--nmt", "Fork$"]);
+        let matched: Vec<_> = tests.iter().filter(|test| filter.matches_test(test)).collect();
+        assert_eq!(matched, [&"testDeposit", &"testWithdraw"]);
+
+        let contracts = ["VaultTest", "VaultForkTest", "TokenTest"];
+        let filter = Filter::parse_from([
+            "forge",
+            "--match-contract",
+            "Vault",
+            "--no-match-contract",
+            "Fork",
+        ]);
+        let matched: Vec<_> =
+            contracts.iter().filter(|contract| filter.matches_contract(contract)).collect();
+        assert_eq!(matched, [&"VaultTest"]);
+
+        // excludes win over includes of the same name
+        let filter =
+            Filter::parse_from(["forge", "--match-contract", "TokenTest", "--nmc", "TokenTest"]);
+        assert!(!filter.matches_contract("TokenTest"));
+    }
+
+    #[test]
+    fn matches_paths_relative_to_root() {
+        let root = Path::new("/project");
+        let filter =
+            Filter::parse_from(["forge", "--match-path", "test/integration/**"]).with_root(root);
+        assert!(filter.matches_path("/project/test/integration/Vault.t.sol"));
+        assert!(filter.matches_path("/project/test/integration/nested/Vault.t.sol"));
+        assert!(filter.matches_path("test/integration/Vault.t.sol"));
+        assert!(!filter.matches_path("/project/test/unit/Vault.t.sol"));
+        assert!(filter.is_match(Path::new("/project/test/integration/Vault.t.sol")));
+
+        // combined with AND semantics
+        let filter = Filter::parse_from([
+            "forge",
+            "--match-path",
+            "test/**",
+            "--no-match-path",
+            "test/integration/**",
+        ])
+        .with_root(root);
+        assert!(filter.matches_path("/project/test/unit/Vault.t.sol"));
+        assert!(!filter.matches_path("/project/test/integration/Vault.t.sol"));
+        assert!(!filter.matches_path("/project/src/Vault.sol"));
+        assert!(!filter.is_match(Path::new("/project/test/integration/Vault.t.sol")));
+
+        // the existing leading wildcard patterns still match
+        let filter = Filter::parse_from(["forge", "--match-path", "*src/ATest.t.sol"]);
+        assert!(filter.matches_path("/project/src/ATest.t.sol"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn matches_paths_with_any_separator() {
+        let root = Path::new("C:\\project");
+        for glob in ["test/integration/**", "test\\integration\\**"] {
+            let filter = Filter::parse_from(["forge", "--match-path", glob]).with_root(root);
+            assert!(filter.matches_path("C:\\project\\test\\integration\\Vault.t.sol"));
+            assert!(filter.matches_path("test/integration/Vault.t.sol"));
+            assert!(!filter.matches_path("C:\\project\\test\\unit\\Vault.t.sol"));
+        }
+    }
+
+    #[test]
+    fn json_results_round_trip() {
+        let mut failure = test_result(false, Some("Assertion failed"));
+        let cases = [300, 100, 200].map(|gas| FuzzCase { calldata: Bytes::new(), gas, stipend: 0 });
+        failure.kind = TestKind::Fuzz(FuzzedCases::new(cases.to_vec()));
+        failure.fuzz_seed = Some(1337);
+        let results = [
+            JsonTestResult::new("src/A.t.sol:ATest", "testPass()", &test_result(true, None)),
+            JsonTestResult::new("src/A.t.sol:ATest", "testFuzz(uint256)", &failure),
+        ];
+
+        let json = serde_json::to_string(&results).unwrap();
+        let parsed: Vec<JsonTestResult> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, results);
+        assert_eq!(parsed[0].gas, 100);
+        assert_eq!(parsed[0].duration_ms, 5.0);
+        assert_eq!(parsed[0].fuzz, None);
+        assert!(!parsed[1].success);
+        assert_eq!(parsed[1].reason.as_deref(), Some("Assertion failed"));
+        assert_eq!(
+            parsed[1].fuzz,
+            Some(JsonFuzzStats {
+                runs: 3,
+                mean_gas: 200,
+                median_gas: 200,
+                seed: Some(1337),
+                mean_run_duration_ms: mean_run_duration(Duration::from_millis(5), 3).as_secs_f64() *
+                    1000.0
+            })
+        );
+
+        // the fuzz stats are omitted for non fuzz tests
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(value[0].get("fuzz").is_none());
+        assert_eq!(value[1]["fuzz"]["runs"], 3);
+        assert_eq!(value[1]["fuzz"]["seed"], 1337);
+    }
+
+    #[test]
+    fn reports_slowest_tests() {
+        let with_duration = |millis| {
+            let mut result = test_result(true, None);
+            result.duration = Duration::from_millis(millis);
+            result
+        };
+        let mut fuzz = with_duration(1200);
+        let cases = [100; 4].map(|gas| FuzzCase { calldata: Bytes::new(), gas, stipend: 0 });
+        fuzz.kind = TestKind::Fuzz(FuzzedCases::new(cases.to_vec()));
+
+        let mut tests = BTreeMap::from([("testFuzz(uint256)".to_string(), fuzz)]);
+        for i in 0..12 {
+            tests.insert(format!("test{}()", i), with_duration(i * 10));
+        }
+        let outcome = TestOutcome::new(
+            BTreeMap::from([(
+                "src/A.t.sol:ATest".to_string(),
+                SuiteResult::new(Duration::from_secs(2), tests, vec![]),
+            )]),
+            false,
+        );
+
+        let slowest = outcome.slowest(SLOWEST_TESTS);
+        assert_eq!(slowest.len(), 10);
+        assert_eq!(slowest[0].1, "testFuzz(uint256)");
+        assert_eq!(slowest[1].1, "test11()");
+        assert_eq!(slowest[9].1, "test3()");
+
+        let summary = slowest_tests_summary(&outcome);
+        let lines = summary.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 11);
+        assert_eq!(lines[0], "Slowest tests:");
+        assert_eq!(lines[1], "  [1.20s, 300.00ms/run] ATest::testFuzz(uint256)");
+        assert_eq!(lines[2], "  [110.00ms] ATest::test11()");
     }
 }
@@ -1,20 +1,79 @@
 //! tree command
 
-use crate::cmd::{forge::build::ProjectPathsArgs, Cmd};
-use clap::Parser;
-use ethers::solc::Graph;
-use foundry_config::Config;
+use crate::{
+    cmd::{forge::build::ProjectPathsArgs, Cmd},
+    opts::forge::ContractInfo,
+};
+use clap::{ArgEnum, Parser};
+use ethers::solc::{Graph, ProjectPathsConfig, Solc};
+use foundry_config::{Config, SolcReq};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 foundry_config::impl_figment_convert!(TreeArgs, opts);
 use ethers::solc::resolver::{Charset, TreeOptions};
 
+/// Matches the names of all contracts, libraries and interfaces declared in a file
+static CONTRACT_NAME_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?m)^[ \t]*(?:abstract[ \t]+)?(?:contract|library|interface)[ \t]+([A-Za-z0-9_$]+)",
+    )
+    .unwrap()
+});
+
+/// Matches the version requirement of the `pragma solidity` of a file
+static PRAGMA_SOLIDITY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)^[ \t]*pragma[ \t]+solidity[ \t]+([^;]+);").unwrap());
+
+/// Matches the paths of all import statements of a file
+static IMPORT_PATH_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?m)^\s*import\s+(?:[^;]*?\s+from\s+)?["']([^"']+)["']"#).unwrap());
+
 /// Command to display the project's dependency tree
 #[derive(Debug, Clone, Parser)]
 pub struct TreeArgs {
-    #[clap(help = "Do not de-duplicate (repeats all shared dependencies)", long)]
+    /// Only show the tree of the given file or contract.
+    ///
+    /// Can be a path (`src/Vault.sol`), `<path>:<contractname>` or just the contract name
+    /// if it is unambiguous.
+    #[clap(value_name = "TARGET")]
+    target: Option<String>,
+    /// Print every project file that imports the given file, directly or transitively.
+    ///
+    /// Each file is printed with the chain of imports that leads to the given file. In the `json`
+    /// and `dot` formats the chains are the edges between the files.
+    #[clap(long, value_name = "PATH", conflicts_with_all = &["target", "depth", "filter"])]
+    reverse: Option<String>,
+    /// Only show the trees of the files matching the glob.
+    ///
+    /// Paths are matched relative to the project root, e.g. `src/tokens/**`.
+    #[clap(long, value_name = "GLOB", conflicts_with = "target")]
+    filter: Option<globset::Glob>,
+    #[clap(help = "Maximum depth of the printed tree", long, value_name = "DEPTH")]
+    depth: Option<usize>,
+    /// Do not de-duplicate (repeats all shared dependencies).
+    ///
+    /// With a target, `--filter` or `--depth`, imports that lead back to a file that is already
+    /// being printed are marked with `(cycle)` instead of being expanded again. In the `json` and
+    /// `dot` formats every repeated file is a node of its own and cycles are edges back to the
+    /// importing node.
+    #[clap(long)]
     no_dedupe: bool,
     #[clap(help = "Character set to use in output: utf8, ascii", default_value = "utf8", long)]
     charset: Charset,
+    /// The format of the tree.
+    ///
+    /// `json` prints the files as `nodes` and the imports as `edges`, `dot` prints a Graphviz
+    /// digraph that can be rendered with e.g. `dot -Tsvg`.
+    #[clap(long, arg_enum, default_value = "text", value_name = "FORMAT")]
+    format: TreeFormat,
     #[clap(flatten, next_help_heading = "PROJECT OPTIONS")]
     opts: ProjectPathsArgs,
 }
@@ -24,10 +83,557 @@ impl Cmd for TreeArgs {
 
     fn run(self) -> eyre::Result<Self::Output> {
         let config: Config = From::from(&self);
-        let graph = Graph::resolve(&config.project_paths())?;
-        let opts = TreeOptions { charset: self.charset, no_dedupe: self.no_dedupe };
-        graph.print_with_options(opts);
+        let paths = config.project_paths();
+        let graph = Graph::resolve(&paths)?;
+
+        let reverse = match self.reverse {
+            Some(ref target) => Some(resolve_target(&graph, &paths, target)?),
+            None => None,
+        };
+        if let Some(target) = reverse.filter(|_| self.format == TreeFormat::Text) {
+            let arrow = if let Charset::Ascii = self.charset { "->" } else { "→" };
+            for chain in import_chains(&graph, &paths, target) {
+                let chain =
+                    chain.iter().map(|id| display_path(graph.node(*id).path(), &paths.root));
+                println!("{}", chain.collect::<Vec<_>>().join(&format!(" {} ", arrow)));
+            }
+            return Ok(())
+        }
+
+        if self.format == TreeFormat::Text &&
+            self.target.is_none() &&
+            self.depth.is_none() &&
+            self.filter.is_none()
+        {
+            let opts = TreeOptions { charset: self.charset, no_dedupe: self.no_dedupe };
+            graph.print_with_options(opts);
+            return Ok(())
+        }
+
+        let roots = match (&self.target, &self.filter) {
+            (Some(target), _) => vec![resolve_target(&graph, &paths, target)?],
+            (None, Some(glob)) => matching_files(&graph, &paths.root, glob),
+            (None, None) => project_files(&graph, &paths),
+        };
+
+        if self.format == TreeFormat::Text {
+            let printer = TreePrinter {
+                graph: &graph,
+                root: &paths.root,
+                symbols: if let Charset::Ascii = self.charset {
+                    &ASCII_SYMBOLS
+                } else {
+                    &UTF8_SYMBOLS
+                },
+                no_dedupe: self.no_dedupe,
+                max_depth: self.depth,
+            };
+            let mut visited = HashSet::new();
+            for id in roots {
+                printer.print_node(id, &mut String::new(), None, &mut Vec::new(), &mut visited);
+            }
+            return Ok(())
+        }
+
+        let mut builder = TreeBuilder {
+            graph: &graph,
+            paths: &paths,
+            no_dedupe: self.no_dedupe,
+            max_depth: self.depth,
+            versions: SolcVersions::new(&config),
+            tree: DependencyTree::default(),
+            nodes: HashMap::new(),
+        };
+        match reverse {
+            Some(target) => {
+                builder.file_node(target);
+                builder.add_chains(&import_chains(&graph, &paths, target));
+            }
+            None => {
+                for id in roots {
+                    builder.add_node(id, &mut Vec::new());
+                }
+            }
+        }
+        builder.tree.sort();
+        match self.format {
+            TreeFormat::Json => println!("{}", serde_json::to_string_pretty(&builder.tree)?),
+            _ => print!("{}", builder.tree.to_dot()),
+        }
 
         Ok(())
     }
 }
+
+/// Supported formats of the tree
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum TreeFormat {
+    /// An indented tree, for the terminal
+    Text,
+    /// A JSON object with the `nodes` and `edges` of the tree
+    Json,
+    /// A Graphviz digraph
+    Dot,
+}
+
+/// Returns the indices of all source and test files of the project, sorted by path
+fn project_files(graph: &Graph, paths: &ProjectPathsConfig) -> Vec<usize> {
+    let mut files = graph
+        .files()
+        .iter()
+        .filter(|(path, _)| path.starts_with(&paths.sources) || path.starts_with(&paths.tests))
+        .map(|(path, id)| (path.clone(), *id))
+        .collect::<Vec<_>>();
+    files.sort();
+    files.into_iter().map(|(_, id)| id).collect()
+}
+
+/// Returns the indices of all files whose path relative to the root matches the glob, sorted by
+/// path
+fn matching_files(graph: &Graph, root: &Path, glob: &globset::Glob) -> Vec<usize> {
+    let matcher = glob.compile_matcher();
+    let mut files = graph
+        .files()
+        .iter()
+        .filter(|(path, _)| matcher.is_match(path.strip_prefix(root).unwrap_or(path)))
+        .map(|(path, id)| (path.clone(), *id))
+        .collect::<Vec<_>>();
+    files.sort();
+    files.into_iter().map(|(_, id)| id).collect()
+}
+
+/// Returns the shortest import chain from every project file that imports the target, directly
+/// or transitively, to the target itself
+fn import_chains(graph: &Graph, paths: &ProjectPathsConfig, target: usize) -> Vec<Vec<usize>> {
+    let mut importers: HashMap<usize, Vec<usize>> = HashMap::new();
+    for id in graph.files().values() {
+        for import in graph.imported_nodes(*id) {
+            importers.entry(*import).or_default().push(*id);
+        }
+    }
+
+    // walk the graph backwards, `next` points to the next file in the chain towards the target
+    let mut next = HashMap::from([(target, target)]);
+    let mut queue = VecDeque::from([target]);
+    while let Some(id) = queue.pop_front() {
+        for importer in importers.get(&id).into_iter().flatten() {
+            if !next.contains_key(importer) {
+                next.insert(*importer, id);
+                queue.push_back(*importer);
+            }
+        }
+    }
+
+    project_files(graph, paths)
+        .into_iter()
+        .filter(|id| *id != target && next.contains_key(id))
+        .map(|mut id| {
+            let mut chain = vec![id];
+            while id != target {
+                id = next[&id];
+                chain.push(id);
+            }
+            chain
+        })
+        .collect()
+}
+
+/// Resolves the target to the index of a node in the graph
+///
+/// The target is either a path to a file or a [ContractInfo]
+fn resolve_target(graph: &Graph, paths: &ProjectPathsConfig, target: &str) -> eyre::Result<usize> {
+    let is_path = !target.contains(':') && (target.ends_with(".sol") || target.contains('/'));
+    if is_path {
+        return find_file(graph, paths, target)
+    }
+
+    let info = ContractInfo::from_str(target)?;
+    if let Some(path) = info.path {
+        return find_file(graph, paths, &path)
+    }
+
+    let mut matches = graph
+        .files()
+        .iter()
+        .filter(|(_, id)| {
+            CONTRACT_NAME_REGEX
+                .captures_iter(graph.node(**id).content())
+                .any(|cap| cap[1] == info.name)
+        })
+        .map(|(path, id)| (path.clone(), *id))
+        .collect::<Vec<_>>();
+    matches.sort();
+
+    match matches.len() {
+        1 => Ok(matches[0].1),
+        0 => {
+            let names = graph
+                .files()
+                .values()
+                .flat_map(|id| {
+                    CONTRACT_NAME_REGEX
+                        .captures_iter(graph.node(*id).content())
+                        .map(|cap| cap[1].to_string())
+                        .collect::<Vec<_>>()
+                })
+                .collect::<HashSet<_>>();
+            let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+            not_found(target, &names)
+        }
+        _ => {
+            let paths = matches
+                .iter()
+                .map(|(path, _)| format!("{}:{}", display_path(path, &paths.root), info.name))
+                .collect::<Vec<_>>();
+            eyre::bail!(
+                "`{}` is ambiguous, did you mean one of:\n    {}",
+                target,
+                paths.join("\n    ")
+            )
+        }
+    }
+}
+
+/// Returns the index of the file in the graph
+///
+/// Remapped paths like `@solmate/utils/SafeTransferLib.sol` are resolved to the file they point
+/// to
+fn find_file(graph: &Graph, paths: &ProjectPathsConfig, path: &str) -> eyre::Result<usize> {
+    let root = &paths.root;
+    let candidates = remapped_path(paths, path).into_iter().chain(std::iter::once(path.into()));
+    for file in candidates {
+        if let Some(id) = graph.files().get(&canonical_path(root, file)) {
+            return Ok(*id)
+        }
+    }
+
+    let files = graph.files().keys().map(|file| display_path(file, root)).collect::<Vec<_>>();
+    let files = files.iter().map(String::as_str).collect::<Vec<_>>();
+    not_found(path, &files)
+}
+
+/// Returns the path the first remapping whose name is a prefix of the path maps it to
+fn remapped_path(paths: &ProjectPathsConfig, path: &str) -> Option<PathBuf> {
+    paths
+        .remappings
+        .iter()
+        .find(|remapping| path.starts_with(&remapping.name))
+        .map(|remapping| PathBuf::from(&remapping.path).join(&path[remapping.name.len()..]))
+}
+
+/// Returns the canonical path of the file, a relative path is relative to the root
+fn canonical_path(root: &Path, mut file: PathBuf) -> PathBuf {
+    if file.is_relative() {
+        file = root.join(file);
+    }
+    dunce::canonicalize(&file).unwrap_or(file)
+}
+
+fn not_found<T>(target: &str, candidates: &[&str]) -> eyre::Result<T> {
+    let matches = similar::get_close_matches(target, candidates, 5, 0.6);
+    if matches.is_empty() {
+        eyre::bail!("`{}` does not match any file or contract of the project", target)
+    }
+    eyre::bail!(
+        "`{}` does not match any file or contract of the project, did you mean one of:\n    {}",
+        target,
+        matches.join("\n    ")
+    )
+}
+
+fn display_path(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).display().to_string()
+}
+
+/// The characters used to draw the tree
+struct Symbols {
+    down: &'static str,
+    tee: &'static str,
+    ell: &'static str,
+    right: &'static str,
+}
+
+static UTF8_SYMBOLS: Symbols = Symbols { down: "│", tee: "├", ell: "└", right: "─" };
+
+static ASCII_SYMBOLS: Symbols = Symbols { down: "|", tee: "|", ell: "`", right: "-" };
+
+struct TreePrinter<'a> {
+    graph: &'a Graph,
+    root: &'a Path,
+    symbols: &'static Symbols,
+    no_dedupe: bool,
+    max_depth: Option<usize>,
+}
+
+impl<'a> TreePrinter<'a> {
+    /// Prints the node and, up to the max depth, all of its imports
+    ///
+    /// `is_last` is `None` for the root of the tree, `ancestors` are the nodes on the path from
+    /// the root to this node.
+    fn print_node(
+        &self,
+        id: usize,
+        prefix: &mut String,
+        is_last: Option<bool>,
+        ancestors: &mut Vec<usize>,
+        visited: &mut HashSet<usize>,
+    ) {
+        let node = self.graph.node(id);
+        let imports = self.graph.imported_nodes(id);
+        let new_node = visited.insert(id);
+        let depth = ancestors.len();
+
+        let mut line = prefix.clone();
+        if let Some(is_last) = is_last {
+            let branch = if is_last { self.symbols.ell } else { self.symbols.tee };
+            line.push_str(&format!("{}{}{} ", branch, self.symbols.right, self.symbols.right));
+        }
+        line.push_str(&display_path(node.path(), self.root));
+        let is_cycle = ancestors.contains(&id);
+        let expand = (new_node || self.no_dedupe) && !is_cycle;
+        if is_cycle {
+            line.push_str(" (cycle)");
+        } else if !expand && !imports.is_empty() {
+            line.push_str(" (*)");
+        }
+        println!("{}", line);
+
+        if !expand || self.max_depth.map(|max| depth >= max).unwrap_or_default() {
+            return
+        }
+
+        let len = prefix.len();
+        match is_last {
+            Some(true) => prefix.push_str("    "),
+            Some(false) => prefix.push_str(&format!("{}   ", self.symbols.down)),
+            None => {}
+        }
+        ancestors.push(id);
+        for (idx, import) in imports.iter().enumerate() {
+            self.print_node(*import, prefix, Some(idx == imports.len() - 1), ancestors, visited);
+        }
+        ancestors.pop();
+        prefix.truncate(len);
+    }
+}
+
+/// The files and imports of the tree, for the `json` and `dot` formats
+#[derive(Debug, Default, Serialize)]
+struct DependencyTree {
+    nodes: Vec<TreeNode>,
+    edges: Vec<TreeEdge>,
+}
+
+#[derive(Debug, Serialize)]
+struct TreeNode {
+    /// The index of the node in the `nodes` of the tree
+    id: usize,
+    /// The path of the file, relative to the project root
+    path: String,
+    /// The solc version the file is compiled with, `None` if no known version satisfies the
+    /// version requirements of the file and its imports
+    version: Option<String>,
+    /// The version requirement of the `pragma solidity` of the file
+    pragma: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TreeEdge {
+    /// The node of the importing file
+    from: usize,
+    /// The node of the imported file
+    to: usize,
+    /// Whether the import path was resolved through a remapping
+    remapped: bool,
+}
+
+impl DependencyTree {
+    /// Orders the nodes by path and the edges by their nodes, so the output doesn't depend on the
+    /// order the imports were walked in
+    ///
+    /// Nodes of the same file keep the order they were added in.
+    fn sort(&mut self) {
+        let mut order = (0..self.nodes.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| self.nodes[*a].path.cmp(&self.nodes[*b].path));
+        let mut ids = vec![0; order.len()];
+        for (id, node) in order.iter().enumerate() {
+            ids[*node] = id;
+        }
+
+        for node in &mut self.nodes {
+            node.id = ids[node.id];
+        }
+        self.nodes.sort_by_key(|node| node.id);
+        for edge in &mut self.edges {
+            edge.from = ids[edge.from];
+            edge.to = ids[edge.to];
+        }
+        self.edges.sort_by_key(|edge| (edge.from, edge.to));
+    }
+
+    /// Renders the tree as a Graphviz digraph, imports resolved through remappings are dashed
+    fn to_dot(&self) -> String {
+        fn escape(s: &str) -> String {
+            s.replace('\\', "\\\\").replace('"', "\\\"")
+        }
+
+        let mut dot = String::from("digraph {\n");
+        for node in &self.nodes {
+            let mut label = escape(&node.path);
+            if let Some(ref version) = node.version {
+                label.push_str(&format!("\\n{}", escape(version)));
+            }
+            dot.push_str(&format!("    {} [label=\"{}\"];\n", node.id, label));
+        }
+        for edge in &self.edges {
+            let style = if edge.remapped { " [style=dashed]" } else { "" };
+            dot.push_str(&format!("    {} -> {}{};\n", edge.from, edge.to, style));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+struct TreeBuilder<'a> {
+    graph: &'a Graph,
+    paths: &'a ProjectPathsConfig,
+    no_dedupe: bool,
+    max_depth: Option<usize>,
+    versions: SolcVersions,
+    tree: DependencyTree,
+    /// The node of every file that was added already, by index in the graph
+    nodes: HashMap<usize, usize>,
+}
+
+impl<'a> TreeBuilder<'a> {
+    /// Adds the node of the file and, up to the max depth, of all of its imports, returns the
+    /// index of the node
+    ///
+    /// `ancestors` are the files and nodes on the path from the root to this file.
+    fn add_node(&mut self, id: usize, ancestors: &mut Vec<(usize, usize)>) -> usize {
+        if let Some((_, node)) = ancestors.iter().find(|(file, _)| *file == id) {
+            return *node
+        }
+        if let Some(node) = self.nodes.get(&id).filter(|_| !self.no_dedupe) {
+            return *node
+        }
+
+        let node = self.push_node(id);
+        if self.max_depth.map(|max| ancestors.len() >= max).unwrap_or_default() {
+            return node
+        }
+
+        let remapped = self.remapped_imports(id);
+        ancestors.push((id, node));
+        for import in self.graph.imported_nodes(id) {
+            let to = self.add_node(*import, ancestors);
+            let remapped = remapped.contains(self.graph.node(*import).path());
+            self.tree.edges.push(TreeEdge { from: node, to, remapped });
+        }
+        ancestors.pop();
+        node
+    }
+
+    /// Adds the files of the import chains and the imports between them, every file is a single
+    /// node
+    fn add_chains(&mut self, chains: &[Vec<usize>]) {
+        let mut edges = HashSet::new();
+        for chain in chains {
+            for pair in chain.windows(2) {
+                let (from, to) = (self.file_node(pair[0]), self.file_node(pair[1]));
+                if edges.insert((from, to)) {
+                    let remapped =
+                        self.remapped_imports(pair[0]).contains(self.graph.node(pair[1]).path());
+                    self.tree.edges.push(TreeEdge { from, to, remapped });
+                }
+            }
+        }
+    }
+
+    /// Returns the node of the file, adds it if the file has no node yet
+    fn file_node(&mut self, id: usize) -> usize {
+        match self.nodes.get(&id) {
+            Some(node) => *node,
+            None => self.push_node(id),
+        }
+    }
+
+    /// Adds a new node for the file, returns the index of the node
+    fn push_node(&mut self, id: usize) -> usize {
+        let file = self.graph.node(id);
+        let node = self.tree.nodes.len();
+        self.tree.nodes.push(TreeNode {
+            id: node,
+            path: display_path(file.path(), &self.paths.root),
+            version: self.versions.resolve(self.graph, id).map(|version| version.to_string()),
+            pragma: pragma_solidity(file.content()),
+        });
+        self.nodes.insert(id, node);
+        node
+    }
+
+    /// Returns the paths of the files the file imports through remappings
+    fn remapped_imports(&self, id: usize) -> HashSet<PathBuf> {
+        IMPORT_PATH_REGEX
+            .captures_iter(self.graph.node(id).content())
+            .filter_map(|cap| remapped_path(self.paths, &cap[1]))
+            .map(|path| canonical_path(&self.paths.root, path))
+            .collect()
+    }
+}
+
+/// Returns the version requirement of the `pragma solidity` of the source
+fn pragma_solidity(content: &str) -> Option<String> {
+    PRAGMA_SOLIDITY_REGEX.captures(content).map(|cap| cap[1].trim().to_string())
+}
+
+/// Resolves the solc version files are compiled with
+enum SolcVersions {
+    /// The `solc` of the config, used for every file
+    Pinned(Option<Version>),
+    /// The known solc versions, ascending, the latest one that satisfies the requirements of a
+    /// file and all of its imports is used, like the graph resolves the versions when compiling
+    Detected(Vec<Version>),
+}
+
+impl SolcVersions {
+    fn new(config: &Config) -> Self {
+        match config.solc {
+            Some(SolcReq::Version(ref version)) => SolcVersions::Pinned(Some(version.clone())),
+            Some(SolcReq::Local(ref solc)) => SolcVersions::Pinned(Solc::new(solc).version().ok()),
+            None => {
+                let versions =
+                    if config.offline { Solc::installed_versions() } else { Solc::all_versions() };
+                let mut versions =
+                    versions.iter().map(|version| version.as_ref().clone()).collect::<Vec<_>>();
+                versions.sort();
+                SolcVersions::Detected(versions)
+            }
+        }
+    }
+
+    /// Returns the version the file is compiled with
+    fn resolve(&self, graph: &Graph, id: usize) -> Option<Version> {
+        let versions = match self {
+            SolcVersions::Pinned(version) => return version.clone(),
+            SolcVersions::Detected(versions) => versions,
+        };
+
+        let mut reqs: Vec<VersionReq> = Vec::new();
+        let mut visited = HashSet::from([id]);
+        let mut queue = VecDeque::from([id]);
+        while let Some(id) = queue.pop_front() {
+            if let Some(req) = pragma_solidity(graph.node(id).content())
+                .and_then(|pragma| Solc::version_req(&pragma).ok())
+            {
+                reqs.push(req);
+            }
+            for import in graph.imported_nodes(id) {
+                if visited.insert(*import) {
+                    queue.push_back(*import);
+                }
+            }
+        }
+        versions.iter().rev().find(|version| reqs.iter().all(|req| req.matches(version))).cloned()
+    }
+}
@@ -0,0 +1,158 @@
+//! Verify contract source on a Blockscout instance
+
+use ethers::etherscan::contract::VerifyContract;
+use eyre::Context;
+use reqwest::multipart::Form;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// The request to verify a contract, sent as multipart form
+///
+/// Blockscout accepts the same fields as Etherscan's `verifysourcecode`, except for the
+/// constructor arguments which it expects as `constructorArguments`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockscoutVerifyRequest {
+    pub fields: BTreeMap<String, String>,
+}
+
+impl BlockscoutVerifyRequest {
+    /// Creates the request from the Etherscan verification request
+    pub fn new(verify: &VerifyContract) -> eyre::Result<Self> {
+        let value = serde_json::to_value(verify)?;
+        let mut fields = BTreeMap::new();
+        for (key, value) in value.as_object().into_iter().flatten() {
+            let value = match value {
+                serde_json::Value::Null => continue,
+                serde_json::Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            let key = if key == "constructorArguements" { "constructorArguments" } else { key };
+            fields.insert(key.to_string(), value);
+        }
+        Ok(Self { fields })
+    }
+
+    fn form(&self) -> Form {
+        self.fields
+            .iter()
+            .fold(Form::new(), |form, (key, value)| form.text(key.clone(), value.clone()))
+    }
+}
+
+/// The response of the Etherscan compatible api of Blockscout
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockscoutResponse {
+    pub status: String,
+    pub message: String,
+    pub result: String,
+}
+
+/// A client for the verification endpoints of a Blockscout instance
+#[derive(Debug, Clone)]
+pub struct BlockscoutClient {
+    client: reqwest::Client,
+    api_url: String,
+}
+
+impl BlockscoutClient {
+    /// Creates a client for the Blockscout instance at the url, e.g.
+    /// `https://blockscout.com/xdai/mainnet`, with or without the trailing `/api`
+    pub fn new(url: impl Into<String>) -> Self {
+        let url = url.into();
+        let url = url.trim_end_matches('/');
+        let api_url = if url.ends_with("/api") { url.to_string() } else { format!("{}/api", url) };
+        Self { client: reqwest::Client::new(), api_url }
+    }
+
+    /// Submits the contract for verification, the result of the response is the GUID of the
+    /// verification
+    pub async fn verify(
+        &self,
+        request: &BlockscoutVerifyRequest,
+    ) -> eyre::Result<BlockscoutResponse> {
+        self.client
+            .post(&self.api_url)
+            .query(&[("module", "contract"), ("action", "verifysourcecode")])
+            .multipart(request.form())
+            .send()
+            .await
+            .wrap_err("Failed to submit contract verification")?
+            .json()
+            .await
+            .wrap_err("Failed to parse the Blockscout response")
+    }
+
+    /// Returns the status of the verification with the GUID
+    pub async fn check(&self, guid: &str) -> eyre::Result<BlockscoutResponse> {
+        self.client
+            .get(&self.api_url)
+            .query(&[("module", "contract"), ("action", "checkverifystatus"), ("guid", guid)])
+            .send()
+            .await
+            .wrap_err("Failed to request verification status")?
+            .json()
+            .await
+            .wrap_err("Failed to parse the Blockscout response")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::forge::verify::mock::mock_server;
+    use ethers::{abi::Address, etherscan::contract::CodeFormat};
+
+    #[test]
+    fn submits_multipart_request() {
+        let address: Address = "0x8ba1f109551bd432803012645ac136ddd64dba72".parse().unwrap();
+        let verify = VerifyContract::new(
+            address,
+            "Vault".to_string(),
+            "contract Vault {}".to_string(),
+            "v0.8.10+commit.fc410830".to_string(),
+        )
+        .constructor_arguments(Some("0x2a".to_string()))
+        .code_format(CodeFormat::SingleFile)
+        .optimization(true)
+        .runs(200);
+        let request = BlockscoutVerifyRequest::new(&verify).unwrap();
+        assert_eq!(request.fields["contractaddress"], "0x8ba1f109551bd432803012645ac136ddd64dba72");
+        assert_eq!(request.fields["constructorArguments"], "0x2a");
+        assert!(!request.fields.contains_key("constructorArguements"));
+
+        let (url, requests) = mock_server(vec![
+            r#"{"message":"OK","result":"b7a25de8c2b5d1a3a7d9a6f0e91e3d5e8d1f6a2b61b3c2cf","status":"1"}"#,
+            r#"{"message":"OK","result":"Pass - Verified","status":"1"}"#,
+        ]);
+        let client = BlockscoutClient::new(format!("{}/xdai/mainnet/", url));
+
+        let resp = crate::utils::block_on(client.verify(&request)).unwrap();
+        assert_eq!(resp.status, "1");
+        assert_eq!(resp.result, "b7a25de8c2b5d1a3a7d9a6f0e91e3d5e8d1f6a2b61b3c2cf");
+
+        let (request_line, body) = requests.recv().unwrap();
+        assert_eq!(
+            request_line,
+            "POST /xdai/mainnet/api?module=contract&action=verifysourcecode HTTP/1.1"
+        );
+        for (name, value) in [
+            ("codeformat", "solidity-single-file"),
+            ("contractname", "Vault"),
+            ("sourceCode", "contract Vault {}"),
+            ("constructorArguments", "0x2a"),
+            ("optimizationUsed", "1"),
+            ("runs", "200"),
+        ] {
+            let part = format!("name=\"{}\"\r\n\r\n{}\r\n", name, value);
+            assert!(body.contains(&part), "missing {} in {}", part, body);
+        }
+
+        let resp = crate::utils::block_on(client.check(&resp.result)).unwrap();
+        assert_eq!(resp.result, "Pass - Verified");
+        let (request_line, _) = requests.recv().unwrap();
+        assert_eq!(
+            request_line,
+            "GET /xdai/mainnet/api?module=contract&action=checkverifystatus&guid=b7a25de8c2b5d1a3a7d9a6f0e91e3d5e8d1f6a2b61b3c2cf HTTP/1.1"
+        );
+    }
+}
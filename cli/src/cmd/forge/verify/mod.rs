@@ -0,0 +1,907 @@
+//! Verify contract source on etherscan, sourcify or blockscout
+
+use super::{
+    build::{CoreBuildArgs, ProjectPathsArgs},
+    create::ConstructorArgsFile,
+};
+use crate::{
+    compile,
+    opts::forge::{CompilerArgs, ContractInfo},
+};
+use blockscout::{BlockscoutClient, BlockscoutVerifyRequest};
+use clap::{ArgEnum, Parser, ValueHint};
+use ethers::{
+    abi::Address,
+    etherscan::{
+        contract::{CodeFormat, VerifyContract},
+        Client,
+    },
+    solc::{
+        artifacts::{output_selection::ContractOutputSelection, BytecodeHash, Source},
+        AggregatedCompilerOutput, Artifact, CompilerInput, Project, Solc,
+    },
+};
+use eyre::Context;
+use foundry_config::{Chain, Config};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sourcify::{SourcifyClient, SourcifyMatch, SourcifyVerifyRequest, SOURCIFY_URL};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{trace, warn};
+
+pub mod blockscout;
+#[cfg(test)]
+mod mock;
+pub mod sourcify;
+
+/// The services contracts can be verified with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum VerificationProvider {
+    Etherscan,
+    Sourcify,
+    Blockscout,
+}
+
+/// How the contract's source is submitted for verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VerificationMode {
+    /// The solc standard JSON input with all sources and the project's compiler settings
+    StandardJson,
+    /// A single file with all sources flattened into it
+    Flattened,
+}
+
+impl VerificationMode {
+    fn name(&self) -> &'static str {
+        match self {
+            VerificationMode::StandardJson => "standard-json",
+            VerificationMode::Flattened => "flattened",
+        }
+    }
+}
+
+/// Verification arguments
+#[derive(Debug, Clone, Parser)]
+pub struct VerifyArgs {
+    #[clap(help = "The address of the contract to verify.")]
+    pub address: Address,
+
+    #[clap(help = "The contract identifier in the form `<path>:<contractname>`.")]
+    pub contract: ContractInfo,
+
+    #[clap(
+        long,
+        help = "the encoded constructor arguments",
+        conflicts_with = "constructor-args-path"
+    )]
+    pub constructor_args: Option<String>,
+
+    #[clap(
+        long,
+        help = "The path to a file containing the constructor arguments.",
+        long_help = "The path to a file containing the constructor arguments, in any format of `forge create --constructor-args-path`. Unless the arguments are ABI-encoded already, the project is compiled to encode them.",
+        value_hint = ValueHint::FilePath,
+        conflicts_with = "constructor-args"
+    )]
+    pub constructor_args_path: Option<PathBuf>,
+
+    #[clap(long, help = "The compiler version used to build the smart contract.")]
+    pub compiler_version: String,
+
+    #[clap(
+        alias = "optimizer-runs",
+        long,
+        help = "The number of optimization runs used to build the smart contract."
+    )]
+    pub num_of_optimizations: Option<u32>,
+
+    #[clap(
+        long,
+        alias = "chain-id",
+        env = "CHAIN",
+        help = "The chain ID the contract is deployed to.",
+        default_value = "mainnet"
+    )]
+    pub chain: Chain,
+
+    #[clap(
+        help = "Your Etherscan API key, only needed with `--verifier etherscan`.",
+        env = "ETHERSCAN_API_KEY"
+    )]
+    pub etherscan_key: Option<String>,
+
+    /// How to submit the source code.
+    ///
+    /// `standard-json` submits the solc standard JSON input with the exact settings the project
+    /// is compiled with, `flattened` submits a single flattened file.
+    #[clap(long, arg_enum, default_value = "standard-json", value_name = "MODE")]
+    pub via: VerificationMode,
+
+    #[clap(
+        help = "Flatten the source code before verifying, same as `--via flattened`.",
+        long = "flatten",
+        conflicts_with = "via"
+    )]
+    pub flatten: bool,
+
+    #[clap(
+        short,
+        long,
+        help = "Do not compile the flattened smart contract before verifying (if --flatten is passed)."
+    )]
+    pub force: bool,
+
+    #[clap(flatten, next_help_heading = "PROJECT OPTIONS")]
+    pub project_paths: ProjectPathsArgs,
+
+    #[clap(flatten)]
+    pub verifier: VerifierArgs,
+}
+
+/// The verification provider to use
+#[derive(Debug, Clone, Parser)]
+pub struct VerifierArgs {
+    /// The service to verify the contract with.
+    #[clap(long, arg_enum, default_value = "etherscan", value_name = "VERIFIER")]
+    pub verifier: VerificationProvider,
+
+    /// The url of the Sourcify server or of the Blockscout instance to verify with.
+    ///
+    /// Required with `--verifier blockscout`, e.g. `https://blockscout.com/xdai/mainnet`.
+    #[clap(long, value_name = "URL")]
+    pub verifier_url: Option<String>,
+}
+
+impl VerifierArgs {
+    fn sourcify(&self) -> SourcifyClient {
+        SourcifyClient::new(self.verifier_url.as_deref().unwrap_or(SOURCIFY_URL))
+    }
+
+    fn blockscout(&self) -> eyre::Result<BlockscoutClient> {
+        let url = self.verifier_url.as_deref().ok_or_else(|| {
+            eyre::eyre!(
+                "`--verifier blockscout` requires the url of the instance, see `--verifier-url`"
+            )
+        })?;
+        Ok(BlockscoutClient::new(url))
+    }
+}
+
+/// Returns the etherscan key, which is only required when verifying with etherscan
+fn etherscan_key(key: &Option<String>) -> eyre::Result<&str> {
+    key.as_deref().ok_or_else(|| {
+        eyre::eyre!(
+            "An Etherscan API key is required, pass it as argument or set `ETHERSCAN_API_KEY`"
+        )
+    })
+}
+
+impl VerifyArgs {
+    /// Returns how the source code is submitted
+    fn mode(&self) -> VerificationMode {
+        if self.flatten {
+            VerificationMode::Flattened
+        } else {
+            self.via
+        }
+    }
+
+    /// Run the verify command to submit the contract's source code for verification on etherscan
+    /// or sourcify
+    pub async fn run(&self) -> eyre::Result<()> {
+        self.submit().await.map(drop)
+    }
+
+    /// Submits the contract and checks the status of the verification until it succeeded or
+    /// failed, at most `retries` times every `interval`
+    pub async fn run_and_wait(&self, retries: u32, interval: Duration) -> eyre::Result<()> {
+        if let Some(guid) = self.submit().await? {
+            let check = VerifyCheckArgs {
+                guid,
+                chain: self.chain,
+                etherscan_key: self.etherscan_key.clone(),
+                verifier: self.verifier.clone(),
+            };
+            check.wait(retries, interval).await?;
+        }
+        Ok(())
+    }
+
+    /// Submits the contract for verification and returns the GUID of the verification
+    ///
+    /// Returns `None` if there's nothing left to check, because the contract is verified already
+    /// or was verified with sourcify, which doesn't queue verifications.
+    async fn submit(&self) -> eyre::Result<Option<String>> {
+        if self.contract.path.is_none() {
+            eyre::bail!("Contract info must be provided in the format <path>:<name>")
+        }
+
+        match self.verifier.verifier {
+            VerificationProvider::Sourcify => return self.verify_sourcify().await.map(|_| None),
+            VerificationProvider::Blockscout => return self.verify_blockscout().await,
+            VerificationProvider::Etherscan => {}
+        }
+
+        let etherscan = Client::new(self.chain.try_into()?, etherscan_key(&self.etherscan_key)?)
+            .wrap_err("Failed to create etherscan client")?;
+
+        let verify_args = self.create_verify_request()?;
+
+        trace!("submitting verification request {:?}", verify_args);
+
+        let resp = etherscan
+            .submit_contract_verification(&verify_args)
+            .await
+            .wrap_err("Failed to submit contract verification")?;
+
+        if resp.status == "0" {
+            if resp.message == "Contract source code already verified" {
+                println!("Contract source code already verified.");
+                return Ok(None)
+            }
+
+            if resp.result == "Contract source code already verified" {
+                println!("Contract source code already verified");
+                return Ok(None)
+            }
+
+            warn!("Failed verify submission: {:?}", resp);
+
+            eprintln!(
+                "Encountered an error verifying this contract:\nResponse: `{}`\nDetails: `{}`",
+                resp.message, resp.result
+            );
+            std::process::exit(1)
+        }
+
+        println!(
+            r#"Submitted contract for verification:
+    Response: `{}`
+    GUID: `{}`
+    url: {}#code"#,
+            resp.message,
+            resp.result,
+            etherscan.address_url(self.address)
+        );
+        self.record_submission(&resp.result);
+        Ok(Some(resp.result))
+    }
+
+    /// Submits the contract to the blockscout instance, the same request as for etherscan is sent
+    /// in blockscout's format
+    async fn verify_blockscout(&self) -> eyre::Result<Option<String>> {
+        let client = self.verifier.blockscout()?;
+        let request = BlockscoutVerifyRequest::new(&self.create_verify_request()?)?;
+
+        trace!("submitting blockscout verification request {:?}", request);
+
+        let resp = client.verify(&request).await?;
+        if resp.status == "0" {
+            if resp.result.contains("already verified") {
+                println!("Contract source code already verified.");
+                return Ok(None)
+            }
+
+            warn!("Failed verify submission: {:?}", resp);
+
+            eprintln!(
+                "Encountered an error verifying this contract:\nResponse: `{}`\nDetails: `{}`",
+                resp.message, resp.result
+            );
+            std::process::exit(1)
+        }
+
+        println!(
+            r#"Submitted contract for verification:
+    Response: `{}`
+    GUID: `{}`"#,
+            resp.message, resp.result
+        );
+        self.record_submission(&resp.result);
+        Ok(Some(resp.result))
+    }
+
+    /// Submits the contract's metadata and sources to sourcify and waits until it is verified
+    async fn verify_sourcify(&self) -> eyre::Result<()> {
+        let request = self.create_sourcify_request()?;
+        trace!("submitting sourcify verification request for {:?}", request.files.keys());
+
+        let client = self.verifier.sourcify();
+        let verified = match client.verify(&request).await? {
+            Some(verified) => Some(verified),
+            None => {
+                client
+                    .wait_for_verification(self.address, self.chain.id(), 5, Duration::from_secs(3))
+                    .await?
+            }
+        };
+
+        match verified {
+            Some(verified) => {
+                print_sourcify_match(verified);
+                Ok(())
+            }
+            None => {
+                eprintln!("Contract verification failed: Sourcify didn't report a match.");
+                std::process::exit(1)
+            }
+        }
+    }
+
+    /// Creates the sourcify request with the contract's metadata and all the sources it lists
+    fn create_sourcify_request(&self) -> eyre::Result<SourcifyVerifyRequest> {
+        let project = self.project(vec![ContractOutputSelection::Metadata])?;
+        self.contract_path(&project)?;
+
+        let output = compile::suppress_compile(&project)?;
+        let artifact = output.find(&self.contract.name).ok_or_else(|| {
+            eyre::eyre!(
+                "Could not find artifact `{}` in the compiled artifacts",
+                self.contract.name
+            )
+        })?;
+        let metadata = serde_json::to_value(artifact.metadata.as_ref().ok_or_else(|| {
+            eyre::eyre!("Failed to extract the metadata of `{}`", self.contract.name)
+        })?)?;
+
+        let mut sources = Vec::new();
+        for name in metadata["sources"].as_object().into_iter().flat_map(|sources| sources.keys()) {
+            let path = project.root().join(name);
+            let content = std::fs::read_to_string(&path)
+                .wrap_err_with(|| format!("Failed to read source {}", path.display()))?;
+            sources.push((name.clone(), content));
+        }
+
+        Ok(SourcifyVerifyRequest::new(
+            self.address,
+            self.chain.id(),
+            serde_json::to_string(&metadata)?,
+            sources,
+        ))
+    }
+
+    /// Returns the project the contract is part of, requesting the extra compiler output
+    fn project(&self, extra_output: Vec<ContractOutputSelection>) -> eyre::Result<Project> {
+        let build_args = CoreBuildArgs {
+            project_paths: self.project_paths.clone(),
+            out_path: Default::default(),
+            compiler: CompilerArgs {
+                extra_output: (!extra_output.is_empty()).then(|| extra_output),
+                ..Default::default()
+            },
+            ignored_error_codes: vec![],
+            no_auto_detect: false,
+            use_solc: None,
+            offline: false,
+            force: false,
+            locked: false,
+            config_path: None,
+        };
+
+        build_args.project()
+    }
+
+    /// Returns the path of the contract, which must be part of the project's source dir
+    fn contract_path(&self, project: &Project) -> eyre::Result<PathBuf> {
+        let contract_path =
+            project.root().join(self.contract.path.as_ref().expect("Is present; qed"));
+
+        if !contract_path.exists() {
+            eyre::bail!("Contract {:?} does not exist.", contract_path);
+        }
+
+        if !contract_path.starts_with(project.sources_path()) {
+            eyre::bail!("Contract {:?} is outside of project source directory", contract_path);
+        }
+        Ok(contract_path)
+    }
+
+    /// Records how the contract was submitted, so `verify-check` can interpret the result of the
+    /// verification with the GUID
+    fn record_submission(&self, guid: &str) {
+        let submission = Submission {
+            mode: self.mode(),
+            address: self.address,
+            contract: self.contract.name.clone(),
+        };
+        if let Err(err) = submission.save(guid) {
+            warn!(?err, "failed to record verification submission");
+        }
+    }
+
+    /// Creates the `VerifyContract` etherescan request in order to verify the contract
+    ///
+    /// With `--via flattened` this will send with [`CodeFormat::SingleFile`] otherwise this will
+    /// use the [`CodeFormat::StandardJsonInput`]
+    fn create_verify_request(&self) -> eyre::Result<VerifyContract> {
+        let project = self.project(vec![])?;
+        let contract_path = self.contract_path(&project)?;
+
+        let (source, contract_name, code_format) = match self.mode() {
+            VerificationMode::Flattened => flattened_source(self, &project, &contract_path)?,
+            VerificationMode::StandardJson => standard_json_source(self, &project, &contract_path)?,
+        };
+
+        let mut verify_args =
+            VerifyContract::new(self.address, contract_name, source, self.compiler_version.clone())
+                .constructor_arguments(self.constructor_args(&project)?)
+                .code_format(code_format);
+
+        verify_args = if let Some(optimizations) = self.num_of_optimizations {
+            verify_args.optimization(true).runs(optimizations)
+        } else {
+            verify_args.optimization(false)
+        };
+
+        Ok(verify_args)
+    }
+
+    /// Returns the hex encoded constructor arguments of `--constructor-args` or
+    /// `--constructor-args-path`
+    fn constructor_args(&self, project: &Project) -> eyre::Result<Option<String>> {
+        let path = match self.constructor_args_path {
+            Some(ref path) => path,
+            None => return Ok(self.constructor_args.clone()),
+        };
+        let encoded = match ConstructorArgsFile::read(path)? {
+            ConstructorArgsFile::Encoded(encoded) => encoded,
+            file => {
+                let output = compile::suppress_compile(project)?;
+                let artifact = output.find(&self.contract.name).ok_or_else(|| {
+                    eyre::eyre!(
+                        "Could not find artifact `{}` in the compiled artifacts",
+                        self.contract.name
+                    )
+                })?;
+                let constructor = artifact
+                    .clone()
+                    .into_contract_bytecode()
+                    .abi
+                    .and_then(|abi| abi.constructor)
+                    .ok_or_else(|| {
+                        eyre::eyre!("`{}` has no constructor arguments", self.contract.name)
+                    })?;
+                ethers::abi::encode(&file.tokens(&constructor)?)
+            }
+        };
+        Ok(Some(hex::encode(encoded)))
+    }
+
+    /// Parses the [Version] from the provided compiler version
+    ///
+    /// All etherscan supported compiler versions are listed here <https://etherscan.io/solcversions>
+    ///
+    /// **Note:** this is only for local compilation as a dry run, therefore this will return a
+    /// sanitized variant of the specific version so that it can be installed. This is merely
+    /// intended to ensure the flattened code can be compiled without errors.
+    ///
+    /// # Example
+    ///
+    /// the `compiler_version` `v0.8.7+commit.e28d00a7` will be returned as `0.8.7`
+    fn sanitized_solc_version(&self) -> eyre::Result<Version> {
+        let v: Version = self.compiler_version.trim_start_matches('v').parse()?;
+        Ok(Version::new(v.major, v.minor, v.patch))
+    }
+
+    /// Attempts to compile the flattened content locally with the compiler version.
+    ///
+    /// This expects the completely flattened `content´ and will try to compile it using the
+    /// provided compiler. If the compiler is missing it will be installed.
+    ///
+    /// # Errors
+    ///
+    /// If it failed to install a missing solc compiler
+    ///
+    /// # Exits
+    ///
+    /// If the solc compiler output contains errors, this could either be due to a bug in the
+    /// flattening code or could to conflict in the flattened code, for example if there are
+    /// multiple interfaces with the same name.
+    fn check_flattened(&self, content: impl Into<String>) -> eyre::Result<()> {
+        let version: Version = self.sanitized_solc_version()?;
+        let solc = if let Some(solc) = Solc::find_svm_installed_version(version.to_string())? {
+            solc
+        } else {
+            Solc::blocking_install(&version)?
+        };
+        let input = CompilerInput {
+            language: "Solidity".to_string(),
+            sources: BTreeMap::from([("constract.sol".into(), Source { content: content.into() })]),
+            settings: Default::default(),
+        };
+
+        let out = solc.compile(&input)?;
+        if out.has_error() {
+            let mut o = AggregatedCompilerOutput::default();
+            o.extend(version, out);
+            eprintln!("{}", o.diagnostics(&[]));
+
+            eprintln!(
+                r#"Failed to compile the flattened code locally.
+This could be a bug, please inspect the outout of `forge flatten {}` and report an issue.
+To skip this solc dry, pass `--force`.
+"#,
+                self.contract.path.as_ref().expect("Path is some;")
+            );
+            std::process::exit(1)
+        }
+
+        Ok(())
+    }
+}
+
+/// A submitted verification, recorded by its GUID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Submission {
+    /// How the source code was submitted
+    mode: VerificationMode,
+    address: Address,
+    /// The name of the verified contract
+    contract: String,
+}
+
+impl Submission {
+    /// Returns the file the submission with the GUID is recorded in
+    fn file(guid: &str) -> Option<PathBuf> {
+        Some(Config::foundry_cache_dir()?.join("verify").join(format!("{}.json", guid)))
+    }
+
+    fn save(&self, guid: &str) -> eyre::Result<()> {
+        let file = Self::file(guid).ok_or_else(|| eyre::eyre!("No cache directory"))?;
+        std::fs::create_dir_all(file.parent().expect("has parent"))?;
+        std::fs::write(file, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the submission with the GUID, if it was submitted from this machine
+    fn load(guid: &str) -> Option<Self> {
+        let content = std::fs::read_to_string(Self::file(guid)?).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Explains a failed verification of the submission
+    fn failure_hint(&self) -> String {
+        match self.mode {
+            VerificationMode::Flattened => format!(
+                "`{}` was submitted as flattened source, which often fails for projects with complex imports or duplicate SPDX identifiers. Retry with `--via standard-json`.",
+                self.contract
+            ),
+            VerificationMode::StandardJson => format!(
+                "`{}` was submitted as standard JSON input with the project's compiler settings, make sure they match the settings the deployed contract was compiled with.",
+                self.contract
+            ),
+        }
+    }
+}
+
+/// Check verification status arguments
+#[derive(Debug, Clone, Parser)]
+pub struct VerifyCheckArgs {
+    #[clap(help = "The verification GUID, or the contract address with `--verifier sourcify`.")]
+    guid: String,
+
+    #[clap(
+        long,
+        alias = "chain-id",
+        env = "CHAIN",
+        help = "The chain ID the contract is deployed to.",
+        default_value = "mainnet"
+    )]
+    chain: Chain,
+
+    #[clap(
+        help = "Your Etherscan API key, only needed with `--verifier etherscan`.",
+        env = "ETHERSCAN_API_KEY"
+    )]
+    etherscan_key: Option<String>,
+
+    #[clap(flatten)]
+    verifier: VerifierArgs,
+}
+
+impl VerifyCheckArgs {
+    /// Executes the command to check verification status on Etherscan, Sourcify or Blockscout
+    pub async fn run(&self) -> eyre::Result<()> {
+        self.check().await.map(drop)
+    }
+
+    /// Checks the status of the verification until it's no longer pending, at most `retries`
+    /// times every `interval`
+    pub async fn wait(&self, retries: u32, interval: Duration) -> eyre::Result<()> {
+        for attempt in 0..=retries {
+            if attempt > 0 {
+                tokio::time::sleep(interval).await;
+            }
+            if self.check().await? {
+                return Ok(())
+            }
+        }
+        eyre::bail!(
+            "The verification is still pending, check its status later with `forge verify-check {}`",
+            self.guid
+        )
+    }
+
+    /// Checks the status of the verification once, returns `false` if it's still pending
+    ///
+    /// Exits if the verification failed.
+    async fn check(&self) -> eyre::Result<bool> {
+        if self.verifier.verifier == VerificationProvider::Blockscout {
+            let resp = self.verifier.blockscout()?.check(&self.guid).await?;
+            if resp.result == "Pending in queue" {
+                println!("Verification is pending...");
+                return Ok(false)
+            }
+            if resp.status == "0" || resp.result.starts_with("Fail") {
+                warn!("Failed verification: {:?}", resp);
+
+                eprintln!(
+                    "Contract verification failed:\nResponse: `{}`\nDetails: `{}`",
+                    resp.message, resp.result
+                );
+                self.print_failure_hint();
+                std::process::exit(1);
+            }
+            self.print_success();
+            return Ok(true)
+        }
+
+        if self.verifier.verifier == VerificationProvider::Sourcify {
+            let address: Address = self.guid.parse().map_err(|_| {
+                eyre::eyre!("`{}` is not a contract address, which Sourcify expects", self.guid)
+            })?;
+            match self.verifier.sourcify().check(address, self.chain.id()).await? {
+                Some(verified) => print_sourcify_match(verified),
+                None => {
+                    eprintln!("Contract is not verified on Sourcify.");
+                    std::process::exit(1);
+                }
+            }
+            return Ok(true)
+        }
+
+        let etherscan = Client::new(self.chain.try_into()?, etherscan_key(&self.etherscan_key)?)
+            .wrap_err("Failed to create etherscan client")?;
+
+        let resp = etherscan
+            .check_contract_verification_status(self.guid.clone())
+            .await
+            .wrap_err("Failed to request verification status")?;
+
+        if resp.status == "0" {
+            if resp.result == "Pending in queue" {
+                println!("Verification is pending...");
+                return Ok(false)
+            }
+
+            if resp.result == "Already Verified" {
+                println!("Contract source code already verified");
+                return Ok(true)
+            }
+
+            warn!("Failed verification: {:?}", resp);
+
+            eprintln!(
+                "Contract verification failed:\nResponse: `{}`\nDetails: `{}`",
+                resp.message, resp.result
+            );
+            self.print_failure_hint();
+
+            std::process::exit(1);
+        }
+
+        self.print_success();
+        Ok(true)
+    }
+
+    fn print_success(&self) {
+        match Submission::load(&self.guid) {
+            Some(submission) => {
+                println!("Contract successfully verified, submitted as {}.", submission.mode.name())
+            }
+            None => println!("Contract successfully verified."),
+        }
+    }
+
+    fn print_failure_hint(&self) {
+        if let Some(submission) = Submission::load(&self.guid) {
+            eprintln!("{}", submission.failure_hint());
+        }
+    }
+}
+
+fn print_sourcify_match(verified: SourcifyMatch) {
+    match verified {
+        SourcifyMatch::Full => println!("Contract successfully verified: full match."),
+        SourcifyMatch::Partial => println!("Contract successfully verified: partial match."),
+    }
+}
+
+fn flattened_source(
+    args: &VerifyArgs,
+    project: &Project,
+    target: &Path,
+) -> eyre::Result<(String, String, CodeFormat)> {
+    let bch = project
+        .solc_config
+        .settings
+        .metadata
+        .as_ref()
+        .and_then(|m| m.bytecode_hash)
+        .unwrap_or_default();
+
+    eyre::ensure!(
+        bch == BytecodeHash::Ipfs,
+        "When using flattened source, bytecodeHash must be set to ipfs. BytecodeHash is currently: {}. Hint: Set the bytecodeHash key in your foundry.toml :)",
+        bch,
+    );
+
+    let source = project.flatten(target).wrap_err("Failed to flatten contract")?;
+
+    if !args.force {
+        // solc dry run of flattened code
+        args.check_flattened(source.clone()).map_err(|err| {
+            eyre::eyre!(
+                "Failed to compile the flattened code locally: `{}`\
+To skip this solc dry, have a look at the  `--force` flag of this command.",
+                err
+            )
+        })?;
+    }
+
+    let name = args.contract.name.clone();
+    Ok((source, name, CodeFormat::SingleFile))
+}
+
+fn standard_json_source(
+    args: &VerifyArgs,
+    project: &Project,
+    target: &Path,
+) -> eyre::Result<(String, String, CodeFormat)> {
+    let input = project
+        .standard_json_input(target)
+        .wrap_err("Failed to get standard json input")?
+        .normalize_evm_version(&args.sanitized_solc_version()?);
+
+    let source = serde_json::to_string(&input).wrap_err("Failed to parse standard json input")?;
+    let name = format!(
+        "{}:{}",
+        target.strip_prefix(project.root()).unwrap_or(target).display(),
+        args.contract.name.clone()
+    );
+    Ok((source, name, CodeFormat::StandardJsonInput))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_json_matches_compiler_input() {
+        let root = std::env::temp_dir().join("forge-verify-standard-json");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let base = "// SPDX-License-Identifier: MIT\npragma solidity 0.8.10;\ncontract Base {}\n";
+        let counter = "// SPDX-License-Identifier: MIT\npragma solidity 0.8.10;\nimport \"./Base.sol\";\ncontract Counter is Base {}\n";
+        std::fs::write(root.join("src/Base.sol"), base).unwrap();
+        std::fs::write(root.join("src/Counter.sol"), counter).unwrap();
+        std::fs::write(root.join("foundry.toml"), "[default]\noptimizer_runs = 1000\n").unwrap();
+
+        let args = VerifyArgs::parse_from([
+            "foundry-cli",
+            "0x8ba1f109551bd432803012645ac136ddd64dba72",
+            "src/Counter.sol:Counter",
+            "--compiler-version",
+            "v0.8.10+commit.fc410830",
+            "--root",
+            root.to_str().unwrap(),
+        ]);
+        assert_eq!(args.mode(), VerificationMode::StandardJson);
+
+        let project = args.project(vec![]).unwrap();
+        let target = args.contract_path(&project).unwrap();
+        let (source, name, code_format) = standard_json_source(&args, &project, &target).unwrap();
+        assert_eq!(name, "src/Counter.sol:Counter");
+        assert!(matches!(code_format, CodeFormat::StandardJsonInput));
+
+        // the input contains every source the contract needs and the project's compiler settings
+        let input: serde_json::Value = serde_json::from_str(&source).unwrap();
+        assert_eq!(input["language"], "Solidity");
+        let sources = input["sources"].as_object().unwrap();
+        assert_eq!(sources.len(), 2);
+        for (file, content) in [("src/Base.sol", base), ("src/Counter.sol", counter)] {
+            let (_, source) = sources.iter().find(|(path, _)| path.ends_with(file)).unwrap();
+            assert_eq!(source["content"], content);
+        }
+        let settings = serde_json::to_value(&project.solc_config.settings).unwrap();
+        assert_eq!(input["settings"]["optimizer"], settings["optimizer"]);
+        assert_eq!(input["settings"]["optimizer"]["runs"], 1000);
+        assert_eq!(input["settings"]["evmVersion"], settings["evmVersion"]);
+    }
+
+    #[test]
+    fn encodes_constructor_args_from_file() {
+        let root = std::env::temp_dir().join("forge-verify-constructor-args");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let counter = "// SPDX-License-Identifier: MIT\npragma solidity 0.8.10;\ncontract Counter {\n    constructor(address owner, uint256[] memory counts) {}\n}\n";
+        std::fs::write(root.join("src/Counter.sol"), counter).unwrap();
+        let args_file = root.join("args.json");
+        std::fs::write(&args_file, r#"["0x8ba1f109551bd432803012645ac136ddd64dba72", [1, "2"]]"#)
+            .unwrap();
+
+        let args = |path: &Path| {
+            VerifyArgs::parse_from([
+                "foundry-cli",
+                "0x8ba1f109551bd432803012645ac136ddd64dba72",
+                "src/Counter.sol:Counter",
+                "--compiler-version",
+                "v0.8.10+commit.fc410830",
+                "--root",
+                root.to_str().unwrap(),
+                "--constructor-args-path",
+                path.to_str().unwrap(),
+            ])
+        };
+        let verify = args(&args_file);
+        let project = verify.project(vec![]).unwrap();
+        let expected = ethers::abi::encode(&[
+            ethers::abi::Token::Address(
+                "0x8ba1f109551bd432803012645ac136ddd64dba72".parse().unwrap(),
+            ),
+            ethers::abi::Token::Array(vec![
+                ethers::abi::Token::Uint(1u64.into()),
+                ethers::abi::Token::Uint(2u64.into()),
+            ]),
+        ]);
+        assert_eq!(verify.constructor_args(&project).unwrap(), Some(hex::encode(&expected)));
+
+        // encoded arguments are passed on as they are
+        let hex_file = root.join("args.hex");
+        std::fs::write(&hex_file, format!("0x{}", hex::encode(&expected))).unwrap();
+        assert_eq!(
+            args(&hex_file).constructor_args(&project).unwrap(),
+            Some(hex::encode(&expected))
+        );
+
+        assert!(VerifyArgs::try_parse_from([
+            "foundry-cli",
+            "0x8ba1f109551bd432803012645ac136ddd64dba72",
+            "src/Counter.sol:Counter",
+            "--compiler-version",
+            "v0.8.10+commit.fc410830",
+            "--constructor-args",
+            "00",
+            "--constructor-args-path",
+            args_file.to_str().unwrap(),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn flatten_is_short_for_via_flattened() {
+        let args = |extra: &[&str]| {
+            let mut argv = vec![
+                "foundry-cli",
+                "0x8ba1f109551bd432803012645ac136ddd64dba72",
+                "src/Counter.sol:Counter",
+                "--compiler-version",
+                "v0.8.10+commit.fc410830",
+            ];
+            argv.extend_from_slice(extra);
+            VerifyArgs::try_parse_from(argv)
+        };
+        assert_eq!(args(&["--flatten"]).unwrap().mode(), VerificationMode::Flattened);
+        assert_eq!(args(&["--via", "flattened"]).unwrap().mode(), VerificationMode::Flattened);
+        assert_eq!(
+            args(&["--via", "standard-json"]).unwrap().mode(),
+            VerificationMode::StandardJson
+        );
+        assert!(args(&["--via", "standard-json", "--flatten"]).is_err());
+    }
+}
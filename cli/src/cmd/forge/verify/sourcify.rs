@@ -0,0 +1,183 @@
+//! Verify contract source on Sourcify
+
+use ethers::abi::Address;
+use eyre::Context;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+
+/// The default Sourcify server
+pub static SOURCIFY_URL: &str = "https://sourcify.dev/server";
+
+/// How well the verified sources match the deployed contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourcifyMatch {
+    /// The bytecode matches, including the metadata hash
+    Full,
+    /// The bytecode matches, but the metadata (e.g. comments) differs
+    Partial,
+}
+
+impl SourcifyMatch {
+    /// Parses the `status` Sourcify reports for a contract, `None` if it isn't verified
+    fn from_status(status: &str) -> Option<Self> {
+        match status {
+            "perfect" => Some(SourcifyMatch::Full),
+            "partial" => Some(SourcifyMatch::Partial),
+            _ => None,
+        }
+    }
+}
+
+/// The request to verify a contract, the files are keyed by name
+#[derive(Debug, Clone, Serialize)]
+pub struct SourcifyVerifyRequest {
+    pub address: String,
+    pub chain: String,
+    pub files: BTreeMap<String, String>,
+}
+
+impl SourcifyVerifyRequest {
+    /// Creates a request that submits the contract's metadata and all of its sources
+    pub fn new(
+        address: Address,
+        chain_id: u64,
+        metadata: String,
+        sources: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        let mut files = BTreeMap::from([("metadata.json".to_string(), metadata)]);
+        files.extend(sources);
+        Self { address: format!("{:?}", address), chain: chain_id.to_string(), files }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SourcifyVerifyResponse {
+    #[serde(default)]
+    result: Vec<SourcifyContractStatus>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SourcifyContractStatus {
+    status: String,
+}
+
+/// A client for the verification endpoints of a Sourcify server
+#[derive(Debug, Clone)]
+pub struct SourcifyClient {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl SourcifyClient {
+    /// Creates a client for the Sourcify server at the url, e.g. [SOURCIFY_URL]
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { client: reqwest::Client::new(), url: url.into().trim_end_matches('/').to_string() }
+    }
+
+    /// Submits the metadata and sources of the contract
+    ///
+    /// Returns the match of the contract if Sourcify could verify it right away.
+    pub async fn verify(
+        &self,
+        request: &SourcifyVerifyRequest,
+    ) -> eyre::Result<Option<SourcifyMatch>> {
+        let resp: SourcifyVerifyResponse = self
+            .client
+            .post(format!("{}/verify", self.url))
+            .json(request)
+            .send()
+            .await
+            .wrap_err("Failed to submit contract verification")?
+            .json()
+            .await
+            .wrap_err("Failed to parse the Sourcify response")?;
+
+        if let Some(error) = resp.error {
+            eyre::bail!("Encountered an error verifying this contract:\nDetails: `{}`", error)
+        }
+        Ok(resp.result.iter().find_map(|status| SourcifyMatch::from_status(&status.status)))
+    }
+
+    /// Returns the match of the contract, `None` if it isn't verified
+    pub async fn check(
+        &self,
+        address: Address,
+        chain_id: u64,
+    ) -> eyre::Result<Option<SourcifyMatch>> {
+        let statuses: Vec<SourcifyContractStatus> = self
+            .client
+            .get(format!("{}/check-by-addresses", self.url))
+            .query(&[("addresses", format!("{:?}", address)), ("chainIds", chain_id.to_string())])
+            .send()
+            .await
+            .wrap_err("Failed to request verification status")?
+            .json()
+            .await
+            .wrap_err("Failed to parse the Sourcify response")?;
+        Ok(statuses.iter().find_map(|status| SourcifyMatch::from_status(&status.status)))
+    }
+
+    /// Polls the status of the contract until it is verified, at most `retries` times
+    pub async fn wait_for_verification(
+        &self,
+        address: Address,
+        chain_id: u64,
+        retries: usize,
+        interval: Duration,
+    ) -> eyre::Result<Option<SourcifyMatch>> {
+        for attempt in 0..=retries {
+            if attempt > 0 {
+                tokio::time::sleep(interval).await;
+            }
+            if let Some(verified) = self.check(address, chain_id).await? {
+                return Ok(Some(verified))
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::forge::verify::mock::mock_server;
+
+    #[test]
+    fn verifies_full_match() {
+        let address: Address = "0x8ba1f109551bd432803012645ac136ddd64dba72".parse().unwrap();
+        let (url, requests) = mock_server(vec![
+            r#"{"result":[{"address":"0x8Ba1f109551bD432803012645Ac136ddd64DBA72","chainId":"100","status":"perfect"}]}"#,
+            r#"[{"address":"0x8Ba1f109551bD432803012645Ac136ddd64DBA72","status":"perfect","chainIds":["100"]}]"#,
+        ]);
+        let client = SourcifyClient::new(format!("{}/", url));
+
+        let request = SourcifyVerifyRequest::new(
+            address,
+            100,
+            r#"{"language":"Solidity"}"#.to_string(),
+            [("src/Vault.sol".to_string(), "contract Vault {}".to_string())],
+        );
+        let verified = crate::utils::block_on(client.verify(&request)).unwrap();
+        assert_eq!(verified, Some(SourcifyMatch::Full));
+
+        let (request_line, body) = requests.recv().unwrap();
+        assert_eq!(request_line, "POST /verify HTTP/1.1");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["address"], "0x8ba1f109551bd432803012645ac136ddd64dba72");
+        assert_eq!(body["chain"], "100");
+        assert_eq!(body["files"]["metadata.json"], r#"{"language":"Solidity"}"#);
+        assert_eq!(body["files"]["src/Vault.sol"], "contract Vault {}");
+
+        let verified =
+            crate::utils::block_on(client.wait_for_verification(address, 100, 0, Duration::ZERO))
+                .unwrap();
+        assert_eq!(verified, Some(SourcifyMatch::Full));
+        let (request_line, _) = requests.recv().unwrap();
+        assert_eq!(
+            request_line,
+            "GET /check-by-addresses?addresses=0x8ba1f109551bd432803012645ac136ddd64dba72&chainIds=100 HTTP/1.1"
+        );
+    }
+}
@@ -31,16 +31,20 @@ pub struct WatchArgs {
     /// action will be to do nothing.
     ///
     /// Defaults to 50ms. Parses as decimal seconds by default, but
-    /// using an integer with the `ms` suffix may be more convenient.
+    /// the `ms`, `s`, `m` and `h` suffixes may be more convenient.
     ///
     /// When using --poll mode, you'll want a larger duration, or risk
     /// overloading disk I/O.
-    #[clap(short = 'd', long = "delay", forbid_empty_values = true)]
+    #[clap(short = 'd', long = "delay", visible_alias = "watch-delay", forbid_empty_values = true)]
     pub delay: Option<String>,
 
     #[clap(long = "no-restart", help = "Do not restart the command while it's still running.")]
     pub no_restart: bool,
 
+    /// Clear the screen before every rerun of the command.
+    #[clap(long, requires = "watch")]
+    pub clear: bool,
+
     /// Explicitly re-run all tests when a change is made.
     ///
     /// By default, only the tests of the last modified test file are executed.
@@ -49,7 +53,7 @@ pub struct WatchArgs {
 
     /// Watch specific file(s) or folder(s)
     ///
-    /// By default, the project's source directory is watched.
+    /// By default, the project's source, test and library directories are watched.
     #[clap(
         short = 'w',
         long = "watch",
@@ -57,19 +61,23 @@ pub struct WatchArgs {
         min_values = 0,
         multiple_values = true,
         multiple_occurrences = false,
-        help = "Watches the given files or folders for changes. If no paths are specified, the source directory of the project is watched."
+        help = "Watches the given files or folders for changes. If no paths are specified, the source, test and library directories of the project are watched."
     )]
     pub watch: Option<Vec<PathBuf>>,
+
+    /// Changes in these directories never trigger a rerun, e.g. the build output
+    #[clap(skip)]
+    pub ignored: Vec<PathBuf>,
 }
 
 impl WatchArgs {
     /// Returns new [InitConfig] and [RuntimeConfig] based on the [WatchArgs]
     ///
     /// If paths were provided as arguments the these will be used as the watcher's pathset,
-    /// otherwise the path the closure returns will be used
+    /// otherwise the paths the closure returns will be used
     pub fn watchexec_config(
         &self,
-        f: impl FnOnce() -> PathBuf,
+        f: impl FnOnce() -> Vec<PathBuf>,
     ) -> eyre::Result<(InitConfig, RuntimeConfig)> {
         let init = init()?;
         let mut runtime = runtime(self)?;
@@ -79,10 +87,48 @@ impl WatchArgs {
 
         if !has_paths {
             // use alternative pathset
-            runtime.pathset(Some(f()));
+            runtime.pathset(f());
         }
         Ok((init, runtime))
     }
+
+    /// Ignores changes in the project's `out` and cache directories, which are written by the
+    /// command itself and would otherwise trigger an endless loop of reruns
+    #[must_use]
+    pub fn ignore_build_output(mut self, config: &Config) -> Self {
+        self.ignored = vec![config.out.clone(), config.cache_path.clone()];
+        self
+    }
+}
+
+/// Returns the directories to watch by default: the project's `src`, `test` and `lib`
+/// directories and the directories of all remapped libraries
+pub fn watch_paths(config: &Config) -> Vec<PathBuf> {
+    let mut paths = vec![config.src.clone(), config.test.clone()];
+    paths.extend(config.libs.iter().cloned());
+    paths.extend(config.get_all_remappings().into_iter().map(|r| PathBuf::from(r.path)));
+    let mut paths = paths.into_iter().filter(|path| path.exists()).collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+    // nested directories are already watched
+    let mut watched: Vec<PathBuf> = Vec::with_capacity(paths.len());
+    for path in paths {
+        if !watched.iter().any(|dir| path.starts_with(dir)) {
+            watched.push(path);
+        }
+    }
+    watched
+}
+
+/// Returns true if any of the changed paths is outside of the ignored directories
+fn has_relevant_changes<'a>(
+    events: impl IntoIterator<Item = &'a Event>,
+    ignored: &[PathBuf],
+) -> bool {
+    events
+        .into_iter()
+        .flat_map(|e| e.paths())
+        .any(|(path, _)| !ignored.iter().any(|dir| path.starts_with(dir)))
 }
 
 /// Executes a [`Watchexec`] that listens for changes in the project's src dir and reruns `forge
@@ -94,8 +140,10 @@ pub async fn watch_build(args: BuildArgs) -> eyre::Result<()> {
     trace!("watch build cmd={:?}", cmd);
     runtime.command(cmd.clone());
 
+    let config: Config = (&args).into();
     let wx = Watchexec::new(init, runtime.clone())?;
-    on_action(args.watch, runtime, Arc::clone(&wx), cmd, (), |_| {});
+    let watch = args.watch.ignore_build_output(&config);
+    on_action(watch, runtime, Arc::clone(&wx), cmd, (), |_| {});
 
     // start executing the command immediately
     wx.send_event(Event::default()).await?;
@@ -114,7 +162,9 @@ pub async fn watch_snapshot(args: SnapshotArgs) -> eyre::Result<()> {
     runtime.command(cmd.clone());
     let wx = Watchexec::new(init, runtime.clone())?;
 
-    on_action(args.test.watch.clone(), runtime, Arc::clone(&wx), cmd, (), |_| {});
+    let config: Config = args.build_args().into();
+    let watch = args.test.watch.clone().ignore_build_output(&config);
+    on_action(watch, runtime, Arc::clone(&wx), cmd, (), |_| {});
 
     // start executing the command immediately
     wx.send_event(Event::default()).await?;
@@ -140,12 +190,13 @@ pub async fn watch_test(args: TestArgs) -> eyre::Result<()> {
         args.watch.run_all;
 
     let config: Config = args.build_args().into();
+    let watch = args.watch.clone().ignore_build_output(&config);
     let state = WatchTestState {
         project_root: config.__root.0,
         no_reconfigure,
         last_test_files: Default::default(),
     };
-    on_action(args.watch.clone(), runtime, Arc::clone(&wx), cmd, state, on_test);
+    on_action(watch, runtime, Arc::clone(&wx), cmd, state, on_test);
 
     // start executing the command immediately
     wx.send_event(Event::default()).await?;
@@ -313,6 +364,12 @@ fn on_action<F, T>(
             return fut
         }
 
+        if has_paths && !has_relevant_changes(action.events.iter(), &args.ignored) {
+            // only the output of the command itself changed
+            action.outcome(Outcome::DoNothing);
+            return fut
+        }
+
         if !has_paths {
             if !signals.is_empty() {
                 let mut out = Outcome::DoNothing;
@@ -358,8 +415,7 @@ fn on_action<F, T>(
             other: other.clone(),
         });
 
-        // mattsse: could be made into flag to never clear the shell
-        let clear = false;
+        let clear = args.clear;
         let when_running = match (clear, on_busy) {
             (_, "do-nothing") => Outcome::DoNothing,
             (true, "restart") => {
@@ -415,3 +471,53 @@ fn default_shell() -> Shell {
 fn default_shell() -> Shell {
     Shell::default()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use watchexec::event::Tag;
+
+    fn file_event(path: &str) -> Event {
+        Event {
+            tags: vec![Tag::Path { path: PathBuf::from(path), file_type: None }],
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn reruns_on_source_changes_only() {
+        let ignored = vec![PathBuf::from("/project/out"), PathBuf::from("/project/cache")];
+
+        assert!(has_relevant_changes([&file_event("/project/src/Vault.sol")], &ignored));
+        assert!(has_relevant_changes([&file_event("/project/test/Vault.t.sol")], &ignored));
+        let output = file_event("/project/out/Vault.sol/Vault.json");
+        assert!(!has_relevant_changes([&output], &ignored));
+        assert!(!has_relevant_changes(
+            [&file_event("/project/cache/solidity-files-cache.json")],
+            &ignored
+        ));
+        // a single relevant change is enough
+        assert!(has_relevant_changes([&output, &file_event("/project/src/Vault.sol")], &ignored));
+    }
+
+    #[test]
+    fn watches_project_dirs() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let config = Config {
+            src: root.join("src"),
+            test: root.join("tests"),
+            libs: vec![],
+            ..Default::default()
+        };
+        let paths = watch_paths(&config);
+        assert_eq!(paths, vec![root.join("src"), root.join("tests")]);
+
+        // missing directories are not watched
+        let config = Config { test: root.join("missing"), ..config };
+        assert_eq!(watch_paths(&config), vec![root.join("src")]);
+
+        // library directories are watched, but not twice if they're nested
+        let config = Config { libs: vec![root.join("tests"), root.join("tests/it")], ..config };
+        assert_eq!(watch_paths(&config), vec![root.join("src"), root.join("tests")]);
+    }
+}
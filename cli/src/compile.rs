@@ -1,9 +1,15 @@
 //! Support for compiling [ethers::solc::Project]
 
 use crate::term;
-use ethers::solc::{report::NoReporter, Artifact, FileFilter, Project, ProjectCompileOutput};
-use foundry_utils::to_table;
-use std::{collections::BTreeMap, path::PathBuf};
+use ansi_term::Colour;
+use ethers::solc::{
+    artifacts::BytecodeObject, report::NoReporter, Artifact, FileFilter, Project,
+    ProjectCompileOutput,
+};
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+
+/// The maximum size of the runtime bytecode of a contract in bytes, see EIP-170
+pub const CONTRACT_SIZE_LIMIT: usize = 24576;
 
 /// Compiles the provided [`Project`], throws if there's any compiler error and logs whether
 /// compilation was successful or if there was a cache hit.
@@ -92,7 +98,7 @@ If you are in a subdirectory in a Git repository, try adding `--root .`"#,
             // print the compiler output / warnings
             println!("{}", output);
 
-            // print any sizes or names
+            // print any names
             if print_names {
                 let compiled_contracts = output.compiled_contracts_by_compiler_version();
                 for (version, contracts) in compiled_contracts.into_iter() {
@@ -105,26 +111,20 @@ If you are in a subdirectory in a Git repository, try adding `--root .`"#,
                     }
                 }
             }
-            if print_sizes {
-                // add extra newline if names were already printed
-                if print_names {
-                    println!();
-                }
-                let compiled_contracts = output.compiled_contracts_by_compiler_version();
-                let mut sizes = BTreeMap::new();
-                for (_, contracts) in compiled_contracts.into_iter() {
-                    for (name, contract) in contracts {
-                        let size = contract
-                            .get_bytecode_bytes()
-                            .map(|bytes| bytes.0.len())
-                            .unwrap_or_default();
-                        sizes.insert(name, size);
-                    }
-                }
-                let json = serde_json::to_value(&sizes)?;
-                println!("name             size (bytes)");
-                println!("-----------------------------");
-                println!("{}", to_table(json));
+            // add extra newline if names were already printed
+            if print_names && print_sizes {
+                println!();
+            }
+        }
+
+        if print_sizes {
+            let report = SizeReport::new(&output);
+            println!("{}", report);
+            if report.exceeds_limit() {
+                eyre::bail!(
+                    "some contracts exceed the runtime size limit of {} bytes (EIP-170)",
+                    CONTRACT_SIZE_LIMIT
+                )
             }
         }
 
@@ -132,10 +132,122 @@ If you are in a subdirectory in a Git repository, try adding `--root .`"#,
     }
 }
 
+/// The bytecode sizes of the contracts of a compiled project
+#[derive(Debug, Clone, Default)]
+pub struct SizeReport {
+    /// The sizes by contract name
+    pub contracts: BTreeMap<String, ContractSize>,
+}
+
+impl SizeReport {
+    /// Collects the sizes of all contracts with runtime bytecode, so interfaces and abstract
+    /// contracts are skipped
+    ///
+    /// This includes the cached artifacts, so the report is complete even if nothing was
+    /// recompiled.
+    pub fn new(output: &ProjectCompileOutput) -> Self {
+        let contracts = output
+            .clone()
+            .into_artifacts()
+            .map(|(id, artifact)| (id.name, ContractSize::new(&artifact)))
+            .filter(|(_, size)| size.runtime_size > 0)
+            .collect();
+        Self { contracts }
+    }
+
+    /// Whether any contract exceeds the [CONTRACT_SIZE_LIMIT]
+    pub fn exceeds_limit(&self) -> bool {
+        self.contracts.values().any(ContractSize::exceeds_limit)
+    }
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.contracts.keys().map(String::len).max().unwrap_or_default().max(8);
+        writeln!(
+            f,
+            "{:<width$}  {:>12}  {:>14}  {:>14}",
+            "Contract",
+            "Size (bytes)",
+            "Margin (bytes)",
+            "Init code size",
+            width = width
+        )?;
+        writeln!(f, "{}", "-".repeat(width + 48))?;
+        for (name, size) in &self.contracts {
+            let row = format!(
+                "{:<width$}  {:>12}  {:>14}  {:>14}",
+                name,
+                size.runtime_size,
+                size.margin(),
+                size.init_size,
+                width = width
+            );
+            if size.exceeds_limit() {
+                writeln!(f, "{}", Colour::Red.paint(row))?;
+            } else {
+                writeln!(f, "{}", row)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The bytecode sizes of a single contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ContractSize {
+    /// The size of the deployed code, which is limited by EIP-170
+    pub runtime_size: usize,
+    /// The size of the creation code, without constructor arguments
+    pub init_size: usize,
+}
+
+impl ContractSize {
+    pub fn new(artifact: &impl Artifact) -> Self {
+        Self {
+            runtime_size: artifact
+                .get_deployed_bytecode_object()
+                .map(|bytecode| bytecode_size(&bytecode))
+                .unwrap_or_default(),
+            init_size: artifact
+                .get_bytecode_object()
+                .map(|bytecode| bytecode_size(&bytecode))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The number of bytes left until the [CONTRACT_SIZE_LIMIT], negative if it is exceeded
+    pub fn margin(&self) -> isize {
+        CONTRACT_SIZE_LIMIT as isize - self.runtime_size as isize
+    }
+
+    pub fn exceeds_limit(&self) -> bool {
+        self.runtime_size > CONTRACT_SIZE_LIMIT
+    }
+}
+
+/// Returns the size of the bytecode in bytes, library placeholders of unlinked bytecode have the
+/// size of the address they are replaced with
+fn bytecode_size(bytecode: &BytecodeObject) -> usize {
+    match bytecode {
+        BytecodeObject::Bytecode(bytes) => bytes.0.len(),
+        BytecodeObject::Unlinked(hex) => hex.trim_start_matches("0x").len() / 2,
+    }
+}
+
 /// Compiles the provided [`Project`], throws if there's any compiler error and logs whether
 /// compilation was successful or if there was a cache hit.
 /// Doesn't print anything to stdout, thus is "suppressed".
 pub fn suppress_compile(project: &Project) -> eyre::Result<ProjectCompileOutput> {
+    suppress_compile_with(project, |prj| Ok(prj.compile()?))
+}
+
+/// Same as [`suppress_compile()`] but compiles the project with the given closure, see
+/// [`ProjectCompiler::compile_with()`]
+pub fn suppress_compile_with<F>(project: &Project, f: F) -> eyre::Result<ProjectCompileOutput>
+where
+    F: FnOnce(&Project) -> eyre::Result<ProjectCompileOutput>,
+{
     if !project.paths.sources.exists() {
         eyre::bail!(
             r#"no contracts to compile, contracts folder "{}" does not exist.
@@ -149,7 +261,7 @@ If you are in a subdirectory in a Git repository, try adding `--root .`"#,
 
     let output = ethers::solc::report::with_scoped(
         &ethers::solc::report::Report::new(NoReporter::default()),
-        || project.compile(),
+        || f(project),
     )?;
 
     if output.has_compiler_errors() {
@@ -169,3 +281,19 @@ pub fn compile_files(project: &Project, files: Vec<PathBuf>) -> eyre::Result<Pro
     println!("{}", output);
     Ok(output)
 }
+
+/// Same as [`compile_files()`] but doesn't print anything to stdout
+pub fn suppress_compile_files(
+    project: &Project,
+    files: Vec<PathBuf>,
+) -> eyre::Result<ProjectCompileOutput> {
+    let output = ethers::solc::report::with_scoped(
+        &ethers::solc::report::Report::new(NoReporter::default()),
+        || project.compile_files(files),
+    )?;
+
+    if output.has_compiler_errors() {
+        eyre::bail!(output.to_string())
+    }
+    Ok(output)
+}
@@ -4,12 +4,20 @@ mod opts;
 mod term;
 mod utils;
 
-use crate::cmd::{forge::watch, Cmd};
+use crate::cmd::{
+    forge::{
+        install::{self, Lockfile},
+        remappings, watch,
+    },
+    Cmd,
+};
 use opts::forge::{Dependency, Opts, Subcommands};
-use std::process::Command;
+use std::{path::Path, process::Command};
 
+use ansi_term::Colour;
 use clap::{IntoApp, Parser};
 use clap_complete::generate;
+use foundry_config::Config;
 
 fn main() -> eyre::Result<()> {
     color_eyre::install()?;
@@ -47,24 +55,42 @@ fn main() -> eyre::Result<()> {
         Subcommands::Create(cmd) => {
             cmd.run()?;
         }
-        Subcommands::Update { lib } => {
-            let mut cmd = Command::new("git");
+        Subcommands::Update { dependency, quiet } => {
+            let root = std::env::current_dir()?;
 
-            cmd.args(&["submodule", "update", "--remote", "--init", "--recursive"]);
+            // a dependency with a ref is checked out at that ref
+            if let Some(dep) = dependency.as_ref().filter(|dep| dep.tag.is_some()) {
+                install::update_to_ref(&root, dep, quiet)?;
+            } else {
+                let mut cmd = Command::new("git");
 
-            // if a lib is specified, open it
-            if let Some(lib) = lib {
-                cmd.args(&["--", lib.display().to_string().as_str()]);
-            }
+                cmd.args(&["submodule", "update", "--remote", "--init", "--recursive"]);
+                if quiet {
+                    cmd.arg("--quiet");
+                }
+
+                // if a lib is specified, open it
+                let lib = dependency.as_ref().map(|dep| Path::new("lib").join(&dep.name));
+                if let Some(ref lib) = lib {
+                    cmd.args(&["--", lib.display().to_string().as_str()]);
+                }
+
+                cmd.spawn()?.wait()?;
 
-            cmd.spawn()?.wait()?;
+                // record the new revisions in the lockfile
+                let mut lockfile = Lockfile::read(&root)?;
+                if !lockfile.is_empty() {
+                    lockfile.update(&root, dependency.as_ref().map(|dep| dep.name.as_str()))?;
+                    lockfile.write(&root)?;
+                }
+            }
         }
         // TODO: Make it work with updates?
         Subcommands::Install(cmd) => {
             cmd.run()?;
         }
-        Subcommands::Remove { dependencies } => {
-            remove(std::env::current_dir()?, dependencies)?;
+        Subcommands::Remove { dependencies, dry_run } => {
+            remove(std::env::current_dir()?, dependencies, dry_run)?;
         }
         Subcommands::Remappings(cmd) => {
             cmd.run()?;
@@ -75,9 +101,30 @@ fn main() -> eyre::Result<()> {
         Subcommands::Completions { shell } => {
             generate(shell, &mut Opts::command(), "forge", &mut std::io::stdout())
         }
-        Subcommands::Clean { root } => {
-            let config = utils::load_config_with_root(root);
-            config.project()?.cleanup()?;
+        Subcommands::Clean { root, all, profile, cache_only } => {
+            let config = if let Some(profile) = profile {
+                let root = match root {
+                    Some(root) => root,
+                    None => utils::find_project_root_path()?,
+                };
+                Config::from_provider(Config::figment_with_root(root).select(profile)).sanitized()
+            } else {
+                utils::load_config_with_root(root)
+            };
+            if cache_only {
+                let cache = config.project_paths().cache;
+                if cache.exists() {
+                    std::fs::remove_file(cache)?;
+                }
+            } else {
+                config.project()?.cleanup()?;
+            }
+            if all && config.cache_path.exists() {
+                std::fs::remove_dir_all(&config.cache_path)?;
+            }
+        }
+        Subcommands::Cache(cmd) => {
+            cmd.run()?;
         }
         Subcommands::Snapshot(cmd) => {
             if cmd.is_watch() {
@@ -86,9 +133,12 @@ fn main() -> eyre::Result<()> {
                 cmd.run()?;
             }
         }
-        // Subcommands::Fmt(cmd) => {
-        //     cmd.run()?;
-        // }
+        Subcommands::Coverage(cmd) => {
+            cmd.run()?;
+        }
+        Subcommands::Fmt(cmd) => {
+            cmd.run()?;
+        }
         Subcommands::Config(cmd) => {
             cmd.run()?;
         }
@@ -106,14 +156,64 @@ fn main() -> eyre::Result<()> {
     Ok(())
 }
 
-fn remove(root: impl AsRef<std::path::Path>, dependencies: Vec<Dependency>) -> eyre::Result<()> {
+fn remove(
+    root: impl AsRef<std::path::Path>,
+    dependencies: Vec<Dependency>,
+    dry_run: bool,
+) -> eyre::Result<()> {
     let libs = std::path::Path::new("lib");
     let git_mod_libs = std::path::Path::new(".git/modules/lib");
 
     dependencies.iter().try_for_each(|dep| -> eyre::Result<_> {
         let path = libs.join(&dep.name);
         let git_mod_path = git_mod_libs.join(&dep.name);
-        println!("Removing {} in {:?}, (url: {}, tag: {:?})", dep.name, path, dep.url, dep.tag);
+        if dry_run {
+            println!("Would remove {} in {:?}", dep.name, path);
+        } else {
+            println!("Removing {} in {:?}, (url: {}, tag: {:?})", dep.name, path, dep.url, dep.tag);
+        }
+
+        // remappings in `remappings.txt` can be rewritten, remappings in the config are left to
+        // the user
+        for remapping in remappings::remove_remappings(root.as_ref(), &path, dry_run)? {
+            if dry_run {
+                println!("Would remove remapping {}", remapping);
+            } else {
+                println!("Removed remapping {}", remapping);
+            }
+        }
+        for remapping in remappings::find_config_remappings(root.as_ref(), &path) {
+            eprintln!(
+                "{} remapping `{}` in {} points into the removed dependency, remove it manually",
+                Colour::Yellow.bold().paint("Warning:"),
+                remapping,
+                Config::FILE_NAME
+            );
+        }
+
+        let mut lockfile = Lockfile::read(&root)?;
+        if dry_run {
+            if lockfile.get(&dep.name).is_some() {
+                println!("Would remove {} from {}", dep.name, install::LOCKFILE);
+            }
+            return Ok(())
+        }
+
+        // vendored dependencies are plain folders that can simply be deleted
+        let abs_path = root.as_ref().join(&path);
+        let is_vendored = lockfile
+            .get(&dep.name)
+            .map(|entry| entry.vendored)
+            .unwrap_or_else(|| abs_path.is_dir() && !abs_path.join(".git").exists());
+        if is_vendored {
+            if abs_path.exists() {
+                std::fs::remove_dir_all(&abs_path)?;
+            }
+            if lockfile.remove(&dep.name).is_some() {
+                lockfile.write(&root)?;
+            }
+            return Ok(())
+        }
 
         // remove submodule entry from .git/config
         Command::new("git")
@@ -136,6 +236,10 @@ fn remove(root: impl AsRef<std::path::Path>, dependencies: Vec<Dependency>) -> e
             .spawn()?
             .wait()?;
 
+        if lockfile.remove(&dep.name).is_some() {
+            lockfile.write(&root)?;
+        }
+
         Ok(())
     })
 }
@@ -1,14 +1,26 @@
 use clap::{Parser, Subcommand, ValueHint};
 
-use ethers::solc::{artifacts::output_selection::ContractOutputSelection, EvmVersion};
-use std::{path::PathBuf, str::FromStr};
+use ethers::{
+    solc::{
+        artifacts::{output_selection::ContractOutputSelection, BytecodeHash, RevertStrings},
+        EvmVersion,
+    },
+    types::Address,
+};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use crate::cmd::forge::{
     bind::BindArgs,
     build::BuildArgs,
+    cache::CacheArgs,
     config,
+    coverage::CoverageArgs,
     create::CreateArgs,
     flatten,
+    fmt::FmtArgs,
     init::InitArgs,
     inspect,
     install::InstallArgs,
@@ -25,9 +37,14 @@ use regex::Regex;
 static GH_REPO_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new("[A-Za-z\\d-]+/[A-Za-z\\d_.-]+").unwrap());
 
+static ALIAS_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?P<alias>[A-Za-z0-9_.-]+)=").unwrap());
+
 static GH_REPO_PREFIX_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"((git@)|(git\+https://)|(https://))?([A-Za-z0-9-]+)\.([A-Za-z0-9-]+)(/|:)")
-        .unwrap()
+    Regex::new(
+        r"^((?P<ssh>(ssh://)?git@)|(git\+https://)|(https://))?(?P<host>[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)+(:\d+)?)(/|:)",
+    )
+    .unwrap()
 });
 
 #[derive(Debug, Parser)]
@@ -65,11 +82,16 @@ pub enum Subcommands {
         long_about = "Update one or multiple dependencies. If no arguments are provided, then all dependencies are updated."
     )]
     Update {
-        #[clap(
-            help = "The path to the dependency you want to update.",
-            value_hint = ValueHint::DirPath
-        )]
-        lib: Option<PathBuf>,
+        /// The dependency you want to update.
+        ///
+        /// The dependency can be provided by its name, its path in `lib` or in the same format
+        /// as for `forge install`. If a ref is provided by adding @ to the dependency, e.g.
+        /// `solmate@v7`, the dependency is checked out at that ref, otherwise the tracked branch
+        /// is updated.
+        #[clap(parse(try_from_str = parse_installed_dependency), value_hint = ValueHint::DirPath)]
+        dependency: Option<Dependency>,
+        #[clap(help = "Do not print any messages except errors.", short, long)]
+        quiet: bool,
     },
 
     /// Install one or multiple dependencies.
@@ -78,24 +100,32 @@ pub enum Subcommands {
     #[clap(alias = "i")]
     Install(InstallArgs),
 
-    #[clap(alias = "rm", about = "Remove one or multiple dependencies.")]
+    /// Remove one or multiple dependencies.
+    ///
+    /// Remappings in `remappings.txt` that point into a removed dependency are removed as well.
+    #[clap(alias = "rm")]
     Remove {
-        #[clap(help = "The path to the dependency you want to remove.")]
+        #[clap(
+            help = "The dependency you want to remove, by name, path or url.",
+            parse(try_from_str = parse_installed_dependency)
+        )]
         dependencies: Vec<Dependency>,
+        #[clap(help = "Only print what would be removed.", long)]
+        dry_run: bool,
     },
 
     #[clap(about = "Get the automatically inferred remappings for the project.")]
     Remappings(RemappingArgs),
 
     #[clap(
-        about = "Verify smart contracts on Etherscan.",
-        long_about = "Verify smart contracts on Etherscan."
+        about = "Verify smart contracts on Etherscan, Sourcify or Blockscout.",
+        long_about = "Verify smart contracts on Etherscan, Sourcify or Blockscout."
     )]
     VerifyContract(VerifyArgs),
 
     #[clap(
-        about = "Check verification status on Etherscan.",
-        long_about = "Check verification status on Etherscan."
+        about = "Check verification status on Etherscan, Sourcify or Blockscout.",
+        long_about = "Check verification status on Etherscan, Sourcify or Blockscout."
     )]
     VerifyCheck(VerifyCheckArgs),
 
@@ -119,18 +149,40 @@ pub enum Subcommands {
             value_hint = ValueHint::DirPath
         )]
         root: Option<PathBuf>,
+        #[clap(help = "Also remove the stored fuzz counterexamples and test failures.", long)]
+        all: bool,
+        /// Only remove the artifacts and cache of this profile.
+        ///
+        /// The artifacts of other profiles with a different `out` directory are kept.
+        #[clap(long, value_name = "PROFILE")]
+        profile: Option<String>,
+        #[clap(help = "Only remove the cache, the artifacts are kept.", long)]
+        cache_only: bool,
     },
 
+    /// Inspect and prune the cached storage of forks in `~/.foundry/cache`.
+    ///
+    /// Unlike `clean`, this manages the global cache that is shared by all projects.
+    Cache(CacheArgs),
+
     #[clap(about = "Create a snapshot of each test's gas usage.")]
     Snapshot(snapshot::SnapshotArgs),
 
+    /// Generate a coverage report of the project's sources.
+    ///
+    /// Prints the line, branch and function coverage of every source file and writes an LCOV
+    /// report.
+    Coverage(CoverageArgs),
+
     #[clap(about = "Display the current config.")]
     Config(config::ConfigArgs),
 
     #[clap(about = "Flatten a source file and all of its imports into one file.")]
     Flatten(flatten::FlattenArgs),
-    // #[clap(about = "formats Solidity source files")]
-    // Fmt(FmtArgs),
+
+    #[clap(about = "Format Solidity source files.")]
+    Fmt(FmtArgs),
+
     #[clap(about = "Get specialized information about a smart contract")]
     Inspect(inspect::InspectArgs),
     #[clap(about = "Display a tree visualization of the project's dependency graph.")]
@@ -155,6 +207,54 @@ pub struct CompilerArgs {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub optimize_runs: Option<usize>,
 
+    #[clap(help = "Use the Yul intermediate representation compilation pipeline.", long)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub via_ir: bool,
+
+    /// How to treat revert and require reason strings.
+    ///
+    /// Valid values: default, strip, debug, verboseDebug
+    #[clap(long, value_name = "REVERT")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_strings: Option<RevertStrings>,
+
+    /// The metadata hash solc appends to the deployed bytecode.
+    ///
+    /// Use `none` for deterministic bytecode across machines.
+    ///
+    /// Valid values: none, ipfs, bzzr1
+    #[clap(long, value_name = "HASH")]
+    #[serde(rename = "bytecode_hash", skip_serializing_if = "Option::is_none")]
+    pub metadata_hash: Option<BytecodeHash>,
+
+    /// Set pre-linked libraries.
+    ///
+    /// Libraries are provided as `<path>:<libname>:<address>`, e.g.
+    /// `src/DssSpell.sol:DssExecLib:0xfD88CeE74f7D78697775aBDAE53f9Da1559728E4`. Multiple
+    /// libraries in one value are separated by commas or whitespace.
+    #[clap(
+        help_heading = "LINKER OPTIONS",
+        long,
+        env = "DAPP_LIBRARIES",
+        parse(try_from_str = parse_libraries)
+    )]
+    #[serde(skip)]
+    pub libraries: Option<Vec<Vec<String>>>,
+
+    /// Additional paths solc is allowed to read source files from.
+    ///
+    /// Relative paths are resolved against the project root.
+    #[clap(long, value_hint = ValueHint::DirPath, value_name = "PATH")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_paths: Option<Vec<PathBuf>>,
+
+    /// Additional base directories to resolve imports from.
+    ///
+    /// Relative paths are resolved against the project root.
+    #[clap(long, value_hint = ValueHint::DirPath, value_name = "PATH")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_paths: Option<Vec<PathBuf>>,
+
     /// Extra output to include in the contract's artifact.
     ///
     /// Example keys: evm.assembly, ewasm, ir, irOptimized, metadata
@@ -172,6 +272,63 @@ pub struct CompilerArgs {
     pub extra_output_files: Option<Vec<ContractOutputSelection>>,
 }
 
+/// Validates the libraries of a `--libraries` value, see [parse_library]
+///
+/// Like in the `DAPP_LIBRARIES` environment variable, a value can contain multiple libraries that
+/// are separated by commas or whitespace, optionally wrapped in `[..]`.
+pub fn parse_libraries(libs: &str) -> eyre::Result<Vec<String>> {
+    libs.trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|lib| !lib.is_empty())
+        .map(parse_library)
+        .collect()
+}
+
+/// Validates a library in the form of `<path>:<libname>:<address>`
+///
+/// The path may contain `:` itself, like the drive of a Windows path.
+pub fn parse_library(lib: &str) -> eyre::Result<String> {
+    let err = || eyre::eyre!("invalid library `{}`, expected `<path>:<libname>:<address>`", lib);
+    let mut items = lib.rsplitn(3, ':');
+    let (address, name, file) = match (items.next(), items.next(), items.next()) {
+        (Some(address), Some(name), Some(file)) => (address, name, file),
+        _ => return Err(err()),
+    };
+    if file.is_empty() || name.is_empty() {
+        return Err(err())
+    }
+    Address::from_str(address)
+        .map_err(|_| eyre::eyre!("invalid address `{}` for library `{}`", address, name))?;
+    Ok(lib.to_string())
+}
+
+/// Validates the solc to use, either a version in the form of `x.y.z` or `solc:x.y.z` or the path
+/// to a solc binary
+pub fn parse_solc(solc: &str) -> eyre::Result<String> {
+    let solc = solc.strip_prefix("solc:").unwrap_or(solc);
+    let path = Path::new(solc);
+    // everything that starts like a version has to be a valid version, unless it's a file
+    if solc.starts_with(|c: char| c.is_ascii_digit()) && !path.exists() {
+        semver::Version::parse(solc)
+            .map_err(|err| eyre::eyre!("invalid solc version `{}`: {}", solc, err))?;
+        return Ok(solc.to_string())
+    }
+
+    if !path.is_file() {
+        eyre::bail!("solc `{}` does not exist, expected a version like `0.8.17` or a path", solc)
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if path.metadata()?.permissions().mode() & 0o111 == 0 {
+            eyre::bail!("solc `{}` is not executable", solc)
+        }
+    }
+    Ok(solc.to_string())
+}
+
 /// Represents the common dapp argument pattern for `<path>:<contractname>` where `<path>:` is
 /// optional.
 #[derive(Clone, Debug)]
@@ -230,7 +387,20 @@ impl FromStr for FullContractInfo {
 /// * commit: 8e8128
 ///
 /// Non Github URLs must be provided with an https:// prefix.
-/// Adding dependencies as local paths is not supported yet.
+///
+/// Github repositories can point to a folder inside of the repository by appending its path, e.g.
+/// `org-name/repo-name/packages/contracts@v1`.
+///
+/// Local dependencies can be provided as a relative or absolute path, optionally prefixed with
+/// `file:`, e.g. `../shared-contracts` or `file:./lib-src/bar`. These are linked into the `lib`
+/// folder instead of being installed as a submodule.
+///
+/// The dependency can be given a different name, which is also its folder in `lib`, by prefixing it
+/// with `<name>=`, e.g. `mylabel=org-name/contracts@v2`.
+///
+/// Release archives can be provided as a `.tar.gz`, `.tgz` or `.zip` url, e.g.
+/// `https://github.com/org/repo/archive/refs/tags/v1.0.0.tar.gz`. These are downloaded and
+/// unpacked into the `lib` folder.
 #[derive(Clone, Debug)]
 pub struct Dependency {
     /// The name of the dependency
@@ -239,41 +409,215 @@ pub struct Dependency {
     pub url: String,
     /// Optional tag corresponding to a Git SHA, tag, or branch.
     pub tag: Option<String>,
+    /// The path to the dependency on the local filesystem, if this is a local dependency
+    pub path: Option<PathBuf>,
+    /// The transport the dependency was provided with
+    pub scheme: Scheme,
+    /// The folder inside of the repository that contains the dependency, for repositories that
+    /// publish multiple packages, e.g. `org/repo/packages/contracts`
+    pub subdir: Option<String>,
+}
+
+/// The transport that's used to fetch a [Dependency]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    /// `https://host/org/repo`, used for shorthands and https urls
+    Https,
+    /// `git@host:org/repo`, used if the dependency was provided as `git@` or `ssh://git@` url
+    Ssh,
+    /// A path on the local filesystem
+    File,
+}
+
+impl Scheme {
+    /// Returns the url to the repository `path` on `host` for this transport
+    ///
+    /// The `host` may include a port, e.g. `git.company.io:8443`.
+    pub fn url(&self, host: &str, path: &str) -> String {
+        match self {
+            Scheme::Https => format!("https://{}/{}", host, path),
+            // the scp-like syntax can't express a port
+            Scheme::Ssh if host.contains(':') => format!("ssh://git@{}/{}", host, path),
+            Scheme::Ssh => format!("git@{}:{}", host, path),
+            Scheme::File => path.to_string(),
+        }
+    }
+}
+
+impl Dependency {
+    /// Returns `true` if this dependency points to a local path rather than a git repository
+    pub fn is_local(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Returns `true` if this dependency is a release archive rather than a git repository
+    pub fn is_archive(&self) -> bool {
+        !self.is_local() && archive_name(&self.url).is_some()
+    }
 }
 
 const GITHUB: &str = "github.com";
 const VERSION_SEPARATOR: char = '@';
+const FILE_PREFIX: &str = "file:";
+const ARCHIVE_EXTENSIONS: [&str; 3] = [".tar.gz", ".tgz", ".zip"];
+
+/// Returns the name of the dependency if it is an http(s) url to a release archive
+///
+/// Archives of a repository, like `https://github.com/<owner>/<repo>/archive/v1.0.0.tar.gz` or
+/// `.../<repo>/releases/download/v1.0.0/<file>`, are named after the repository, any other archive
+/// after its file name without the extension.
+fn archive_name(dependency: &str) -> Option<&str> {
+    let url = dependency.strip_prefix("https://").or_else(|| dependency.strip_prefix("http://"))?;
+    let segments = url.split('/').collect::<Vec<_>>();
+    let file_name = ARCHIVE_EXTENSIONS
+        .iter()
+        .find_map(|ext| segments.last()?.strip_suffix(ext))
+        .filter(|name| !name.is_empty())?;
+
+    // `<host>/<owner>/<repo>/archive/...`, gitlab puts a `-` in front of `archive`
+    let repo = segments
+        .iter()
+        .position(|segment| *segment == "archive" || *segment == "releases")
+        .filter(|idx| *idx >= 3)
+        .map(|idx| if segments[idx - 1] == "-" { segments[idx - 2] } else { segments[idx - 1] });
+    Some(repo.unwrap_or(file_name))
+}
+
+/// Returns the local path if the dependency is a `file:` url or a relative/absolute path
+fn local_dependency_path(dependency: &str) -> Option<PathBuf> {
+    if let Some(path) = dependency.strip_prefix(FILE_PREFIX) {
+        return Some(PathBuf::from(path))
+    }
+    let path = Path::new(dependency);
+    if path.is_absolute() || dependency.starts_with("./") || dependency.starts_with("../") {
+        return Some(path.to_path_buf())
+    }
+    None
+}
 
 impl FromStr for Dependency {
     type Err = eyre::Error;
     fn from_str(dependency: &str) -> Result<Self, Self::Err> {
-        let url_with_version = if let Some(captures) = GH_REPO_PREFIX_REGEX.captures(dependency) {
-            let brand = captures.get(5).unwrap().as_str();
-            let tld = captures.get(6).unwrap().as_str();
-            let project = GH_REPO_PREFIX_REGEX.replace(dependency, "");
-            format!("https://{}.{}/{}", brand, tld, project)
+        // `<alias>=<dependency>` installs the dependency under a different name
+        if let Some(captures) = ALIAS_REGEX.captures(dependency) {
+            let alias = captures.name("alias").unwrap().as_str();
+            let mut dep = Dependency::from_str(&dependency[captures[0].len()..])?;
+            dep.name = alias.to_string();
+            return Ok(dep)
+        }
+
+        if let Some(path) = local_dependency_path(dependency) {
+            let name = path
+                .file_name()
+                .ok_or_else(|| eyre::eyre!("no dependency name found in path `{}`", dependency))?
+                .to_string_lossy()
+                .to_string();
+            return Ok(Dependency {
+                name,
+                url: path.display().to_string(),
+                tag: None,
+                path: Some(path),
+                scheme: Scheme::File,
+                subdir: None,
+            })
+        }
+
+        if let Some(name) = archive_name(dependency) {
+            return Ok(Dependency {
+                name: name.to_string(),
+                url: dependency.to_string(),
+                tag: None,
+                path: None,
+                scheme: Scheme::Https,
+                subdir: None,
+            })
+        }
+
+        let (scheme, host, path_with_version) = if let Some(captures) =
+            GH_REPO_PREFIX_REGEX.captures(dependency)
+        {
+            let host = captures.name("host").unwrap().as_str();
+            let scheme = if captures.name("ssh").is_some() { Scheme::Ssh } else { Scheme::Https };
+            let project = GH_REPO_PREFIX_REGEX.replace(dependency, "").to_string();
+            (scheme, host.to_string(), project)
         } else {
             if !GH_REPO_REGEX.is_match(dependency) {
                 eyre::bail!("invalid github repository name `{}`", dependency);
             }
-            format!("https://{}/{}", GITHUB, dependency)
+            (Scheme::Https, GITHUB.to_string(), dependency.to_string())
+        };
+
+        // everything after the last "@" should be considered the version, the host and any
+        // `git@` user have already been stripped at this point, so the version may contain `/`
+        let (path, tag) = match path_with_version.rsplit_once(VERSION_SEPARATOR) {
+            Some((path, tag)) => (path, Some(tag.to_string())),
+            None => (path_with_version.as_str(), None),
         };
+        if path.is_empty() {
+            eyre::bail!("no dependency path was provided")
+        }
+        if tag.as_deref() == Some("") {
+            eyre::bail!("empty version provided for dependency `{}`", dependency)
+        }
 
-        // everything after the "@" should be considered the version
-        let mut split = url_with_version.split(VERSION_SEPARATOR);
-        let url =
-            split.next().ok_or_else(|| eyre::eyre!("no dependency path was provided"))?.to_string();
-        let name = url
+        // github repositories are always `org/repo`, any remaining segments point to a folder
+        // inside of the repository
+        let (path, subdir) = match path.splitn(3, '/').collect::<Vec<_>>().as_slice() {
+            [org, repo, subdir] if host == GITHUB && !subdir.is_empty() => (
+                &path[..org.len() + repo.len() + 1],
+                Some(subdir.trim_end_matches('/').to_string()),
+            ),
+            _ => (path, None),
+        };
+        // the repository can be nested in multiple groups, e.g. `group/subgroup/repo.git` on gitlab
+        let name = subdir
+            .as_deref()
+            .unwrap_or(path)
             .split('/')
             .last()
+            .map(|name| name.strip_suffix(".git").unwrap_or(name))
+            .filter(|name| !name.is_empty())
             .ok_or_else(|| eyre::eyre!("no dependency name found"))?
             .to_string();
-        let tag = split.next().map(ToString::to_string);
+        let url = scheme.url(&host, path);
 
-        Ok(Dependency { name, url, tag })
+        Ok(Dependency { name, url, tag, path: None, scheme, subdir })
     }
 }
 
+/// Parses a dependency that's already installed in `lib`
+///
+/// In addition to the [Dependency] formats, this accepts the bare name of the dependency or its
+/// path, optionally followed by a ref, e.g. `solmate@v7` or `lib/solmate`.
+pub fn parse_installed_dependency(dependency: &str) -> eyre::Result<Dependency> {
+    let dependency = dependency.trim_end_matches('/');
+    if let Ok(dep) = Dependency::from_str(dependency) {
+        return Ok(dep)
+    }
+
+    let (name, tag) = match dependency.rsplit_once(VERSION_SEPARATOR) {
+        Some((name, tag)) => (name, Some(tag.to_string())),
+        None => (dependency, None),
+    };
+    if tag.as_deref() == Some("") {
+        eyre::bail!("empty version provided for dependency `{}`", dependency)
+    }
+    let name = Path::new(name)
+        .file_name()
+        .ok_or_else(|| eyre::eyre!("no dependency name found in `{}`", dependency))?
+        .to_string_lossy()
+        .to_string();
+
+    Ok(Dependency {
+        name,
+        url: String::new(),
+        tag,
+        path: None,
+        scheme: Scheme::Https,
+        subdir: None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -289,14 +633,23 @@ mod tests {
                 "https://github.com/gakonst/lootloose",
                 None,
             ),
-            (
-                "git@github.com:gakonst/lootloose@v1",
-                "https://github.com/gakonst/lootloose",
-                Some("v1"),
-            ),
-            ("git@github.com:gakonst/lootloose", "https://github.com/gakonst/lootloose", None),
             ("https://gitlab.com/gakonst/lootloose", "https://gitlab.com/gakonst/lootloose", None),
             ("https://github.xyz/gakonst/lootloose", "https://github.xyz/gakonst/lootloose", None),
+            (
+                "https://gitlab.com/mygroup/subgroup/lootloose",
+                "https://gitlab.com/mygroup/subgroup/lootloose",
+                None,
+            ),
+            (
+                "https://gitlab.com/mygroup/subgroup/lootloose.git@v1.2.0",
+                "https://gitlab.com/mygroup/subgroup/lootloose.git",
+                Some("v1.2.0"),
+            ),
+            (
+                "https://github.com/gakonst/lootloose.git",
+                "https://github.com/gakonst/lootloose.git",
+                None,
+            ),
             ("gakonst/lootloose@0.1.0", "https://github.com/gakonst/lootloose", Some("0.1.0")),
             ("gakonst/lootloose@develop", "https://github.com/gakonst/lootloose", Some("develop")),
             (
@@ -311,15 +664,312 @@ mod tests {
             assert_eq!(dep.url, expected_path.to_string());
             assert_eq!(dep.tag, expected_tag.map(ToString::to_string));
             assert_eq!(dep.name, "lootloose");
+            assert!(!dep.is_local());
+            assert_eq!(dep.scheme, Scheme::Https);
+        });
+    }
+
+    #[test]
+    fn parses_dependency_hosts() {
+        [
+            (
+                "https://bitbucket.org/gakonst/lootloose",
+                "https://bitbucket.org/gakonst/lootloose",
+                None,
+                Scheme::Https,
+            ),
+            (
+                "git@bitbucket.org:gakonst/lootloose@v1",
+                "git@bitbucket.org:gakonst/lootloose",
+                Some("v1"),
+                Scheme::Ssh,
+            ),
+            (
+                "git.internal.company.io/gakonst/lootloose",
+                "https://git.internal.company.io/gakonst/lootloose",
+                None,
+                Scheme::Https,
+            ),
+            (
+                "https://git.internal.company.io:8443/gakonst/lootloose@v2",
+                "https://git.internal.company.io:8443/gakonst/lootloose",
+                Some("v2"),
+                Scheme::Https,
+            ),
+            (
+                "git@git.internal.company.io:gakonst/lootloose",
+                "git@git.internal.company.io:gakonst/lootloose",
+                None,
+                Scheme::Ssh,
+            ),
+            (
+                "ssh://git@git.internal.company.io:2222/gakonst/lootloose@release/1.2",
+                "ssh://git@git.internal.company.io:2222/gakonst/lootloose",
+                Some("release/1.2"),
+                Scheme::Ssh,
+            ),
+        ]
+        .iter()
+        .for_each(|(input, expected_url, expected_tag, expected_scheme)| {
+            let dep = Dependency::from_str(input).unwrap();
+            assert_eq!(dep.url, expected_url.to_string());
+            assert_eq!(dep.tag, expected_tag.map(ToString::to_string));
+            assert_eq!(dep.scheme, *expected_scheme);
+            assert_eq!(dep.name, "lootloose");
+            assert_eq!(dep.subdir, None);
+
+            // the emitted url parses back to the same dependency
+            let round_trip = Dependency::from_str(&dep.url).unwrap();
+            assert_eq!(round_trip.url, dep.url);
+        });
+    }
+
+    #[test]
+    fn parses_dependency_aliases() {
+        [
+            ("mylabel=gakonst/lootloose@v2", "https://github.com/gakonst/lootloose", Some("v2")),
+            ("mylabel=gakonst/lootloose", "https://github.com/gakonst/lootloose", None),
+            (
+                "mylabel=git@github.com:gakonst/lootloose@feature/x",
+                "git@github.com:gakonst/lootloose",
+                Some("feature/x"),
+            ),
+            (
+                "mylabel=https://gitlab.com/mygroup/subgroup/lootloose@v1",
+                "https://gitlab.com/mygroup/subgroup/lootloose",
+                Some("v1"),
+            ),
+        ]
+        .iter()
+        .for_each(|(input, expected_url, expected_tag)| {
+            let dep = Dependency::from_str(input).unwrap();
+            assert_eq!(dep.name, "mylabel");
+            assert_eq!(dep.url, expected_url.to_string());
+            assert_eq!(dep.tag, expected_tag.map(ToString::to_string));
+        });
+
+        let dep = Dependency::from_str("shared=../lootloose").unwrap();
+        assert_eq!(dep.name, "shared");
+        assert_eq!(dep.path, Some(PathBuf::from("../lootloose")));
+
+        assert!(Dependency::from_str("mylabel=").is_err());
+    }
+
+    #[test]
+    fn parses_archive_dependencies() {
+        [
+            ("https://github.com/org/lib/archive/refs/tags/v1.0.0.tar.gz", "lib"),
+            ("https://github.com/org/lib/archive/v1.0.0.zip", "lib"),
+            ("https://github.com/org/lib/releases/download/v1.0.0/lib-v1.0.0.tar.gz", "lib"),
+            ("https://gitlab.com/org/lib/-/archive/v1.0.0/lib-v1.0.0.tar.gz", "lib"),
+            ("https://example.com/releases/lootloose-1.2.tgz", "lootloose-1.2"),
+            ("http://example.com/lootloose.zip", "lootloose"),
+        ]
+        .iter()
+        .for_each(|(input, expected_name)| {
+            let dep = Dependency::from_str(input).unwrap();
+            assert!(dep.is_archive());
+            assert_eq!(dep.url, input.to_string());
+            assert_eq!(dep.name, expected_name.to_string());
+            assert_eq!(dep.tag, None);
+            assert_eq!(dep.subdir, None);
+        });
+
+        assert!(!Dependency::from_str("gakonst/lootloose").unwrap().is_archive());
+        assert!(!Dependency::from_str("../lootloose.zip").unwrap().is_archive());
+    }
+
+    #[test]
+    fn parses_dependency_refs_with_slashes() {
+        [
+            ("gakonst/lootloose@feature/x", "https://github.com/gakonst/lootloose", "feature/x"),
+            (
+                "gakonst/lootloose@release/1.2",
+                "https://github.com/gakonst/lootloose",
+                "release/1.2",
+            ),
+            (
+                "https://github.com/gakonst/lootloose@release/v1.2/rc",
+                "https://github.com/gakonst/lootloose",
+                "release/v1.2/rc",
+            ),
+            (
+                "git@github.com:gakonst/lootloose@feature/x",
+                "git@github.com:gakonst/lootloose",
+                "feature/x",
+            ),
+            ("gakonst/lootloose@v1.2.0", "https://github.com/gakonst/lootloose", "v1.2.0"),
+            (
+                "gakonst/lootloose@98369d0edc900c71d0ec33a01dfba1d92111deed",
+                "https://github.com/gakonst/lootloose",
+                "98369d0edc900c71d0ec33a01dfba1d92111deed",
+            ),
+        ]
+        .iter()
+        .for_each(|(input, expected_url, expected_tag)| {
+            let dep = Dependency::from_str(input).unwrap();
+            assert_eq!(dep.url, expected_url.to_string());
+            assert_eq!(dep.tag, Some(expected_tag.to_string()));
+            assert_eq!(dep.name, "lootloose");
         });
     }
 
+    #[test]
+    fn parses_dependency_subdirs() {
+        [
+            (
+                "gakonst/lootloose/packages/contracts@v1",
+                "https://github.com/gakonst/lootloose",
+                Some("v1"),
+                Some("packages/contracts"),
+                "contracts",
+            ),
+            (
+                "https://github.com/gakonst/lootloose/packages/contracts",
+                "https://github.com/gakonst/lootloose",
+                None,
+                Some("packages/contracts"),
+                "contracts",
+            ),
+            (
+                "git@github.com:gakonst/lootloose/contracts@release/1.2",
+                "git@github.com:gakonst/lootloose",
+                Some("release/1.2"),
+                Some("contracts"),
+                "contracts",
+            ),
+            (
+                "gakonst/lootloose@v1",
+                "https://github.com/gakonst/lootloose",
+                Some("v1"),
+                None,
+                "lootloose",
+            ),
+        ]
+        .iter()
+        .for_each(|(input, expected_url, expected_tag, expected_subdir, expected_name)| {
+            let dep = Dependency::from_str(input).unwrap();
+            assert_eq!(dep.url, expected_url.to_string());
+            assert_eq!(dep.tag, expected_tag.map(ToString::to_string));
+            assert_eq!(dep.subdir, expected_subdir.map(ToString::to_string));
+            assert_eq!(dep.name, expected_name.to_string());
+        });
+    }
+
+    #[test]
+    fn preserves_ssh_transport() {
+        [
+            ("git@github.com:gakonst/lootloose", "git@github.com:gakonst/lootloose", None),
+            ("git@github.com:gakonst/lootloose@v1", "git@github.com:gakonst/lootloose", Some("v1")),
+            ("ssh://git@github.com/gakonst/lootloose", "git@github.com:gakonst/lootloose", None),
+            ("git@gitlab.com:gakonst/lootloose", "git@gitlab.com:gakonst/lootloose", None),
+            (
+                "git@gitlab.com:mygroup/subgroup/lootloose@v1.2.0",
+                "git@gitlab.com:mygroup/subgroup/lootloose",
+                Some("v1.2.0"),
+            ),
+            (
+                "git@gitlab.com:mygroup/subgroup/lootloose.git",
+                "git@gitlab.com:mygroup/subgroup/lootloose.git",
+                None,
+            ),
+        ]
+        .iter()
+        .for_each(|(input, expected_url, expected_tag)| {
+            let dep = Dependency::from_str(input).unwrap();
+            assert_eq!(dep.scheme, Scheme::Ssh);
+            assert_eq!(dep.url, expected_url.to_string());
+            assert_eq!(dep.tag, expected_tag.map(ToString::to_string));
+            assert_eq!(dep.name, "lootloose");
+
+            // the emitted url parses back to the same dependency
+            let round_trip = Dependency::from_str(&dep.url).unwrap();
+            assert_eq!(round_trip.scheme, Scheme::Ssh);
+            assert_eq!(round_trip.url, dep.url);
+        });
+    }
+
+    #[test]
+    fn parses_local_dependencies() {
+        [
+            ("../foo", "../foo", "foo"),
+            ("file:./bar", "./bar", "bar"),
+            ("file:../shared-contracts/", "../shared-contracts/", "shared-contracts"),
+            ("/abs/path/baz", "/abs/path/baz", "baz"),
+        ]
+        .iter()
+        .for_each(|(input, expected_path, expected_name)| {
+            let dep = Dependency::from_str(input).unwrap();
+            assert!(dep.is_local());
+            assert_eq!(dep.path, Some(PathBuf::from(expected_path)));
+            assert_eq!(dep.tag, None);
+            assert_eq!(dep.name, expected_name.to_string());
+        });
+    }
+
+    #[test]
+    fn parses_installed_dependencies() {
+        [
+            ("solmate", "solmate", None),
+            ("solmate@v7", "solmate", Some("v7")),
+            ("lib/solmate", "solmate", None),
+            ("lib/solmate/", "solmate", None),
+            ("solmate@feature/x", "solmate", Some("feature/x")),
+            (
+                "OpenZeppelin/openzeppelin-contracts@v4.8.0",
+                "openzeppelin-contracts",
+                Some("v4.8.0"),
+            ),
+        ]
+        .iter()
+        .for_each(|(input, expected_name, expected_tag)| {
+            let dep = parse_installed_dependency(input).unwrap();
+            assert_eq!(dep.name, expected_name.to_string());
+            assert_eq!(dep.tag, expected_tag.map(ToString::to_string));
+        });
+
+        assert!(parse_installed_dependency("solmate@").is_err());
+    }
+
     #[test]
     #[should_panic]
     fn test_invalid_github_repo_dependency() {
         Dependency::from_str("solmate").unwrap();
     }
 
+    #[test]
+    fn parses_libraries() {
+        let lib = "src/DssSpell.sol:DssExecLib:0xfD88CeE74f7D78697775aBDAE53f9Da1559728E4";
+        assert_eq!(parse_library(lib).unwrap(), lib);
+
+        let lib =
+            r"C:\project\src\DssSpell.sol:DssExecLib:0xfD88CeE74f7D78697775aBDAE53f9Da1559728E4";
+        assert_eq!(parse_library(lib).unwrap(), lib);
+
+        [
+            "src/DssSpell.sol:DssExecLib",
+            "src/DssSpell.sol:DssExecLib:0xfD88CeE74f7D78697775aBDAE53f9Da1559728E4:extra",
+            ":DssExecLib:0xfD88CeE74f7D78697775aBDAE53f9Da1559728E4",
+            "src/DssSpell.sol::0xfD88CeE74f7D78697775aBDAE53f9Da1559728E4",
+            "src/DssSpell.sol:DssExecLib:0x1234",
+            "src/DssSpell.sol:DssExecLib:not-an-address",
+        ]
+        .iter()
+        .for_each(|input| assert!(parse_library(input).is_err(), "{}", input));
+
+        // `DAPP_LIBRARIES` values can contain multiple libraries
+        let other = "src/Other.sol:Other:0x8De6DDbCd5053d32292AAA0D2105A32d108484a6";
+        for libs in [
+            format!("{},{}", lib, other),
+            format!("{} {}", lib, other),
+            format!("[{}, {}]", lib, other),
+            format!("{}\n{}\n", lib, other),
+        ] {
+            assert_eq!(parse_libraries(&libs).unwrap(), vec![lib, other], "{}", libs);
+        }
+        assert!(parse_libraries(&format!("{},src/A.sol:A", lib)).is_err());
+    }
+
     #[test]
     fn parses_contract_info() {
         [
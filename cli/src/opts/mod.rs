@@ -1,7 +1,11 @@
 pub mod cast;
 pub mod forge;
 
-use std::{convert::TryFrom, str::FromStr};
+use std::{
+    convert::TryFrom,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use clap::Parser;
 use ethers::{
@@ -25,6 +29,8 @@ use foundry_config::{
 
 use serde::Serialize;
 
+use crate::utils::{read_secret, read_signer, PasswordSource, SecretKind};
+
 const FLASHBOTS_URL: &str = "https://rpc.flashbots.net";
 
 // Helper for exposing enum values for `Chain`
@@ -106,17 +112,21 @@ impl EthereumOpts {
         provider: Provider<Http>,
     ) -> eyre::Result<Option<WalletType>> {
         if self.wallet.ledger {
-            let derivation = match &self.wallet.hd_path {
+            let derivation = match &self.wallet.local.hd_path {
                 Some(hd_path) => LedgerHDPath::Other(hd_path.clone()),
-                None => LedgerHDPath::LedgerLive(self.wallet.mnemonic_index as usize),
+                None => LedgerHDPath::LedgerLive(
+                    self.wallet.local.mnemonic_index.unwrap_or_default() as usize,
+                ),
             };
             let ledger = Ledger::new(derivation, chain_id.as_u64()).await?;
 
             Ok(Some(WalletType::Ledger(SignerMiddleware::new(provider, ledger))))
         } else if self.wallet.trezor {
-            let derivation = match &self.wallet.hd_path {
+            let derivation = match &self.wallet.local.hd_path {
                 Some(hd_path) => TrezorHDPath::Other(hd_path.clone()),
-                None => TrezorHDPath::TrezorLive(self.wallet.mnemonic_index as usize),
+                None => TrezorHDPath::TrezorLive(
+                    self.wallet.local.mnemonic_index.unwrap_or_default() as usize,
+                ),
             };
 
             // cached to ~/.ethers-rs/trezor/cache/trezor.session
@@ -124,15 +134,7 @@ impl EthereumOpts {
 
             Ok(Some(WalletType::Trezor(SignerMiddleware::new(provider, trezor))))
         } else {
-            let local = self
-                .wallet
-                .private_key()
-                .transpose()
-                .or_else(|| self.wallet.interactive().transpose())
-                .or_else(|| self.wallet.mnemonic().transpose())
-                .or_else(|| self.wallet.keystore().transpose())
-                .transpose()?
-                .ok_or_else(|| eyre::eyre!("error accessing local wallet, did you set a private key, mnemonic or keystore? Run `cast send --help` or `forge create --help` and use the corresponding CLI flag to set your key via --private-key, --mnemonic-path, --interactive, --trezor or --ledger. Alternatively, if you're using a local node with unlocked accounts, set the `ETH_FROM` environment variable to the address of the account you want to use"))?;
+            let local = self.wallet.local.signer()?.ok_or_else(|| eyre::eyre!("error accessing local wallet, did you set a private key, mnemonic or keystore? Run `cast send --help` or `forge create --help` and use the corresponding CLI flag to set your key via --private-key, --mnemonic, --interactive, --trezor or --ledger. Alternatively, if you're using a local node with unlocked accounts, set the `ETH_FROM` environment variable to the address of the account you want to use"))?;
 
             let local = local.with_chain_id(chain_id.as_u64());
 
@@ -179,13 +181,47 @@ pub enum WalletType {
 The wallet options can either be:
 1. Ledger
 2. Trezor
-3. Mnemonic (via file path)
+3. Mnemonic (via file path, cleartext in CLI or interactively via secure prompt)
 4. Keystore (via file path)
 5. Private Key (cleartext in CLI)
 6. Private Key (interactively via secure prompt)
 "#
 )]
 pub struct Wallet {
+    #[clap(flatten)]
+    #[serde(flatten)]
+    pub local: LocalWalletOpts,
+
+    #[clap(
+        short,
+        long = "ledger",
+        help_heading = "WALLET OPTIONS - HARDWARE WALLET",
+        help = "Use a Ledger hardware wallet."
+    )]
+    pub ledger: bool,
+
+    #[clap(
+        short,
+        long = "trezor",
+        help_heading = "WALLET OPTIONS - HARDWARE WALLET",
+        help = "Use a Trezor hardware wallet."
+    )]
+    pub trezor: bool,
+
+    #[clap(
+        env = "ETH_FROM",
+        short,
+        long = "from",
+        help_heading = "WALLET OPTIONS - REMOTE",
+        help = "The sender account."
+    )]
+    pub from: Option<Address>,
+}
+
+/// The options of the wallets whose key is available locally: a private key, a mnemonic or a
+/// keystore
+#[derive(Parser, Debug, Clone, Serialize)]
+pub struct LocalWalletOpts {
     #[clap(
         long,
         short,
@@ -202,19 +238,28 @@ pub struct Wallet {
     pub private_key: Option<String>,
 
     #[clap(
-        long = "mnemonic-path",
+        long = "mnemonic",
+        alias = "mnemonic-path",
         help_heading = "WALLET OPTIONS - RAW",
-        help = "Use the mnemonic file at the specified path."
+        help = "Use the mnemonic phrase or the mnemonic file at the specified path. Pass `-` to enter the mnemonic in an interactive prompt.",
+        value_name = "FILE_OR_PHRASE"
     )]
-    pub mnemonic_path: Option<String>,
+    pub mnemonic: Option<String>,
 
     #[clap(
         long = "mnemonic-index",
         help_heading = "WALLET OPTIONS - RAW",
-        help = "Use the private key from the given mnemonic index. Used with --mnemonic-path.",
-        default_value = "0"
+        help = "Use the private key from the given mnemonic index, 0 by default. Used with --mnemonic or hardware wallets.",
+        conflicts_with = "hd-path"
     )]
-    pub mnemonic_index: u32,
+    pub mnemonic_index: Option<u32>,
+
+    #[clap(
+        long = "hd-path",
+        help_heading = "WALLET OPTIONS - RAW",
+        help = "The derivation path of the key, e.g. `m/44'/60'/0'/0/1`. Used with --mnemonic or hardware wallets."
+    )]
+    pub hd_path: Option<String>,
 
     #[clap(
         env = "ETH_KEYSTORE",
@@ -225,51 +270,37 @@ pub struct Wallet {
     pub keystore_path: Option<String>,
 
     #[clap(
+        env = "ETH_PASSWORD",
         long = "password",
         help_heading = "WALLET OPTIONS - KEYSTORE",
-        help = "The keystore password. Used with --keystore.",
-        requires = "keystore-path"
+        help = "The keystore password. Used with --keystore."
     )]
     pub keystore_password: Option<String>,
 
     #[clap(
-        short,
-        long = "ledger",
-        help_heading = "WALLET OPTIONS - HARDWARE WALLET",
-        help = "Use a Ledger hardware wallet."
-    )]
-    pub ledger: bool,
-
-    #[clap(
-        short,
-        long = "trezor",
-        help_heading = "WALLET OPTIONS - HARDWARE WALLET",
-        help = "Use a Trezor hardware wallet."
-    )]
-    pub trezor: bool,
-
-    #[clap(
-        long = "hd-path",
-        help_heading = "WALLET OPTIONS - HARDWARE WALLET",
-        help = "The derivation path to use with hardware wallets."
-    )]
-    pub hd_path: Option<String>,
-
-    #[clap(
-        env = "ETH_FROM",
-        short,
-        long = "from",
-        help_heading = "WALLET OPTIONS - REMOTE",
-        help = "The sender account."
+        long = "password-file",
+        help_heading = "WALLET OPTIONS - KEYSTORE",
+        help = "The file to read the keystore password from, takes precedence over --password. Used with --keystore.",
+        requires = "keystore-path",
+        value_hint = clap::ValueHint::FilePath
     )]
-    pub from: Option<Address>,
+    pub keystore_password_file: Option<PathBuf>,
 }
 
-impl Wallet {
+impl LocalWalletOpts {
+    /// Returns the wallet of the private key, mnemonic or keystore, if any was set
+    pub fn signer(&self) -> Result<Option<LocalWallet>> {
+        self.private_key()
+            .transpose()
+            .or_else(|| self.interactive().transpose())
+            .or_else(|| self.mnemonic().transpose())
+            .or_else(|| self.keystore().transpose())
+            .transpose()
+    }
+
     fn interactive(&self) -> Result<Option<LocalWallet>> {
         Ok(if self.interactive {
-            println!("Insert private key:");
-            let private_key = rpassword::read_password()?;
+            let private_key = read_secret(true, None, SecretKind::Secret("private key"))?;
             let private_key = private_key.strip_prefix("0x").unwrap_or(&private_key);
             Some(LocalWallet::from_str(private_key)?)
         } else {
@@ -290,49 +321,67 @@ impl Wallet {
     }
 
     fn keystore(&self) -> Result<Option<LocalWallet>> {
-        Ok(match (&self.keystore_path, &self.keystore_password) {
-            (Some(path), Some(password)) => Some(LocalWallet::decrypt_keystore(path, password)?),
-            (Some(path), None) => {
-                println!("Insert keystore password:");
-                let password = rpassword::read_password().unwrap();
-                Some(LocalWallet::decrypt_keystore(path, password)?)
-            }
-            (None, _) => None,
-        })
+        let path = match self.keystore_path {
+            Some(ref path) => path,
+            None => return Ok(None),
+        };
+        // `--password` can also be set via `ETH_PASSWORD`, an explicit password file wins
+        let password = match (&self.keystore_password_file, &self.keystore_password) {
+            (Some(file), _) => PasswordSource::File(file.clone()),
+            (None, Some(password)) => PasswordSource::Value(password.clone()),
+            (None, None) => PasswordSource::Stdin,
+        };
+        Ok(Some(read_signer(path, &password)?))
     }
 
     fn mnemonic(&self) -> Result<Option<LocalWallet>> {
-        Ok(if let Some(ref path) = self.mnemonic_path {
-            let mnemonic = std::fs::read_to_string(path)?.replace('\n', "");
-            Some(
-                MnemonicBuilder::<English>::default()
-                    .phrase(mnemonic.as_str())
-                    .index(self.mnemonic_index)?
-                    .build()?,
-            )
+        Ok(if let Some(ref mnemonic) = self.mnemonic {
+            let phrase = read_mnemonic(mnemonic)?;
+            let builder = MnemonicBuilder::<English>::default().phrase(phrase.as_str());
+            let builder = match self.hd_path {
+                Some(ref hd_path) => builder.derivation_path(hd_path)?,
+                None => builder.index(self.mnemonic_index.unwrap_or_default())?,
+            };
+            Some(builder.build()?)
         } else {
             None
         })
     }
 }
 
+/// Returns the phrase of `--mnemonic`, which is either `-` to prompt for the phrase, the path of
+/// a file containing the phrase or the phrase itself
+fn read_mnemonic(mnemonic: &str) -> Result<String> {
+    if mnemonic == "-" {
+        return read_secret(true, None, SecretKind::Mnemonic)
+    }
+    let phrase = if Path::new(mnemonic).is_file() {
+        std::fs::read_to_string(mnemonic)
+            .map_err(|err| eyre!("Failed to read mnemonic file {}: {}", mnemonic, err))?
+    } else if mnemonic.split_whitespace().nth(1).is_some() {
+        mnemonic.to_string()
+    } else {
+        // a single word is no phrase, most likely the path is wrong
+        eyre::bail!("Mnemonic file {} does not exist", mnemonic)
+    };
+    Ok(phrase.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn illformed_private_key_generates_user_friendly_error() {
-        let wallet = Wallet {
-            from: None,
+        let wallet = LocalWalletOpts {
             interactive: false,
             private_key: Some("123".to_string()),
             keystore_path: None,
             keystore_password: None,
-            mnemonic_path: None,
-            ledger: false,
-            trezor: false,
+            keystore_password_file: None,
+            mnemonic: None,
+            mnemonic_index: None,
             hd_path: None,
-            mnemonic_index: 0,
         };
         match wallet.private_key() {
             Ok(_) => {
@@ -346,4 +395,68 @@ mod tests {
             }
         }
     }
+
+    const MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn can_derive_mnemonic_wallets() {
+        let signer = |args: &[&str]| {
+            let opts = LocalWalletOpts::try_parse_from([&["forge"][..], args].concat()).unwrap();
+            opts.signer().unwrap().unwrap().address()
+        };
+
+        let first: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+        let second: Address = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap();
+        assert_eq!(signer(&["--mnemonic", MNEMONIC]), first);
+        assert_eq!(signer(&["--mnemonic", MNEMONIC, "--mnemonic-index", "1"]), second);
+        assert_eq!(signer(&["--mnemonic", MNEMONIC, "--hd-path", "m/44'/60'/0'/0/1"]), second);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mnemonic.txt");
+        std::fs::write(&path, format!("{}\n", MNEMONIC)).unwrap();
+        assert_eq!(signer(&["--mnemonic-path", path.to_str().unwrap()]), first);
+    }
+
+    #[test]
+    fn mnemonic_index_conflicts_with_hd_path() {
+        let args = [
+            "forge",
+            "--mnemonic",
+            MNEMONIC,
+            "--mnemonic-index",
+            "1",
+            "--hd-path",
+            "m/44'/60'/0'/0/1",
+        ];
+        assert!(LocalWalletOpts::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn fails_on_missing_mnemonic_file() {
+        let err = read_mnemonic("does-not-exist.txt").unwrap_err().to_string();
+        assert!(err.contains("Mnemonic file does-not-exist.txt does not exist"), "{}", err);
+    }
+
+    #[test]
+    fn decrypts_keystore_with_password() {
+        let keystore = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../testdata/fixtures/Keystore/test-keystore.json");
+        let keystore = keystore.to_str().unwrap();
+        let signer = |password: &str| {
+            LocalWalletOpts::try_parse_from([
+                "forge",
+                "--keystore",
+                keystore,
+                "--password",
+                password,
+            ])
+            .unwrap()
+            .signer()
+        };
+
+        let address: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+        assert_eq!(signer("foundry").unwrap().unwrap().address(), address);
+        let err = signer("hunter2").unwrap_err().to_string();
+        assert!(err.contains("wrong password for keystore"), "{}", err);
+    }
 }
@@ -1,14 +1,26 @@
 use ethers::{
     abi::token::{LenientTokenizer, Tokenizer},
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, WalletError},
     solc::EvmVersion,
-    types::U256,
+    types::{Block, H256, U256},
 };
-use forge::executor::{opts::EvmOpts, Fork, SpecId};
+use eyre::WrapErr;
+use forge::executor::{
+    fork::{resolve_endpoint, MultiForkConfig},
+    opts::EvmOpts,
+    Fork, SpecId,
+};
+use foundry_common::evm::{EvmArgs, ForkBlock};
 use foundry_config::{caching::StorageCachingConfig, Config};
+use foundry_utils::RuntimeOrHandle;
+use once_cell::sync::Lazy;
 use std::{
+    collections::HashMap,
     future::Future,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::Mutex,
     time::Duration,
 };
 use tracing_error::ErrorLayer;
@@ -30,15 +42,27 @@ pub(crate) const VERSION_MESSAGE: &str = concat!(
 );
 
 /// Useful extensions to [`std::path::Path`].
+///
+/// All extension checks are case-sensitive, `Contract.SOL` is not a solidity file, consistent with
+/// how sources are discovered when compiling the project.
 pub trait FoundryPathExt {
     /// Returns true if the [`Path`] ends with `.t.sol`
     fn is_sol_test(&self) -> bool;
 
+    /// Returns true if the [`Path`] ends with `.s.sol`
+    fn is_sol_script(&self) -> bool;
+
     /// Returns true if the  [`Path`] has a `sol` extension
     fn is_sol(&self) -> bool;
 
     /// Returns true if the  [`Path`] has a `yul` extension
     fn is_yul(&self) -> bool;
+
+    /// Returns true if the  [`Path`] has a `vy` extension
+    fn is_vyper(&self) -> bool;
+
+    /// Returns true if the  [`Path`] has a `json` extension
+    fn is_json(&self) -> bool;
 }
 
 impl<T: AsRef<Path>> FoundryPathExt for T {
@@ -50,6 +74,14 @@ impl<T: AsRef<Path>> FoundryPathExt for T {
             .unwrap_or_default()
     }
 
+    fn is_sol_script(&self) -> bool {
+        self.as_ref()
+            .file_name()
+            .and_then(|s| s.to_str())
+            .map(|s| s.ends_with(".s.sol"))
+            .unwrap_or_default()
+    }
+
     fn is_sol(&self) -> bool {
         self.as_ref().extension() == Some(std::ffi::OsStr::new("sol"))
     }
@@ -57,6 +89,14 @@ impl<T: AsRef<Path>> FoundryPathExt for T {
     fn is_yul(&self) -> bool {
         self.as_ref().extension() == Some(std::ffi::OsStr::new("yul"))
     }
+
+    fn is_vyper(&self) -> bool {
+        self.as_ref().extension() == Some(std::ffi::OsStr::new("vy"))
+    }
+
+    fn is_json(&self) -> bool {
+        self.as_ref().extension() == Some(std::ffi::OsStr::new("json"))
+    }
 }
 
 /// Initializes a tracing Subscriber for logging
@@ -69,28 +109,97 @@ pub fn subscriber() {
         .init()
 }
 
-pub fn evm_spec(evm: &EvmVersion) -> SpecId {
-    match evm {
+/// Returns the [SpecId] of the EVM that corresponds to the given solc [EvmVersion]
+pub fn evm_spec(evm: &EvmVersion) -> eyre::Result<SpecId> {
+    Ok(match evm {
+        EvmVersion::Homestead => SpecId::HOMESTEAD,
+        EvmVersion::TangerineWhistle => SpecId::TANGERINE,
+        EvmVersion::SpuriousDragon => SpecId::SPURIOUS_DRAGON,
+        EvmVersion::Byzantium => SpecId::BYZANTINE,
+        EvmVersion::Constantinople => SpecId::CONSTANTINOPLE,
+        EvmVersion::Petersburg => SpecId::PETERSBURG,
         EvmVersion::Istanbul => SpecId::ISTANBUL,
         EvmVersion::Berlin => SpecId::BERLIN,
         EvmVersion::London => SpecId::LONDON,
-        _ => panic!("Unsupported EVM version"),
-    }
+        #[allow(unreachable_patterns)]
+        _ => eyre::bail!("Unsupported EVM version: {}", evm),
+    })
+}
+
+/// What [read_secret] prompts for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecretKind {
+    /// A password or any other secret, read as is. Holds what to prompt for, e.g. `"private key"`
+    Secret(&'static str),
+    /// A mnemonic phrase, its words are separated by single spaces
+    Mnemonic,
 }
 
 /// Securely reads a secret from stdin, or proceeds to return a fallback value
 /// which was provided in cleartext via CLI or env var
-#[allow(dead_code)]
-pub fn read_secret(secret: bool, unsafe_secret: Option<String>) -> eyre::Result<String> {
+pub fn read_secret(
+    secret: bool,
+    unsafe_secret: Option<String>,
+    kind: SecretKind,
+) -> eyre::Result<String> {
     Ok(if secret {
-        println!("Insert secret:");
-        rpassword::read_password()?
+        match kind {
+            SecretKind::Secret(name) => {
+                println!("Insert {}:", name);
+                rpassword::read_password()?
+            }
+            SecretKind::Mnemonic => {
+                println!("Insert mnemonic:");
+                rpassword::read_password()?.split_whitespace().collect::<Vec<_>>().join(" ")
+            }
+        }
     } else {
         // guaranteed to be Some(..)
         unsafe_secret.unwrap()
     })
 }
 
+/// Where to read the password of a keystore from
+#[derive(Debug, Clone, PartialEq)]
+pub enum PasswordSource {
+    /// Prompt for the password on stdin
+    Stdin,
+    /// Read the password from a file, a trailing newline is ignored
+    File(PathBuf),
+    /// The password itself, e.g. passed with `--password`
+    Value(String),
+}
+
+impl PasswordSource {
+    /// Reads the password from the source
+    pub fn read(&self) -> eyre::Result<String> {
+        Ok(match self {
+            PasswordSource::Stdin => {
+                read_secret(true, None, SecretKind::Secret("keystore password"))?
+            }
+            PasswordSource::File(path) => std::fs::read_to_string(path)
+                .map_err(|err| {
+                    eyre::eyre!("failed to read password file {}: {}", path.display(), err)
+                })?
+                .trim_end_matches(&['\r', '\n'][..])
+                .to_string(),
+            PasswordSource::Value(password) => password.clone(),
+        })
+    }
+}
+
+/// Decrypts the keystore at the given path with the password read from `password`
+pub fn read_signer(path: impl AsRef<Path>, password: &PasswordSource) -> eyre::Result<LocalWallet> {
+    let path = path.as_ref();
+    let password = password.read()?;
+    LocalWallet::decrypt_keystore(path, password).map_err(|err| match err {
+        WalletError::EthKeystoreError(eth_keystore::KeystoreError::MacMismatch) => {
+            eyre::eyre!("wrong password for keystore {}", path.display())
+        }
+        err => eyre::eyre!("failed to decrypt keystore {}: {}", path.display(), err),
+    })
+}
+
 /// Artifact/Contract identifier can take the following form:
 /// `<artifact file name>:<contract name>`, the `artifact file name` is the name of the json file of
 /// the contract's artifact and the contract name is the name of the solidity contract, like
@@ -125,8 +234,33 @@ pub fn get_file_name(id: &str) -> &str {
 }
 
 /// parse a hex str or decimal str as U256
+///
+/// Decimal strings may contain `_` separators and use scientific notation, e.g. `1_000` or `1.5e2`
 pub fn parse_u256(s: &str) -> eyre::Result<U256> {
-    Ok(if s.starts_with("0x") { U256::from_str(s)? } else { U256::from_dec_str(s)? })
+    if s.starts_with("0x") {
+        return Ok(U256::from_str(s)?)
+    }
+
+    // digits can be separated by underscores, e.g. `1_000_000`
+    let s = s.replace('_', "");
+    let (mantissa, exp) = match s.split_once(|c| c == 'e' || c == 'E') {
+        Some((mantissa, exp)) => (mantissa, exp.parse::<usize>()?),
+        None => return Ok(U256::from_dec_str(&s)?),
+    };
+
+    // expand `<mantissa>e<exp>`, e.g. `1.5e2` to `150`
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let frac = frac.trim_end_matches('0');
+    if int.is_empty() && frac.is_empty() {
+        eyre::bail!("`{}` is missing a mantissa", s)
+    }
+    if frac.len() > exp {
+        eyre::bail!("`{}` is not a whole number", s)
+    }
+    if exp > 78 {
+        eyre::bail!("`{}` is too large for a uint256", s)
+    }
+    Ok(U256::from_dec_str(&format!("{}{}{}", int, frac, "0".repeat(exp - frac.len())))?)
 }
 
 /// Parses an ether value from a string.
@@ -136,89 +270,331 @@ pub fn parse_u256(s: &str) -> eyre::Result<U256> {
 /// If the string represents an untagged amount (e.g. "100") then
 /// it is interpreted as wei.
 pub fn parse_ether_value(value: &str) -> eyre::Result<U256> {
-    Ok(if value.starts_with("0x") {
-        U256::from_str(value)?
+    parse_unit_value(value, "wei")
+}
+
+/// Parses a gwei value from a string.
+///
+/// The amount can be tagged with a unit, e.g. "1ether", "3gwei" or "100wei".
+///
+/// If the string represents an untagged amount (e.g. "3") then
+/// it is interpreted as gwei.
+pub fn parse_gwei_value(value: &str) -> eyre::Result<U256> {
+    parse_unit_value(value, "gwei")
+}
+
+/// Parses an amount that's optionally tagged with a unit, untagged amounts are interpreted as
+/// `default_unit`.
+///
+/// Hex values are always interpreted as wei.
+fn parse_unit_value(value: &str, default_unit: &str) -> eyre::Result<U256> {
+    let value = value.trim();
+    let err = || {
+        eyre::eyre!(
+            "invalid value `{}`, expected a number optionally followed by a unit, e.g. `1ether` or `10gwei`",
+            value
+        )
+    };
+    if value.starts_with("0x") {
+        return U256::from_str(value).map_err(|_| err())
+    }
+    if value.is_empty() || !value.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return Err(err())
+    }
+
+    let value = if value.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        format!("{}{}", value, default_unit)
     } else {
-        U256::from(LenientTokenizer::tokenize_uint(value)?)
-    })
+        value.to_string()
+    };
+    Ok(U256::from(LenientTokenizer::tokenize_uint(&value).map_err(|_| err())?))
 }
 
 /// Parses a `Duration` from a &str
+///
+/// The delay can be tagged with a unit, e.g. "500ms", "2s", "2m" or "1h".
+///
+/// If the string represents an untagged delay (e.g. "1.5") then
+/// it is interpreted as seconds.
 pub fn parse_delay(delay: &str) -> eyre::Result<Duration> {
-    let delay = if delay.ends_with("ms") {
-        let d: u64 = delay.trim_end_matches("ms").parse()?;
-        Duration::from_millis(d)
+    let delay = delay.trim();
+    let (value, unit_millis) = if let Some(value) = delay.strip_suffix("ms") {
+        (value, 1.0)
+    } else if let Some(value) = delay.strip_suffix('s') {
+        (value, 1_000.0)
+    } else if let Some(value) = delay.strip_suffix('m') {
+        (value, 60_000.0)
+    } else if let Some(value) = delay.strip_suffix('h') {
+        (value, 3_600_000.0)
     } else {
-        let d: f64 = delay.parse()?;
-        let delay = (d * 1000.0).round();
-        if delay.is_infinite() || delay.is_nan() || delay.is_sign_negative() {
-            eyre::bail!("delay must be finite and non-negative");
-        }
-
-        Duration::from_millis(delay as u64)
+        (delay, 1_000.0)
     };
-    Ok(delay)
+
+    let d: f64 = value.parse().map_err(|_| {
+        eyre::eyre!(
+            "invalid delay `{}`, expected a number optionally followed by a unit (ms, s, m, h), e.g. `500ms` or `2m`",
+            delay
+        )
+    })?;
+    let millis = (d * unit_millis).round();
+    if millis.is_infinite() || millis.is_nan() || millis.is_sign_negative() {
+        eyre::bail!("delay must be finite and non-negative");
+    }
+
+    Ok(Duration::from_millis(millis as u64))
 }
 
-/// Runs the `future` in a new [`tokio::runtime::Runtime`]
+/// The name of the worker threads of the shared [`tokio::runtime::Runtime`]
+const RUNTIME_THREAD_NAME: &str = "foundry-rt";
+
+/// The [`tokio::runtime::Runtime`] shared by all [`block_on`] calls
+static RUNTIME: Lazy<tokio::runtime::Runtime> = Lazy::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .thread_name(RUNTIME_THREAD_NAME)
+        .enable_all()
+        .build()
+        .expect("could not start tokio rt")
+});
+
+/// Runs the `future` in a shared, lazily initialized [`tokio::runtime::Runtime`]
+///
+/// Like [`tokio::runtime::Runtime::block_on`] this panics if called from within an async context.
 #[allow(unused)]
 pub fn block_on<F: Future>(future: F) -> F::Output {
-    let rt = tokio::runtime::Runtime::new().expect("could not start tokio rt");
-    rt.block_on(future)
+    RUNTIME.block_on(future)
 }
 
 /// Helper function that returns the [Fork] to use, if any.
 ///
 /// storage caching for the [Fork] will be enabled if
 ///   - `fork_url` is present
-///   - `fork_block_number` is present
 ///   - [StorageCachingConfig] allows the `fork_url` +  chain id pair
 ///   - storage is allowed (`no_storage_caching = false`)
 ///
 /// If all these criteria are met, then storage caching is enabled and storage info will be written
-/// to [Config::foundry_cache_dir()]/<str(chainid)>/<block>/storage.json
+/// to [Config::foundry_cache_dir()]/<str(chainid)>/<block>/storage.bin
 ///
 /// for `mainnet` and `--fork-block-number 14435000` on mac the corresponding storage cache will be
-/// at `~/.foundry/cache/mainnet/14435000/storage.json`
+/// at `~/.foundry/cache/mainnet/14435000/storage.bin`
+///
+/// If no `fork_block_number` is pinned, the fork is pinned to the latest block of the endpoint
+/// instead, which costs a single extra `eth_blockNumber` RPC call. The resolved block is
+/// remembered per endpoint, so all forks of the same endpoint within this process share the same
+/// block and cache. If the block number can't be fetched, the fork follows the latest block and
+/// storage is not cached.
 pub fn get_fork(evm_opts: &EvmOpts, config: &StorageCachingConfig) -> Option<Fork> {
-    /// Returns the path where the cache file should be stored
-    ///
-    /// or `None` if caching should not be enabled
-    ///
-    /// See also [ Config::foundry_block_cache_file()]
-    fn get_block_storage_path(
+    let url = evm_opts.fork_url.clone()?;
+    get_forks(evm_opts, &[(url, evm_opts.fork_block_number)], config).pop()
+}
+
+/// Returns a [Fork] for every `(fork_url, fork_block_number)` pair, see [get_fork]
+///
+/// The same storage caching rules apply to each fork, each fork is cached separately, keyed by its
+/// own chain id and block. When forking multiple networks, the configured chain id is ignored and
+/// the chain id of each fork is requested from its endpoint with `eth_chainId` instead. A fork
+/// whose chain id can't be requested is not cached, so forks of different networks never share a
+/// cache.
+pub fn get_forks(
+    evm_opts: &EvmOpts,
+    forks: &[(String, Option<u64>)],
+    config: &StorageCachingConfig,
+) -> Vec<Fork> {
+    /// Whether storage of the fork of the `url` should be cached
+    fn is_caching_enabled(
         evm_opts: &EvmOpts,
         config: &StorageCachingConfig,
+        url: &str,
         chain_id: u64,
-    ) -> Option<PathBuf> {
+    ) -> bool {
         if evm_opts.no_storage_caching {
             // storage caching explicitly opted out of
-            return None
+            return false
         }
-        let url = evm_opts.fork_url.as_ref()?;
-        // cache only if block explicitly pinned
-        let block = evm_opts.fork_block_number?;
+        config.enable_for_endpoint(url) && config.enable_for_chain_id(chain_id)
+    }
+
+    forks
+        .iter()
+        .map(|(url, block)| {
+            let mut evm_opts = evm_opts.clone();
+            evm_opts.fork_url = Some(url.clone());
+            evm_opts.fork_block_number = *block;
+            let fork_chain_id = if forks.len() > 1 && !evm_opts.no_storage_caching {
+                remote_chain_id(url)
+            } else {
+                None
+            };
+            if forks.len() > 1 {
+                evm_opts.env.chain_id = fork_chain_id;
+            }
+
+            let chain_id = evm_opts.get_chain_id();
+            let retry = evm_opts.fork_retry_config();
+            let unknown_chain = forks.len() > 1 && fork_chain_id.is_none();
+            if unknown_chain || !is_caching_enabled(&evm_opts, config, url, chain_id) {
+                return Fork {
+                    url: url.clone(),
+                    pin_block: *block,
+                    cache_path: None,
+                    chain_id,
+                    retry,
+                }
+            }
 
+            // pin the fork to the latest block so its storage can be cached
+            let pin_block = block.or_else(|| latest_block_number(url));
+            // See also [Config::foundry_block_cache_file()]
+            let cache_path =
+                pin_block.and_then(|block| Config::foundry_block_cache_file(chain_id, block));
+            Fork { url: url.clone(), pin_block, cache_path, chain_id, retry }
+        })
+        .collect()
+}
+
+/// Returns the settings for the forks created by tests with the `createFork` cheatcode
+///
+/// Forks can be created by the name of one of the `rpc_endpoints` of the config. Their storage is
+/// cached by the same rules as the storage of the `--fork-url`, see [get_fork].
+pub fn fork_config(evm_opts: &EvmOpts, config: &StorageCachingConfig) -> MultiForkConfig {
+    let fork_config = MultiForkConfig::new(evm_opts.rpc_endpoints.clone())
+        .with_retry(evm_opts.fork_retry_config());
+    if evm_opts.no_storage_caching {
+        return fork_config
+    }
+    let config = config.clone();
+    fork_config.with_cache_path(move |url, chain_id, block| {
         if config.enable_for_endpoint(url) && config.enable_for_chain_id(chain_id) {
-            return Config::foundry_block_cache_file(chain_id, block)
+            Config::foundry_block_cache_file(chain_id, block)
+        } else {
+            None
         }
+    })
+}
 
-        None
+/// The latest block numbers resolved for unpinned forks, by endpoint
+static LATEST_BLOCK_NUMBERS: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(Default::default);
+
+/// The chain ids reported by the endpoints of forks, by endpoint
+static CHAIN_IDS: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(Default::default);
+
+/// Returns the chain id the endpoint reports via `eth_chainId`
+///
+/// Unlike [EvmOpts::get_chain_id] this doesn't guess the chain from the url, which would mistake
+/// e.g. `opt-mainnet` endpoints for ethereum mainnet.
+fn remote_chain_id(url: &str) -> Option<u64> {
+    let mut chain_ids = CHAIN_IDS.lock().unwrap();
+    if let Some(id) = chain_ids.get(url) {
+        return Some(*id)
     }
 
-    if let Some(ref url) = evm_opts.fork_url {
-        let chain_id = evm_opts.get_chain_id();
-        let cache_storage = get_block_storage_path(evm_opts, config, chain_id);
-        let fork = Fork {
-            url: url.clone(),
-            pin_block: evm_opts.fork_block_number,
-            cache_path: cache_storage,
-            chain_id,
-        };
-        return Some(fork)
+    let provider = Provider::try_from(url).ok()?;
+    let id = match RuntimeOrHandle::new().block_on(provider.get_chainid()) {
+        Ok(id) => id.as_u64(),
+        Err(err) => {
+            tracing::warn!("failed to fetch the chain id of {}: {}", url, err);
+            return None
+        }
+    };
+    chain_ids.insert(url.to_string(), id);
+    Some(id)
+}
+
+/// Returns the latest block number of the endpoint
+///
+/// The block number is only fetched once per endpoint, subsequent calls return the same block.
+fn latest_block_number(url: &str) -> Option<u64> {
+    let mut blocks = LATEST_BLOCK_NUMBERS.lock().unwrap();
+    if let Some(block) = blocks.get(url) {
+        return Some(*block)
+    }
+
+    let provider = Provider::try_from(url).ok()?;
+    let block = match RuntimeOrHandle::new().block_on(provider.get_block_number()) {
+        Ok(block) => block.as_u64(),
+        Err(err) => {
+            tracing::warn!("failed to fetch the latest block number of {}: {}", url, err);
+            return None
+        }
+    };
+    tracing::trace!("pinned fork of {} to latest block {}", url, block);
+    blocks.insert(url.to_string(), block);
+    Some(block)
+}
+
+/// Pins the fork to the block of the `--fork-block-number` tag, e.g. `latest` or `-10`
+///
+/// The tag is resolved against the `fork_url` once, so the whole run and its storage cache use
+/// the same block. Block numbers are already merged into the [EvmOpts] and are left unchanged.
+pub fn resolve_fork_block(evm_opts: &mut EvmOpts, block: Option<ForkBlock>) -> eyre::Result<()> {
+    let (block, url) = match (block, evm_opts.fork_url.clone()) {
+        (Some(block), Some(url)) if block.as_number().is_none() => (block, url),
+        _ => return Ok(()),
+    };
+    let number = resolve_block(&url, block)?;
+    if evm_opts.verbosity >= 2 {
+        println!("Forking from block {} ({})", number, block);
+    }
+    evm_opts.fork_block_number = Some(number);
+    Ok(())
+}
+
+/// Returns a [Fork] for every `--fork-url` and `--fork-block-number` pair of the arguments, see
+/// [get_forks]
+///
+/// The first fork is the fork of the [EvmOpts], which already are resolved, see
+/// [resolve_fork_block]. The endpoints and block tags of the other forks are resolved the same way.
+/// Returns no forks if there is no `fork_url`.
+pub fn get_forks_of_args(
+    evm_opts: &EvmOpts,
+    args: &EvmArgs,
+    config: &StorageCachingConfig,
+) -> eyre::Result<Vec<Fork>> {
+    let url = match evm_opts.fork_url.clone() {
+        Some(url) => url,
+        None => return Ok(Vec::new()),
+    };
+    let mut forks = vec![(url, evm_opts.fork_block_number)];
+    for (endpoint, block) in args.forks().into_iter().skip(1) {
+        let url = resolve_endpoint(&endpoint, &evm_opts.rpc_endpoints)?;
+        let block = block.map(|block| resolve_block(&url, block)).transpose()?;
+        forks.push((url, block));
     }
+    Ok(get_forks(evm_opts, &forks, config))
+}
 
-    None
+/// Returns the number of the block of the endpoint, tags are resolved against the endpoint
+fn resolve_block(url: &str, block: ForkBlock) -> eyre::Result<u64> {
+    if let Some(number) = block.as_number() {
+        return Ok(number)
+    }
+    let provider = Provider::try_from(url)?;
+    RuntimeOrHandle::new()
+        .block_on(fork_block_number(&provider, block))
+        .wrap_err_with(|| format!("Failed to resolve fork block `{}` of {}", block, url))
+}
+
+/// Returns the number of the block, tags are resolved against the endpoint
+async fn fork_block_number(provider: &Provider<Http>, block: ForkBlock) -> eyre::Result<u64> {
+    let number = match block {
+        ForkBlock::Number(number) => number,
+        ForkBlock::Latest => provider.get_block_number().await?.as_u64(),
+        ForkBlock::BeforeLatest(blocks) => {
+            let latest = provider.get_block_number().await?.as_u64();
+            latest.checked_sub(blocks).ok_or_else(|| {
+                eyre::eyre!("the latest block {} is less than {} blocks", latest, blocks)
+            })?
+        }
+        ForkBlock::Safe | ForkBlock::Finalized => {
+            let tag = block.to_string();
+            let resolved: Option<Block<H256>> =
+                provider.request("eth_getBlockByNumber", (&tag, false)).await?;
+            resolved
+                .and_then(|block| block.number)
+                .ok_or_else(|| eyre::eyre!("the endpoint has no {} block", tag))?
+                .as_u64()
+        }
+    };
+    Ok(number)
 }
 
 /// Conditionally print a message
@@ -249,5 +625,342 @@ mod tests {
         assert!(p.is_sol());
         let p = Path::new("contracts/Greeter.sol");
         assert!(!p.is_sol_test());
+        assert!(!p.is_vyper());
+        let p = Path::new("contracts/Greeter.vy");
+        assert!(p.is_vyper());
+        assert!(!p.is_sol());
+        let p = Path::new("out/Greeter.sol/Greeter.json");
+        assert!(p.is_json());
+        assert!(!p.is_sol());
+
+        // extensions are case-sensitive
+        assert!(!Path::new("contracts/Greeter.SOL").is_sol());
+        assert!(!Path::new("contracts/Greeter.VY").is_vyper());
+        assert!(!Path::new("out/Greeter.JSON").is_json());
+    }
+
+    #[test]
+    fn can_read_signer_from_keystore() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("../testdata/fixtures/Keystore");
+        let keystore = fixtures.join("test-keystore.json");
+        let address: ethers::types::Address =
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+
+        let password = PasswordSource::File(fixtures.join("password.txt"));
+        let signer = read_signer(&keystore, &password).unwrap();
+        assert_eq!(ethers::signers::Signer::address(&signer), address);
+
+        let password = PasswordSource::Value("foundry".to_string());
+        let signer = read_signer(&keystore, &password).unwrap();
+        assert_eq!(ethers::signers::Signer::address(&signer), address);
+
+        let password = PasswordSource::Value("hunter2".to_string());
+        let err = read_signer(&keystore, &password).unwrap_err();
+        assert!(err.to_string().contains("wrong password"));
+
+        let password = PasswordSource::File(fixtures.join("missing.txt"));
+        assert!(read_signer(&keystore, &password).is_err());
+    }
+
+    #[test]
+    fn block_on_reuses_runtime() {
+        // the task spawned by the first call has to survive all calls in between, a runtime that
+        // is dropped after a call would cancel it
+        let task = block_on(async {
+            tokio::spawn(async {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                std::thread::current().name().map(str::to_string)
+            })
+        });
+        for i in 0..1000 {
+            assert_eq!(block_on(async move { i }), i);
+        }
+        let name = block_on(task).unwrap();
+        assert_eq!(name.as_deref(), Some(RUNTIME_THREAD_NAME));
+    }
+
+    #[test]
+    fn caches_each_fork() {
+        let evm_opts = EvmOpts::default();
+        let config = StorageCachingConfig::default();
+        let l1 = "https://eth-mainnet.alchemyapi.io/v2/key".to_string();
+        let l2 = "https://opt-mainnet.g.alchemy.com/v2/key".to_string();
+
+        let forks = get_forks(&evm_opts, &[(l1.clone(), Some(14435000))], &config);
+        assert_eq!(forks.len(), 1);
+        assert_eq!(forks[0].url, l1);
+        assert_eq!(forks[0].pin_block, Some(14435000));
+        assert_eq!(forks[0].cache_path, Config::foundry_block_cache_file(1u64, 14435000));
+        assert!(forks[0].cache_path.is_some());
+
+        // pretend the chain ids were already fetched from the endpoints, the `mainnet` in the url
+        // of the optimism endpoint must not make it share the cache of ethereum mainnet
+        CHAIN_IDS.lock().unwrap().extend([(l1.clone(), 1), (l2.clone(), 10)]);
+        let forks = get_forks(
+            &evm_opts,
+            &[(l1.clone(), Some(14435000)), (l2.clone(), Some(14435000))],
+            &config,
+        );
+        assert_eq!(forks[0].cache_path, Config::foundry_block_cache_file(1u64, 14435000));
+        assert_eq!(forks[1].cache_path, Config::foundry_block_cache_file(10u64, 14435000));
+        assert_eq!(forks[1].chain_id, 10);
+        assert!(forks[1].cache_path.is_some());
+        assert_ne!(forks[0].cache_path, forks[1].cache_path);
+
+        // forks of unknown chains are not cached
+        let unknown = "http://127.0.0.1:1".to_string();
+        let forks = get_forks(
+            &evm_opts,
+            &[(l1.clone(), Some(14435000)), (unknown, Some(14435000))],
+            &config,
+        );
+        assert!(forks[0].cache_path.is_some());
+        assert!(forks[1].cache_path.is_none());
+
+        let no_caching = EvmOpts { no_storage_caching: true, ..Default::default() };
+        let forks = get_forks(&no_caching, &[(l1.clone(), Some(14435000))], &config);
+        assert!(forks[0].cache_path.is_none());
+        // unpinned forks are not pinned to the latest block if they're not cached
+        let forks = get_forks(&no_caching, &[(l1.clone(), None)], &config);
+        assert_eq!(forks[0].pin_block, None);
+        assert!(forks[0].cache_path.is_none());
+
+        // `get_fork` is the single fork case
+        let evm_opts =
+            EvmOpts { fork_url: Some(l1), fork_block_number: Some(14435000), ..Default::default() };
+        let fork = get_fork(&evm_opts, &config).unwrap();
+        assert_eq!(fork.cache_path, Config::foundry_block_cache_file(1u64, 14435000));
+        assert!(get_fork(&EvmOpts::default(), &config).is_none());
+    }
+
+    #[test]
+    fn caches_unpinned_fork_at_latest_block() {
+        let url = "https://eth-mainnet.alchemyapi.io/v2/latest".to_string();
+        // pretend the latest block was already fetched from the endpoint
+        LATEST_BLOCK_NUMBERS.lock().unwrap().insert(url.clone(), 15000000);
+
+        let evm_opts = EvmOpts { fork_url: Some(url.clone()), ..Default::default() };
+        let fork = get_fork(&evm_opts, &StorageCachingConfig::default()).unwrap();
+        assert_eq!(fork.pin_block, Some(15000000));
+        assert_eq!(fork.cache_path, Config::foundry_block_cache_file(1u64, 15000000));
+        assert!(fork.cache_path.is_some());
+
+        // an explicitly pinned block takes precedence
+        let forks = get_forks(&evm_opts, &[(url, Some(14435000))], &Default::default());
+        assert_eq!(forks[0].pin_block, Some(14435000));
+        assert_eq!(forks[0].cache_path, Config::foundry_block_cache_file(1u64, 14435000));
+    }
+
+    #[test]
+    fn parses_fork_blocks() {
+        assert_eq!("14435000".parse::<ForkBlock>().unwrap(), ForkBlock::Number(14435000));
+        assert_eq!("latest".parse::<ForkBlock>().unwrap(), ForkBlock::Latest);
+        assert_eq!("safe".parse::<ForkBlock>().unwrap(), ForkBlock::Safe);
+        assert_eq!("finalized".parse::<ForkBlock>().unwrap(), ForkBlock::Finalized);
+        assert_eq!("-10".parse::<ForkBlock>().unwrap(), ForkBlock::BeforeLatest(10));
+        assert_eq!(ForkBlock::BeforeLatest(10).to_string(), "-10");
+
+        for block in ["", "pending", "-", "-latest", "0x10", "1.5", "--10"] {
+            assert!(block.parse::<ForkBlock>().is_err(), "{}", block);
+        }
+    }
+
+    #[test]
+    fn merges_only_resolved_fork_blocks() {
+        use clap::Parser;
+
+        let extract = |block: &str| {
+            let args = EvmArgs::parse_from([
+                "foundry",
+                "--fork-url",
+                "http://localhost:8545",
+                "--fork-block-number",
+                block,
+            ]);
+            Config::figment().merge(args).extract::<EvmOpts>().unwrap()
+        };
+        assert_eq!(extract("14435000").fork_block_number, Some(14435000));
+        // tags are resolved against the endpoint before the run
+        assert_eq!(extract("latest").fork_block_number, None);
+        assert_eq!(extract("-10").fork_block_number, None);
+
+        // block numbers don't need to be resolved
+        let mut evm_opts = extract("14435000");
+        resolve_fork_block(&mut evm_opts, Some(ForkBlock::Number(14435000))).unwrap();
+        assert_eq!(evm_opts.fork_block_number, Some(14435000));
+    }
+
+    #[test]
+    fn forks_every_fork_url() {
+        use clap::Parser;
+        use foundry_config::caching::CachedEndpoints;
+
+        let l1 = "https://eth-mainnet.alchemyapi.io/v2/key";
+        let l2 = "https://opt-mainnet.g.alchemy.com/v2/key";
+        let args = EvmArgs::parse_from([
+            "foundry",
+            "--fork-url",
+            l1,
+            "--fork-block-number",
+            "14435000",
+            "--fork-url",
+            "optimism",
+            "--fork-block-number",
+            "14435001",
+            "--fork-url",
+            l2,
+        ]);
+        assert_eq!(
+            args.forks(),
+            vec![
+                (l1.to_string(), Some(ForkBlock::Number(14435000))),
+                ("optimism".to_string(), Some(ForkBlock::Number(14435001))),
+                (l2.to_string(), None),
+            ]
+        );
+
+        // only the first fork is merged into the options
+        let mut evm_opts = Config::figment().merge(args.clone()).extract::<EvmOpts>().unwrap();
+        assert_eq!(evm_opts.fork_url.as_deref(), Some(l1));
+        assert_eq!(evm_opts.fork_block_number, Some(14435000));
+
+        // the other endpoints are resolved like the first one
+        assert!(get_forks_of_args(&evm_opts, &args, &Default::default()).is_err());
+        CHAIN_IDS.lock().unwrap().extend([(l1.to_string(), 1), (l2.to_string(), 10)]);
+        evm_opts.rpc_endpoints.insert("optimism".to_string(), l2.to_string());
+        let args = EvmArgs::parse_from([
+            "foundry",
+            "--fork-url",
+            l1,
+            "--fork-block-number",
+            "14435000",
+            "--fork-url",
+            "optimism",
+            "--fork-block-number",
+            "14435001",
+        ]);
+        let forks = get_forks_of_args(&evm_opts, &args, &Default::default()).unwrap();
+        assert_eq!(forks.len(), 2);
+        assert_eq!((forks[0].url.as_str(), forks[0].pin_block), (l1, Some(14435000)));
+        assert_eq!((forks[1].url.as_str(), forks[1].pin_block), (l2, Some(14435001)));
+        assert!(forks[0].cache_path.is_some());
+        assert!(forks[1].cache_path.is_some());
+        assert_ne!(forks[0].cache_path, forks[1].cache_path);
+
+        // caching is decided per endpoint
+        let config = StorageCachingConfig {
+            endpoints: CachedEndpoints::Pattern("eth-mainnet".parse().unwrap()),
+            ..Default::default()
+        };
+        let forks = get_forks_of_args(&evm_opts, &args, &config).unwrap();
+        assert!(forks[0].cache_path.is_some());
+        assert!(forks[1].cache_path.is_none());
+
+        assert!(get_forks_of_args(&EvmOpts::default(), &args, &Default::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn detects_sol_scripts() {
+        let p = Path::new("script/MyScript.s.sol");
+        assert!(p.is_sol_script());
+        assert!(!p.is_sol_test());
+        assert!(p.is_sol());
+        let p = Path::new("test/MyTest.t.sol");
+        assert!(!p.is_sol_script());
+        assert!(p.is_sol_test());
+        let p = Path::new("src/Plain.sol");
+        assert!(!p.is_sol_script());
+        assert!(!p.is_sol_test());
+    }
+
+    #[test]
+    fn maps_evm_versions() {
+        // fails to compile if a variant is added to `EvmVersion` without being covered below
+        let covered = |evm: &EvmVersion| match evm {
+            EvmVersion::Homestead |
+            EvmVersion::TangerineWhistle |
+            EvmVersion::SpuriousDragon |
+            EvmVersion::Byzantium |
+            EvmVersion::Constantinople |
+            EvmVersion::Petersburg |
+            EvmVersion::Istanbul |
+            EvmVersion::Berlin |
+            EvmVersion::London => true,
+        };
+        [
+            (EvmVersion::Homestead, SpecId::HOMESTEAD),
+            (EvmVersion::TangerineWhistle, SpecId::TANGERINE),
+            (EvmVersion::SpuriousDragon, SpecId::SPURIOUS_DRAGON),
+            (EvmVersion::Byzantium, SpecId::BYZANTINE),
+            (EvmVersion::Constantinople, SpecId::CONSTANTINOPLE),
+            (EvmVersion::Petersburg, SpecId::PETERSBURG),
+            (EvmVersion::Istanbul, SpecId::ISTANBUL),
+            (EvmVersion::Berlin, SpecId::BERLIN),
+            (EvmVersion::London, SpecId::LONDON),
+        ]
+        .iter()
+        .for_each(|(evm, spec)| {
+            assert!(covered(evm));
+            assert_eq!(evm_spec(evm).unwrap(), *spec);
+        });
+    }
+
+    #[test]
+    fn parses_delay() {
+        assert_eq!(parse_delay("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_delay("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_delay("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_delay("1h").unwrap(), Duration::from_secs(3600));
+        assert_eq!(parse_delay("1.5").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_delay("0.5m").unwrap(), Duration::from_secs(30));
+
+        for delay in ["-1", "-1s", "inf", "NaN", "1m30s", "2d", "ms", ""] {
+            assert!(parse_delay(delay).is_err(), "{}", delay);
+        }
+    }
+
+    #[test]
+    fn parses_u256() {
+        assert_eq!(parse_u256("1000000").unwrap(), U256::from(1_000_000u64));
+        assert_eq!(parse_u256("1_000_000").unwrap(), U256::from(1_000_000u64));
+        assert_eq!(parse_u256("0x10").unwrap(), U256::from(16u64));
+        assert_eq!(parse_u256("1e18").unwrap(), U256::exp10(18));
+        assert_eq!(parse_u256("1E18").unwrap(), U256::exp10(18));
+        assert_eq!(parse_u256("1.5e2").unwrap(), U256::from(150u64));
+        assert_eq!(parse_u256("1.50e1").unwrap(), U256::from(15u64));
+        assert_eq!(parse_u256("1_000e3").unwrap(), U256::from(1_000_000u64));
+        assert_eq!(parse_u256("0e0").unwrap(), U256::zero());
+
+        for value in ["1.55e1", "1.5", "e5", "1e", "1e-2", "1e100", "abc", "1.5.5e3"] {
+            assert!(parse_u256(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parses_ether_values() {
+        assert_eq!(parse_ether_value("3").unwrap(), U256::from(3u64));
+        assert_eq!(parse_ether_value("3gwei").unwrap(), U256::from(3_000_000_000u64));
+        assert_eq!(parse_ether_value("1ether").unwrap(), U256::exp10(18));
+        assert_eq!(parse_ether_value("0x10").unwrap(), U256::from(16u64));
+
+        for value in ["", "ether", "3lol", "0xzz", "-1"] {
+            assert!(parse_ether_value(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parses_gwei_values() {
+        assert_eq!(parse_gwei_value("3").unwrap(), U256::from(3_000_000_000u64));
+        assert_eq!(parse_gwei_value("3gwei").unwrap(), U256::from(3_000_000_000u64));
+        assert_eq!(parse_gwei_value("1.5").unwrap(), U256::from(1_500_000_000u64));
+        assert_eq!(parse_gwei_value("100wei").unwrap(), U256::from(100u64));
+        assert_eq!(parse_gwei_value("1ether").unwrap(), U256::exp10(18));
+        assert_eq!(parse_gwei_value("0x10").unwrap(), U256::from(16u64));
+
+        for value in ["", "gwei", "3lol", "0xzz", "-1"] {
+            assert!(parse_gwei_value(value).is_err(), "{}", value);
+        }
     }
 }
@@ -29,3 +29,17 @@ casttest!(finds_block, |_: TestProject, mut cmd: TestCommand| {
     // Output block time: Mar 21 2022 06:20:09 UTC
     assert!(output.contains("14428082"), "{}", output);
 });
+
+// tests that the keystore password can be passed via `ETH_PASSWORD`
+casttest!(reads_keystore_password_from_env, |_: TestProject, mut cmd: TestCommand| {
+    let keystore = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../testdata/fixtures/Keystore/test-keystore.json");
+    cmd.args(["wallet", "address", "--keystore", keystore.to_str().unwrap()]);
+    cmd.set_env("ETH_PASSWORD", "foundry");
+    let output = cmd.stdout_lossy();
+    assert!(output.contains("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"), "{}", output);
+
+    cmd.set_env("ETH_PASSWORD", "hunter2");
+    cmd.assert_err();
+    assert!(cmd.stderr_lossy().contains("wrong password for keystore"));
+});
@@ -9,8 +9,10 @@ use foundry_cli_test_utils::{
     forgetest, forgetest_ignore, forgetest_init,
     util::{pretty_err, read_string, TestCommand, TestProject},
 };
-use foundry_config::{parse_with_profile, BasicConfig, Config, SolidityErrorCode};
-use std::{env, fs};
+use foundry_config::{
+    fmt::FormatterConfig, parse_with_profile, BasicConfig, Config, SolcReq, SolidityErrorCode,
+};
+use std::{collections::BTreeMap, env, fs, path::Path};
 
 // import forge utils as mod
 #[allow(unused)]
@@ -87,6 +89,397 @@ forgetest!(can_init_no_git, |prj: TestProject, mut cmd: TestCommand| {
     assert!(!prj.root().join("lib/ds-test/.git").exists());
 });
 
+// Checks that a dependency can be vendored without git and removed again
+forgetest!(can_install_and_remove_vendored, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    cmd.args(["install", "--no-git", "transmissions11/solmate@v7"]).root_arg();
+    cmd.assert_non_empty_stdout();
+
+    let solmate = prj.root().join("lib/solmate");
+    assert!(solmate.join("src").exists());
+    assert!(!solmate.join(".git").exists());
+
+    let lock: serde_json::Value =
+        ethers::solc::utils::read_json_file(prj.root().join("foundry.lock")).unwrap();
+    assert_eq!(lock["solmate"]["tag"], "v7");
+    assert_eq!(lock["solmate"]["vendored"], true);
+    assert_eq!(lock["solmate"]["rev"].as_str().unwrap().len(), 40);
+
+    cmd.forge_fuse().args(["remove", "transmissions11/solmate"]);
+    cmd.assert_non_empty_stdout();
+    assert!(!solmate.exists());
+});
+
+// Checks that a dependency can be installed from a release archive and removed again
+forgetest!(can_install_and_remove_archive, |prj: TestProject, mut cmd: TestCommand| {
+    let url = "https://github.com/transmissions11/solmate/archive/refs/tags/v7.tar.gz";
+    cmd.set_current_dir(prj.root());
+    cmd.args(["install", url, "--name", "solmate", "--checksum", "00"]).root_arg();
+    cmd.assert_err();
+
+    let solmate = prj.root().join("lib/solmate");
+    assert!(!solmate.exists());
+
+    cmd.forge_fuse().args(["install", url, "--name", "solmate"]).root_arg();
+    cmd.assert_non_empty_stdout();
+    assert!(solmate.join("src").exists());
+
+    let lock: serde_json::Value =
+        ethers::solc::utils::read_json_file(prj.root().join("foundry.lock")).unwrap();
+    assert_eq!(lock["solmate"]["url"], url);
+    assert_eq!(lock["solmate"]["vendored"], true);
+    assert_eq!(lock["solmate"]["rev"].as_str().unwrap().len(), 64);
+
+    cmd.forge_fuse().args(["remove", "solmate"]);
+    cmd.assert_non_empty_stdout();
+    assert!(!solmate.exists());
+});
+
+// Checks that removing a dependency also removes its remappings and lockfile entry
+forgetest!(can_remove_dependency_remappings, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    cmd.args(["install", "--no-git", "transmissions11/solmate@v7"]).root_arg();
+    cmd.assert_non_empty_stdout();
+
+    let remappings = prj.root().join("remappings.txt");
+    let content = "ds-test/=lib/ds-test/src/\nsolmate/=lib/solmate/src/\n";
+    pretty_err(&remappings, fs::write(&remappings, content));
+
+    cmd.forge_fuse().args(["remove", "--dry-run", "solmate"]);
+    cmd.assert_non_empty_stdout();
+    assert!(prj.root().join("lib/solmate").exists());
+    assert_eq!(read_string(&remappings), content);
+
+    cmd.forge_fuse().args(["remove", "solmate"]);
+    cmd.assert_non_empty_stdout();
+    assert!(!prj.root().join("lib/solmate").exists());
+    assert_eq!(read_string(&remappings), "ds-test/=lib/ds-test/src/\n");
+
+    let lock: serde_json::Value =
+        ethers::solc::utils::read_json_file(prj.root().join("foundry.lock")).unwrap();
+    assert!(lock.get("solmate").is_none());
+});
+
+// Checks that the nested dependencies of a dependency are installed as well
+forgetest_init!(can_install_recursive, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+
+    // solmate has `ds-test` as nested submodule
+    cmd.args(["install", "transmissions11/solmate@v7"]).root_arg();
+    cmd.assert_non_empty_stdout();
+    assert!(prj.root().join("lib/solmate/lib/ds-test/src").exists());
+
+    cmd.forge_fuse()
+        .args(["install", "--no-git", "vendored=transmissions11/solmate@v7"])
+        .root_arg();
+    cmd.assert_non_empty_stdout();
+    assert!(prj.root().join("lib/vendored/lib/ds-test/src").exists());
+    assert!(!prj.root().join("lib/vendored/lib/ds-test/.git").exists());
+
+    cmd.forge_fuse()
+        .args(["install", "--no-recursive", "shallow=transmissions11/solmate@v7"])
+        .root_arg();
+    cmd.assert_non_empty_stdout();
+    assert!(prj.root().join("lib/shallow/src").exists());
+    assert!(!prj.root().join("lib/shallow/lib/ds-test/src").exists());
+});
+
+// Checks that installing a dependency at a ref that doesn't exist leaves the repository untouched
+forgetest_init!(can_reject_invalid_install_ref, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    let gitmodules = prj.root().join(".gitmodules");
+    let before = read_string(&gitmodules);
+    let git_status = || {
+        let output = std::process::Command::new("git")
+            .args(["status", "--porcelain"])
+            .current_dir(prj.root())
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    let status = git_status();
+
+    // unknown tag is rejected before the submodule is added
+    cmd.args(["install", "transmissions11/solmate@v0.0.0-missing"]).root_arg();
+    cmd.assert_err();
+    assert!(cmd.stderr_lossy().contains("`v0.0.0-missing` not found"));
+    assert!(!prj.root().join("lib/solmate").exists());
+    assert_eq!(read_string(&gitmodules), before);
+    assert_eq!(git_status(), status);
+
+    // unknown commit is only detected after the submodule was added and is rolled back
+    cmd.forge_fuse().args(["install", "transmissions11/solmate@deadbeefdeadbeef"]).root_arg();
+    cmd.assert_err();
+    assert!(!prj.root().join("lib/solmate").exists());
+    assert!(!prj.root().join(".git/modules/lib/solmate").exists());
+    assert_eq!(read_string(&gitmodules), before);
+    assert_eq!(git_status(), status);
+});
+
+/// Runs git with the given args in `dir`, allowing local submodule remotes, and returns its
+/// trimmed stdout
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .args(["-c", "protocol.file.allow=always"])
+        .args(["-c", "user.name=foundry", "-c", "user.email=foundry@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Creates a local dependency with a `v1` tagged commit and a newer commit, and adds it to the
+/// git repository at `root` as the `lib/dep` submodule
+///
+/// Returns the remote and the hashes of both commits
+fn add_local_dependency(root: &Path) -> (tempfile::TempDir, String, String) {
+    let remote = tempfile::tempdir().unwrap();
+    let dir = remote.path();
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/Dep.sol"), "contract Dep {}\n").unwrap();
+    git(dir, &["init", "-q"]);
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "-q", "-m", "v1"]);
+    git(dir, &["tag", "v1"]);
+    let v1 = git(dir, &["rev-parse", "HEAD"]);
+    fs::write(dir.join("src/Dep.sol"), "contract Dep { uint256 x; }\n").unwrap();
+    git(dir, &["commit", "-q", "-am", "v2"]);
+    let v2 = git(dir, &["rev-parse", "HEAD"]);
+
+    git(root, &["init", "-q"]);
+    git(root, &["submodule", "add", "-q", dir.to_str().unwrap(), "lib/dep"]);
+    git(root, &["commit", "-q", "-m", "add dep"]);
+    (remote, v1, v2)
+}
+
+// Checks that `foundry.lock` pins the dependencies and `--locked` detects drifted checkouts
+forgetest!(can_pin_dependencies_with_lockfile, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    prj.inner().add_source("Foo", "pragma solidity 0.8.10; contract Foo {}").unwrap();
+
+    // the submodule is at the latest commit, the lockfile pins the first one
+    let root = prj.root();
+    let lib = root.join("lib/dep");
+    let (remote, v1, v2) = add_local_dependency(root);
+    let lockfile = serde_json::json!({
+        "dep": { "url": remote.path().display().to_string(), "rev": v1, "vendored": false }
+    });
+    fs::write(root.join("foundry.lock"), lockfile.to_string()).unwrap();
+
+    cmd.args(["build", "--locked"]);
+    cmd.assert_err();
+    assert!(cmd.stderr_lossy().contains(&format!("dep: expected {}, found {}", v1, v2)));
+
+    // installing checks out the locked revision
+    cmd.forge_fuse().arg("install");
+    cmd.set_env("GIT_ALLOW_PROTOCOL", "file");
+    cmd.assert_non_empty_stdout();
+    assert_eq!(git(&lib, &["rev-parse", "HEAD"]), v1);
+    cmd.forge_fuse().args(["build", "--locked"]);
+    cmd.assert_non_empty_stdout();
+
+    // updating moves the dependency and rewrites its entry
+    cmd.forge_fuse().args(["update", "dep"]);
+    cmd.set_env("GIT_ALLOW_PROTOCOL", "file");
+    cmd.output();
+    assert_eq!(git(&lib, &["rev-parse", "HEAD"]), v2);
+    let lockfile: serde_json::Value =
+        ethers::solc::utils::read_json_file(root.join("foundry.lock")).unwrap();
+    assert_eq!(lockfile["dep"]["rev"], v2);
+    cmd.forge_fuse().args(["build", "--locked"]);
+    cmd.assert_non_empty_stdout();
+});
+
+// Checks that `forge update <dep>@<ref>` checks out the dependency at the ref and commits it
+forgetest!(can_update_dependency_to_ref, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    let root = prj.root();
+    let lib = root.join("lib/dep");
+    let (_remote, v1, v2) = add_local_dependency(root);
+    assert_eq!(git(&lib, &["rev-parse", "HEAD"]), v2);
+
+    // the tag is checked out and the pointer bump is committed
+    cmd.args(["update", "dep@v1"]);
+    cmd.set_env("GIT_ALLOW_PROTOCOL", "file");
+    cmd.assert_non_empty_stdout();
+    assert_eq!(git(&lib, &["rev-parse", "HEAD"]), v1);
+    assert_eq!(git(root, &["log", "-1", "--format=%B"]), "forge update: dep\n\nv1");
+    assert_eq!(git(root, &["status", "--porcelain", "lib/dep"]), "");
+
+    // commits can be checked out as well
+    cmd.forge_fuse().args(["update", &format!("lib/dep@{}", v2)]);
+    cmd.set_env("GIT_ALLOW_PROTOCOL", "file");
+    cmd.assert_non_empty_stdout();
+    assert_eq!(git(&lib, &["rev-parse", "HEAD"]), v2);
+
+    // unknown refs are rejected and leave the dependency untouched
+    cmd.forge_fuse().args(["update", "dep@v0.0.0-missing"]);
+    cmd.set_env("GIT_ALLOW_PROTOCOL", "file");
+    cmd.assert_err();
+    assert!(cmd
+        .stderr_lossy()
+        .contains("Ref \"v0.0.0-missing\" of dependency \"dep\" not found on the remote"));
+    assert_eq!(git(&lib, &["rev-parse", "HEAD"]), v2);
+});
+
+// Checks that the tree of a single file or contract can be printed, up to a depth
+forgetest_init!(can_print_tree_of_target, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+
+    cmd.args(["tree", "src/Contract.sol"]);
+    let out = cmd.stdout_lossy();
+    assert!(out.starts_with("src/Contract.sol"));
+    assert!(!out.contains("test/Contract.t.sol"));
+
+    // resolves the contract by name
+    cmd.forge_fuse().args(["tree", "ContractTest"]);
+    let out = cmd.stdout_lossy();
+    assert!(out.starts_with("test/Contract.t.sol"));
+    assert!(out.contains("lib/ds-test/src/test.sol"));
+
+    cmd.forge_fuse().args(["tree", "ContractTest", "--depth", "0"]);
+    assert_eq!(cmd.stdout_lossy().trim(), "test/Contract.t.sol");
+
+    cmd.forge_fuse().args(["tree", "src/Contrct.sol"]);
+    cmd.assert_err();
+    assert!(cmd.stderr_lossy().contains("src/Contract.sol"));
+});
+
+// tests `forge tree` on a graph with shared imports and a cycle:
+// A -> B -> C -> B, A -> D -> C
+forgetest!(can_print_tree_with_cycles, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    for (name, imports) in
+        [("A", &["B", "nested/D"][..]), ("B", &["C"]), ("C", &["B"]), ("nested/D", &["C"])]
+    {
+        let imports = imports
+            .iter()
+            .map(|import| {
+                let depth = name.matches('/').count();
+                format!("import \"./{}{}.sol\";\n", "../".repeat(depth), import)
+            })
+            .collect::<String>();
+        let contract = name.rsplit('/').next().unwrap();
+        let source = format!("pragma solidity 0.8.10;\n{}contract {} {{}}\n", imports, contract);
+        prj.inner().add_source(name, source).unwrap();
+    }
+
+    cmd.args(["tree", "src/A.sol", "--charset", "ascii"]);
+    assert_eq!(
+        cmd.stdout_lossy().trim(),
+        "src/A.sol
+|-- src/B.sol
+|   `-- src/C.sol
+|       `-- src/B.sol (cycle)
+`-- src/nested/D.sol
+    `-- src/C.sol (*)"
+    );
+
+    // without de-duplication shared imports are repeated, but cycles are still cut off
+    cmd.forge_fuse().args(["tree", "src/A.sol", "--charset", "ascii", "--no-dedupe"]);
+    assert_eq!(
+        cmd.stdout_lossy().trim(),
+        "src/A.sol
+|-- src/B.sol
+|   `-- src/C.sol
+|       `-- src/B.sol (cycle)
+`-- src/nested/D.sol
+    `-- src/C.sol
+        `-- src/B.sol
+            `-- src/C.sol (cycle)"
+    );
+
+    cmd.forge_fuse().args(["tree", "--filter", "src/nested/**", "--charset", "ascii"]);
+    assert_eq!(
+        cmd.stdout_lossy().trim(),
+        "src/nested/D.sol
+`-- src/C.sol
+    `-- src/B.sol
+        `-- src/C.sol (cycle)"
+    );
+
+    cmd.forge_fuse().args(["tree", "--filter", "src/nested/**", "--depth", "1"]);
+    assert_eq!(cmd.stdout_lossy().trim().lines().count(), 2);
+});
+
+// tests that `--charset ascii` renders the same tree with ascii connectors
+forgetest!(can_print_tree_with_charsets, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    for (name, imports) in
+        [("A", "import \"./B.sol\";\nimport \"./C.sol\";\n"), ("B", ""), ("C", "")]
+    {
+        let source = format!("pragma solidity 0.8.10;\n{}contract {} {{}}\n", imports, name);
+        prj.inner().add_source(name, source).unwrap();
+    }
+
+    // both with the project's tree and with a target
+    for args in [&["tree"][..], &["tree", "src/A.sol"]] {
+        cmd.forge_fuse().args(args);
+        let utf8 = cmd.stdout_lossy();
+        cmd.forge_fuse().args(args).args(["--charset", "utf8"]);
+        assert_eq!(cmd.stdout_lossy(), utf8);
+        cmd.forge_fuse().args(args).args(["--charset", "ascii"]);
+        let ascii = cmd.stdout_lossy();
+
+        assert!(utf8.contains("├── src/B.sol") && utf8.contains("└── src/C.sol"), "{}", utf8);
+        assert!(ascii.is_ascii(), "{}", ascii);
+        assert_eq!(
+            ascii,
+            utf8.replace('├', "|").replace('└', "`").replace('─', "-").replace('│', "|")
+        );
+    }
+});
+
+// tests that `forge fmt` formats the project's sources and `--check` detects unformatted files
+forgetest!(can_format_sources, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("../testdata/fixtures/Fmt");
+    for fixture in ["Contracts.sol", "Enums.sol", "Imports.sol"] {
+        let source = fs::read_to_string(fixtures.join(fixture)).unwrap();
+        prj.inner().add_source(fixture, source).unwrap();
+    }
+
+    cmd.args(["fmt", "--check"]);
+    cmd.assert_err();
+
+    cmd.forge_fuse().arg("fmt");
+    let out = cmd.stdout_lossy();
+    assert!(out.contains("Formatted src/Enums.sol"));
+    assert!(out.contains("Formatted src/Imports.sol"));
+
+    // formatting is idempotent
+    cmd.forge_fuse().args(["fmt", "--check"]);
+    cmd.assert_empty_stdout();
+    cmd.forge_fuse().arg("fmt");
+    cmd.assert_empty_stdout();
+
+    // respects the configured tab width
+    let fmt = FormatterConfig { tab_width: 2, ..Default::default() };
+    prj.write_config(Config { fmt, ..Default::default() });
+    cmd.forge_fuse().args(["fmt", "src/Enums.sol", "--raw"]);
+    assert!(cmd.stdout_lossy().contains("contract Enums {\n  enum Side {\n    Buy,"));
+    cmd.forge_fuse().args(["fmt", "--check"]);
+    cmd.assert_err();
+});
+
+forgetest_init!(can_print_reverse_tree, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+
+    cmd.args(["tree", "--reverse", "lib/ds-test/src/test.sol", "--charset", "ascii"]);
+    let out = cmd.stdout_lossy();
+    assert_eq!(out.trim(), "test/Contract.t.sol -> lib/ds-test/src/test.sol");
+
+    // remapped paths resolve to the same file
+    cmd.forge_fuse().args(["tree", "--reverse", "ds-test/test.sol", "--charset", "ascii"]);
+    assert_eq!(cmd.stdout_lossy(), out);
+
+    // nothing imports the test file
+    cmd.forge_fuse().args(["tree", "--reverse", "test/Contract.t.sol"]);
+    assert!(cmd.stdout_lossy().trim().is_empty());
+});
+
 // Checks that quiet mode does not print anything
 forgetest!(can_init_quiet, |prj: TestProject, mut cmd: TestCommand| {
     prj.wipe();
@@ -120,8 +513,9 @@ forgetest!(can_init_vscode, |prj: TestProject, mut cmd: TestCommand| {
     assert_eq!(
         settings,
         serde_json::json!({
-             "solidity.packageDefaultDependenciesContractsDirectory": "src",
-            "solidity.packageDefaultDependenciesDirectory": "lib"
+            "solidity.packageDefaultDependenciesContractsDirectory": "src",
+            "solidity.packageDefaultDependenciesDirectory": "lib",
+            "solidity.remappings": ["ds-test/=lib/ds-test/src/"]
         })
     );
 
@@ -129,6 +523,68 @@ forgetest!(can_init_vscode, |prj: TestProject, mut cmd: TestCommand| {
     assert!(remappings.is_file());
     let content = std::fs::read_to_string(remappings).unwrap();
     assert_eq!(content, "ds-test/=lib/ds-test/src/");
+
+    let gitignore = std::fs::read_to_string(prj.root().join(".gitignore")).unwrap();
+    assert!(gitignore.lines().any(|line| line == ".vscode/"));
+});
+
+// Checks that `init --vscode` keeps existing settings
+forgetest!(can_init_vscode_with_existing_settings, |prj: TestProject, mut cmd: TestCommand| {
+    prj.wipe();
+    prj.create_file(
+        ".vscode/settings.json",
+        r#"{ "editor.formatOnSave": true, "solidity.packageDefaultDependenciesDirectory": "deps" }"#,
+    );
+
+    cmd.arg("init").arg(prj.root()).args(["--vscode", "--force"]);
+    cmd.assert_non_empty_stdout();
+
+    let settings = prj.root().join(".vscode/settings.json");
+    let settings: serde_json::Value = ethers::solc::utils::read_json_file(&settings).unwrap();
+    assert_eq!(
+        settings,
+        serde_json::json!({
+            "editor.formatOnSave": true,
+            "solidity.packageDefaultDependenciesContractsDirectory": "src",
+            "solidity.packageDefaultDependenciesDirectory": "deps",
+            "solidity.remappings": ["ds-test/=lib/ds-test/src/"]
+        })
+    );
+});
+
+// checks that `init --template` copies the files of the template without its git history
+forgetest!(can_init_from_template, |prj: TestProject, mut cmd: TestCommand| {
+    let template = prj.root().join("template");
+    prj.create_file("template/Makefile", "build:\n\tforge build\n");
+    prj.create_file("template/.github/workflows/ci.yml", "name: ci\n");
+    prj.create_file("template/src/Counter.sol", "contract Counter {}\n");
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=foundry", "-c", "user.email=foundry@example.com"])
+            .args(args)
+            .current_dir(&template)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+    };
+    git(&["init"]);
+    git(&["add", "."]);
+    git(&["commit", "-m", "template"]);
+
+    let project = prj.root().join("project");
+    cmd.arg("init").arg(&project).args(["--template", template.to_str().unwrap(), "--no-git"]);
+    cmd.assert_non_empty_stdout();
+    assert!(project.join("Makefile").is_file());
+    assert!(project.join(".github/workflows/ci.yml").is_file());
+    assert!(project.join("src/Counter.sol").is_file());
+    assert!(project.join(Config::FILE_NAME).is_file());
+    assert!(!project.join(".git").exists());
+    assert!(!project.join("src/Contract.sol").exists());
+
+    // the project dir is not empty anymore
+    cmd.forge_fuse().arg("init").arg(&project).args(["--template", template.to_str().unwrap()]);
+    cmd.assert_err();
 });
 
 // checks that `clean` removes dapptools style paths
@@ -166,6 +622,76 @@ forgetest_init!(can_clean_config, |prj: TestProject, mut cmd: TestCommand| {
     assert!(!artifact.exists());
 });
 
+// checks that `clean --cache-only` keeps the artifacts
+forgetest_init!(can_clean_cache_only, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    cmd.arg("build");
+    cmd.assert_non_empty_stdout();
+
+    let artifact = prj.paths().artifacts.join("Contract.t.sol/ContractTest.json");
+    assert!(artifact.exists());
+    assert!(prj.paths().cache.exists());
+
+    cmd.forge_fuse().args(["clean", "--cache-only"]);
+    cmd.output();
+    assert!(artifact.exists());
+    assert!(!prj.paths().cache.exists());
+});
+
+// checks that `clean --profile` only removes the artifacts of the profile
+forgetest_init!(can_clean_profile, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    prj.create_file(
+        Config::FILE_NAME,
+        r#"
+[default]
+out = "out"
+
+[ci]
+out = "ci-out"
+"#,
+    );
+    cmd.arg("build");
+    cmd.assert_non_empty_stdout();
+    cmd.forge_fuse().arg("build");
+    cmd.set_env("FOUNDRY_PROFILE", "ci");
+    cmd.assert_non_empty_stdout();
+
+    let artifact = prj.root().join("out/Contract.t.sol/ContractTest.json");
+    let ci_artifact = prj.root().join("ci-out/Contract.t.sol/ContractTest.json");
+    assert!(artifact.exists());
+    assert!(ci_artifact.exists());
+
+    cmd.forge_fuse().args(["clean", "--profile", "ci"]);
+    cmd.output();
+    assert!(artifact.exists());
+    assert!(!ci_artifact.exists());
+});
+
+// checks that `--metadata-hash none` leaves the metadata hash out of the bytecode
+forgetest!(can_build_without_metadata_hash, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner().add_source("Foo", "pragma solidity 0.8.10;\ncontract Foo {}\n").unwrap();
+    let bytecode = || {
+        let artifact: serde_json::Value =
+            ethers::solc::utils::read_json_file(prj.paths().artifacts.join("Foo.sol/Foo.json"))
+                .unwrap();
+        artifact["deployedBytecode"]["object"].as_str().unwrap().to_string()
+    };
+    // the CBOR encoded `ipfs` key, followed by the 34 byte multihash
+    let ipfs = "a264697066735822";
+
+    cmd.args(["build", "--metadata-hash", "ipfs"]).root_arg();
+    cmd.assert_non_empty_stdout();
+    assert!(bytecode().contains(ipfs));
+
+    cmd.forge_fuse().args(["build", "--metadata-hash", "none", "--force"]).root_arg();
+    cmd.assert_non_empty_stdout();
+    let bytecode = bytecode();
+    assert!(!bytecode.contains(ipfs), "{}", bytecode);
+    // only the compiler version is left, `{"solc": 0.8.10}` and its length
+    assert!(bytecode.ends_with("a164736f6c634300080a000a"), "{}", bytecode);
+});
+
 // checks that extra output works
 forgetest_init!(can_emit_extra_output, |prj: TestProject, mut cmd: TestCommand| {
     cmd.set_current_dir(prj.root());
@@ -184,6 +710,119 @@ forgetest_init!(can_emit_extra_output, |prj: TestProject, mut cmd: TestCommand|
     let _artifact: Metadata = ethers::solc::utils::read_json_file(metadata_path).unwrap();
 });
 
+forgetest!(can_flatten_multiple_files, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "A",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract A {}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "nested/B",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "../A.sol";
+contract B is A {}
+   "#,
+        )
+        .unwrap();
+    cmd.set_current_dir(prj.root());
+
+    // a single target is still printed to stdout
+    cmd.args(["flatten", "src/nested/B.sol"]);
+    let out = cmd.stdout_lossy();
+    assert!(out.contains("contract A {}"));
+    assert!(out.contains("contract B is A {}"));
+
+    cmd.forge_fuse().args(["flatten", "src"]);
+    cmd.assert_err();
+
+    cmd.forge_fuse().args(["flatten", "src", "--output", "flattened"]);
+    let out = cmd.stdout_lossy();
+    assert!(out.contains("Flattened 2 files"));
+    assert!(read_string(prj.root().join("flattened/A.sol")).contains("contract A {}"));
+    let b = read_string(prj.root().join("flattened/nested/B.sol"));
+    assert!(b.contains("contract A {}"));
+    assert!(b.contains("contract B is A {}"));
+
+    cmd.forge_fuse().args(["flatten", "src/*.sol", "--output", "flattened-glob"]);
+    cmd.assert_non_empty_stdout();
+    assert!(prj.root().join("flattened-glob/A.sol").exists());
+    assert!(!prj.root().join("flattened-glob/nested/B.sol").exists());
+});
+
+forgetest!(can_flatten_with_single_header, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "A",
+            r#"
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+contract A {}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "B",
+            r#"
+// SPDX-License-Identifier: MIT
+pragma solidity >=0.8.4 <0.9.0;
+import "./A.sol";
+contract B is A {}
+   "#,
+        )
+        .unwrap();
+    cmd.set_current_dir(prj.root());
+
+    cmd.args(["flatten", "src/B.sol"]);
+    let out = cmd.stdout_lossy();
+    assert_eq!(out.matches("SPDX-License-Identifier").count(), 1);
+    assert_eq!(out.matches("pragma solidity").count(), 1);
+    assert!(out.contains("pragma solidity >=0.8.4 <0.9.0;"));
+});
+
+forgetest!(can_compile_flattened_diamond_imports, |prj: TestProject, mut cmd: TestCommand| {
+    let sources = [
+        ("Base", "contract Base {}"),
+        ("Left", "import \"./Base.sol\";\ncontract Left is Base {}"),
+        ("Right", "import {Base} from \"./Base.sol\";\ncontract Right is Base {}"),
+        (
+            "Diamond",
+            "import \"./Right.sol\";\nimport \"./Left.sol\";\n/// @notice the diamond\ncontract Diamond is Left, Right {}",
+        ),
+    ];
+    for (name, content) in sources {
+        let content = format!(
+            "// SPDX-License-Identifier: UNLICENSED\npragma solidity 0.8.10;\n{}\n",
+            content
+        );
+        prj.inner().add_source(name, content).unwrap();
+    }
+    cmd.set_current_dir(prj.root());
+
+    let flattened = prj.root().join("Flattened.sol");
+    cmd.args(["flatten", "src/Diamond.sol", "--strip-comments", "--output"]).arg(&flattened);
+    cmd.assert_non_empty_stdout();
+    let content = read_string(&flattened);
+    assert!(!content.contains("@notice"));
+    assert!(content.find("contract Base").unwrap() < content.find("contract Left").unwrap());
+    assert!(content.find("contract Base").unwrap() < content.find("contract Right").unwrap());
+    assert!(content.find("contract Right").unwrap() < content.find("contract Diamond").unwrap());
+
+    // the flattened file compiles on its own
+    prj.wipe();
+    prj.inner().add_source("Flattened", content).unwrap();
+    cmd.forge_fuse().arg("build");
+    assert!(cmd.stdout_lossy().contains("Compiler run successful"));
+});
+
 forgetest!(can_print_warnings, |prj: TestProject, mut cmd: TestCommand| {
     prj.inner()
         .add_source(
@@ -215,6 +854,115 @@ Warning: Unused function parameter. Remove or comment out the variable name to s
     ));
 });
 
+// tests the stable shape of `forge tree --format json` and the `dot` format, with a remapped
+// import: A -> B -> dep/Dep, A -> dep/Dep
+forgetest!(can_print_tree_as_json_and_dot, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    let lib = prj.root().join("lib").join("dep").join("src");
+    fs::create_dir_all(&lib).unwrap();
+    fs::write(lib.join("Dep.sol"), "pragma solidity ^0.8.0;\ncontract Dep {}\n").unwrap();
+    fs::write(prj.root().join("remappings.txt"), "dep/=lib/dep/src/\n").unwrap();
+    prj.inner()
+        .add_source(
+            "A",
+            "pragma solidity 0.8.10;\nimport \"./B.sol\";\nimport {Dep} from \"dep/Dep.sol\";\ncontract A {}\n",
+        )
+        .unwrap();
+    prj.inner()
+        .add_source("B", "pragma solidity 0.8.10;\nimport \"dep/Dep.sol\";\ncontract B {}\n")
+        .unwrap();
+
+    // without a configured solc the latest version that satisfies all pragmas of the imports
+    cmd.args(["tree", "src/A.sol", "--format", "json"]);
+    let tree: serde_json::Value = serde_json::from_str(&cmd.stdout_lossy()).unwrap();
+    assert_eq!(tree["nodes"][1]["version"], "0.8.10");
+    assert_eq!(tree["nodes"][2]["version"], "0.8.10");
+
+    prj.write_config(Config {
+        solc: Some(SolcReq::Version("0.8.10".parse().unwrap())),
+        ..Default::default()
+    });
+    cmd.forge_fuse().args(["tree", "src/A.sol", "--format", "json"]);
+    assert_eq!(
+        cmd.stdout_lossy().trim(),
+        r#"{
+  "nodes": [
+    {
+      "id": 0,
+      "path": "lib/dep/src/Dep.sol",
+      "version": "0.8.10",
+      "pragma": "^0.8.0"
+    },
+    {
+      "id": 1,
+      "path": "src/A.sol",
+      "version": "0.8.10",
+      "pragma": "0.8.10"
+    },
+    {
+      "id": 2,
+      "path": "src/B.sol",
+      "version": "0.8.10",
+      "pragma": "0.8.10"
+    }
+  ],
+  "edges": [
+    {
+      "from": 1,
+      "to": 0,
+      "remapped": true
+    },
+    {
+      "from": 1,
+      "to": 2,
+      "remapped": false
+    },
+    {
+      "from": 2,
+      "to": 0,
+      "remapped": true
+    }
+  ]
+}"#
+    );
+
+    // without de-duplication the shared import is a node of its own for every importer
+    cmd.forge_fuse().args(["tree", "src/A.sol", "--format", "json", "--no-dedupe"]);
+    let tree: serde_json::Value = serde_json::from_str(&cmd.stdout_lossy()).unwrap();
+    assert_eq!(tree["nodes"].as_array().unwrap().len(), 4);
+    assert_eq!(tree["edges"][0], serde_json::json!({ "from": 2, "to": 1, "remapped": true }));
+
+    cmd.forge_fuse().args(["tree", "src/A.sol", "--format", "dot"]);
+    assert_eq!(
+        cmd.stdout_lossy().trim(),
+        r#"digraph {
+    0 [label="lib/dep/src/Dep.sol\n0.8.10"];
+    1 [label="src/A.sol\n0.8.10"];
+    2 [label="src/B.sol\n0.8.10"];
+    1 -> 0 [style=dashed];
+    1 -> 2;
+    2 -> 0 [style=dashed];
+}"#
+    );
+
+    // the reverse tree has an edge for every import on the chains to the target
+    cmd.forge_fuse().args(["tree", "--reverse", "lib/dep/src/Dep.sol", "--format", "dot"]);
+    assert_eq!(
+        cmd.stdout_lossy().trim(),
+        r#"digraph {
+    0 [label="lib/dep/src/Dep.sol\n0.8.10"];
+    1 [label="src/A.sol\n0.8.10"];
+    2 [label="src/B.sol\n0.8.10"];
+    1 -> 0 [style=dashed];
+    2 -> 0 [style=dashed];
+}"#
+    );
+    cmd.forge_fuse().args(["tree", "--reverse", "dep/Dep.sol", "--format", "json"]);
+    let tree: serde_json::Value = serde_json::from_str(&cmd.stdout_lossy()).unwrap();
+    assert_eq!(tree["nodes"].as_array().unwrap().len(), 3);
+    assert_eq!(tree["edges"][0], serde_json::json!({ "from": 1, "to": 0, "remapped": true }));
+});
+
 // tests that direct import paths are handled correctly
 forgetest!(can_handle_direct_imports_into_src, |prj: TestProject, mut cmd: TestCommand| {
     prj.inner()
@@ -366,6 +1114,154 @@ Gas used: 3957
     ),));
 });
 
+// Tests that the arguments of the script function can be tagged with units and are checked
+forgetest!(can_execute_run_command_with_unit_args, |prj: TestProject, mut cmd: TestCommand| {
+    let script = prj
+        .inner()
+        .add_source(
+            "Foo",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Demo {
+    event log_uint(uint);
+    function run(address who, uint256 amount) external {
+        emit log_uint(amount);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let address = "0x00000000000000000000000000000000000000aa";
+    cmd.arg("run").arg(&script).args(["--sig", "run(address,uint256)", address, "2gwei"]);
+    assert!(cmd.stdout_lossy().ends_with("== Logs ==\n  2000000000\n"));
+
+    cmd.forge_fuse().arg("run").arg(&script).args(["--sig", "run(address,uint256)", address]);
+    assert!(cmd.stderr_lossy().contains("`run(address,uint256)` expects 2 argument(s), got 1"));
+
+    cmd.forge_fuse().arg("run").arg(&script).args(["--sig", "run(address,uint256)", "0x1", "1"]);
+    assert!(cmd.stderr_lossy().contains("argument 1 of `run(address,uint256)`"));
+});
+
+// Tests that the run command prints its result as json
+forgetest!(can_execute_run_command_with_json, |prj: TestProject, mut cmd: TestCommand| {
+    let script = prj
+        .inner()
+        .add_source(
+            "Foo",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Demo {
+    event Ping(address indexed from, uint256 value);
+    uint256 public count;
+    function run() external returns (uint256 result) {
+        count = 42;
+        emit Ping(msg.sender, 7);
+        return count;
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.arg("run").arg(script).arg("--json");
+    let output: serde_json::Value = serde_json::from_str(&cmd.stdout_lossy()).unwrap();
+    assert_eq!(output["success"], true);
+    assert_eq!(output["returns"][0]["name"], "result");
+    assert_eq!(output["returns"][0]["value"], "42");
+
+    let event = &output["events"][0];
+    assert_eq!(event["name"], "Ping");
+    assert_eq!(event["args"][1]["name"], "value");
+    assert_eq!(event["args"][1]["value"], "7");
+    assert_eq!(output["logs"].as_array().unwrap().len(), 1);
+
+    let script_address = event["address"].as_str().unwrap();
+    let storage = &output["state_diffs"][script_address]["storage"];
+    assert_eq!(storage.as_object().unwrap().len(), 1);
+    assert_eq!(storage["0x0"]["to"], "0x2a");
+    assert!(output.get("traces").is_none());
+
+    cmd.arg("-vvv");
+    let output: serde_json::Value = serde_json::from_str(&cmd.stdout_lossy()).unwrap();
+    assert!(!output["traces"].as_array().unwrap().is_empty());
+});
+
+// Tests that the run command writes the transactions of the script without `--broadcast`
+forgetest!(can_write_run_transactions, |prj: TestProject, mut cmd: TestCommand| {
+    let script = prj
+        .inner()
+        .add_source(
+            "Deploy",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Counter {
+    uint256 public count;
+    function increment() external {
+        count += 1;
+    }
+}
+contract Deploy {
+    function run() external {
+        Counter counter = new Counter();
+        counter.increment();
+        require(counter.count() == 1);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.arg("run").arg(script).args(["--target-contract", "Deploy"]);
+    let output = cmd.stdout_lossy();
+    assert!(output.contains("2 transaction(s) written to"));
+
+    let sequence: serde_json::Value = ethers::solc::utils::read_json_file(
+        prj.root().join("broadcast").join("Deploy.sol").join("run-latest.json"),
+    )
+    .unwrap();
+    let transactions = sequence["transactions"].as_array().unwrap();
+    assert_eq!(transactions.len(), 2);
+
+    // the counter is created and then called by the sender, not by the script
+    let counter = transactions[0]["contract_address"].as_str().unwrap();
+    assert!(transactions[0]["transaction"]["to"].is_null());
+    assert_eq!(transactions[1]["transaction"]["to"].as_str().unwrap(), counter);
+    assert_eq!(transactions[0]["transaction"]["from"], transactions[1]["transaction"]["from"]);
+    assert!(transactions.iter().all(|tx| tx["hash"].is_null()));
+});
+
+// Tests that resuming a script that was never broadcast fails
+forgetest!(can_not_resume_without_transactions, |prj: TestProject, mut cmd: TestCommand| {
+    let script = prj
+        .inner()
+        .add_source(
+            "Deploy",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Deploy {
+    function run() external {}
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.arg("run").arg(script).args([
+        "--resume",
+        "--rpc-url",
+        "http://localhost:8545",
+        "--private-key",
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+    ]);
+    let err = cmd.stderr_lossy();
+    assert!(err.contains("No transactions to resume"), "{}", err);
+    assert!(err.contains(&format!("broadcast{}Deploy.sol", std::path::MAIN_SEPARATOR)), "{}", err);
+});
+
 // tests that the `inspect` command works correctly
 forgetest!(can_execute_inspect_command, |prj: TestProject, mut cmd: TestCommand| {
     // explicitly set to include the ipfs bytecode hash
@@ -402,6 +1298,92 @@ contract Foo {
     assert_eq!(dynamic_bytecode, output);
 });
 
+// tests that `inspect` prints the selectors and the storage layout of a contract
+forgetest!(can_inspect_selectors_and_storage_layout, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Token",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Token {
+    mapping(address => uint256) public balanceOf;
+    uint256 public totalSupply;
+
+    function transfer(address to, uint256 amount) external returns (bool) {
+        balanceOf[msg.sender] -= amount;
+        balanceOf[to] += amount;
+        return true;
+    }
+}
+    "#,
+        )
+        .unwrap();
+
+    cmd.args(["inspect", "Token", "selectors"]);
+    let selectors: BTreeMap<String, String> = serde_json::from_str(&cmd.stdout()).unwrap();
+    assert_eq!(
+        selectors,
+        BTreeMap::from([
+            ("0x18160ddd".to_string(), "totalSupply()".to_string()),
+            ("0x70a08231".to_string(), "balanceOf(address)".to_string()),
+            ("0xa9059cbb".to_string(), "transfer(address,uint256)".to_string()),
+        ])
+    );
+
+    cmd.forge_fuse().args(["inspect", "Token", "storageLayout"]);
+    let layout: serde_json::Value = serde_json::from_str(&cmd.stdout()).unwrap();
+    let labels = layout["storage"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|slot| slot["label"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(labels, vec!["balanceOf", "totalSupply"]);
+});
+
+forgetest!(can_inspect_events_and_errors, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Vault",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Vault {
+    event Transfer(address indexed from, address indexed to, uint256 amount);
+    error Unauthorized();
+    error InsufficientBalance(uint256 available, uint256 required);
+
+    function withdraw(uint256 amount) external {
+        if (amount > 0) revert InsufficientBalance(0, amount);
+        emit Transfer(address(this), msg.sender, amount);
+    }
+}
+    "#,
+        )
+        .unwrap();
+
+    cmd.args(["inspect", "Vault", "events"]);
+    let events: Vec<serde_json::Value> = serde_json::from_str(&cmd.stdout()).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["name"], "Transfer");
+    assert_eq!(
+        events[0]["topic0"],
+        "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+    );
+
+    cmd.forge_fuse().args(["inspect", "Vault", "errors"]);
+    let errors: Vec<serde_json::Value> = serde_json::from_str(&cmd.stdout()).unwrap();
+    let selectors = errors
+        .iter()
+        .map(|error| (error["name"].as_str().unwrap(), error["selector"].as_str().unwrap()))
+        .collect::<BTreeMap<_, _>>();
+    assert_eq!(
+        selectors,
+        BTreeMap::from([("InsufficientBalance", "0xcf479181"), ("Unauthorized", "0x82b42900")])
+    );
+});
+
 // test that `forge snapshot` commands work
 forgetest!(can_check_snapshot, |prj: TestProject, mut cmd: TestCommand| {
     prj.insert_ds_test();
@@ -455,6 +1437,67 @@ contract BTest is DSTest {
     let _ = cmd.output();
 });
 
+// tests that the same suite always produces the same snapshot file
+forgetest!(can_write_reproducible_snapshot, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    for contract in ["BTest", "ATest", "CTest"] {
+        prj.inner()
+            .add_source(
+                contract,
+                format!(
+                    r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract {} is DSTest {{
+    uint256 x;
+    function testWrite() public {{
+        x = 1;
+    }}
+    function testPass() public {{
+        assertTrue(true);
+    }}
+}}
+   "#,
+                    contract
+                ),
+            )
+            .unwrap();
+    }
+
+    let snapshot = prj.root().join(".gas-snapshot");
+    cmd.arg("snapshot");
+    cmd.assert_non_empty_stdout();
+    let first = fs::read(&snapshot).unwrap();
+    cmd.assert_non_empty_stdout();
+    assert_eq!(fs::read(&snapshot).unwrap(), first);
+
+    let first = String::from_utf8(first).unwrap();
+    let tests = first.lines().map(|line| line.split(' ').next().unwrap()).collect::<Vec<_>>();
+    assert_eq!(
+        tests,
+        [
+            "ATest:testPass()",
+            "ATest:testWrite()",
+            "BTest:testPass()",
+            "BTest:testWrite()",
+            "CTest:testPass()",
+            "CTest:testWrite()",
+        ]
+    );
+
+    // the snapshot can still be checked against
+    cmd.arg("--check");
+    cmd.assert_non_empty_stdout();
+
+    // the gas range only includes the cheaper tests
+    cmd.forge_fuse().args(["snapshot", "--max", "20000", "--sort", "gas", "--desc"]);
+    cmd.assert_non_empty_stdout();
+    let filtered = fs::read_to_string(&snapshot).unwrap();
+    assert_eq!(filtered.lines().count(), 3);
+    assert!(filtered.lines().all(|line| line.contains("testPass()")));
+});
+
 // test that `forge build` does not print `(with warnings)` if there arent any
 forgetest!(can_compile_without_warnings, |prj: TestProject, mut cmd: TestCommand| {
     let config = Config {
@@ -493,6 +1536,410 @@ contract A {
     );
 });
 
+// the debugger needs exactly one test, otherwise all matching tests are listed
+forgetest_init!(can_list_ambiguous_debug_tests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Other.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract OtherTest {
+    function testExample() public {}
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--debug", "testExample"]);
+    cmd.assert_err();
+    let err = cmd.stderr_lossy();
+    assert!(err.contains("2 tests matched your criteria"), "{}", err);
+    assert!(err.contains(":OtherTest::testExample()"), "{}", err);
+    assert!(err.contains(":ContractTest::testExample()"), "{}", err);
+
+    cmd.forge_fuse().args(["test", "--debug", "testMissing"]);
+    cmd.assert_err();
+    assert!(cmd.stderr_lossy().contains("No test matched your criteria"));
+});
+
+// tests that `bind` only generates bindings for the selected contracts
+forgetest!(can_bind_selected_contracts, |prj: TestProject, mut cmd: TestCommand| {
+    for contract in ["Vault", "VaultHelper", "Token"] {
+        prj.inner()
+            .add_source(
+                contract,
+                format!(
+                    r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract {} {{
+    function run() external {{}}
+}}
+   "#,
+                    contract
+                ),
+            )
+            .unwrap();
+    }
+    cmd.arg("build");
+    cmd.assert_non_empty_stdout();
+
+    let bindings = prj.root().join("bindings");
+    cmd.forge_fuse().args([
+        "bind",
+        "--module",
+        "--bindings-path",
+        bindings.to_str().unwrap(),
+        "--select",
+        "Vault*",
+        "--skip",
+        "*Helper",
+    ]);
+    cmd.assert_non_empty_stdout();
+
+    let mut files = fs::read_dir(&bindings)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(files, ["mod.rs", "vault.rs"]);
+
+    cmd.forge_fuse().args([
+        "bind",
+        "--module",
+        "--overwrite",
+        "--bindings-path",
+        bindings.to_str().unwrap(),
+        "--select",
+        "Missing*",
+    ]);
+    cmd.assert_err();
+    assert!(cmd.stderr_lossy().contains("No contract matches --select and --skip"));
+});
+
+// tests that `bind --single-file` writes all bindings as modules of a single valid Rust file
+forgetest!(can_bind_single_file, |prj: TestProject, mut cmd: TestCommand| {
+    for contract in ["Vault", "Token"] {
+        prj.inner()
+            .add_source(
+                contract,
+                format!(
+                    r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract {} {{
+    event Transfer(address indexed from, address indexed to, uint256 amount);
+    function run(uint256 amount) external returns (bool) {{}}
+}}
+   "#,
+                    contract
+                ),
+            )
+            .unwrap();
+    }
+    cmd.arg("build");
+    cmd.assert_non_empty_stdout();
+
+    let bindings = prj.root().join("bindings");
+    cmd.forge_fuse().args([
+        "bind",
+        "--module",
+        "--single-file",
+        "--bindings-path",
+        bindings.to_str().unwrap(),
+    ]);
+    cmd.assert_non_empty_stdout();
+
+    let files = fs::read_dir(&bindings).unwrap().count();
+    assert_eq!(files, 1);
+    let file = syn::parse_file(&fs::read_to_string(bindings.join("mod.rs")).unwrap()).unwrap();
+    let modules = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Mod(module) => Some(module.ident.to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert!(modules.contains(&"vault".to_string()), "{:?}", modules);
+    assert!(modules.contains(&"token".to_string()), "{:?}", modules);
+});
+
+// checks that `forge build --exclude` drops the matching files from the compiler input
+forgetest!(can_build_with_exclude, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Counter",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Counter {
+    uint256 public number;
+}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "mocks/CounterMock",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "../Counter.sol";
+contract CounterMock is Counter {}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["build", "--names", "--exclude", "src/mocks/**"]);
+    let stdout = cmd.stdout_lossy();
+    assert!(stdout.contains("Counter"));
+    assert!(!stdout.contains("CounterMock"));
+    let artifacts = prj.paths().artifacts.clone();
+    assert!(artifacts.join("Counter.sol").exists());
+    assert!(!artifacts.join("CounterMock.sol").exists());
+
+    cmd.forge_fuse().args(["build", "--exclude", "src/Counter.sol"]);
+    cmd.assert_err();
+    let stderr = cmd.stderr_lossy();
+    assert!(
+        stderr.contains("`src/mocks/CounterMock.sol` imports `src/Counter.sol`, which is excluded"),
+        "{}",
+        stderr
+    );
+});
+
+// checks that `build --sizes` reports the runtime and init code size of every contract
+forgetest!(can_build_with_sizes, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Counter",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+interface ICounter {
+    function increment() external;
+}
+contract Counter is ICounter {
+    uint256 public number;
+    function increment() external {
+        number++;
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["build", "--sizes"]);
+    let stdout = cmd.stdout_lossy();
+
+    let artifact_path = prj.paths().artifacts.join("Counter.sol/Counter.json");
+    let artifact: ConfigurableContractArtifact =
+        ethers::solc::utils::read_json_file(artifact_path).unwrap();
+    let size =
+        artifact.deployed_bytecode.unwrap().bytecode.unwrap().object.as_bytes().unwrap().0.len();
+    let init_size = artifact.bytecode.unwrap().object.as_bytes().unwrap().0.len();
+
+    let row = stdout
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .find(|row| row.first() == Some(&"Counter"))
+        .unwrap_or_else(|| panic!("no size of Counter in {}", stdout));
+    assert_eq!(row[1..], [size.to_string(), (24576 - size).to_string(), init_size.to_string()]);
+    // interfaces are not deployed
+    assert!(!stdout.contains("ICounter"));
+
+    // the sizes are reported for cached artifacts as well
+    cmd.forge_fuse().args(["build", "--sizes"]);
+    let stdout = cmd.stdout_lossy();
+    assert!(stdout.contains("No files changed, compilation skipped"));
+    assert!(stdout.contains(&size.to_string()));
+});
+
+// checks that `build --sizes` fails if a contract exceeds the EIP-170 size limit
+forgetest!(can_fail_build_with_sizes_over_limit, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Large",
+            &format!(
+                r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Large {{
+    function data() external pure returns (string memory) {{
+        return "{}";
+    }}
+}}
+   "#,
+                "a".repeat(25_000)
+            ),
+        )
+        .unwrap();
+
+    cmd.args(["build", "--sizes"]);
+    cmd.assert_err();
+    let stderr = cmd.stderr_lossy();
+    assert!(stderr.contains("exceed the runtime size limit of 24576 bytes"), "{}", stderr);
+    assert!(prj.paths().artifacts.join("Large.sol/Large.json").exists());
+});
+
+// checks that `--offline` builds with installed compilers but never downloads missing ones
+forgetest!(can_build_offline, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Counter",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity >=0.4.10;
+contract Counter {
+    uint256 public number;
+}
+   "#,
+        )
+        .unwrap();
+
+    // make sure the compiler is installed
+    cmd.args(["build", "--use", "0.8.10"]);
+    cmd.assert_non_empty_stdout();
+
+    cmd.forge_fuse().args(["build", "--offline", "--force", "--use", "0.8.10"]);
+    assert!(cmd.stdout_lossy().contains("Compiler run successful"));
+
+    cmd.forge_fuse().args(["build", "--offline", "--force", "--use", "0.4.10"]);
+    cmd.assert_err();
+    let stderr = cmd.stderr_lossy();
+    assert!(stderr.contains("can't install missing solc 0.4.10 in offline mode"), "{}", stderr);
+});
+
+// checks that `install --offline` fails instead of downloading dependencies
+forgetest!(can_not_install_offline, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    cmd.args(["install", "--offline", "--no-git", "dapphub/ds-test"]).root_arg();
+    cmd.assert_err();
+    let stderr = cmd.stderr_lossy();
+    assert!(stderr.contains("Can't install ds-test in offline mode"), "{}", stderr);
+    assert!(!prj.root().join("lib/ds-test").exists());
+});
+
+// checks that the global fork storage cache can be listed and pruned
+forgetest!(can_list_and_clean_fork_cache, |prj: TestProject, mut cmd: TestCommand| {
+    let home = prj.root().join("home");
+    let cache = home.join(".foundry").join("cache");
+    for (chain, block, size) in
+        [("mainnet", 14000000, 100), ("mainnet", 14100000, 200), ("optimism", 1, 300)]
+    {
+        let dir = cache.join(chain).join(block.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("storage.bin"), "0".repeat(size)).unwrap();
+    }
+    // the etherscan cache is not a block
+    fs::create_dir_all(cache.join("mainnet").join("etherscan")).unwrap();
+
+    cmd.set_env("HOME", home.display());
+    cmd.args(["cache", "ls", "--json"]);
+    let entries: serde_json::Value = serde_json::from_str(&cmd.stdout_lossy()).unwrap();
+    let blocks = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (entry["chain"].as_str().unwrap().to_string(), entry["block"].as_u64().unwrap())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        blocks,
+        vec![
+            ("mainnet".to_string(), 14000000),
+            ("mainnet".to_string(), 14100000),
+            ("optimism".to_string(), 1)
+        ]
+    );
+
+    cmd.forge_fuse();
+    cmd.set_env("HOME", home.display());
+    cmd.args(["cache", "ls"]);
+    let stdout = cmd.stdout_lossy();
+    assert!(stdout.contains("mainnet (300 B on disk)"));
+    assert!(stdout.contains("block 14100000"));
+
+    cmd.forge_fuse();
+    cmd.set_env("HOME", home.display());
+    cmd.args(["cache", "clean", "mainnet", "--blocks", "14000000"]);
+    assert!(cmd.stdout_lossy().contains("Removed the cache of 1 block(s), 100 B freed"));
+    assert!(!cache.join("mainnet").join("14000000").exists());
+    assert!(cache.join("mainnet").join("14100000").exists());
+
+    cmd.forge_fuse();
+    cmd.set_env("HOME", home.display());
+    cmd.args(["cache", "clean", "--max-size", "0"]);
+    cmd.stdout_lossy();
+    assert!(!cache.join("optimism").exists());
+    assert!(!cache.join("mainnet").join("14100000").exists());
+    assert!(cache.join("mainnet").join("etherscan").exists());
+});
+
+// checks that endpoint names in `--fork-url` are resolved and other values are used as urls
+forgetest_init!(can_resolve_fork_url_aliases, |prj: TestProject, mut cmd: TestCommand| {
+    cmd.set_current_dir(prj.root());
+    let config = Config {
+        rpc_endpoints: BTreeMap::from([(
+            "mainnet".to_string(),
+            "https://eth-mainnet.alchemyapi.io/v2/${ALCHEMY_KEY}".to_string(),
+        )]),
+        ..Default::default()
+    };
+    prj.write_config(config);
+
+    // not an endpoint name, so it's used as a url
+    cmd.args(["test", "--fork-url", "optimism"]);
+    cmd.assert_err();
+    assert!(!cmd.stderr_lossy().contains("RPC endpoint"));
+
+    cmd.forge_fuse().args(["test", "--fork-url", "mainnet"]);
+    cmd.unset_env("ALCHEMY_KEY");
+    cmd.assert_err();
+    assert!(cmd
+        .stderr_lossy()
+        .contains("environment variable `ALCHEMY_KEY` of RPC endpoint `mainnet` is not set"));
+});
+
+// tests that `forge create --compute-address` prints the CREATE2 address without a signer
+forgetest!(can_compute_create2_address, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Counter",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Counter {
+    uint256 public count;
+    constructor(uint256 start) {
+        count = start;
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let salt = "0x0000000000000000000000000000000000000000000000000000000000000001";
+    let mut compute = |start: &str, deployer: Option<&str>| {
+        cmd.forge_fuse().args(["create", "src/Counter.sol:Counter", "--json"]);
+        cmd.args(["--salt", salt, "--compute-address", "--constructor-args", start]);
+        if let Some(deployer) = deployer {
+            cmd.args(["--deployer", deployer]);
+        }
+        let output: serde_json::Value = serde_json::from_str(&cmd.stdout()).unwrap();
+        output["computedAddress"].as_str().unwrap().to_string()
+    };
+
+    let address = compute("1", None);
+    assert_eq!(address, compute("1", None));
+    // the constructor arguments are part of the init code
+    assert_ne!(address, compute("2", None));
+    assert_ne!(address, compute("1", Some("0x00000000000000000000000000000000deadbeef")));
+});
+
 // test against a local checkout, useful to debug with local ethers-rs patch
 forgetest_ignore!(can_compile_local_spells, |_: TestProject, mut cmd: TestCommand| {
     let current_dir = std::env::current_dir().unwrap();
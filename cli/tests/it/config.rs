@@ -1,6 +1,6 @@
 //! Contains various tests for checking forge commands related to config values
 use ethers::{
-    prelude::artifacts::YulDetails,
+    prelude::artifacts::{RevertStrings, YulDetails},
     types::{Address, U256},
 };
 use forge::executor::opts::EvmOpts;
@@ -11,9 +11,10 @@ use foundry_cli_test_utils::{
 };
 use foundry_config::{
     caching::{CachedChains, CachedEndpoints, StorageCachingConfig},
+    fmt::FormatterConfig,
     Config, OptimizerDetails, SolcReq,
 };
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{collections::BTreeMap, fs, path::PathBuf, str::FromStr};
 
 // import forge utils as mod
 #[allow(unused)]
@@ -31,6 +32,8 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         test: "test-test".into(),
         out: "out-test".into(),
         libs: vec!["lib-test".into()],
+        allow_paths: vec!["allow-test".into()],
+        include_paths: vec!["include-test".into()],
         cache: true,
         cache_path: "test-cache".into(),
         force: true,
@@ -53,6 +56,10 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         fuzz_runs: 1000,
         fuzz_max_local_rejects: 2000,
         fuzz_max_global_rejects: 100203,
+        fuzz_seed: Some(1337),
+        invariant_runs: 100,
+        invariant_depth: 20,
+        test_timeout: Some(60),
         ffi: true,
         sender: "00a329c0648769A73afAc7F9381D08FB43dBEA72".parse().unwrap(),
         tx_origin: "00a329c0648769A73afAc7F9F81E08FB43dBEA72".parse().unwrap(),
@@ -68,6 +75,7 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         block_difficulty: 10,
         block_gas_limit: Some(100.into()),
         eth_rpc_url: Some("localhost".to_string()),
+        rpc_endpoints: BTreeMap::from([("mainnet".to_string(), "localhost".to_string())]),
         etherscan_api_key: None,
         verbosity: 4,
         remappings: vec![Remapping::from_str("ds-test=lib/ds-test/").unwrap().into()],
@@ -76,6 +84,7 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         ],
         ignored_error_codes: vec![],
         via_ir: true,
+        revert_strings: Some(RevertStrings::Strip),
         rpc_storage_caching: StorageCachingConfig {
             chains: CachedChains::None,
             endpoints: CachedEndpoints::Remote,
@@ -83,6 +92,7 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         no_storage_caching: true,
         bytecode_hash: Default::default(),
         sparse_mode: true,
+        fmt: FormatterConfig { line_length: 100, tab_width: 2, bracket_spacing: true },
         __non_exhaustive: (),
     };
     prj.write_config(input.clone());
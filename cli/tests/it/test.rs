@@ -97,3 +97,273 @@ contract FailTest is DSTest {
     cmd.args(["test", "--match-path", "*src/ATest.t.sol"]);
     cmd.stdout().contains("[PASS]") && !cmd.stdout().contains("[FAIL]")
 });
+
+// tests that `--match-path` and `--no-match-path` globs are relative to the project root
+forgetest!(can_test_with_match_path_glob, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    let source = |name: &str| {
+        format!(
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "../test.sol";
+contract {} is DSTest {{
+    function testPass() external {{
+        assertTrue(true);
+    }}
+}}
+   "#,
+            name
+        )
+    };
+    prj.inner().add_source("integration/ATest.t.sol", source("ATest")).unwrap();
+    prj.inner().add_source("integration/BTest.t.sol", source("BTest")).unwrap();
+    prj.inner().add_source("unit/CTest.t.sol", source("CTest")).unwrap();
+
+    cmd.args(["test", "--match-path", "src/integration/**"]);
+    let out = cmd.stdout();
+    assert!(out.contains("ATest") && out.contains("BTest") && !out.contains("CTest"));
+
+    cmd.forge_fuse().args([
+        "test",
+        "--match-path",
+        "src/integration/**",
+        "--no-match-path",
+        "src/integration/B*",
+    ]);
+    let out = cmd.stdout();
+    assert!(out.contains("ATest") && !out.contains("BTest") && !out.contains("CTest"));
+
+    // `forge snapshot` shares the filters
+    cmd.forge_fuse().args(["snapshot", "--match-path", "src/unit/**"]);
+    cmd.assert_non_empty_stdout();
+    let snapshot = std::fs::read_to_string(prj.root().join(".gas-snapshot")).unwrap();
+    assert!(snapshot.contains("CTest:testPass()") && !snapshot.contains("ATest"));
+});
+
+// tests that `--rerun` only runs the tests that failed in the previous runs
+forgetest!(can_rerun_failed_tests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    let source = |fail: bool| {
+        format!(
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {{
+    function testPass() external {{
+        assertTrue(true);
+    }}
+
+    function testMaybeFail() external {{
+        assertTrue({});
+    }}
+
+    function testOtherMaybeFail() external {{
+        assertTrue({});
+    }}
+}}
+   "#,
+            !fail, !fail
+        )
+    };
+    prj.inner().add_source("ATest.t.sol", source(true)).unwrap();
+
+    // nothing failed yet, so all tests run and the failures are recorded
+    cmd.args(["test", "--rerun"]);
+    cmd.assert_err();
+    assert!(cmd.stdout_lossy().contains("running all tests"));
+    let failures = prj.root().join("cache").join("test-failures");
+    let recorded = std::fs::read_to_string(&failures).unwrap();
+    assert_eq!(recorded.lines().count(), 2);
+    assert!(recorded.lines().all(|line| line.contains("ATest.t.sol:ATest:test")));
+
+    // exactly the recorded failures are run
+    cmd.forge_fuse().args(["test", "--rerun", "--json"]);
+    cmd.assert_err();
+    let results: Vec<serde_json::Value> = serde_json::from_str(&cmd.stdout_lossy()).unwrap();
+    let mut tests = results.iter().map(|test| test["test"].as_str().unwrap()).collect::<Vec<_>>();
+    tests.sort_unstable();
+    assert_eq!(tests, ["testMaybeFail()", "testOtherMaybeFail()"]);
+
+    // the match filters narrow down the failures
+    cmd.forge_fuse().args(["test", "--rerun", "--json", "--match-test", "Other"]);
+    cmd.assert_err();
+    let results: Vec<serde_json::Value> = serde_json::from_str(&cmd.stdout_lossy()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["test"], "testOtherMaybeFail()");
+
+    // the tests pass now, which clears the recorded failures
+    prj.inner().add_source("ATest.t.sol", source(false)).unwrap();
+    cmd.forge_fuse().args(["test", "--rerun", "--json"]);
+    let results: Vec<serde_json::Value> = serde_json::from_str(&cmd.stdout()).unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(std::fs::read_to_string(&failures).unwrap(), "");
+
+    cmd.forge_fuse().args(["test", "--rerun"]);
+    assert!(cmd.stdout().contains("running all tests"));
+});
+
+// tests that the counterexamples of fuzz tests are stored and only removed by `forge clean --all`
+forgetest!(can_persist_fuzz_counterexamples, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "FuzzTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract FuzzTest is DSTest {
+    function testNotZero(uint256 x) external {
+        assertTrue(x != 0);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let corpus = prj.root().join("cache").join("fuzz").join("corpus.json");
+    cmd.args(["test", "--no-fuzz-persistence"]);
+    cmd.assert_err();
+    assert!(!corpus.exists());
+
+    cmd.forge_fuse().args(["test"]);
+    cmd.assert_err();
+    let stored: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&corpus).unwrap()).unwrap();
+    let (contract, tests) = stored.as_object().unwrap().iter().next().unwrap();
+    assert!(contract.ends_with("FuzzTest.t.sol:FuzzTest"));
+    assert_eq!(tests["testNotZero(uint256)"].as_array().unwrap().len(), 1);
+
+    cmd.forge_fuse().arg("clean");
+    cmd.assert_empty_stdout();
+    assert!(corpus.exists());
+
+    cmd.forge_fuse().args(["clean", "--all"]);
+    cmd.assert_empty_stdout();
+    assert!(!corpus.exists());
+});
+
+// tests that `--json` and `--json-stream` only print parseable test results
+forgetest!(can_print_json_results, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPass() external {
+        assertTrue(true);
+    }
+
+    function testFuzz(uint256 x) external {
+        assertTrue(x == x);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--json"]);
+    let results: Vec<serde_json::Value> = serde_json::from_str(&cmd.stdout()).unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|test| test["success"] == true));
+    let fuzz = results.iter().find(|test| test["test"] == "testFuzz(uint256)").unwrap();
+    assert!(fuzz["fuzz"]["runs"].as_u64().unwrap() > 0);
+    let pass = results.iter().find(|test| test["test"] == "testPass()").unwrap();
+    assert!(pass["fuzz"].is_null());
+    assert!(pass["gas"].as_u64().unwrap() > 0);
+
+    cmd.forge_fuse().args(["test", "--json-stream"]);
+    let stdout = cmd.stdout();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    for line in lines {
+        let test: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert!(test["contract"].as_str().unwrap().ends_with("ATest"));
+    }
+});
+
+// tests that `--fail-fast` skips the test contracts after the first failure
+forgetest!(can_fail_fast, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    let source = |name: &str, pass: bool| {
+        format!(
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract {} is DSTest {{
+    function testMaybeFail() external {{
+        assertTrue({});
+    }}
+}}
+   "#,
+            name, pass
+        )
+    };
+    prj.inner().add_source("ATest.t.sol", source("ATest", false)).unwrap();
+    prj.inner().add_source("BTest.t.sol", source("BTest", true)).unwrap();
+
+    // all tests run by default
+    cmd.args(["test", "--json", "--jobs", "1"]);
+    cmd.assert_err();
+    let results: Vec<serde_json::Value> = serde_json::from_str(&cmd.stdout_lossy()).unwrap();
+    assert_eq!(results.len(), 2);
+
+    // the contracts run in order of their identifiers, so `BTest` is skipped
+    cmd.forge_fuse().args(["test", "--json", "--jobs", "1", "--fail-fast"]);
+    cmd.assert_err();
+    let results: Vec<serde_json::Value> = serde_json::from_str(&cmd.stdout_lossy()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0]["contract"].as_str().unwrap().ends_with("ATest"));
+    assert_eq!(results[0]["success"], false);
+});
+
+// tests that `--junit` writes a report of all tests, including the failures
+forgetest!(can_write_junit_report, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPass() external {
+        assertTrue(true);
+    }
+
+    function testRevert() external {
+        require(false, "not allowed");
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let report = prj.root().join("reports").join("junit.xml");
+    cmd.arg("test").arg("--junit").arg(&report);
+    cmd.assert_err();
+    // the human readable output is printed as usual
+    assert!(cmd.stdout_lossy().contains("testPass()"));
+
+    let xml = std::fs::read_to_string(&report).unwrap();
+    assert!(xml.starts_with("<?xml"));
+    assert!(xml.trim_end().ends_with("</testsuites>"));
+    assert!(xml.contains(r#"<testsuites name="forge" tests="2" failures="1""#));
+    assert_eq!(xml.matches("<testcase ").count(), 2);
+    assert_eq!(xml.matches("<failure ").count(), 1);
+    assert!(xml.contains(r#"<failure message="not allowed">"#));
+});
@@ -10,7 +10,8 @@ use foundry_config::{
     },
     Config,
 };
-use serde::Serialize;
+use serde::{Serialize, Serializer};
+use std::{fmt, str::FromStr};
 
 /// `EvmArgs` and `EnvArgs` take the highest precedence in the Config/Figment hierarchy.
 /// All vars are opt-in, their default values are expected to be set by the
@@ -37,17 +38,35 @@ use serde::Serialize;
 pub struct EvmArgs {
     /// Fetch state over a remote endpoint instead of starting from an empty state.
     ///
+    /// Either a url or the name of one of the `rpc_endpoints` of the config.
+    ///
     /// If you want to fetch state from a specific block number, see --fork-block-number.
-    #[clap(long, short, alias = "rpc-url")]
-    #[serde(rename = "eth_rpc_url", skip_serializing_if = "Option::is_none")]
-    pub fork_url: Option<String>,
+    ///
+    /// Pass multiple times to fork several networks, the first endpoint is the fork the run
+    /// starts on. Forks of the other endpoints created with `createFork` are pinned to their
+    /// --fork-block-number.
+    #[clap(long, short, alias = "rpc-url", value_name = "URL", multiple_occurrences = true)]
+    #[serde(skip)]
+    pub fork_url: Vec<String>,
 
     /// Fetch state from a specific block number over a remote endpoint.
     ///
+    /// Either a block number, `latest`, `safe`, `finalized` or a number of blocks before the
+    /// latest block like `-10`. Tags are resolved against the endpoint once, the run is pinned to
+    /// the resolved block.
+    ///
+    /// Pass multiple times to pin every --fork-url, in the same order.
+    ///
     /// See --fork-url.
-    #[clap(long, requires = "fork-url")]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub fork_block_number: Option<u64>,
+    #[clap(
+        long,
+        requires = "fork-url",
+        value_name = "BLOCK",
+        allow_hyphen_values = true,
+        multiple_occurrences = true
+    )]
+    #[serde(skip)]
+    pub fork_block_number: Vec<ForkBlock>,
 
     /// Explicitly disables the use of RPC caching.
     ///
@@ -60,6 +79,26 @@ pub struct EvmArgs {
     #[serde(skip)]
     pub no_storage_caching: bool,
 
+    /// Number of times a request to a fork's endpoint is retried if it failed because of a rate
+    /// limit, a server error or a connection error.
+    #[clap(long, value_name = "RETRIES")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fork_retries: Option<u32>,
+
+    /// Initial delay in milliseconds before a failed request to a fork's endpoint is retried.
+    ///
+    /// The delay is doubled for every further retry.
+    #[clap(long, value_name = "BACKOFF")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fork_retry_backoff: Option<u64>,
+
+    /// Limits the requests to a fork's endpoint to this many compute units per second.
+    ///
+    /// Requests are priced like Alchemy does, e.g. `eth_getStorageAt` costs 17 compute units.
+    #[clap(long, alias = "cups", value_name = "CUPS")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compute_units_per_second: Option<u64>,
+
     /// The initial balance of deployed test contracts.
     #[clap(long)]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -94,6 +133,24 @@ pub struct EvmArgs {
     pub env: EnvArgs,
 }
 
+impl EvmArgs {
+    /// Returns the `--fork-block-number` of the first `--fork-url`
+    pub fn fork_block(&self) -> Option<ForkBlock> {
+        self.fork_block_number.first().copied()
+    }
+
+    /// Returns every `--fork-url` paired with its `--fork-block-number`
+    ///
+    /// The n-th block belongs to the n-th url, urls without a block are not pinned.
+    pub fn forks(&self) -> Vec<(String, Option<ForkBlock>)> {
+        self.fork_url
+            .iter()
+            .enumerate()
+            .map(|(i, url)| (url.clone(), self.fork_block_number.get(i).copied()))
+            .collect()
+    }
+}
+
 // Make this set of options a `figment::Provider` so that it can be merged into the `Config`
 impl Provider for EvmArgs {
     fn metadata(&self) -> Metadata {
@@ -118,14 +175,92 @@ impl Provider for EvmArgs {
             dict.insert("no_storage_caching".to_string(), self.no_storage_caching.into());
         }
 
-        if let Some(fork_url) = &self.fork_url {
+        if let Some(fork_url) = self.fork_url.first() {
             dict.insert("eth_rpc_url".to_string(), fork_url.clone().into());
         }
 
+        // only block numbers are merged, tags are resolved against the endpoint first
+        if let Some(number) = self.fork_block().and_then(|block| block.as_number()) {
+            dict.insert("fork_block_number".to_string(), number.into());
+        }
+
         Ok(Map::from([(Config::selected_profile(), dict)]))
     }
 }
 
+/// The block to fork from, see `--fork-block-number`
+///
+/// Only block numbers are merged into the config, tags have to be resolved against the endpoint
+/// first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForkBlock {
+    /// A block number
+    Number(u64),
+    /// The latest block
+    Latest,
+    /// The latest block that is safe from reorgs
+    Safe,
+    /// The latest finalized block
+    Finalized,
+    /// The given number of blocks before the latest block, e.g. `-10`
+    BeforeLatest(u64),
+}
+
+impl ForkBlock {
+    /// Returns the block number, `None` if the block is a tag that needs to be resolved
+    pub fn as_number(&self) -> Option<u64> {
+        match self {
+            ForkBlock::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ForkBlock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let block = match s {
+            "latest" => ForkBlock::Latest,
+            "safe" => ForkBlock::Safe,
+            "finalized" => ForkBlock::Finalized,
+            _ => match s.strip_prefix('-') {
+                Some(blocks) => ForkBlock::BeforeLatest(blocks.parse().map_err(|_| {
+                    format!("invalid number of blocks before the latest block `{}`", s)
+                })?),
+                None => ForkBlock::Number(s.parse().map_err(|_| {
+                    format!(
+                        "invalid block `{}`, expected a number, `latest`, `safe`, `finalized` or `-<blocks>`",
+                        s
+                    )
+                })?),
+            },
+        };
+        Ok(block)
+    }
+}
+
+impl fmt::Display for ForkBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForkBlock::Number(number) => write!(f, "{}", number),
+            ForkBlock::Latest => f.write_str("latest"),
+            ForkBlock::Safe => f.write_str("safe"),
+            ForkBlock::Finalized => f.write_str("finalized"),
+            ForkBlock::BeforeLatest(blocks) => write!(f, "-{}", blocks),
+        }
+    }
+}
+
+impl Serialize for ForkBlock {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ForkBlock::Number(number) => serializer.serialize_u64(*number),
+            block => serializer.collect_str(block),
+        }
+    }
+}
+
 /// Configures the executor environment during tests.
 #[derive(Debug, Clone, Default, Parser, Serialize)]
 #[clap(next_help_heading = "EXECUTOR ENVIRONMENT CONFIG")]
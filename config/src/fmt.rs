@@ -0,0 +1,20 @@
+//! Configuration specific to the `forge fmt` command
+
+use serde::{Deserialize, Serialize};
+
+/// Contains the config for `forge fmt`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatterConfig {
+    /// Maximum line length where formatter will try to wrap the line
+    pub line_length: usize,
+    /// Number of spaces per indentation level
+    pub tab_width: usize,
+    /// Print spaces between brackets
+    pub bracket_spacing: bool,
+}
+
+impl Default for FormatterConfig {
+    fn default() -> Self {
+        FormatterConfig { line_length: 80, tab_width: 4, bracket_spacing: false }
+    }
+}
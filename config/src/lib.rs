@@ -3,15 +3,19 @@
 
 use std::{
     borrow::Cow,
+    collections::BTreeMap,
     path::{Path, PathBuf},
     str::FromStr,
 };
 
-use crate::caching::StorageCachingConfig;
+use crate::{caching::StorageCachingConfig, fmt::FormatterConfig};
 use ethers_core::types::{Address, U256};
 pub use ethers_solc::artifacts::OptimizerDetails;
 use ethers_solc::{
-    artifacts::{output_selection::ContractOutputSelection, BytecodeHash, Optimizer, Settings},
+    artifacts::{
+        output_selection::ContractOutputSelection, BytecodeHash, DebuggingSettings, Optimizer,
+        RevertStrings, Settings, SettingsMetadata,
+    },
     cache::SOLIDITY_FILES_CACHE_FILENAME,
     error::SolcError,
     remappings::{RelativeRemapping, Remapping},
@@ -36,6 +40,7 @@ pub use crate::utils::*;
 
 pub mod caching;
 mod chain;
+pub mod fmt;
 pub use chain::Chain;
 
 // reexport so cli types can implement `figment::Provider` to easily merge compiler arguments
@@ -90,6 +95,13 @@ pub struct Config {
     pub out: PathBuf,
     /// all library folders to include, `lib`, `node_modules`
     pub libs: Vec<PathBuf>,
+    /// additional paths solc is allowed to read source files from, see solc's `--allow-paths`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow_paths: Vec<PathBuf>,
+    /// additional base directories imports are resolved from, passed to solc as `--include-path`
+    /// alongside `--base-path` and therefore requires a pinned `solc` version
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_paths: Vec<PathBuf>,
     /// `Remappings` to use for this repo
     pub remappings: Vec<RelativeRemapping>,
     /// library addresses to link
@@ -134,6 +146,18 @@ pub struct Config {
     pub verbosity: u8,
     /// url of the rpc server that should be used for any rpc calls
     pub eth_rpc_url: Option<String>,
+    /// RPC endpoints by name, tests can create forks of them by name with the `createFork`
+    /// cheatcode and the name can be used instead of a url in `--fork-url`
+    ///
+    /// `${VAR}` in a url is replaced with the value of the environment variable when the endpoint
+    /// is used.
+    ///
+    /// ```toml
+    /// [default.rpc_endpoints]
+    /// arbitrum = "https://arb1.arbitrum.io/rpc"
+    /// mainnet = "https://eth-mainnet.alchemyapi.io/v2/${ALCHEMY_KEY}"
+    /// ```
+    pub rpc_endpoints: BTreeMap<String, String>,
     /// etherscan API key
     pub etherscan_api_key: Option<String>,
     /// list of solidity error codes to always silence in the compiler output
@@ -202,6 +226,18 @@ pub struct Config {
     /// by proptest, to be encountered during usage of `vm.assume`
     /// cheatcode.
     pub fuzz_max_global_rejects: u32,
+    /// The seed of the fuzzer, fuzz tests generate the same inputs on every run if set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzz_seed: Option<u64>,
+    /// The number of call sequences that are run for every invariant
+    pub invariant_runs: u32,
+    /// The maximum number of calls in a call sequence, invariants are checked after every call
+    pub invariant_depth: u32,
+    /// The maximum number of seconds a single test may run before it is failed
+    ///
+    /// Fuzz tests are limited as a whole, not per run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_timeout: Option<u64>,
     /// Print the names of the compiled contracts
     pub names: bool,
     /// Print the sizes of the compiled contracts
@@ -209,6 +245,11 @@ pub struct Config {
     /// If set to true, changes compilation pipeline to go through the Yul intermediate
     /// representation.
     pub via_ir: bool,
+    /// How solc should handle revert strings, e.g. `strip` or `debug`
+    ///
+    /// If unset, solc's default handling is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_strings: Option<RevertStrings>,
     /// RPC storage caching settings determines what chains and endpoints to cache
     pub rpc_storage_caching: StorageCachingConfig,
     /// Disables storage caching entirely. This overrides any settings made in
@@ -225,6 +266,8 @@ pub struct Config {
     /// included in solc's output selection, see also
     /// [OutputSelection](ethers_solc::artifacts::output_selection::OutputSelection)
     pub sparse_mode: bool,
+    /// Configuration for `forge fmt`
+    pub fmt: FormatterConfig,
     /// The root path where the config detection started from, `Config::with_root`
     #[doc(hidden)]
     //  We're skipping serialization here, so it won't be included in the [`Config::to_string()`]
@@ -361,6 +404,8 @@ impl Config {
         self.out = p(&root, &self.out);
 
         self.libs = self.libs.into_iter().map(|lib| p(&root, &lib)).collect();
+        self.allow_paths = self.allow_paths.into_iter().map(|path| p(&root, &path)).collect();
+        self.include_paths = self.include_paths.into_iter().map(|path| p(&root, &path)).collect();
 
         self.remappings =
             self.remappings.into_iter().map(|r| RelativeRemapping::new(r.into(), &root)).collect();
@@ -385,6 +430,12 @@ impl Config {
         config.libs.sort_unstable();
         config.libs.dedup();
 
+        config.allow_paths.sort_unstable();
+        config.allow_paths.dedup();
+
+        config.include_paths.sort_unstable();
+        config.include_paths.dedup();
+
         config
     }
 
@@ -417,6 +468,8 @@ impl Config {
             .paths(self.project_paths())
             .allowed_path(&self.__root.0)
             .allowed_paths(&self.libs)
+            .allowed_paths(&self.allow_paths)
+            .allowed_paths(&self.include_paths)
             .solc_config(SolcConfig::builder().settings(self.solc_settings()?).build())
             .ignore_error_codes(self.ignored_error_codes.iter().copied().map(Into::into))
             .set_auto_detect(self.is_auto_detect())
@@ -433,13 +486,29 @@ impl Config {
             project.solc = solc;
         }
 
+        if !self.include_paths.is_empty() {
+            // `--include-path` is a flag of the solc binary, so it can only be applied to the
+            // pinned compiler and not to the versions detected per source file
+            if self.is_auto_detect() {
+                return Err(SolcError::msg(
+                    "`include_paths` require a pinned `solc` version, disable `auto_detect_solc` or set `solc`",
+                ))
+            }
+            let mut solc =
+                project.solc.clone().arg("--base-path").arg(self.__root.0.display().to_string());
+            for path in &self.include_paths {
+                solc = solc.arg("--include-path").arg(path.display().to_string());
+            }
+            project.solc = solc;
+        }
+
         Ok(project)
     }
 
     /// Ensures that the configured version is installed if explicitly set
     ///
     /// If `solc` is [`SolcReq::Version`] then this will download and install the solc version if
-    /// it's missing.
+    /// it's missing, unless `offline` is set.
     ///
     /// If `solc` is [`SolcReq::Local`] then this will ensure that the path exists.
     fn ensure_solc(&self) -> Result<Option<Solc>, SolcError> {
@@ -449,6 +518,12 @@ impl Config {
                     let v = version.to_string();
                     let mut solc = Solc::find_svm_installed_version(&v)?;
                     if solc.is_none() {
+                        if self.offline {
+                            return Err(SolcError::msg(format!(
+                                "can't install missing solc {} in offline mode",
+                                version
+                            )))
+                        }
                         Solc::blocking_install(version)?;
                         solc = Solc::find_svm_installed_version(&v)?;
                     }
@@ -556,6 +631,7 @@ impl Config {
     ///   - all libraries
     ///   - the optimizer (including details, if configured)
     ///   - evm version
+    ///   - revert strings handling, if configured
     pub fn solc_settings(&self) -> Result<Settings, SolcError> {
         let libraries = parse_libraries(&self.libraries)?;
         let optimizer = self.optimizer();
@@ -564,7 +640,12 @@ impl Config {
             optimizer,
             evm_version: Some(self.evm_version),
             libraries,
-            metadata: Some(self.bytecode_hash.into()),
+            // with `none` solc doesn't hash the metadata into the bytecode, which would change with
+            // every change of the sources' paths or comments
+            metadata: Some(SettingsMetadata {
+                use_literal_content: None,
+                bytecode_hash: Some(self.bytecode_hash),
+            }),
             ..Default::default()
         }
         .with_extra_output(self.configured_artifacts_handler().output_selection())
@@ -574,6 +655,13 @@ impl Config {
             settings = settings.with_via_ir();
         }
 
+        if let Some(revert_strings) = self.revert_strings {
+            settings.debug = Some(DebuggingSettings {
+                revert_strings: Some(revert_strings),
+                ..Default::default()
+            });
+        }
+
         Ok(settings)
     }
 
@@ -701,6 +789,8 @@ impl Config {
                 );
         }
         s = s.replace("[rpc_storage_caching]", &format!("[{}.rpc_storage_caching]", self.profile));
+        s = s.replace("[rpc_endpoints]", &format!("[{}.rpc_endpoints]", self.profile));
+        s = s.replace("[fmt]", &format!("[{}.fmt]", self.profile));
 
         Ok(format!(
             r#"[{}]
@@ -737,13 +827,13 @@ impl Config {
     }
 
     /// Returns the path to the cache file of the `block` on the `chain`
-    /// `~/.foundry/cache/<chain>/<block>/storage.json`
+    /// `~/.foundry/cache/<chain>/<block>/storage.bin`
     pub fn foundry_block_cache_file(chain_id: impl Into<Chain>, block: u64) -> Option<PathBuf> {
         Some(
             Config::foundry_cache_dir()?
                 .join(chain_id.into().to_string())
                 .join(format!("{}", block))
-                .join("storage.json"),
+                .join("storage.bin"),
         )
     }
 
@@ -893,6 +983,8 @@ impl Default for Config {
             test: "test".into(),
             out: "out".into(),
             libs: vec!["lib".into()],
+            allow_paths: vec![],
+            include_paths: vec![],
             cache: true,
             cache_path: "cache".into(),
             force: false,
@@ -911,6 +1003,10 @@ impl Default for Config {
             fuzz_runs: 256,
             fuzz_max_local_rejects: 1024,
             fuzz_max_global_rejects: 65536,
+            fuzz_seed: None,
+            invariant_runs: 256,
+            invariant_depth: 15,
+            test_timeout: None,
             ffi: false,
             sender: "00a329c0648769A73afAc7F9381E08FB43dBEA72".parse().unwrap(),
             tx_origin: "00a329c0648769A73afAc7F9381E08FB43dBEA72".parse().unwrap(),
@@ -926,6 +1022,7 @@ impl Default for Config {
             block_difficulty: 0,
             block_gas_limit: None,
             eth_rpc_url: None,
+            rpc_endpoints: Default::default(),
             etherscan_api_key: None,
             verbosity: 0,
             remappings: vec![],
@@ -933,10 +1030,12 @@ impl Default for Config {
             ignored_error_codes: vec![SolidityErrorCode::SpdxLicenseNotProvided],
             __non_exhaustive: (),
             via_ir: false,
+            revert_strings: None,
             rpc_storage_caching: Default::default(),
             no_storage_caching: false,
             bytecode_hash: BytecodeHash::Ipfs,
             sparse_mode: false,
+            fmt: Default::default(),
         }
     }
 }
@@ -1640,6 +1739,10 @@ mod tests {
                 via_ir = true
                 rpc_storage_caching = { chains = [1, "optimism", 999999], endpoints = "all"}
                 bytecode_hash = "ipfs"
+
+                [default.fmt]
+                line_length = 100
+                tab_width = 2
             "#,
             )?;
 
@@ -1663,6 +1766,7 @@ mod tests {
                         endpoints: CachedEndpoints::All
                     },
                     bytecode_hash: BytecodeHash::Ipfs,
+                    fmt: FormatterConfig { line_length: 100, tab_width: 2, ..Default::default() },
                     ..Config::default()
                 }
             );
@@ -1671,6 +1775,37 @@ mod tests {
         });
     }
 
+    #[test]
+    fn can_parse_rpc_endpoints() {
+        figment::Jail::expect_with(|jail| {
+            jail.create_file(
+                "foundry.toml",
+                r#"
+                [default]
+                eth_rpc_url = "https://example.com/"
+
+                [default.rpc_endpoints]
+                mainnet = "https://example.com/"
+                arbitrum = "https://arb1.arbitrum.io/rpc"
+            "#,
+            )?;
+
+            let config = Config::load();
+            assert_eq!(
+                config.rpc_endpoints,
+                BTreeMap::from([
+                    ("arbitrum".to_string(), "https://arb1.arbitrum.io/rpc".to_string()),
+                    ("mainnet".to_string(), "https://example.com/".to_string()),
+                ])
+            );
+
+            jail.create_file("foundry.toml", &config.to_string_pretty().unwrap())?;
+            assert_eq!(Config::load().rpc_endpoints, config.rpc_endpoints);
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn test_solc_req() {
         figment::Jail::expect_with(|jail| {
@@ -113,23 +113,21 @@ pub fn parse_libraries(
 ) -> Result<BTreeMap<String, BTreeMap<String, String>>, SolcError> {
     let mut libraries = BTreeMap::default();
     for lib in libs {
-        let mut items = lib.split(':');
-        let file = items
+        // the file may contain `:` itself, like the drive of a Windows path
+        let mut items = lib.rsplitn(3, ':');
+        let addr = items
             .next()
             .ok_or_else(|| SolcError::msg(format!("failed to parse invalid library: {}", lib)))?;
-        let lib = items
+        let name = items
             .next()
             .ok_or_else(|| SolcError::msg(format!("failed to parse invalid library: {}", lib)))?;
-        let addr = items
+        let file = items
             .next()
             .ok_or_else(|| SolcError::msg(format!("failed to parse invalid library: {}", lib)))?;
-        if items.next().is_some() {
-            return Err(SolcError::msg(format!("failed to parse invalid library: {}", lib)))
-        }
         libraries
             .entry(file.to_string())
             .or_insert_with(BTreeMap::default)
-            .insert(lib.to_string(), addr.to_string());
+            .insert(name.to_string(), addr.to_string());
     }
     Ok(libraries)
 }
@@ -0,0 +1,103 @@
+use bytes::Bytes;
+use ethers::types::H256;
+use std::collections::BTreeMap;
+
+/// The program counters hit during execution, by the hash of the executed bytecode
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct HitMaps(pub BTreeMap<H256, HitMap>);
+
+impl HitMaps {
+    /// Adds the hits of the other maps to these maps
+    pub fn merge(&mut self, other: HitMaps) {
+        for (code_hash, hit_map) in other.0 {
+            match self.0.get_mut(&code_hash) {
+                Some(existing) => existing.merge(hit_map),
+                None => {
+                    self.0.insert(code_hash, hit_map);
+                }
+            }
+        }
+    }
+}
+
+/// The program counters hit in a single bytecode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HitMap {
+    /// The executed bytecode
+    pub bytecode: Bytes,
+    /// The number of times each program counter was executed
+    pub hits: BTreeMap<usize, u64>,
+    /// The number of times each `JUMPI` jumped and didn't jump, by program counter
+    pub branches: BTreeMap<usize, (u64, u64)>,
+}
+
+impl HitMap {
+    pub fn new(bytecode: Bytes) -> Self {
+        Self { bytecode, hits: BTreeMap::new(), branches: BTreeMap::new() }
+    }
+
+    /// Records a hit of the program counter
+    pub fn hit(&mut self, pc: usize) {
+        *self.hits.entry(pc).or_default() += 1;
+    }
+
+    /// Records whether the `JUMPI` at the program counter jumped
+    pub fn branch(&mut self, pc: usize, jumped: bool) {
+        let (taken, not_taken) = self.branches.entry(pc).or_default();
+        if jumped {
+            *taken += 1;
+        } else {
+            *not_taken += 1;
+        }
+    }
+
+    /// Adds the hits of the other map to this map
+    pub fn merge(&mut self, other: HitMap) {
+        for (pc, hits) in other.hits {
+            *self.hits.entry(pc).or_default() += hits;
+        }
+        for (pc, (taken, not_taken)) in other.branches {
+            let entry = self.branches.entry(pc).or_default();
+            entry.0 += taken;
+            entry.1 += not_taken;
+        }
+    }
+}
+
+/// Merges the coverage of a call into the accumulated coverage
+pub fn merge_coverage(acc: &mut Option<HitMaps>, coverage: Option<HitMaps>) {
+    match (acc.as_mut(), coverage) {
+        (Some(acc), Some(coverage)) => acc.merge(coverage),
+        (None, Some(coverage)) => *acc = Some(coverage),
+        (_, None) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_hits_and_branches() {
+        let code = Bytes::from_static(&[0x60, 0x01, 0x57, 0x00]);
+        let hash = H256::repeat_byte(1);
+
+        let mut a = HitMap::new(code.clone());
+        a.hit(0);
+        a.hit(2);
+        a.branch(2, true);
+        let mut b = HitMap::new(code);
+        b.hit(2);
+        b.hit(3);
+        b.branch(2, false);
+
+        let mut acc = None;
+        merge_coverage(&mut acc, Some(HitMaps(BTreeMap::from([(hash, a)]))));
+        merge_coverage(&mut acc, None);
+        merge_coverage(&mut acc, Some(HitMaps(BTreeMap::from([(hash, b)]))));
+
+        let merged = &acc.unwrap().0[&hash];
+        assert_eq!(merged.hits, BTreeMap::from([(0, 1), (2, 2), (3, 1)]));
+        assert_eq!(merged.branches, BTreeMap::from([(2, (1, 1))]));
+    }
+}
@@ -43,6 +43,10 @@ ethers::contract::abigen!(
             assume(bool)
             setNonce(address,uint64)
             getNonce(address)
+            createFork(string)(uint256)
+            createFork(string,uint256)(uint256)
+            selectFork(uint256)
+            activeFork()(uint256)
     ]"#,
 );
 pub use hevm_mod::{HEVMCalls, HEVM_ABI};
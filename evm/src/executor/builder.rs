@@ -1,12 +1,12 @@
-use ethers::prelude::Provider;
+use ethers::abi::Abi;
 use revm::{
     db::{DatabaseRef, EmptyDB},
     Env, SpecId,
 };
-use std::{path::PathBuf, sync::Arc};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use super::{
-    fork::SharedBackend,
+    fork::{MultiForkConfig, RetryConfig, SharedBackend},
     inspector::{Cheatcodes, InspectorStackConfig},
     Executor,
 };
@@ -24,6 +24,8 @@ pub struct ExecutorBuilder {
     /// The configuration used to build an [InspectorStack].
     inspector_config: InspectorStackConfig,
     gas_limit: Option<U256>,
+    /// The settings for forks created with the `createFork` cheatcode
+    fork_config: MultiForkConfig,
 }
 
 /// Represents a _fork_ of a live chain whose data is available only via the `url` endpoint.
@@ -40,6 +42,8 @@ pub struct Fork {
     pub pin_block: Option<u64>,
     /// chain id retrieved from the endpoint
     pub chain_id: u64,
+    /// How requests to the endpoint are retried and rate limited
+    pub retry: RetryConfig,
 }
 
 impl Fork {
@@ -51,10 +55,9 @@ impl Fork {
     /// endpoint via channels and is intended to be cloned when multiple [revm::Database] are
     /// required. See also [crate::executor::fork::SharedBackend]
     pub async fn spawn_backend(self, env: &Env) -> SharedBackend {
-        let Fork { cache_path, url, pin_block, chain_id } = self;
+        let Fork { cache_path, url, pin_block, chain_id, retry } = self;
 
-        let provider =
-            Arc::new(Provider::try_from(url.clone()).expect("Failed to establish provider"));
+        let provider = Arc::new(retry.provider(&url).expect("Failed to establish provider"));
 
         let mut meta = BlockchainDbMeta::new(env.clone(), url);
 
@@ -137,6 +140,16 @@ impl ExecutorBuilder {
         self
     }
 
+    /// Decodes reverts that don't match an expected revert with the errors, requires cheatcodes
+    /// to be enabled
+    #[must_use]
+    pub fn with_errors(mut self, errors: Abi) -> Self {
+        if let Some(cheatcodes) = self.inspector_config.cheatcodes.as_mut() {
+            cheatcodes.errors = Some(errors);
+        }
+        self
+    }
+
     /// Enables tracing
     #[must_use]
     pub fn with_tracing(mut self) -> Self {
@@ -151,6 +164,28 @@ impl ExecutorBuilder {
         self
     }
 
+    /// Enables coverage collection
+    #[must_use]
+    pub fn with_coverage(mut self) -> Self {
+        self.inspector_config.coverage = true;
+        self
+    }
+
+    /// Halts every call that runs longer than the timeout
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.inspector_config.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the endpoints and the storage caching of the forks created with the `createFork`
+    /// cheatcode
+    #[must_use]
+    pub fn with_forks(mut self, config: MultiForkConfig) -> Self {
+        self.fork_config = config;
+        self
+    }
+
     /// Sets the EVM spec to use
     #[must_use]
     pub fn with_spec(mut self, spec: SpecId) -> Self {
@@ -178,6 +213,8 @@ impl ExecutorBuilder {
     /// Builds the executor as configured.
     pub fn build(self, db: impl Into<Backend>) -> Executor<Backend> {
         let gas_limit = self.gas_limit.unwrap_or(self.env.block.gas_limit);
-        Executor::new(db.into(), self.env, self.inspector_config, gas_limit)
+        let mut executor = Executor::new(db.into(), self.env, self.inspector_config, gas_limit);
+        executor.forks.config = self.fork_config;
+        executor
     }
 }
@@ -18,7 +18,10 @@ use futures::{
 use std::{
     collections::{hash_map::Entry, HashMap, VecDeque},
     pin::Pin,
-    sync::mpsc::{channel as oneshot_channel, Sender as OneshotSender},
+    sync::{
+        mpsc::{channel as oneshot_channel, Sender as OneshotSender},
+        Arc,
+    },
 };
 use tracing::{trace, warn};
 
@@ -34,12 +37,15 @@ enum ProviderRequest<Err> {
     BlockHash(BlockHashFuture<Err>),
 }
 
+/// The result of a request to the endpoint, the error is shared by all listeners of the request
+type FetchResult<T> = Result<T, Arc<eyre::Error>>;
+
 /// The Request type the Backend listens for
 #[derive(Debug)]
 enum BackendRequest {
-    Basic(Address, OneshotSender<AccountInfo>),
-    Storage(Address, U256, OneshotSender<U256>),
-    BlockHash(u64, OneshotSender<H256>),
+    Basic(Address, OneshotSender<FetchResult<AccountInfo>>),
+    Storage(Address, U256, OneshotSender<FetchResult<U256>>),
+    BlockHash(u64, OneshotSender<FetchResult<H256>>),
 }
 
 /// Handles an internal provider and listens for requests.
@@ -54,11 +60,11 @@ struct BackendHandler<M: Middleware> {
     /// Requests currently in progress
     pending_requests: Vec<ProviderRequest<eyre::Error>>,
     /// Listeners that wait for a `get_account` related response
-    account_requests: HashMap<Address, Vec<OneshotSender<AccountInfo>>>,
+    account_requests: HashMap<Address, Vec<OneshotSender<FetchResult<AccountInfo>>>>,
     /// Listeners that wait for a `get_storage_at` response
-    storage_requests: HashMap<(Address, U256), Vec<OneshotSender<U256>>>,
+    storage_requests: HashMap<(Address, U256), Vec<OneshotSender<FetchResult<U256>>>>,
     /// Listeners that wait for a `get_block` response
-    block_requests: HashMap<u64, Vec<OneshotSender<H256>>>,
+    block_requests: HashMap<u64, Vec<OneshotSender<FetchResult<H256>>>>,
     /// Incoming commands.
     incoming: Receiver<BackendRequest>,
     /// unprocessed queued requests
@@ -106,7 +112,7 @@ where
                 // release the lock
                 drop(lock);
                 if let Some(basic) = basic {
-                    let _ = sender.send(basic);
+                    let _ = sender.send(Ok(basic));
                 } else {
                     self.request_account(addr, sender);
                 }
@@ -117,7 +123,7 @@ where
                 // release the lock
                 drop(lock);
                 if let Some(hash) = hash {
-                    let _ = sender.send(hash);
+                    let _ = sender.send(Ok(hash));
                 } else {
                     self.request_hash(number, sender);
                 }
@@ -131,7 +137,7 @@ where
 
                 // account is already stored in the cache
                 if let Some(value) = value {
-                    let _ = sender.send(value);
+                    let _ = sender.send(Ok(value));
                 } else {
                     // account present but not storage -> fetch storage
                     self.request_account_storage(addr, idx, sender);
@@ -145,7 +151,7 @@ where
         &mut self,
         address: Address,
         idx: U256,
-        listener: OneshotSender<U256>,
+        listener: OneshotSender<FetchResult<U256>>,
     ) {
         match self.storage_requests.entry((address, idx)) {
             Entry::Occupied(mut entry) => {
@@ -185,7 +191,11 @@ where
     }
 
     /// process a request for an account
-    fn request_account(&mut self, address: Address, listener: OneshotSender<AccountInfo>) {
+    fn request_account(
+        &mut self,
+        address: Address,
+        listener: OneshotSender<FetchResult<AccountInfo>>,
+    ) {
         match self.account_requests.entry(address) {
             Entry::Occupied(mut entry) => {
                 entry.get_mut().push(listener);
@@ -198,7 +208,7 @@ where
     }
 
     /// process a request for a block hash
-    fn request_hash(&mut self, number: u64, listener: OneshotSender<H256>) {
+    fn request_hash(&mut self, number: u64, listener: OneshotSender<FetchResult<H256>>) {
         match self.block_requests.entry(number) {
             Entry::Occupied(mut entry) => {
                 entry.get_mut().push(listener);
@@ -208,13 +218,15 @@ where
                 entry.insert(vec![listener]);
                 let provider = self.provider.clone();
                 let fut = Box::pin(async move {
-                    let res = provider.get_block(number).await;
-                    let block = res.ok().flatten();
-                    let block_hash = match block {
-                        Some(block) => Ok(block
+                    let block_hash = match provider.get_block(number).await {
+                        Ok(Some(block)) => Ok(block
                             .hash
                             .expect("empty block hash on mined block, this should never happen")),
-                        None => Err(eyre::eyre!("block {} not found", number)),
+                        Ok(None) => {
+                            trace!(target: "backendhandler", "block {} not found", number);
+                            Ok(Default::default())
+                        }
+                        Err(err) => Err(eyre::eyre!(err)),
                     };
                     (block_hash, number)
                 });
@@ -260,10 +272,19 @@ where
                     ProviderRequest::Account(fut) => {
                         if let Poll::Ready((resp, addr)) = fut.poll_unpin(cx) {
                             // get the response
-                            let (balance, nonce, code) = resp.unwrap_or_else(|_| {
-                            trace!( target: "backendhandler", "Failed to get account for {}", addr);
-                            Default::default()
-                        });
+                            let (balance, nonce, code) = match resp {
+                                Ok(resp) => resp,
+                                Err(err) => {
+                                    trace!(target: "backendhandler", "Failed to get account for {}: {}", addr, err);
+                                    let err = Arc::new(err);
+                                    if let Some(listeners) = pin.account_requests.remove(&addr) {
+                                        listeners.into_iter().for_each(|l| {
+                                            let _ = l.send(Err(err.clone()));
+                                        })
+                                    }
+                                    continue
+                                }
+                            };
 
                             // convert it to revm-style types
                             let (code, code_hash) = if !code.0.is_empty() {
@@ -280,7 +301,7 @@ where
                             // notify all listeners
                             if let Some(listeners) = pin.account_requests.remove(&addr) {
                                 listeners.into_iter().for_each(|l| {
-                                    let _ = l.send(acc.clone());
+                                    let _ = l.send(Ok(acc.clone()));
                                 })
                             }
                             continue
@@ -288,10 +309,21 @@ where
                     }
                     ProviderRequest::Storage(fut) => {
                         if let Poll::Ready((resp, addr, idx)) = fut.poll_unpin(cx) {
-                            let value = resp.unwrap_or_else(|_| {
-                            trace!( target: "backendhandler", "Failed to get storage for {} at {}", addr, idx);
-                            Default::default()
-                        });
+                            let value = match resp {
+                                Ok(value) => value,
+                                Err(err) => {
+                                    trace!(target: "backendhandler", "Failed to get storage for {} at {}: {}", addr, idx, err);
+                                    let err = Arc::new(err);
+                                    if let Some(listeners) =
+                                        pin.storage_requests.remove(&(addr, idx))
+                                    {
+                                        listeners.into_iter().for_each(|l| {
+                                            let _ = l.send(Err(err.clone()));
+                                        })
+                                    }
+                                    continue
+                                }
+                            };
 
                             // update the cache
                             pin.db.storage().write().entry(addr).or_default().insert(idx, value);
@@ -299,7 +331,7 @@ where
                             // notify all listeners
                             if let Some(listeners) = pin.storage_requests.remove(&(addr, idx)) {
                                 listeners.into_iter().for_each(|l| {
-                                    let _ = l.send(value);
+                                    let _ = l.send(Ok(value));
                                 })
                             }
                             continue
@@ -307,10 +339,19 @@ where
                     }
                     ProviderRequest::BlockHash(fut) => {
                         if let Poll::Ready((block_hash, number)) = fut.poll_unpin(cx) {
-                            let value = block_hash.unwrap_or_else(|_| {
-                            trace!( target: "backendhandler", "Failed to get block hash for {}", number);
-                            Default::default()
-                        });
+                            let value = match block_hash {
+                                Ok(value) => value,
+                                Err(err) => {
+                                    trace!(target: "backendhandler", "Failed to get block hash for {}: {}", number, err);
+                                    let err = Arc::new(err);
+                                    if let Some(listeners) = pin.block_requests.remove(&number) {
+                                        listeners.into_iter().for_each(|l| {
+                                            let _ = l.send(Err(err.clone()));
+                                        })
+                                    }
+                                    continue
+                                }
+                            };
 
                             // update the cache
                             pin.db.block_hashes().write().insert(number, value);
@@ -318,7 +359,7 @@ where
                             // notify all listeners
                             if let Some(listeners) = pin.block_requests.remove(&number) {
                                 listeners.into_iter().for_each(|l| {
-                                    let _ = l.send(value);
+                                    let _ = l.send(Ok(value));
                                 })
                             }
                             continue
@@ -395,21 +436,51 @@ impl SharedBackend {
         Self { backend }
     }
 
-    fn do_get_basic(&self, address: Address) -> eyre::Result<AccountInfo> {
+    /// Same as [Self::spawn_backend()] but spawns the `BackendHandler` on a separate thread with
+    /// its own runtime
+    ///
+    /// This is useful if the backend is created outside of a runtime or has to outlive it, like
+    /// the backends of forks created by a test.
+    pub fn spawn_backend_thread<M>(
+        provider: M,
+        db: BlockchainDb,
+        pin_block: Option<BlockId>,
+    ) -> Self
+    where
+        M: Middleware + Unpin + 'static + Clone + Send,
+    {
+        let (backend, backend_rx) = channel(1);
+        let handler = BackendHandler::new(provider, db, backend_rx, pin_block);
+        trace!(target: "backendhandler", "spawning Backendhandler thread");
+        std::thread::Builder::new()
+            .name("fork-backend".to_string())
+            .spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .expect("failed to start fork backend runtime");
+                runtime.block_on(handler);
+            })
+            .expect("failed to spawn fork backend thread");
+
+        Self { backend }
+    }
+
+    fn do_get_basic(&self, address: Address) -> eyre::Result<FetchResult<AccountInfo>> {
         let (sender, rx) = oneshot_channel();
         let req = BackendRequest::Basic(address, sender);
         self.backend.clone().try_send(req).map_err(|e| eyre::eyre!("{:?}", e))?;
         Ok(rx.recv()?)
     }
 
-    fn do_get_storage(&self, address: Address, index: U256) -> eyre::Result<U256> {
+    fn do_get_storage(&self, address: Address, index: U256) -> eyre::Result<FetchResult<U256>> {
         let (sender, rx) = oneshot_channel();
         let req = BackendRequest::Storage(address, index, sender);
         self.backend.clone().try_send(req).map_err(|e| eyre::eyre!("{:?}", e))?;
         Ok(rx.recv()?)
     }
 
-    fn do_get_block_hash(&self, number: u64) -> eyre::Result<H256> {
+    fn do_get_block_hash(&self, number: u64) -> eyre::Result<FetchResult<H256>> {
         let (sender, rx) = oneshot_channel();
         let req = BackendRequest::BlockHash(number, sender);
         self.backend.clone().try_send(req).map_err(|e| eyre::eyre!("{:?}", e))?;
@@ -417,12 +488,19 @@ impl SharedBackend {
     }
 }
 
+/// Requests that failed for good fail the current call by panicking, as the state of the fork
+/// can't be determined without them. Values that could not be sent or received over the channel
+/// to the `BackendHandler` default to zero instead.
 impl DatabaseRef for SharedBackend {
     fn basic(&self, address: H160) -> AccountInfo {
-        self.do_get_basic(address).unwrap_or_else(|_| {
-            warn!( target: "sharedbackend", "Failed to send/recv `basic` for {}", address);
-            Default::default()
-        })
+        match self.do_get_basic(address) {
+            Ok(Ok(info)) => info,
+            Ok(Err(err)) => panic!("Failed to fetch account {:?} from the fork: {}", address, err),
+            Err(_) => {
+                warn!( target: "sharedbackend", "Failed to send/recv `basic` for {}", address);
+                Default::default()
+            }
+        }
     }
 
     fn code_by_hash(&self, _address: H256) -> bytes::Bytes {
@@ -430,11 +508,17 @@ impl DatabaseRef for SharedBackend {
     }
 
     fn storage(&self, address: H160, index: U256) -> U256 {
-        self.do_get_storage(address, index)
-            .unwrap_or_else(|_| {
-            warn!( target: "sharedbackend", "Failed to send/recv `storage` for {} at {}", address, index);
-            Default::default()
-        })
+        match self.do_get_storage(address, index) {
+            Ok(Ok(value)) => value,
+            Ok(Err(err)) => panic!(
+                "Failed to fetch storage of {:?} at slot {} from the fork: {}",
+                address, index, err
+            ),
+            Err(_) => {
+                warn!( target: "sharedbackend", "Failed to send/recv `storage` for {} at {}", address, index);
+                Default::default()
+            }
+        }
     }
 
     fn block_hash(&self, number: U256) -> H256 {
@@ -442,10 +526,16 @@ impl DatabaseRef for SharedBackend {
             return KECCAK_EMPTY
         }
         let number = number.as_u64();
-        self.do_get_block_hash(number).unwrap_or_else(|_| {
-            warn!( target: "sharedbackend", "Failed to send/recv `block_hash` for {}", number);
-            Default::default()
-        })
+        match self.do_get_block_hash(number) {
+            Ok(Ok(hash)) => hash,
+            Ok(Err(err)) => {
+                panic!("Failed to fetch the hash of block {} from the fork: {}", number, err)
+            }
+            Err(_) => {
+                warn!( target: "sharedbackend", "Failed to send/recv `block_hash` for {}", number);
+                Default::default()
+            }
+        }
     }
 }
 
@@ -521,7 +611,7 @@ mod tests {
     #[test]
     fn can_read_write_cache() {
         let tmpdir = tempfile::tempdir().unwrap();
-        let cache_path = tmpdir.path().join("storage.json");
+        let cache_path = tmpdir.path().join("1").join("14435000").join("storage.bin");
 
         let block_num = 14435000;
         let env = revm::Env::default();
@@ -531,6 +621,7 @@ mod tests {
             url: ENDPOINT.to_string(),
             pin_block: Some(block_num),
             chain_id: 1,
+            retry: Default::default(),
         };
 
         let runtime = RuntimeOrHandle::new();
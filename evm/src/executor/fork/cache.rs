@@ -1,13 +1,17 @@
 //! Cache related abstraction
-use ethers::types::{Address, H256, U256};
+use ethers::{
+    types::{Address, H256, U256},
+    utils::keccak256,
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use parking_lot::RwLock;
 use revm::AccountInfo;
 use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     collections::{BTreeMap, BTreeSet},
     fs,
-    io::BufWriter,
-    path::PathBuf,
+    io::{BufWriter, Read, Write},
+    path::{Path, PathBuf},
     sync::Arc,
 };
 use tracing::{trace, trace_span, warn};
@@ -164,7 +168,26 @@ pub struct MemDb {
     pub block_hashes: RwLock<BTreeMap<u64, H256>>,
 }
 
-/// A [BlockCacheDB] that stores the cached content in a json file
+/// The name of the directory next to the block directories of a chain that holds the account
+/// objects shared by the caches of all blocks of the chain
+pub const CACHE_OBJECTS_DIR: &str = "objects";
+
+/// The file name of the storage cache before it was compressed, see [JsonBlockCacheDB::load()]
+const LEGACY_CACHE_FILE_NAME: &str = "storage.json";
+
+/// Marks a compressed cache file, followed by the [CACHE_VERSION] byte
+const CACHE_MAGIC: &[u8] = b"foundry-storage-cache";
+
+/// The version of the compressed cache format
+const CACHE_VERSION: u8 = 1;
+
+/// A [BlockCacheDB] that stores the cached content in a gzipped json file
+///
+/// The cache of a block is expected at `<chain>/<block>/<file>`. The file only holds the metadata,
+/// the block hashes and a reference to an object for every account. Objects hold the info and the
+/// storage of an account and are named after the hash of their content, they are stored in the
+/// [CACHE_OBJECTS_DIR] of the chain, so the caches of different blocks share the accounts that
+/// didn't change between the blocks.
 #[derive(Debug)]
 pub struct JsonBlockCacheDB {
     /// Where this cache file is stored.
@@ -183,6 +206,10 @@ impl JsonBlockCacheDB {
 
     /// Loads the contents of the diskmap file and returns the read object
     ///
+    /// Uncompressed json files of earlier versions can still be read. If the `path` does not exist
+    /// but an uncompressed `storage.json` next to it does, it is converted to the compressed
+    /// format at `path`.
+    ///
     /// # Errors
     /// This will fail if
     ///   - the `path` does not exist
@@ -192,9 +219,18 @@ impl JsonBlockCacheDB {
         trace!(target: "cache", "reading json cache path={:?}", path);
         let span = trace_span!("cache", "path={:?}", &path);
         let _enter = span.enter();
-        let file = std::fs::File::open(&path).in_current_span()?;
-        let file = std::io::BufReader::new(file);
-        let data = serde_json::from_reader(file).in_current_span()?;
+
+        let legacy = path.with_file_name(LEGACY_CACHE_FILE_NAME);
+        if !path.exists() && legacy.exists() {
+            trace!(target: "cache", "migrating json cache path={:?}", legacy);
+            let data = read_cache_file(&legacy).in_current_span()?;
+            let cache = Self { cache_path: Some(path), data };
+            cache.flush();
+            let _ = fs::remove_file(legacy);
+            return Ok(cache)
+        }
+
+        let data = read_cache_file(&path).in_current_span()?;
         Ok(Self { cache_path: Some(path), data })
     }
 
@@ -214,28 +250,245 @@ impl JsonBlockCacheDB {
     }
 
     /// Flushes the DB to disk if caching is enabled
+    ///
+    /// Objects the cache no longer refers to are kept, other runs may be about to write a cache
+    /// file that refers to them, see [prune_objects].
     pub fn flush(&self) {
-        // writes the data to a json file
         if let Some(ref path) = self.cache_path {
             trace!(target: "cache", "saving json cache path={:?}", path);
-            if let Some(parent) = path.parent() {
-                let _ = fs::create_dir_all(parent);
+            let objects_dir = match objects_dir(path) {
+                Some(dir) => dir,
+                None => {
+                    warn!(target: "cache", "Cache path {:?} is not in a chain directory", path);
+                    return
+                }
+            };
+            let _ = fs::create_dir_all(&objects_dir);
+
+            let objects = match self.write_objects(&objects_dir) {
+                Ok(objects) => objects,
+                Err(e) => {
+                    warn!(target: "cache", "Failed to write cache objects: {}", e);
+                    return
+                }
+            };
+
+            let meta = self.data.meta.read();
+            let block_hashes = self.data.data.block_hashes.read();
+            let file = CacheFile { meta: &*meta, block_hashes: &*block_hashes, accounts: &objects };
+            let res = serde_json::to_vec(&file)
+                .map_err(Into::into)
+                .and_then(|json| write_compressed(path, &json));
+            if let Err(e) = res {
+                warn!(target: "cache", "Failed to write to json cache: {}", e);
+                return
             }
-            let _ = fs::File::create(path)
-                .map_err(|e| warn!(target: "cache", "Failed to open json cache for writing: {}", e))
-                .and_then(|f| {
-                    serde_json::to_writer(BufWriter::new(f), &self.data)
-                        .map_err(|e| warn!(target: "cache" ,"Failed to write to json cache: {}", e))
-                });
             trace!(target: "cache", "saved json cache path={:?}", path);
         }
     }
+
+    /// Writes an object for every account that doesn't exist yet and returns the objects of all
+    /// accounts
+    fn write_objects(&self, objects_dir: &Path) -> eyre::Result<BTreeMap<Address, H256>> {
+        let accounts = self.data.data.accounts.read();
+        let storage = self.data.data.storage.read();
+        let addresses: BTreeSet<_> = accounts.keys().chain(storage.keys()).collect();
+
+        let mut objects = BTreeMap::new();
+        for address in addresses {
+            let object =
+                AccountObject { info: accounts.get(address), storage: storage.get(address) };
+            let json = serde_json::to_vec(&object)?;
+            let id = H256::from(keccak256(&json));
+            let object_path = objects_dir.join(hex::encode(id));
+            // objects are immutable, an existing object has the same content
+            if !object_path.exists() {
+                write_compressed(&object_path, &json)?;
+            }
+            objects.insert(*address, id);
+        }
+        Ok(objects)
+    }
+}
+
+/// The compressed cache file of a block, see [JsonBlockCacheDB]
+#[derive(Serialize)]
+struct CacheFile<'a> {
+    meta: &'a BlockchainDbMeta,
+    block_hashes: &'a BTreeMap<u64, H256>,
+    /// The object of every account
+    accounts: &'a BTreeMap<Address, H256>,
 }
 
-/// The Data the [JsonBlockCacheDB] can read and flush
+/// The info and the storage of an account, stored in a file named after the hash of its json
+#[derive(Serialize)]
+struct AccountObject<'a> {
+    info: Option<&'a AccountInfo>,
+    storage: Option<&'a StorageInfo>,
+}
+
+/// Returns the objects dir of the chain of the cache file at `<chain>/<block>/<file>`
+fn objects_dir(cache_path: &Path) -> Option<PathBuf> {
+    Some(cache_path.parent()?.parent()?.join(CACHE_OBJECTS_DIR))
+}
+
+/// Reads a compressed or an uncompressed cache file
+fn read_cache_file(path: &Path) -> eyre::Result<JsonBlockCacheData> {
+    let content = fs::read(path)?;
+    let compressed = match decompress(&content)? {
+        Some(compressed) => compressed,
+        // uncompressed caches of earlier versions hold all data in the file itself
+        None => return Ok(serde_json::from_slice(&content)?),
+    };
+
+    #[derive(Deserialize)]
+    struct File {
+        meta: BlockchainDbMeta,
+        block_hashes: BTreeMap<u64, H256>,
+        accounts: BTreeMap<Address, H256>,
+    }
+
+    #[derive(Deserialize)]
+    struct Object {
+        info: Option<AccountInfo>,
+        storage: Option<StorageInfo>,
+    }
+
+    let File { meta, block_hashes, accounts: objects } = serde_json::from_slice(&compressed)?;
+    let objects_dir = objects_dir(path).ok_or_else(|| eyre::eyre!("not in a chain directory"))?;
+
+    let mut accounts = BTreeMap::new();
+    let mut storage = BTreeMap::new();
+    for (address, id) in &objects {
+        // a missing object only means that the account has to be fetched again
+        let object = match fs::read(objects_dir.join(hex::encode(id))) {
+            Ok(object) => object,
+            Err(e) => {
+                warn!(target: "cache", "Failed to read cache object of {:?}: {}", address, e);
+                continue
+            }
+        };
+        let json = decompress(&object)?.ok_or_else(|| eyre::eyre!("malformed cache object"))?;
+        let Object { info, storage: slots } = serde_json::from_slice(&json)?;
+        if let Some(info) = info {
+            accounts.insert(*address, info);
+        }
+        if let Some(slots) = slots {
+            storage.insert(*address, slots);
+        }
+    }
+
+    let data = JsonBlockCacheData {
+        meta: Arc::new(RwLock::new(meta)),
+        data: Arc::new(MemDb {
+            accounts: RwLock::new(accounts),
+            storage: RwLock::new(storage),
+            block_hashes: RwLock::new(block_hashes),
+        }),
+    };
+    Ok(data)
+}
+
+/// Returns the decompressed json of a compressed file or `None` if the file is not compressed
+fn decompress(content: &[u8]) -> eyre::Result<Option<Vec<u8>>> {
+    let content = match content.strip_prefix(CACHE_MAGIC) {
+        Some(content) => content,
+        None => return Ok(None),
+    };
+    match content.split_first() {
+        Some((&CACHE_VERSION, compressed)) => {
+            let mut json = Vec::new();
+            GzDecoder::new(compressed).read_to_end(&mut json)?;
+            Ok(Some(json))
+        }
+        Some((version, _)) => eyre::bail!("unsupported cache version {}", version),
+        None => eyre::bail!("missing cache version"),
+    }
+}
+
+/// Writes the json gzipped with the [CACHE_MAGIC] header
+///
+/// The file is written to a temporary file first and then moved into place, so concurrent runs
+/// sharing the cache never read a partially written file.
+fn write_compressed(path: &Path, json: &[u8]) -> eyre::Result<()> {
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    let res = (|| {
+        let mut writer = BufWriter::new(fs::File::create(&tmp)?);
+        writer.write_all(CACHE_MAGIC)?;
+        writer.write_all(&[CACHE_VERSION])?;
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        encoder.write_all(json)?;
+        encoder.finish()?.flush()?;
+        fs::rename(&tmp, path)
+    })();
+    let _ = fs::remove_file(&tmp);
+    Ok(res?)
+}
+
+/// Returns the paths of the objects the compressed cache file refers to
+///
+/// The objects are shared with the caches of other blocks of the chain, see [JsonBlockCacheDB].
+pub fn cache_objects(path: &Path) -> eyre::Result<Vec<PathBuf>> {
+    #[derive(Deserialize)]
+    struct File {
+        accounts: BTreeMap<Address, H256>,
+    }
+
+    let json = match decompress(&fs::read(path)?)? {
+        Some(json) => json,
+        None => return Ok(Vec::new()),
+    };
+    let objects_dir = objects_dir(path).ok_or_else(|| eyre::eyre!("not in a chain directory"))?;
+    let File { accounts } = serde_json::from_slice(&json)?;
+    let objects: BTreeSet<_> = accounts.into_values().collect();
+    Ok(objects.into_iter().map(|id| objects_dir.join(hex::encode(id))).collect())
+}
+
+/// Removes all objects of the chain that the cache of no block refers to
+///
+/// The objects dir is removed as well if it's empty afterwards. A flush writes its objects before
+/// the cache file that refers to them, so this must not run while forks of the chain are cached,
+/// it's only used by `forge cache clean`.
+pub fn prune_objects(chain_dir: &Path) -> eyre::Result<()> {
+    let objects_dir = chain_dir.join(CACHE_OBJECTS_DIR);
+    if !objects_dir.exists() {
+        return Ok(())
+    }
+
+    let mut referenced = BTreeSet::new();
+    for block_dir in fs::read_dir(chain_dir)? {
+        let block_dir = block_dir?.path();
+        if !block_dir.is_dir() || block_dir == objects_dir {
+            continue
+        }
+        for file in fs::read_dir(&block_dir)? {
+            let file = file?.path();
+            // anything that isn't a compressed cache file doesn't refer to objects
+            if let Ok(objects) = cache_objects(&file) {
+                referenced.extend(objects);
+            }
+        }
+    }
+
+    for object in fs::read_dir(&objects_dir)? {
+        let object = object?.path();
+        // objects that are being written by another run are not referenced yet
+        let is_tmp = object.extension().map_or(false, |ext| ext == "tmp");
+        if !is_tmp && !referenced.contains(&object) {
+            trace!(target: "cache", "removing unused cache object {:?}", object);
+            fs::remove_file(object)?;
+        }
+    }
+    if fs::read_dir(&objects_dir)?.next().is_none() {
+        fs::remove_dir(&objects_dir)?;
+    }
+    Ok(())
+}
+
+/// The Data the [JsonBlockCacheDB] holds
 ///
-/// This will be deserialized in a JSON object with the keys:
-/// `["meta", "accounts", "storage", "block_hashes"]`
+/// This is the format of the uncompressed cache files of earlier versions, a JSON object with the
+/// keys: `["meta", "accounts", "storage", "block_hashes"]`
 #[derive(Debug)]
 pub struct JsonBlockCacheData {
     pub meta: Arc<RwLock<BlockchainDbMeta>>,
@@ -294,3 +547,108 @@ impl<'de> Deserialize<'de> for JsonBlockCacheData {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a cache of the block with 50 accounts with 100 storage slots each, the value of the
+    /// first slot of the first account is `first_value`
+    fn synthetic_cache(dir: &Path, block: u64, first_value: u64) -> JsonBlockCacheDB {
+        let meta = BlockchainDbMeta {
+            cfg_env: Default::default(),
+            block_env: revm::BlockEnv { number: block.into(), ..Default::default() },
+            hosts: Default::default(),
+        };
+        let cache_path = dir.join("mainnet").join(block.to_string()).join("storage.bin");
+        let cache = JsonBlockCacheDB::new(Arc::new(RwLock::new(meta)), Some(cache_path));
+
+        let mut accounts = cache.db().accounts.write();
+        let mut storage = cache.db().storage.write();
+        for account in 0..50u64 {
+            let address = Address::from_slice(&keccak256(account.to_be_bytes())[..20]);
+            let info = AccountInfo { nonce: 1, ..Default::default() };
+            accounts.insert(address, info);
+            let slots = storage.entry(address).or_default();
+            for slot in 0..100u64 {
+                let key = U256::from_big_endian(&keccak256((account * 100 + slot).to_be_bytes()));
+                let value =
+                    if account == 0 && slot == 0 { first_value } else { account * 100 + slot };
+                slots.insert(key, U256::from(value) * U256::exp10(18));
+            }
+        }
+        drop((accounts, storage));
+        cache
+    }
+
+    fn dir_size(dir: &Path) -> u64 {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                if entry.file_type().unwrap().is_dir() {
+                    dir_size(&entry.path())
+                } else {
+                    entry.metadata().unwrap().len()
+                }
+            })
+            .sum()
+    }
+
+    // With 5000 slots, the compressed cache of a block is about 47% smaller than the uncompressed
+    // json file, the caches of two blocks that differ in a single slot are about 72% smaller than
+    // their json files, as they share the objects of 49 of the 50 accounts
+    #[test]
+    fn compresses_and_shares_storage_cache() {
+        let tmpdir = tempfile::tempdir().unwrap();
+
+        let first = synthetic_cache(tmpdir.path(), 14000000, 0);
+        let json_size = serde_json::to_vec(&first.data).unwrap().len() as u64;
+        first.flush();
+        let size = dir_size(tmpdir.path());
+        assert!(size * 10 < json_size * 6, "{} bytes compressed, {} bytes json", size, json_size);
+
+        let second = synthetic_cache(tmpdir.path(), 14000001, 1);
+        second.flush();
+        let objects = tmpdir.path().join("mainnet").join(CACHE_OBJECTS_DIR);
+        assert_eq!(fs::read_dir(&objects).unwrap().count(), 51);
+        let size = dir_size(tmpdir.path());
+        assert!(size * 100 < json_size * 2 * 35, "{} bytes compressed", size);
+
+        let cache_path = tmpdir.path().join("mainnet").join("14000001").join("storage.bin");
+        let loaded = JsonBlockCacheDB::load(&cache_path).unwrap();
+        assert_eq!(*loaded.db().accounts.read(), *second.db().accounts.read());
+        assert_eq!(*loaded.db().storage.read(), *second.db().storage.read());
+        assert_eq!(cache_objects(&cache_path).unwrap().len(), 50);
+
+        // flushing a changed block keeps the objects it no longer refers to
+        let changed = synthetic_cache(tmpdir.path(), 14000001, 2);
+        changed.flush();
+        assert_eq!(fs::read_dir(&objects).unwrap().count(), 52);
+        prune_objects(&tmpdir.path().join("mainnet")).unwrap();
+        assert_eq!(fs::read_dir(&objects).unwrap().count(), 51);
+
+        // the objects only the removed block referred to are pruned
+        fs::remove_dir_all(cache_path.parent().unwrap()).unwrap();
+        prune_objects(&tmpdir.path().join("mainnet")).unwrap();
+        assert_eq!(fs::read_dir(&objects).unwrap().count(), 50);
+    }
+
+    #[test]
+    fn migrates_json_cache() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let cache = synthetic_cache(tmpdir.path(), 14000000, 0);
+        let cache_path = cache.cache_path.clone().unwrap();
+        let legacy = cache_path.with_file_name(LEGACY_CACHE_FILE_NAME);
+        fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        fs::write(&legacy, serde_json::to_vec(&cache.data).unwrap()).unwrap();
+
+        let loaded = JsonBlockCacheDB::load(&cache_path).unwrap();
+        assert_eq!(*loaded.db().storage.read(), *cache.db().storage.read());
+        assert!(!legacy.exists());
+        assert!(decompress(&fs::read(&cache_path).unwrap()).unwrap().is_some());
+
+        let loaded = JsonBlockCacheDB::load(&cache_path).unwrap();
+        assert_eq!(*loaded.db().storage.read(), *cache.db().storage.read());
+    }
+}
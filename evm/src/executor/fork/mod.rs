@@ -5,4 +5,15 @@ mod init;
 pub use init::environment;
 
 mod cache;
-pub use cache::{BlockchainDb, BlockchainDbMeta, JsonBlockCacheDB};
+pub use cache::{
+    cache_objects, prune_objects, BlockchainDb, BlockchainDbMeta, JsonBlockCacheDB,
+    CACHE_OBJECTS_DIR,
+};
+
+mod provider;
+pub use provider::{RetryClient, RetryClientError, RetryConfig, RetryProvider};
+
+mod multi;
+pub use multi::{
+    resolve_endpoint, CreatedFork, ForkCachePath, ForkedDatabase, Forks, MultiFork, MultiForkConfig,
+};
@@ -0,0 +1,437 @@
+//! Support for creating and selecting multiple forks during execution, see the `createFork` and
+//! `selectFork` cheatcodes
+use super::{environment, BlockchainDb, BlockchainDbMeta, RetryConfig, SharedBackend};
+use crate::executor::StateChangeset;
+use ethers::types::{Address, H160, H256, U256};
+use foundry_utils::RuntimeOrHandle;
+use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use revm::{
+    db::{CacheDB, DatabaseCommit, DatabaseRef},
+    AccountInfo, Env,
+};
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+    fmt,
+    path::PathBuf,
+    sync::Arc,
+};
+
+/// Returns the storage cache file of a fork by endpoint, chain id and block, or `None` if the
+/// storage of the fork should not be cached
+pub type ForkCachePath = Arc<dyn Fn(&str, u64, u64) -> Option<PathBuf> + Send + Sync>;
+
+/// Returns the url of the endpoint, which is either the name of one of the `rpc_endpoints` or a url
+///
+/// `${VAR}` in the url of a named endpoint is replaced with the value of the environment variable
+/// `VAR`, so API keys don't have to be written into the config, e.g.
+/// `mainnet = "https://eth-mainnet.alchemyapi.io/v2/${ALCHEMY_KEY}"`.
+pub fn resolve_endpoint(
+    endpoint: &str,
+    rpc_endpoints: &BTreeMap<String, String>,
+) -> eyre::Result<String> {
+    if let Some(url) = rpc_endpoints.get(endpoint) {
+        return interpolate_env_vars(url).map_err(|var| {
+            eyre::eyre!("environment variable `{}` of RPC endpoint `{}` is not set", var, endpoint)
+        })
+    }
+    if endpoint.contains("://") {
+        return Ok(endpoint.to_string())
+    }
+    let known = if rpc_endpoints.is_empty() {
+        "there are no named endpoints".to_string()
+    } else {
+        format!("known endpoints: {}", rpc_endpoints.keys().cloned().collect::<Vec<_>>().join(", "))
+    };
+    eyre::bail!(
+        "unknown RPC endpoint `{}` ({}), add it to the `rpc_endpoints` table of foundry.toml",
+        endpoint,
+        known
+    )
+}
+
+/// Replaces every `${VAR}` with the value of the environment variable, returns the name of the
+/// first variable that is not set
+fn interpolate_env_vars(s: &str) -> Result<String, String> {
+    let mut res = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let var = &rest[start + 2..end];
+        res.push_str(&rest[..start]);
+        res.push_str(&std::env::var(var).map_err(|_| var.to_string())?);
+        rest = &rest[end + 1..];
+    }
+    res.push_str(rest);
+    Ok(res)
+}
+
+/// The settings for forks that are created during execution
+///
+/// All clones share the backends of the forks created so far, so every endpoint is only forked
+/// once per block.
+#[derive(Clone, Default)]
+pub struct MultiForkConfig {
+    /// The endpoints that can be forked by name, the `rpc_endpoints` of the config
+    pub rpc_endpoints: BTreeMap<String, String>,
+    /// Where the storage of a fork is cached, if it is cached
+    pub cache_path: Option<ForkCachePath>,
+    /// How the requests of the forks are retried and rate limited
+    pub retry: RetryConfig,
+    /// The blocks forks of these endpoints are pinned to if no block is requested, by url
+    pub pinned_blocks: BTreeMap<String, u64>,
+    /// The backends of the forks created so far, by endpoint url and requested block
+    backends: Arc<Mutex<HashMap<(String, Option<u64>), (SharedBackend, Env)>>>,
+}
+
+impl MultiForkConfig {
+    pub fn new(rpc_endpoints: BTreeMap<String, String>) -> Self {
+        Self { rpc_endpoints, ..Default::default() }
+    }
+
+    /// Caches the storage of every fork in the file returned by `cache_path`, see [ForkCachePath]
+    #[must_use]
+    pub fn with_cache_path(
+        mut self,
+        cache_path: impl Fn(&str, u64, u64) -> Option<PathBuf> + Send + Sync + 'static,
+    ) -> Self {
+        self.cache_path = Some(Arc::new(cache_path));
+        self
+    }
+
+    /// Retries and rate limits the requests of the forks as configured
+    #[must_use]
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Pins forks of the endpoint `url` to the `block` if they are created without a block
+    #[must_use]
+    pub fn with_pinned_block(mut self, url: impl Into<String>, block: u64) -> Self {
+        self.pinned_blocks.insert(url.into(), block);
+        self
+    }
+
+    /// Returns the url of the endpoint, which is either the name of one of the `rpc_endpoints` or
+    /// a url, see [resolve_endpoint]
+    pub fn endpoint_url(&self, endpoint: &str) -> eyre::Result<String> {
+        resolve_endpoint(endpoint, &self.rpc_endpoints)
+    }
+
+    /// Returns a fork of the endpoint at the block, or at the latest block
+    fn fork(
+        &self,
+        endpoint: &str,
+        block: Option<u64>,
+        origin: Address,
+    ) -> eyre::Result<CreatedFork> {
+        let url = self.endpoint_url(endpoint)?;
+        let block = block.or_else(|| self.pinned_blocks.get(&url).copied());
+        let mut backends = self.backends.lock();
+        let (backend, env) = match backends.entry((url.clone(), block)) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => entry.insert(self.spawn_backend(&url, block, origin)?).clone(),
+        };
+        Ok(CreatedFork { url, env, db: CacheDB::new(backend) })
+    }
+
+    /// Spawns a backend for the endpoint that is pinned to the block, or to the latest block
+    fn spawn_backend(
+        &self,
+        url: &str,
+        block: Option<u64>,
+        origin: Address,
+    ) -> eyre::Result<(SharedBackend, Env)> {
+        let provider = self.retry.provider(url)?;
+        let env = RuntimeOrHandle::new().block_on(environment(&provider, None, block, origin))?;
+
+        // pin the fork to the block so its storage can be cached
+        let block = env.block.number.as_u64();
+        let cache_path = self
+            .cache_path
+            .as_ref()
+            .and_then(|cache_path| cache_path(url, env.cfg.chain_id.as_u64(), block));
+        let db = BlockchainDb::new(BlockchainDbMeta::new(env.clone(), url.to_string()), cache_path);
+        let backend =
+            SharedBackend::spawn_backend_thread(Arc::new(provider), db, Some(block.into()));
+        Ok((backend, env))
+    }
+}
+
+impl fmt::Debug for MultiForkConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultiForkConfig")
+            .field("rpc_endpoints", &self.rpc_endpoints)
+            .field("cache_path", &self.cache_path.is_some())
+            .field("retry", &self.retry)
+            .field("pinned_blocks", &self.pinned_blocks)
+            .finish()
+    }
+}
+
+/// A fork that was created during execution
+#[derive(Clone)]
+pub struct CreatedFork {
+    /// The url of the forked endpoint
+    pub url: String,
+    /// The environment of the forked block
+    pub env: Env,
+    /// The changes committed to the fork on top of the remote state
+    pub db: CacheDB<SharedBackend>,
+}
+
+/// The forks created during execution and the fork that is selected, if any
+///
+/// Accounts that were committed while no fork was selected, like the test contract and the
+/// contracts it deployed in `setUp()`, are persistent and visible on every fork. All other accounts
+/// are loaded from and committed to the selected fork, so they never leak into another fork.
+#[derive(Clone, Default)]
+pub struct Forks {
+    /// The settings for creating forks
+    pub config: MultiForkConfig,
+    /// The forks by id
+    forks: Vec<CreatedFork>,
+    /// The id of the selected fork
+    active: Option<usize>,
+    /// The accounts that are visible on every fork
+    persistent: HashSet<Address>,
+    /// The accounts the current call loaded before it selected another fork, by the id of the
+    /// fork they were loaded from or `None` if they were loaded before any fork was selected
+    journals: HashMap<Option<usize>, StateChangeset>,
+}
+
+impl Forks {
+    pub fn new(config: MultiForkConfig) -> Self {
+        Self { config, ..Default::default() }
+    }
+
+    /// Returns the id of the selected fork
+    pub fn active_id(&self) -> Option<usize> {
+        self.active
+    }
+
+    /// Returns the selected fork
+    pub fn active_fork(&self) -> Option<&CreatedFork> {
+        self.active.map(|id| &self.forks[id])
+    }
+
+    /// Whether the account is visible on every fork
+    pub fn is_persistent(&self, address: &Address) -> bool {
+        self.persistent.contains(address)
+    }
+
+    /// Makes the accounts visible on every fork
+    pub fn make_persistent(&mut self, accounts: impl IntoIterator<Item = Address>) {
+        self.persistent.extend(accounts)
+    }
+
+    /// Creates a fork of the endpoint at the block, or at the latest block, and returns its id
+    pub fn create_fork(
+        &mut self,
+        endpoint: &str,
+        block: Option<u64>,
+        origin: Address,
+    ) -> eyre::Result<usize> {
+        self.forks.push(self.config.fork(endpoint, block, origin)?);
+        Ok(self.forks.len() - 1)
+    }
+
+    /// Selects the fork
+    ///
+    /// The accounts of the previous fork are moved out of the `journal` of the current call and
+    /// the accounts the call loaded from this fork before are moved back in.
+    ///
+    /// *Note:* The accounts are moved out of the journal directly, so a fork should not be selected
+    /// in a call that reverts afterwards.
+    pub fn select_fork(
+        &mut self,
+        id: U256,
+        journal: &mut StateChangeset,
+    ) -> eyre::Result<&CreatedFork> {
+        eyre::ensure!(id < self.forks.len().into(), "fork {} does not exist", id);
+        let id = id.as_usize();
+        if self.active != Some(id) {
+            let previous = self.journals.entry(self.active).or_default();
+            move_fork_accounts(&self.persistent, journal, previous);
+            journal.extend(self.journals.remove(&Some(id)).unwrap_or_default());
+            self.active = Some(id);
+        }
+        Ok(&self.forks[id])
+    }
+
+    /// Commits the changes of a call
+    ///
+    /// The changes to the accounts of a fork are committed to that fork, all other changes are
+    /// committed to `db` and the changed accounts become persistent.
+    pub fn commit<DB: DatabaseCommit>(&mut self, db: &mut DB, mut changeset: StateChangeset) {
+        let mut journals = std::mem::take(&mut self.journals);
+        if let Some(id) = self.active {
+            move_fork_accounts(
+                &self.persistent,
+                &mut changeset,
+                journals.entry(Some(id)).or_default(),
+            );
+        }
+        for (id, accounts) in journals {
+            match id {
+                Some(id) => self.forks[id].db.commit(accounts),
+                None => changeset.extend(accounts),
+            }
+        }
+        self.persistent.extend(changeset.keys().copied());
+        db.commit(changeset);
+    }
+}
+
+/// Moves all accounts that are not persistent from `from` to `to`
+fn move_fork_accounts(
+    persistent: &HashSet<Address>,
+    from: &mut StateChangeset,
+    to: &mut StateChangeset,
+) {
+    let accounts =
+        from.keys().filter(|address| !persistent.contains(*address)).copied().collect::<Vec<_>>();
+    for address in accounts {
+        if let Some(account) = from.remove(&address) {
+            to.insert(address, account);
+        }
+    }
+}
+
+/// A handle to the [Forks] of a single call, shared by the database and the cheatcodes of the call
+#[derive(Clone, Default)]
+pub struct MultiFork(Arc<RwLock<Forks>>);
+
+impl MultiFork {
+    pub fn new(forks: Forks) -> Self {
+        Self(Arc::new(RwLock::new(forks)))
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, Forks> {
+        self.0.read()
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, Forks> {
+        self.0.write()
+    }
+
+    /// Takes the forks out of the handle, to persist them after the call
+    pub fn take(&self) -> Forks {
+        std::mem::take(&mut *self.0.write())
+    }
+}
+
+impl fmt::Debug for MultiFork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MultiFork").field(&self.read().active).finish()
+    }
+}
+
+/// A database that reads the accounts of the selected fork from that fork and all persistent
+/// accounts, or all accounts if no fork is selected, from the underlying database
+pub struct ForkedDatabase<'a, DB> {
+    db: &'a DB,
+    forks: &'a MultiFork,
+}
+
+impl<'a, DB> ForkedDatabase<'a, DB> {
+    pub fn new(db: &'a DB, forks: &'a MultiFork) -> Self {
+        Self { db, forks }
+    }
+}
+
+impl<'a, DB: DatabaseRef> DatabaseRef for ForkedDatabase<'a, DB> {
+    fn basic(&self, address: H160) -> AccountInfo {
+        let forks = self.forks.read();
+        match forks.active_fork() {
+            Some(fork) if !forks.is_persistent(&address) => fork.db.basic(address),
+            _ => self.db.basic(address),
+        }
+    }
+
+    fn code_by_hash(&self, code_hash: H256) -> bytes::Bytes {
+        match self.forks.read().active_fork() {
+            Some(fork) => fork.db.code_by_hash(code_hash),
+            None => self.db.code_by_hash(code_hash),
+        }
+    }
+
+    fn storage(&self, address: H160, index: U256) -> U256 {
+        let forks = self.forks.read();
+        match forks.active_fork() {
+            Some(fork) if !forks.is_persistent(&address) => fork.db.storage(address, index),
+            _ => self.db.storage(address, index),
+        }
+    }
+
+    fn block_hash(&self, number: U256) -> H256 {
+        match self.forks.read().active_fork() {
+            Some(fork) => fork.db.block_hash(number),
+            None => self.db.block_hash(number),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_endpoint_urls() {
+        let config = MultiForkConfig::new(BTreeMap::from([(
+            "arbitrum".to_string(),
+            "https://arb1.arbitrum.io/rpc".to_string(),
+        )]));
+
+        assert_eq!(config.endpoint_url("arbitrum").unwrap(), "https://arb1.arbitrum.io/rpc");
+        assert_eq!(config.endpoint_url("http://localhost:8545").unwrap(), "http://localhost:8545");
+        let err = config.endpoint_url("optimism").unwrap_err().to_string();
+        assert!(err.contains("rpc_endpoints"));
+        assert!(err.contains("known endpoints: arbitrum"));
+    }
+
+    #[test]
+    fn interpolates_env_vars_of_endpoints() {
+        std::env::set_var("FOUNDRY_MULTI_FORK_TEST_KEY", "secret");
+        let endpoints = BTreeMap::from([
+            (
+                "mainnet".to_string(),
+                "https://eth-mainnet.alchemyapi.io/v2/${FOUNDRY_MULTI_FORK_TEST_KEY}".to_string(),
+            ),
+            ("missing".to_string(), "https://${FOUNDRY_MULTI_FORK_TEST_MISSING}/rpc".to_string()),
+        ]);
+
+        assert_eq!(
+            resolve_endpoint("mainnet", &endpoints).unwrap(),
+            "https://eth-mainnet.alchemyapi.io/v2/secret"
+        );
+        let err = resolve_endpoint("missing", &endpoints).unwrap_err().to_string();
+        assert!(
+            err.contains("`FOUNDRY_MULTI_FORK_TEST_MISSING` of RPC endpoint `missing`"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn names_endpoint_of_failed_requests() {
+        let config = MultiForkConfig::default()
+            .with_retry(RetryConfig { max_retries: 0, ..Default::default() });
+        let err = config.fork("http://127.0.0.1:1", None, Address::zero()).err().unwrap();
+        assert!(
+            err.to_string().contains("`eth_blockNumber` request to 127.0.0.1 failed"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn can_not_select_missing_fork() {
+        let mut forks = Forks::default();
+        assert!(forks.active_id().is_none());
+        assert!(forks.select_fork(0u64.into(), &mut StateChangeset::default()).is_err());
+    }
+}
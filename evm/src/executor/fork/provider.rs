@@ -0,0 +1,244 @@
+//! A JSON-RPC client for forks that retries failed requests and limits the request rate
+use async_trait::async_trait;
+use ethers::providers::{Http, HttpClientError, JsonRpcClient, Provider, ProviderError};
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::trace;
+use url::Url;
+
+/// A provider whose requests are retried and rate limited, see [RetryConfig]
+pub type RetryProvider = Provider<RetryClient>;
+
+/// How the requests of a fork to its endpoint are retried and rate limited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// How often a request that failed because of a rate limit, a server error or a transport
+    /// error is retried
+    pub max_retries: u32,
+    /// The delay before the first retry, which is doubled for every further retry up to
+    /// [MAX_BACKOFF]
+    pub initial_backoff: Duration,
+    /// Limits the requests to this many compute units per second, see [compute_units()]
+    pub compute_units_per_second: Option<u64>,
+}
+
+/// The longest delay between two retries of a request
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            compute_units_per_second: None,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Returns a provider for the endpoint that retries and rate limits its requests
+    pub fn provider(&self, url: &str) -> eyre::Result<RetryProvider> {
+        Ok(Provider::new(RetryClient::new(Url::parse(url)?, *self)))
+    }
+
+    /// Returns the delay before the retry that follows the given number of retries
+    pub fn backoff(&self, retries: u32) -> Duration {
+        self.initial_backoff.saturating_mul(2u32.saturating_pow(retries)).min(MAX_BACKOFF)
+    }
+}
+
+/// A [JsonRpcClient] over HTTP that retries requests that failed because of rate limits, server
+/// errors or transport errors with exponential backoff
+#[derive(Debug, Clone)]
+pub struct RetryClient {
+    inner: Http,
+    /// The host of the endpoint, errors only name the host so they don't leak API keys in the url
+    endpoint: String,
+    config: RetryConfig,
+    /// The earliest time the next request can be sent without exceeding the rate limit
+    next_request: Arc<Mutex<Instant>>,
+}
+
+impl RetryClient {
+    pub fn new(url: Url, config: RetryConfig) -> Self {
+        let endpoint = url.host_str().map(str::to_string).unwrap_or_else(|| url.to_string());
+        Self {
+            inner: Http::new(url),
+            endpoint,
+            config,
+            next_request: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Reserves the compute units of the request and returns when it can be sent
+    fn schedule(&self, method: &str) -> Instant {
+        let now = Instant::now();
+        let compute_units_per_second = match self.config.compute_units_per_second {
+            Some(compute_units_per_second) if compute_units_per_second > 0 => {
+                compute_units_per_second
+            }
+            _ => return now,
+        };
+        let cost =
+            Duration::from_nanos(compute_units(method) * 1_000_000_000 / compute_units_per_second);
+        let mut next_request = self.next_request.lock();
+        let start = (*next_request).max(now);
+        *next_request = start + cost;
+        start
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for RetryClient {
+    type Error = RetryClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let mut retries = 0;
+        loop {
+            tokio::time::sleep_until(self.schedule(method).into()).await;
+            let err = match self.inner.request(method, &params).await {
+                Ok(res) => return Ok(res),
+                Err(err) => err,
+            };
+
+            if retries >= self.config.max_retries || !is_retryable(&err) {
+                return Err(RetryClientError {
+                    endpoint: self.endpoint.clone(),
+                    method: method.to_string(),
+                    retries,
+                    source: err,
+                })
+            }
+            let backoff = self.config.backoff(retries);
+            trace!(target: "forkprovider", "`{}` request to {} failed, retrying in {:?}: {}", method, self.endpoint, backoff, err);
+            retries += 1;
+            tokio::time::sleep(backoff).await;
+        }
+    }
+}
+
+/// A request that failed for good
+#[derive(Debug, thiserror::Error)]
+#[error("`{method}` request to {endpoint} failed after {retries} retries: {source}")]
+pub struct RetryClientError {
+    /// The host of the endpoint
+    pub endpoint: String,
+    /// The JSON-RPC method of the request
+    pub method: String,
+    /// How often the request was retried
+    pub retries: u32,
+    source: HttpClientError,
+}
+
+impl From<RetryClientError> for ProviderError {
+    fn from(err: RetryClientError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(err))
+    }
+}
+
+/// Whether the request may succeed if it is sent again
+fn is_retryable(err: &HttpClientError) -> bool {
+    match err {
+        HttpClientError::ReqwestError(err) => {
+            err.is_timeout() ||
+                err.is_connect() ||
+                err.is_request() ||
+                err.status()
+                    .map_or(false, |status| status.as_u16() == 429 || status.is_server_error())
+        }
+        HttpClientError::JsonRpcError(err) => {
+            // 429 is used by Alchemy and -32005 by Infura when the rate limit is exceeded
+            err.code == 429 || err.code == -32005 || is_rate_limit_message(&err.message)
+        }
+        HttpClientError::SerdeJson { text, .. } => {
+            // the endpoint did not respond with JSON at all, like the error pages of gateways and
+            // rate limiters
+            serde_json::from_str::<serde_json::Value>(text).is_err() || is_rate_limit_message(text)
+        }
+    }
+}
+
+fn is_rate_limit_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("rate limit") || message.contains("too many requests")
+}
+
+/// Returns the compute units of a request, following the pricing of Alchemy
+pub fn compute_units(method: &str) -> u64 {
+    match method {
+        "eth_chainId" | "net_version" => 0,
+        "eth_blockNumber" => 10,
+        "eth_getBlockByNumber" => 16,
+        "eth_getStorageAt" => 17,
+        "eth_getBalance" | "eth_gasPrice" => 19,
+        _ => 26,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::providers::JsonRpcError;
+
+    #[test]
+    fn retries_rate_limits() {
+        let rate_limited = JsonRpcError {
+            code: 429,
+            message: "Your app has exceeded its compute units per second capacity".to_string(),
+            data: None,
+        };
+        assert!(is_retryable(&HttpClientError::JsonRpcError(rate_limited)));
+
+        let reverted =
+            JsonRpcError { code: -32000, message: "execution reverted".to_string(), data: None };
+        assert!(!is_retryable(&HttpClientError::JsonRpcError(reverted)));
+
+        let bad_gateway = HttpClientError::SerdeJson {
+            err: serde_json::from_str::<serde_json::Value>("<html>").unwrap_err(),
+            text: "<html><h1>502 Bad Gateway</h1></html>".to_string(),
+        };
+        assert!(is_retryable(&bad_gateway));
+
+        let invalid_response = HttpClientError::SerdeJson {
+            err: serde_json::from_str::<u64>("{}").unwrap_err(),
+            text: r#"{"jsonrpc":"2.0","id":1,"result":"0x"}"#.to_string(),
+        };
+        assert!(!is_retryable(&invalid_response));
+    }
+
+    #[test]
+    fn doubles_backoff_up_to_max() {
+        let config = RetryConfig::default();
+        assert_eq!(config.backoff(0), Duration::from_millis(500));
+        assert_eq!(config.backoff(3), Duration::from_secs(4));
+        assert_eq!(config.backoff(10), MAX_BACKOFF);
+        assert_eq!(config.backoff(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn limits_compute_units_per_second() {
+        let url = Url::parse("https://eth-mainnet.alchemyapi.io/v2/key").unwrap();
+        let client = RetryClient::new(
+            url.clone(),
+            RetryConfig { compute_units_per_second: Some(100), ..Default::default() },
+        );
+        assert_eq!(client.endpoint, "eth-mainnet.alchemyapi.io");
+
+        let first = client.schedule("eth_getStorageAt");
+        let second = client.schedule("eth_getStorageAt");
+        assert_eq!(second - first, Duration::from_millis(170));
+
+        let unlimited = RetryClient::new(url, RetryConfig::default());
+        let first = unlimited.schedule("eth_getStorageAt");
+        assert!(unlimited.schedule("eth_getStorageAt") - first < Duration::from_millis(170));
+    }
+}
@@ -0,0 +1,41 @@
+use ethers::types::{
+    transaction::eip2718::TypedTransaction, Address, NameOrAddress, TransactionRequest, U256,
+};
+
+/// Records the calls and contract creations of a script as transactions of another account
+///
+/// The calls of the script are made from the broadcasting account instead, so the state of the
+/// simulation matches the state after the transactions were sent.
+#[derive(Clone, Debug, Default)]
+pub struct Broadcast {
+    /// Address of the script contract whose calls are recorded
+    pub original_caller: Address,
+    /// The account that sends the transactions
+    pub new_origin: Address,
+    /// The depth of the calls of the script
+    pub depth: u64,
+    /// The nonce of the next transaction of the account
+    pub nonce: u64,
+}
+
+impl Broadcast {
+    /// Returns the transaction of a call or contract creation (without `to`) and increments the
+    /// nonce
+    pub fn transaction(
+        &mut self,
+        to: Option<Address>,
+        value: U256,
+        data: bytes::Bytes,
+    ) -> TypedTransaction {
+        let tx = TransactionRequest {
+            from: Some(self.new_origin),
+            to: to.map(NameOrAddress::Address),
+            value: Some(value),
+            data: Some(data.into()),
+            nonce: Some(self.nonce.into()),
+            ..Default::default()
+        };
+        self.nonce += 1;
+        tx.into()
+    }
+}
@@ -2,7 +2,7 @@ use super::Cheatcodes;
 use crate::abi::HEVMCalls;
 use bytes::Bytes;
 use ethers::{
-    abi::{AbiEncode, RawLog},
+    abi::{Abi, AbiEncode, RawLog},
     types::{Address, H160},
 };
 use revm::{return_ok, Database, EVMData, Return};
@@ -39,11 +39,17 @@ fn expect_revert(state: &mut Cheatcodes, reason: Bytes, depth: u64) -> Result<By
     }
 }
 
+/// Checks that the call reverted with the expected data
+///
+/// Revert strings match either the string or the complete `Error(string)` data, all other reverts
+/// must match the complete revert data, e.g. a custom error with its arguments. If they don't
+/// match, both sides are decoded with the known errors.
 pub fn handle_expect_revert(
     is_create: bool,
     expected_revert: &Bytes,
     status: Return,
     retdata: Bytes,
+    errors: Option<&Abi>,
 ) -> Result<(Option<Address>, Bytes), Bytes> {
     if matches!(status, return_ok!()) {
         return Err("Call did not revert as expected".to_string().encode().into())
@@ -53,6 +59,10 @@ pub fn handle_expect_revert(
         return Err("Call reverted as expected, but without data".to_string().encode().into())
     }
 
+    if &retdata == expected_revert {
+        return Ok(expected_revert_output(is_create))
+    }
+
     let (err, actual_revert): (_, Bytes) = match retdata {
         _ if retdata.len() >= 4 && retdata[0..4] == [8, 195, 121, 160] => {
             // It's a revert string, so we do some conversion to perform the check
@@ -81,9 +91,9 @@ pub fn handle_expect_revert(
         }
         _ => (
             format!(
-                "Error != expected error: 0x{} != 0x{}",
-                hex::encode(&retdata),
-                hex::encode(&expected_revert)
+                "Error != expected error: {} != {}",
+                format_revert(&retdata, errors),
+                format_revert(expected_revert, errors)
             )
             .encode()
             .into(),
@@ -92,16 +102,27 @@ pub fn handle_expect_revert(
     };
 
     if actual_revert == expected_revert {
-        Ok(if is_create {
-            (Some(DUMMY_CREATE_ADDRESS), Bytes::new())
-        } else {
-            (None, DUMMY_CALL_OUTPUT.to_vec().into())
-        })
+        Ok(expected_revert_output(is_create))
     } else {
         Err(err)
     }
 }
 
+/// The output of a call or create that reverted as expected
+fn expected_revert_output(is_create: bool) -> (Option<Address>, Bytes) {
+    if is_create {
+        (Some(DUMMY_CREATE_ADDRESS), Bytes::new())
+    } else {
+        (None, DUMMY_CALL_OUTPUT.to_vec().into())
+    }
+}
+
+/// Decodes the revert data with the known errors, data that can't be decoded is printed as hex
+fn format_revert(data: &[u8], errors: Option<&Abi>) -> String {
+    foundry_utils::decode_revert(data, errors)
+        .unwrap_or_else(|_| format!("0x{}", hex::encode(data)))
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct ExpectedEmit {
     /// The depth at which we expect this emit to have occurred
@@ -194,3 +215,102 @@ pub fn apply<DB: Database>(
         _ => return None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        abi::{AbiError, Param, ParamType, Token},
+        types::U256,
+    };
+
+    fn errors() -> Abi {
+        let error = AbiError {
+            name: "CustomErrorWithArgs".to_string(),
+            inputs: vec![Param {
+                name: "value".to_string(),
+                kind: ParamType::Uint(256),
+                internal_type: None,
+            }],
+        };
+        let mut abi = Abi::default();
+        abi.errors.insert(error.name.clone(), vec![error]);
+        abi
+    }
+
+    fn custom_error(value: u64) -> Bytes {
+        let error = &errors().errors["CustomErrorWithArgs"][0];
+        let mut data = error.signature()[..4].to_vec();
+        data.extend(ethers::abi::encode(&[Token::Uint(U256::from(value))]));
+        data.into()
+    }
+
+    #[test]
+    fn compares_custom_errors_with_arguments() {
+        let errors = errors();
+        assert!(handle_expect_revert(
+            false,
+            &custom_error(1),
+            Return::Revert,
+            custom_error(1),
+            Some(&errors)
+        )
+        .is_ok());
+
+        // the selector alone doesn't match an error with arguments
+        let selector = custom_error(1).slice(..4);
+        assert!(handle_expect_revert(
+            false,
+            &selector,
+            Return::Revert,
+            custom_error(1),
+            Some(&errors)
+        )
+        .is_err());
+
+        let err = handle_expect_revert(
+            false,
+            &custom_error(2),
+            Return::Revert,
+            custom_error(1),
+            Some(&errors),
+        )
+        .unwrap_err();
+        assert_eq!(
+            foundry_utils::decode_revert(&err, None).unwrap(),
+            "Error != expected error: CustomErrorWithArgs(1) != CustomErrorWithArgs(2)"
+        );
+
+        // without the errors both sides are printed as hex
+        let err =
+            handle_expect_revert(false, &custom_error(2), Return::Revert, custom_error(1), None)
+                .unwrap_err();
+        assert_eq!(
+            foundry_utils::decode_revert(&err, None).unwrap(),
+            format!(
+                "Error != expected error: 0x{} != 0x{}",
+                hex::encode(custom_error(1)),
+                hex::encode(custom_error(2))
+            )
+        );
+    }
+
+    #[test]
+    fn matches_revert_strings_and_their_data() {
+        let revert = "revert".to_string().encode();
+        let mut data = vec![8, 195, 121, 160];
+        data.extend(revert);
+        let data = Bytes::from(data);
+
+        assert!(handle_expect_revert(
+            false,
+            &Bytes::from_static(b"revert"),
+            Return::Revert,
+            data.clone(),
+            None
+        )
+        .is_ok());
+        assert!(handle_expect_revert(false, &data, Return::Revert, data.clone(), None).is_ok());
+        assert!(handle_expect_revert(false, &data, Return::Return, Bytes::new(), None).is_err());
+    }
+}
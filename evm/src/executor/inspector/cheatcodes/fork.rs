@@ -0,0 +1,63 @@
+use super::Cheatcodes;
+use crate::{abi::HEVMCalls, executor::fork::MultiFork};
+use bytes::Bytes;
+use ethers::{abi::AbiEncode, types::U256};
+use revm::{BlockEnv, Database, EVMData};
+
+/// Returns the forks of the current call
+fn forks(state: &Cheatcodes) -> Result<&MultiFork, Bytes> {
+    state.forks.as_ref().ok_or_else(|| "Forks are not supported here".to_string().encode().into())
+}
+
+fn create_fork<DB: Database>(
+    state: &Cheatcodes,
+    data: &EVMData<'_, DB>,
+    endpoint: &str,
+    block: Option<U256>,
+) -> Result<Bytes, Bytes> {
+    if block.map_or(false, |block| block > U256::from(u64::MAX)) {
+        return Err("Block number is too large".to_string().encode().into())
+    }
+
+    let id = forks(state)?
+        .write()
+        .create_fork(endpoint, block.map(|block| block.as_u64()), data.env.tx.caller)
+        .map_err(|err| format!("Failed to create fork: {}", err).encode())?;
+    Ok(U256::from(id).encode().into())
+}
+
+fn select_fork<DB: Database>(
+    state: &Cheatcodes,
+    data: &mut EVMData<'_, DB>,
+    id: U256,
+) -> Result<Bytes, Bytes> {
+    let mut forks = forks(state)?.write();
+    let fork =
+        forks.select_fork(id, data.subroutine.state()).map_err(|err| err.to_string().encode())?;
+
+    // Switch to the block of the fork, the block gas limit is kept as it also limits the call
+    data.env.block = BlockEnv { gas_limit: data.env.block.gas_limit, ..fork.env.block.clone() };
+    data.env.cfg.chain_id = fork.env.cfg.chain_id;
+    Ok(Bytes::new())
+}
+
+fn active_fork(state: &Cheatcodes) -> Result<Bytes, Bytes> {
+    match forks(state)?.read().active_id() {
+        Some(id) => Ok(U256::from(id).encode().into()),
+        None => Err("No fork is selected".to_string().encode().into()),
+    }
+}
+
+pub fn apply<DB: Database>(
+    state: &mut Cheatcodes,
+    data: &mut EVMData<'_, DB>,
+    call: &HEVMCalls,
+) -> Option<Result<Bytes, Bytes>> {
+    Some(match call {
+        HEVMCalls::CreateFork0(inner) => create_fork(state, data, &inner.0, None),
+        HEVMCalls::CreateFork1(inner) => create_fork(state, data, &inner.0, Some(inner.1)),
+        HEVMCalls::SelectFork(inner) => select_fork(state, data, inner.0),
+        HEVMCalls::ActiveFork(_) => active_fork(state),
+        _ => return None,
+    })
+}
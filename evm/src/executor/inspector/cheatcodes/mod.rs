@@ -1,3 +1,6 @@
+/// Recording of the transactions of scripts
+mod broadcast;
+pub use broadcast::Broadcast;
 /// Cheatcodes related to the execution environment.
 mod env;
 pub use env::{Prank, RecordAccess};
@@ -6,6 +9,8 @@ mod expect;
 pub use expect::{ExpectedEmit, ExpectedRevert};
 /// Cheatcodes that interact with the external environment (FFI etc.)
 mod ext;
+/// Cheatcodes that create and select forks
+mod fork;
 /// Cheatcodes that configure the fuzzer
 mod fuzz;
 /// Utility cheatcodes (`sign` etc.)
@@ -14,16 +19,16 @@ mod util;
 use self::expect::{handle_expect_emit, handle_expect_revert};
 use crate::{
     abi::HEVMCalls,
-    executor::{CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS},
+    executor::{fork::MultiFork, CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS},
 };
 use bytes::Bytes;
 use ethers::{
-    abi::{AbiDecode, AbiEncode, RawLog},
-    types::{Address, H256},
+    abi::{Abi, AbiDecode, AbiEncode, RawLog},
+    types::{transaction::eip2718::TypedTransaction, Address, H256},
 };
 use revm::{
-    opcode, BlockEnv, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter,
-    Return,
+    opcode, BlockEnv, CallInputs, CreateInputs, CreateScheme, Database, EVMData, Gas, Inspector,
+    Interpreter, Return,
 };
 use std::collections::BTreeMap;
 
@@ -62,6 +67,18 @@ pub struct Cheatcodes {
 
     /// Expected emits
     pub expected_emits: Vec<ExpectedEmit>,
+
+    /// All known errors, used to decode the reverts that don't match an expected revert
+    pub errors: Option<Abi>,
+
+    /// The forks of the current call
+    pub forks: Option<MultiFork>,
+
+    /// Records the calls of a script as transactions, see [Broadcast]
+    pub broadcast: Option<Broadcast>,
+
+    /// The transactions recorded by `broadcast`
+    pub broadcastable_transactions: Vec<TypedTransaction>,
 }
 
 impl Cheatcodes {
@@ -83,6 +100,7 @@ impl Cheatcodes {
             .or_else(|| util::apply(self, data, &decoded))
             .or_else(|| expect::apply(self, data, &decoded))
             .or_else(|| fuzz::apply(data, &decoded))
+            .or_else(|| fork::apply(self, data, &decoded))
             .or_else(|| ext::apply(self.ffi, &decoded))
             .ok_or_else(|| "Cheatcode was unhandled. This is a bug.".to_string().encode())?
     }
@@ -96,7 +114,7 @@ where
         &mut self,
         data: &mut EVMData<'_, DB>,
         call: &mut CallInputs,
-        _: bool,
+        is_static: bool,
     ) -> (Return, Gas, Bytes) {
         if call.contract == CHEATCODE_ADDRESS {
             match self.apply_cheatcode(data, call.context.caller, call) {
@@ -142,6 +160,22 @@ where
                 }
             }
 
+            // Record the state changing calls of the script
+            if let Some(broadcast) = &mut self.broadcast {
+                if !is_static &&
+                    data.subroutine.depth() == broadcast.depth &&
+                    call.context.caller == broadcast.original_caller
+                {
+                    call.context.caller = broadcast.new_origin;
+                    call.transfer.source = broadcast.new_origin;
+                    self.broadcastable_transactions.push(broadcast.transaction(
+                        Some(call.contract),
+                        call.transfer.value,
+                        call.input.clone(),
+                    ));
+                }
+            }
+
             (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
         } else {
             (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
@@ -231,7 +265,13 @@ where
         if let Some(expected_revert) = &self.expected_revert {
             if data.subroutine.depth() <= expected_revert.depth {
                 let expected_revert = std::mem::take(&mut self.expected_revert).unwrap();
-                return match handle_expect_revert(false, &expected_revert.reason, status, retdata) {
+                return match handle_expect_revert(
+                    false,
+                    &expected_revert.reason,
+                    status,
+                    retdata,
+                    self.errors.as_ref(),
+                ) {
                     Err(retdata) => (Return::Revert, remaining_gas, retdata),
                     Ok((_, retdata)) => (Return::Return, remaining_gas, retdata),
                 }
@@ -310,6 +350,37 @@ where
             }
         }
 
+        // Record the contract creations of the script
+        if let Some(broadcast) = &mut self.broadcast {
+            if data.subroutine.depth() == broadcast.depth &&
+                call.caller == broadcast.original_caller
+            {
+                if let CreateScheme::Create2 { .. } = call.scheme {
+                    return (
+                        Return::Revert,
+                        None,
+                        Gas::new(call.gas_limit),
+                        "CREATE2 can't be broadcast, the broadcasting account is not a contract"
+                            .to_string()
+                            .encode()
+                            .into(),
+                    )
+                }
+
+                // The address of the contract is derived from the nonce of the transaction
+                data.subroutine.load_account(broadcast.new_origin, data.db);
+                let account = data.subroutine.state().get_mut(&broadcast.new_origin).unwrap();
+                account.info.nonce = broadcast.nonce;
+
+                call.caller = broadcast.new_origin;
+                self.broadcastable_transactions.push(broadcast.transaction(
+                    None,
+                    call.value,
+                    call.init_code.clone(),
+                ));
+            }
+        }
+
         (Return::Continue, None, Gas::new(call.gas_limit), Bytes::new())
     }
 
@@ -336,7 +407,13 @@ where
         if let Some(expected_revert) = &self.expected_revert {
             if data.subroutine.depth() <= expected_revert.depth {
                 let expected_revert = std::mem::take(&mut self.expected_revert).unwrap();
-                return match handle_expect_revert(true, &expected_revert.reason, status, retdata) {
+                return match handle_expect_revert(
+                    true,
+                    &expected_revert.reason,
+                    status,
+                    retdata,
+                    self.errors.as_ref(),
+                ) {
                     Err(retdata) => (Return::Revert, None, remaining_gas, retdata),
                     Ok((address, retdata)) => (Return::Return, address, remaining_gas, retdata),
                 }
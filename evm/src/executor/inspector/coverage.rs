@@ -0,0 +1,75 @@
+use crate::coverage::{HitMap, HitMaps};
+use bytes::Bytes;
+use ethers::{
+    types::{Address, H256},
+    utils::keccak256,
+};
+use revm::{
+    opcode, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter, Return,
+};
+
+/// An inspector that records the program counters hit in every executed bytecode.
+#[derive(Default, Debug)]
+pub struct CoverageCollector {
+    /// The hit maps of all executed bytecodes
+    pub maps: HitMaps,
+    /// The hash of the bytecode of the current execution context, computed on its first step
+    current: Option<H256>,
+}
+
+impl<DB> Inspector<DB> for CoverageCollector
+where
+    DB: Database,
+{
+    fn initialize_interp(
+        &mut self,
+        _: &mut Interpreter,
+        _: &mut EVMData<'_, DB>,
+        _: bool,
+    ) -> Return {
+        self.current = None;
+        Return::Continue
+    }
+
+    fn step(&mut self, interpreter: &mut Interpreter, _: &mut EVMData<'_, DB>, _: bool) -> Return {
+        let code = &interpreter.contract.code;
+        let code_hash = *self.current.get_or_insert_with(|| H256(keccak256(code)));
+        let hit_map = self.maps.0.entry(code_hash).or_insert_with(|| HitMap::new(code.clone()));
+
+        let pc = interpreter.program_counter();
+        hit_map.hit(pc);
+        if code.get(pc) == Some(&opcode::JUMPI) {
+            let condition = try_or_continue!(interpreter.stack().peek(1));
+            hit_map.branch(pc, !condition.is_zero());
+        }
+
+        Return::Continue
+    }
+
+    fn call_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        _: &CallInputs,
+        remaining_gas: Gas,
+        status: Return,
+        retdata: Bytes,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        // Execution continues in the calling context
+        self.current = None;
+        (status, remaining_gas, retdata)
+    }
+
+    fn create_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        _: &CreateInputs,
+        status: Return,
+        address: Option<Address>,
+        remaining_gas: Gas,
+        retdata: Bytes,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        self.current = None;
+        (status, address, remaining_gas, retdata)
+    }
+}
@@ -14,9 +14,16 @@ mod stack;
 pub use stack::{InspectorData, InspectorStack};
 
 mod cheatcodes;
-pub use cheatcodes::Cheatcodes;
+pub use cheatcodes::{Broadcast, Cheatcodes};
+
+mod timeout;
+pub use timeout::Timeout;
+
+mod coverage;
+pub use coverage::CoverageCollector;
 
 use revm::BlockEnv;
+use std::time::{Duration, Instant};
 
 #[derive(Default, Clone, Debug)]
 pub struct InspectorStackConfig {
@@ -32,6 +39,10 @@ pub struct InspectorStackConfig {
     pub tracing: bool,
     /// Whether or not the debugger is enabled
     pub debugger: bool,
+    /// The maximum wall-clock time of a single call, if any
+    pub timeout: Option<Duration>,
+    /// Whether or not coverage info should be collected
+    pub coverage: bool,
 }
 
 impl InspectorStackConfig {
@@ -50,6 +61,10 @@ impl InspectorStackConfig {
         if self.debugger {
             stack.debugger = Some(Debugger::default());
         }
+        if self.coverage {
+            stack.coverage = Some(CoverageCollector::default());
+        }
+        stack.timeout = self.timeout.map(|timeout| Timeout::new(Instant::now() + timeout));
         stack
     }
 }
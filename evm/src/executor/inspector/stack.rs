@@ -1,5 +1,5 @@
-use super::{Cheatcodes, Debugger, LogCollector, Tracer};
-use crate::{debug::DebugArena, trace::CallTraceArena};
+use super::{Cheatcodes, CoverageCollector, Debugger, LogCollector, Timeout, Tracer};
+use crate::{coverage::HitMaps, debug::DebugArena, trace::CallTraceArena};
 use bytes::Bytes;
 use ethers::{
     abi::RawLog,
@@ -25,6 +25,7 @@ pub struct InspectorData {
     pub labels: BTreeMap<Address, String>,
     pub traces: Option<CallTraceArena>,
     pub debug: Option<DebugArena>,
+    pub coverage: Option<HitMaps>,
     pub cheatcodes: Option<Cheatcodes>,
 }
 
@@ -38,6 +39,8 @@ pub struct InspectorStack {
     pub logs: Option<LogCollector>,
     pub cheatcodes: Option<Cheatcodes>,
     pub debugger: Option<Debugger>,
+    pub timeout: Option<Timeout>,
+    pub coverage: Option<CoverageCollector>,
 }
 
 impl InspectorStack {
@@ -51,6 +54,7 @@ impl InspectorStack {
                 .unwrap_or_default(),
             traces: self.tracer.map(|tracer| tracer.traces),
             debug: self.debugger.map(|debugger| debugger.arena),
+            coverage: self.coverage.map(|coverage| coverage.maps),
             cheatcodes: self.cheatcodes,
         }
     }
@@ -68,7 +72,13 @@ where
     ) -> Return {
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.debugger,
+                &mut self.coverage,
+                &mut self.tracer,
+                &mut self.logs,
+                &mut self.cheatcodes
+            ],
             {
                 let status = inspector.initialize_interp(interpreter, data, is_static);
 
@@ -90,7 +100,14 @@ where
     ) -> Return {
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.timeout,
+                &mut self.debugger,
+                &mut self.coverage,
+                &mut self.tracer,
+                &mut self.logs,
+                &mut self.cheatcodes
+            ],
             {
                 let status = inspector.step(interpreter, data, is_static);
 
@@ -172,7 +189,13 @@ where
     ) -> (Return, Gas, Bytes) {
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.debugger,
+                &mut self.coverage,
+                &mut self.tracer,
+                &mut self.logs,
+                &mut self.cheatcodes
+            ],
             {
                 let (new_status, new_gas, new_retdata) = inspector.call_end(
                     data,
@@ -226,7 +249,13 @@ where
     ) -> (Return, Option<Address>, Gas, Bytes) {
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.debugger,
+                &mut self.coverage,
+                &mut self.tracer,
+                &mut self.logs,
+                &mut self.cheatcodes
+            ],
             {
                 let (new_status, new_address, new_gas, new_retdata) = inspector.create_end(
                     data,
@@ -0,0 +1,42 @@
+use revm::{db::Database, EVMData, Inspector, Interpreter, Return};
+use std::time::Instant;
+
+/// The number of steps between two checks of the deadline, reading the clock on every step would
+/// slow down execution considerably
+const CHECK_INTERVAL: u64 = 1024;
+
+/// An inspector that halts execution once a deadline has passed.
+///
+/// Every call frame still running once the deadline passed runs out of gas.
+#[derive(Debug, Clone)]
+pub struct Timeout {
+    /// When to halt execution
+    pub deadline: Instant,
+    /// The number of steps executed so far
+    steps: u64,
+}
+
+impl Timeout {
+    pub fn new(deadline: Instant) -> Self {
+        Self { deadline, steps: 0 }
+    }
+
+    /// Whether the deadline has passed
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+}
+
+impl<DB> Inspector<DB> for Timeout
+where
+    DB: Database,
+{
+    fn step(&mut self, _: &mut Interpreter, _: &mut EVMData<'_, DB>, _: bool) -> Return {
+        self.steps += 1;
+        if self.steps % CHECK_INTERVAL == 0 && self.is_expired() {
+            return Return::OutOfGas
+        }
+
+        Return::Continue
+    }
+}
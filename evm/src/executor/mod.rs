@@ -26,19 +26,25 @@ pub use revm::db::DatabaseRef;
 
 pub use revm::Env;
 
-use self::inspector::{InspectorData, InspectorStackConfig};
-use crate::{debug::DebugArena, trace::CallTraceArena, CALLER};
+use self::{
+    fork::{ForkedDatabase, Forks, MultiFork},
+    inspector::{Broadcast, InspectorData, InspectorStack, InspectorStackConfig},
+};
+use crate::{coverage::HitMaps, debug::DebugArena, trace::CallTraceArena, CALLER};
 use bytes::Bytes;
 use ethers::{
     abi::{Abi, Detokenize, RawLog, Tokenize},
     prelude::{decode_function_data, encode_function_data, Address, U256},
+    types::transaction::eip2718::TypedTransaction,
+    utils::get_contract_address,
 };
 use eyre::Result;
 use foundry_utils::IntoFunction;
 use hashbrown::HashMap;
 use revm::{
     db::{CacheDB, DatabaseCommit, EmptyDB},
-    return_ok, Account, BlockEnv, CreateScheme, Return, TransactOut, TransactTo, TxEnv, EVM,
+    return_ok, Account, BlockEnv, CfgEnv, CreateScheme, Return, TransactOut, TransactTo, TxEnv,
+    EVM,
 };
 use std::collections::BTreeMap;
 
@@ -57,6 +63,7 @@ pub enum EvmError {
         logs: Vec<RawLog>,
         traces: Option<CallTraceArena>,
         debug: Option<DebugArena>,
+        coverage: Option<HitMaps>,
         labels: BTreeMap<Address, String>,
         state_changeset: Option<StateChangeset>,
     },
@@ -81,6 +88,8 @@ pub struct DeployResult {
     pub traces: Option<CallTraceArena>,
     /// The debug nodes of the call
     pub debug: Option<DebugArena>,
+    /// The coverage info collected during the call
+    pub coverage: Option<HitMaps>,
 }
 
 /// The result of a call.
@@ -102,10 +111,12 @@ pub struct CallResult<D: Detokenize> {
     pub traces: Option<CallTraceArena>,
     /// The debug nodes of the call
     pub debug: Option<DebugArena>,
+    /// The coverage info collected during the call
+    pub coverage: Option<HitMaps>,
     /// The changeset of the state.
     ///
-    /// This is only present if the changed state was not committed to the database (i.e. if you
-    /// used `call` and `call_raw` not `call_committing` or `call_raw_committing`).
+    /// With `call_committing` and `call_raw_committing` this is the changeset that was committed
+    /// to the database.
     pub state_changeset: Option<StateChangeset>,
 }
 
@@ -130,10 +141,12 @@ pub struct RawCallResult {
     pub traces: Option<CallTraceArena>,
     /// The debug nodes of the call
     pub debug: Option<DebugArena>,
+    /// The coverage info collected during the call
+    pub coverage: Option<HitMaps>,
     /// The changeset of the state.
     ///
-    /// This is only present if the changed state was not committed to the database (i.e. if you
-    /// used `call` and `call_raw` not `call_committing` or `call_raw_committing`).
+    /// With `call_committing` and `call_raw_committing` this is the changeset that was committed
+    /// to the database.
     pub state_changeset: Option<StateChangeset>,
 }
 
@@ -149,11 +162,13 @@ impl Default for RawCallResult {
             labels: BTreeMap::new(),
             traces: None,
             debug: None,
+            coverage: None,
             state_changeset: None,
         }
     }
 }
 
+#[derive(Clone)]
 pub struct Executor<DB: DatabaseRef> {
     // Note: We do not store an EVM here, since we are really
     // only interested in the database. REVM's `EVM` is a thin
@@ -170,6 +185,8 @@ pub struct Executor<DB: DatabaseRef> {
     /// the passed in environment, as those limits are used by the EVM for certain opcodes like
     /// `gaslimit`.
     gas_limit: U256,
+    /// The forks created with the `createFork` cheatcode and the selected fork
+    forks: Forks,
 }
 
 impl<DB> Executor<DB>
@@ -191,7 +208,10 @@ where
             revm::AccountInfo { code: Some(Bytes::from_static(&[1])), ..Default::default() },
         );
 
-        Executor { db, env, inspector_config, gas_limit }
+        let mut forks = Forks::default();
+        forks.make_persistent([CHEATCODE_ADDRESS]);
+
+        Executor { db, env, inspector_config, gas_limit, forks }
     }
 
     /// Set the balance of an account.
@@ -200,6 +220,7 @@ where
         account.balance = amount;
 
         self.db.insert_cache(address, account);
+        self.forks.make_persistent([address]);
     }
 
     /// Gets the balance of an account
@@ -213,6 +234,42 @@ where
         account.nonce = nonce;
 
         self.db.insert_cache(address, account);
+        self.forks.make_persistent([address]);
+    }
+
+    /// Records the calls and contract creations of the script as transactions sent by `sender`,
+    /// starting with the current nonce of `sender`.
+    ///
+    /// The transactions are returned by [Executor::take_broadcastable_transactions], requires
+    /// cheatcodes to be enabled.
+    pub fn broadcast(&mut self, script: Address, sender: Address) {
+        let nonce = self.db.basic(sender).nonce;
+        if let Some(cheatcodes) = self.inspector_config.cheatcodes.as_mut() {
+            cheatcodes.broadcast =
+                Some(Broadcast { original_caller: script, new_origin: sender, depth: 1, nonce });
+        }
+    }
+
+    /// Returns the transactions recorded since [Executor::broadcast] by committing calls
+    pub fn take_broadcastable_transactions(&mut self) -> Vec<TypedTransaction> {
+        self.inspector_config
+            .cheatcodes
+            .as_mut()
+            .map(|cheatcodes| std::mem::take(&mut cheatcodes.broadcastable_transactions))
+            .unwrap_or_default()
+    }
+
+    /// Returns the address and runtime code of every contract the account created
+    ///
+    /// Only contracts created by the account itself are returned, not the contracts they created.
+    pub fn created_contracts(&self, creator: Address) -> Vec<(Address, Vec<u8>)> {
+        (0..self.db.basic(creator).nonce)
+            .map(|nonce| get_contract_address(creator, nonce))
+            .filter_map(|address| {
+                let code = self.db.basic(address).code?;
+                (!code.is_empty()).then(|| (address, code.to_vec()))
+            })
+            .collect()
     }
 
     /// Calls the `setUp()` function on a contract.
@@ -244,6 +301,7 @@ where
             labels,
             traces,
             debug,
+            coverage,
             state_changeset,
         } = self.call_raw_committing(from, to, calldata, value)?;
         match status {
@@ -258,6 +316,7 @@ where
                     labels,
                     traces,
                     debug,
+                    coverage,
                     state_changeset,
                 })
             }
@@ -272,6 +331,7 @@ where
                     logs,
                     traces,
                     debug,
+                    coverage,
                     labels,
                     state_changeset,
                 })
@@ -292,24 +352,29 @@ where
         let stipend = stipend(&calldata, self.env.cfg.spec_id);
 
         // Build VM
+        let forks = MultiFork::new(self.forks.clone());
+        let db = ForkedDatabase::new(&self.db, &forks);
         let mut evm = EVM::new();
         evm.env = self.build_env(from, TransactTo::Call(to), calldata, value);
-        evm.database(&mut self.db);
+        evm.database(&db);
 
         // Run the call
-        let mut inspector = self.inspector_config.stack();
-        let (status, out, gas, _) = evm.inspect_commit(&mut inspector);
+        let mut inspector = self.inspector(&forks);
+        let (status, out, gas, state_changeset, _) = evm.inspect_ref(&mut inspector);
         let result = match out {
             TransactOut::Call(data) => data,
             _ => Bytes::default(),
         };
 
-        let InspectorData { logs, labels, traces, debug, cheatcodes } =
+        let InspectorData { logs, labels, traces, debug, coverage, cheatcodes } =
             inspector.collect_inspector_states();
 
         // Persist the changed block environment
         self.inspector_config.block = evm.env.block.clone();
 
+        // Commit the changes to the selected fork or the database
+        self.commit(&forks, state_changeset.clone());
+
         // Persist cheatcode state
         self.inspector_config.cheatcodes = cheatcodes;
 
@@ -323,7 +388,8 @@ where
             labels,
             traces,
             debug,
-            state_changeset: None,
+            coverage,
+            state_changeset: Some(state_changeset),
         })
     }
 
@@ -351,6 +417,7 @@ where
             labels,
             traces,
             debug,
+            coverage,
             state_changeset,
         } = self.call_raw(from, to, calldata, value)?;
         match status {
@@ -365,6 +432,7 @@ where
                     labels,
                     traces,
                     debug,
+                    coverage,
                     state_changeset,
                 })
             }
@@ -379,6 +447,7 @@ where
                     logs,
                     traces,
                     debug,
+                    coverage,
                     labels,
                     state_changeset,
                 })
@@ -399,19 +468,21 @@ where
         let stipend = stipend(&calldata, self.env.cfg.spec_id);
 
         // Build VM
+        let forks = MultiFork::new(self.forks.clone());
+        let db = ForkedDatabase::new(&self.db, &forks);
         let mut evm = EVM::new();
         evm.env = self.build_env(from, TransactTo::Call(to), calldata, value);
-        evm.database(&self.db);
+        evm.database(&db);
 
         // Run the call
-        let mut inspector = self.inspector_config.stack();
+        let mut inspector = self.inspector(&forks);
         let (status, out, gas, state_changeset, _) = evm.inspect_ref(&mut inspector);
         let result = match out {
             TransactOut::Call(data) => data,
             _ => Bytes::default(),
         };
 
-        let InspectorData { logs, labels, traces, debug, .. } =
+        let InspectorData { logs, labels, traces, debug, coverage, .. } =
             inspector.collect_inspector_states();
         Ok(RawCallResult {
             status,
@@ -423,18 +494,21 @@ where
             labels,
             traces,
             debug,
+            coverage,
             state_changeset: Some(state_changeset),
         })
     }
 
     /// Deploys a contract and commits the new state to the underlying database.
     pub fn deploy(&mut self, from: Address, code: Bytes, value: U256) -> Result<DeployResult> {
+        let forks = MultiFork::new(self.forks.clone());
+        let db = ForkedDatabase::new(&self.db, &forks);
         let mut evm = EVM::new();
         evm.env = self.build_env(from, TransactTo::Create(CreateScheme::Create), code, value);
-        evm.database(&mut self.db);
+        evm.database(&db);
 
-        let mut inspector = self.inspector_config.stack();
-        let (status, out, gas, _) = evm.inspect_commit(&mut inspector);
+        let mut inspector = self.inspector(&forks);
+        let (status, out, gas, state_changeset, _) = evm.inspect_ref(&mut inspector);
         let address = match status {
             return_ok!() => {
                 if let TransactOut::Create(_, Some(addr)) = out {
@@ -447,16 +521,19 @@ where
             // regarding deployments in general
             _ => eyre::bail!("deployment failed: {:?}", status),
         };
-        let InspectorData { logs, traces, debug, cheatcodes, .. } =
+        let InspectorData { logs, traces, debug, coverage, cheatcodes, .. } =
             inspector.collect_inspector_states();
 
         // Persist the changed block environment
         self.inspector_config.block = evm.env.block.clone();
 
+        // Commit the changes to the selected fork or the database
+        self.commit(&forks, state_changeset);
+
         // Persist cheatcode state
         self.inspector_config.cheatcodes = cheatcodes;
 
-        Ok(DeployResult { address, gas, logs, traces, debug })
+        Ok(DeployResult { address, gas, logs, traces, debug, coverage })
     }
 
     /// Check if a call to a test contract was successful.
@@ -495,9 +572,33 @@ where
         should_fail ^ success
     }
 
+    /// Returns the inspectors for a call, the cheatcodes create and select forks in `forks`
+    fn inspector(&self, forks: &MultiFork) -> InspectorStack {
+        let mut inspector = self.inspector_config.stack();
+        if let Some(cheatcodes) = inspector.cheatcodes.as_mut() {
+            cheatcodes.forks = Some(forks.clone());
+        }
+        inspector
+    }
+
+    /// Commits the changes of a call and persists the forks the call created and selected
+    fn commit(&mut self, forks: &MultiFork, state_changeset: StateChangeset) {
+        let mut forks = forks.take();
+        forks.commit(&mut self.db, state_changeset);
+        self.forks = forks;
+    }
+
     fn build_env(&self, caller: Address, transact_to: TransactTo, data: Bytes, value: U256) -> Env {
         Env {
-            cfg: self.env.cfg.clone(),
+            cfg: CfgEnv {
+                // The chain id of the selected fork, its block environment is persisted with the
+                // rest of the block environment
+                chain_id: self
+                    .forks
+                    .active_fork()
+                    .map_or(self.env.cfg.chain_id, |fork| fork.env.cfg.chain_id),
+                ..self.env.cfg.clone()
+            },
             // We always set the gas price to 0 so we can execute the transaction regardless of
             // network conditions - the actual gas price is kept in `self.block` and is applied by
             // the cheatcode handler if it is enabled
@@ -1,11 +1,12 @@
 use ethers::{
-    providers::{Middleware, Provider},
+    providers::Middleware,
     types::{Address, Chain, U256},
 };
 use revm::{BlockEnv, CfgEnv, SpecId, TxEnv};
 use serde::{Deserialize, Deserializer, Serialize};
+use std::{collections::BTreeMap, time::Duration};
 
-use super::fork::environment;
+use super::fork::{environment, resolve_endpoint, RetryConfig};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct EvmOpts {
@@ -19,9 +20,26 @@ pub struct EvmOpts {
     /// pins the block number for the state fork
     pub fork_block_number: Option<u64>,
 
+    /// the endpoints tests can fork by name with the `createFork` cheatcode
+    #[serde(default)]
+    pub rpc_endpoints: BTreeMap<String, String>,
+
     /// Disables storage caching entirely.
     pub no_storage_caching: bool,
 
+    /// how often a failed request to a fork's endpoint is retried
+    #[serde(default)]
+    pub fork_retries: Option<u32>,
+
+    /// the delay before the first retry of a failed request in milliseconds, doubled for every
+    /// further retry
+    #[serde(default)]
+    pub fork_retry_backoff: Option<u64>,
+
+    /// limits the requests to a fork's endpoint to this many compute units per second
+    #[serde(default)]
+    pub compute_units_per_second: Option<u64>,
+
     /// the initial balance of each deployed test contract
     pub initial_balance: U256,
 
@@ -38,11 +56,12 @@ pub struct EvmOpts {
 impl EvmOpts {
     pub async fn evm_env(&self) -> revm::Env {
         if let Some(ref fork_url) = self.fork_url {
-            let provider =
-                Provider::try_from(fork_url.as_str()).expect("could not instantiated provider");
+            let provider = self.fork_retry_config().provider(fork_url).unwrap_or_else(|err| {
+                panic!("Failed to establish provider to {}: {}", fork_url, err)
+            });
             environment(&provider, self.env.chain_id, self.fork_block_number, self.sender)
                 .await
-                .expect("could not instantiate forked environment")
+                .unwrap_or_else(|err| panic!("Failed to instantiate forked environment: {}", err))
         } else {
             revm::Env {
                 block: BlockEnv {
@@ -70,6 +89,32 @@ impl EvmOpts {
         }
     }
 
+    /// Replaces the `fork_url` with the url of the endpoint if it's the name of one of the
+    /// `rpc_endpoints`, see [resolve_endpoint]
+    ///
+    /// Any other `fork_url` is used as a literal url. Fails if an environment variable in the url
+    /// of the endpoint is not set.
+    pub fn resolve_fork_url(&mut self) -> eyre::Result<()> {
+        if let Some(ref endpoint) = self.fork_url {
+            if self.rpc_endpoints.contains_key(endpoint) {
+                self.fork_url = Some(resolve_endpoint(endpoint, &self.rpc_endpoints)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns how requests to the endpoints of forks are retried and rate limited
+    pub fn fork_retry_config(&self) -> RetryConfig {
+        let default = RetryConfig::default();
+        RetryConfig {
+            max_retries: self.fork_retries.unwrap_or(default.max_retries),
+            initial_backoff: self
+                .fork_retry_backoff
+                .map_or(default.initial_backoff, Duration::from_millis),
+            compute_units_per_second: self.compute_units_per_second,
+        }
+    }
+
     pub fn gas_limit(&self) -> U256 {
         self.env.block_gas_limit.unwrap_or(self.env.gas_limit).into()
     }
@@ -93,7 +138,9 @@ impl EvmOpts {
                 tracing::trace!("auto detected mainnet chain from url {}", url);
                 return Some(Chain::Mainnet)
             }
-            let provider = Provider::try_from(url.as_str())
+            let provider = self
+                .fork_retry_config()
+                .provider(url)
                 .unwrap_or_else(|_| panic!("Failed to establish provider to {}", url));
 
             if let Ok(id) = foundry_utils::RuntimeOrHandle::new().block_on(provider.get_chainid()) {
@@ -172,3 +219,54 @@ where
         _ => Ok(None),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts(fork_url: &str) -> EvmOpts {
+        EvmOpts {
+            fork_url: Some(fork_url.to_string()),
+            rpc_endpoints: BTreeMap::from([
+                (
+                    "mainnet".to_string(),
+                    "https://mainnet.infura.io/v3/${FOUNDRY_OPTS_TEST_KEY}".to_string(),
+                ),
+                ("missing".to_string(), "https://${FOUNDRY_OPTS_TEST_MISSING}/rpc".to_string()),
+                ("local".to_string(), "http://localhost:8545".to_string()),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resolves_fork_url_aliases() {
+        std::env::set_var("FOUNDRY_OPTS_TEST_KEY", "secret");
+        let mut evm_opts = opts("mainnet");
+        evm_opts.resolve_fork_url().unwrap();
+        assert_eq!(evm_opts.fork_url.unwrap(), "https://mainnet.infura.io/v3/secret");
+
+        let mut evm_opts = opts("local");
+        evm_opts.resolve_fork_url().unwrap();
+        assert_eq!(evm_opts.fork_url.unwrap(), "http://localhost:8545");
+    }
+
+    #[test]
+    fn keeps_unknown_fork_url() {
+        for url in ["https://eth.llamarpc.com", "optimism"] {
+            let mut evm_opts = opts(url);
+            evm_opts.resolve_fork_url().unwrap();
+            assert_eq!(evm_opts.fork_url.unwrap(), url);
+        }
+    }
+
+    #[test]
+    fn fails_on_missing_env_var_of_fork_url() {
+        let err = opts("missing").resolve_fork_url().unwrap_err().to_string();
+        assert!(
+            err.contains("environment variable `FOUNDRY_OPTS_TEST_MISSING` of RPC endpoint `missing` is not set"),
+            "{}",
+            err
+        );
+    }
+}
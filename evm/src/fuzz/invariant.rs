@@ -0,0 +1,369 @@
+//! Stateful invariant testing
+
+use super::strategies::{fuzz_calldata, fuzz_param};
+use crate::{
+    coverage::{merge_coverage, HitMaps},
+    executor::{Executor, RawCallResult},
+    trace::CallTraceArena,
+};
+use ethers::{
+    abi::{Abi, Function, ParamType, RawLog, StateMutability},
+    types::{Address, Bytes},
+};
+use proptest::{
+    prelude::{Just, Strategy},
+    strategy::Union,
+    test_runner::{TestCaseError, TestError, TestRunner},
+};
+use revm::db::DatabaseRef;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    fmt,
+    time::Instant,
+};
+
+/// The number and length of the call sequences of invariant campaigns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvariantConfig {
+    /// The number of call sequences that are run for every invariant
+    pub runs: u32,
+    /// The maximum number of calls in a sequence, the invariant is checked after every call
+    pub depth: u32,
+}
+
+impl Default for InvariantConfig {
+    fn default() -> Self {
+        Self { runs: 256, depth: 15 }
+    }
+}
+
+/// A contract that is called during invariant campaigns
+#[derive(Debug, Clone)]
+pub struct TargetContract {
+    pub address: Address,
+    /// The name of the contract, used to print the calls
+    pub name: String,
+    pub abi: Abi,
+}
+
+impl TargetContract {
+    /// Returns the functions of the contract that can change its state
+    pub fn mutable_functions(&self) -> impl Iterator<Item = &Function> {
+        self.abi.functions().filter(|func| {
+            !matches!(func.state_mutability, StateMutability::Pure | StateMutability::View)
+        })
+    }
+
+    /// Returns the called function and its decoded arguments, e.g. `deposit(5)`
+    fn decode(&self, calldata: &[u8]) -> String {
+        let func = self
+            .abi
+            .functions()
+            .find(|func| calldata.len() >= 4 && func.short_signature() == calldata[..4]);
+        match func.and_then(|func| Some((func, func.decode_input(&calldata[4..]).ok()?))) {
+            Some((func, args)) => format!(
+                "{}({})",
+                func.name,
+                foundry_utils::format_tokens(&args).collect::<Vec<_>>().join(", ")
+            ),
+            None => format!("0x{}", hex::encode(calldata)),
+        }
+    }
+}
+
+/// A call of the sequence that broke an invariant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvariantCall {
+    pub sender: Address,
+    pub target: Address,
+    /// The name of the called contract
+    pub contract: String,
+    pub calldata: Bytes,
+    /// The called function and its decoded arguments, e.g. `deposit(5)`
+    pub decoded: String,
+}
+
+impl fmt::Display for InvariantCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sender={:?} target={}({:?}) calldata={}",
+            self.sender, self.contract, self.target, self.decoded
+        )
+    }
+}
+
+/// The outcome of an invariant campaign
+#[derive(Debug)]
+pub struct InvariantTestResult {
+    /// Whether the invariant held after every call
+    pub success: bool,
+
+    /// The reason the invariant broke, or why the campaign couldn't run
+    pub reason: Option<String>,
+
+    /// The shrunk sequence of calls after which the invariant broke
+    pub call_sequence: Option<Vec<InvariantCall>>,
+
+    /// The number of call sequences that were run
+    pub runs: usize,
+
+    /// The number of calls made to the target contracts
+    pub calls: usize,
+
+    /// The number of calls to the target contracts that reverted
+    pub reverts: usize,
+
+    /// The logs of the call that broke the invariant
+    pub logs: Vec<RawLog>,
+
+    /// The traces of the call that broke the invariant
+    pub traces: Option<CallTraceArena>,
+
+    /// Labeled addresses
+    pub labeled_addresses: BTreeMap<Address, String>,
+
+    /// The coverage info collected during all calls
+    pub coverage: Option<HitMaps>,
+}
+
+impl InvariantTestResult {
+    /// The result of a campaign that didn't find a call sequence breaking the invariant
+    fn finished(success: bool, (runs, calls, reverts): (usize, usize, usize)) -> Self {
+        Self {
+            success,
+            reason: None,
+            call_sequence: None,
+            runs,
+            calls,
+            reverts,
+            logs: Vec::new(),
+            traces: None,
+            labeled_addresses: BTreeMap::new(),
+            coverage: None,
+        }
+    }
+}
+
+/// Wrapper around an [`Executor`] which calls random sequences of functions of the target
+/// contracts and checks an invariant of the test contract after every call.
+///
+/// Every sequence starts from the state of the wrapped executor, sequences that break the
+/// invariant are shrunk by [`proptest`](https://docs.rs/proptest/1.0.0/proptest/).
+pub struct InvariantExecutor<'a, DB: DatabaseRef> {
+    /// The VM, in the state after `setUp`
+    executor: &'a Executor<DB>,
+    /// The fuzzer
+    runner: TestRunner,
+    /// The account that calls the invariants, and one of the senders of the calls
+    sender: Address,
+    /// The number and length of the call sequences
+    config: InvariantConfig,
+    /// No new sequences are run once the deadline has passed
+    deadline: Option<Instant>,
+}
+
+impl<'a, DB> InvariantExecutor<'a, DB>
+where
+    DB: DatabaseRef + Clone,
+{
+    /// Instantiates an invariant executor, the runner's number of cases is replaced by the
+    /// number of runs of the config
+    pub fn new(
+        executor: &'a Executor<DB>,
+        mut runner: TestRunner,
+        sender: Address,
+        config: InvariantConfig,
+    ) -> Self {
+        let fuzz_config =
+            proptest::test_runner::Config { cases: config.runs, ..runner.config().clone() };
+        let runner = TestRunner::new_with_rng(fuzz_config, runner.new_rng());
+        Self { executor, runner, sender, config, deadline: None }
+    }
+
+    /// Stops the campaign once the deadline has passed
+    #[must_use]
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Calls random sequences of the targets' state changing functions and checks the invariant
+    /// of the test contract at `address` before the first and after every call
+    pub fn invariant_fuzz(
+        &self,
+        invariant: &Function,
+        address: Address,
+        targets: &[TargetContract],
+        errors: Option<&Abi>,
+    ) -> InvariantTestResult {
+        // The invariant has to hold before any call
+        let (mut call, broken) = self.check_invariant(self.executor, invariant, address);
+        let coverage = RefCell::new(call.coverage.take());
+        if broken {
+            return InvariantTestResult {
+                coverage: coverage.into_inner(),
+                ..self.broken(Vec::new(), call, targets, errors, (0, 0, 0))
+            }
+        }
+
+        let functions = targets
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, target)| target.mutable_functions().map(move |func| (idx, func)))
+            .collect::<Vec<_>>();
+        if functions.is_empty() {
+            return InvariantTestResult {
+                reason: Some(
+                    "No target contract with state changing functions, deploy them in `setUp()` or return them from `targetContracts()`".to_string(),
+                ),
+                ..InvariantTestResult::finished(false, (0, 0, 0))
+            }
+        }
+
+        let senders = Union::new_weighted(vec![
+            (50, Just(self.sender).boxed()),
+            (
+                50,
+                fuzz_param(&ParamType::Address)
+                    .prop_map(|token| token.into_address().expect("fuzzed an address"))
+                    .boxed(),
+            ),
+        ]);
+        let calls = Union::new(functions.into_iter().map(|(idx, func)| {
+            fuzz_calldata(func.clone()).prop_map(move |calldata| (idx, calldata)).boxed()
+        }));
+        let strat =
+            proptest::collection::vec((senders, calls), 1..=self.config.depth.max(1) as usize);
+
+        let (runs, calls, reverts) = (Cell::new(0), Cell::new(0), Cell::new(0));
+        // Stores the prefix of the last sequence that broke the invariant and the failed check
+        let failure: RefCell<Option<(Vec<(Address, usize, Bytes)>, RawCallResult)>> =
+            RefCell::new(None);
+
+        tracing::debug!(invariant = ?invariant.name, "invariant fuzzing");
+        let run_result = self.runner.clone().run(&strat, |sequence| {
+            if self.deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                return Err(TestCaseError::reject("timed out"))
+            }
+            runs.set(runs.get() + 1);
+
+            let mut executor = self.executor.clone();
+            let mut done = Vec::with_capacity(sequence.len());
+            for (sender, (target, calldata)) in sequence {
+                let mut call = executor
+                    .call_raw_committing(
+                        sender,
+                        targets[target].address,
+                        calldata.0.clone(),
+                        0.into(),
+                    )
+                    .expect("could not make raw evm call");
+                merge_coverage(&mut coverage.borrow_mut(), call.coverage.take());
+                calls.set(calls.get() + 1);
+                if call.reverted {
+                    reverts.set(reverts.get() + 1);
+                }
+                done.push((sender, target, calldata));
+
+                let (mut check, broken) = self.check_invariant(&executor, invariant, address);
+                merge_coverage(&mut coverage.borrow_mut(), check.coverage.take());
+                if broken {
+                    let reason = revert_reason(&check, errors).unwrap_or_default();
+                    *failure.borrow_mut() = Some((done, check));
+                    return Err(TestCaseError::fail(reason))
+                }
+            }
+            Ok(())
+        });
+
+        let stats = (runs.get(), calls.get(), reverts.get());
+        let result = match (run_result, failure.into_inner()) {
+            (Err(TestError::Fail(..)), Some((sequence, check))) => {
+                self.broken(sequence, check, targets, errors, stats)
+            }
+            (Err(TestError::Abort(reason)), _) => InvariantTestResult {
+                reason: Some(reason.to_string()),
+                ..InvariantTestResult::finished(false, stats)
+            },
+            _ => InvariantTestResult::finished(true, stats),
+        };
+        InvariantTestResult { coverage: coverage.into_inner(), ..result }
+    }
+
+    /// Calls the invariant, returns the call and whether the invariant is broken
+    ///
+    /// An invariant is broken if it reverts, fails a DSTest assertion or returns `false`.
+    fn check_invariant(
+        &self,
+        executor: &Executor<DB>,
+        invariant: &Function,
+        address: Address,
+    ) -> (RawCallResult, bool) {
+        let calldata = invariant.encode_input(&[]).expect("invariants take no arguments");
+        let call = executor
+            .call_raw(self.sender, address, calldata.into(), 0.into())
+            .expect("could not make raw evm call");
+        let state_changeset =
+            call.state_changeset.clone().expect("we should have a state changeset");
+        let mut success = executor.is_success(address, call.reverted, state_changeset, false);
+
+        if success && invariant.outputs.len() == 1 && invariant.outputs[0].kind == ParamType::Bool {
+            success = invariant
+                .decode_output(call.result.as_ref())
+                .ok()
+                .and_then(|tokens| tokens.into_iter().next()?.into_bool())
+                .unwrap_or(true);
+        }
+        (call, !success)
+    }
+
+    /// Builds the result of a broken invariant
+    fn broken(
+        &self,
+        sequence: Vec<(Address, usize, Bytes)>,
+        check: RawCallResult,
+        targets: &[TargetContract],
+        errors: Option<&Abi>,
+        (runs, calls, reverts): (usize, usize, usize),
+    ) -> InvariantTestResult {
+        let call_sequence = sequence
+            .into_iter()
+            .map(|(sender, target, calldata)| {
+                let target = &targets[target];
+                InvariantCall {
+                    sender,
+                    target: target.address,
+                    contract: target.name.clone(),
+                    decoded: target.decode(calldata.as_ref()),
+                    calldata,
+                }
+            })
+            .collect();
+
+        InvariantTestResult {
+            success: false,
+            reason: revert_reason(&check, errors),
+            call_sequence: Some(call_sequence),
+            runs,
+            calls,
+            reverts,
+            logs: check.logs,
+            traces: check.traces,
+            labeled_addresses: check.labels,
+            coverage: None,
+        }
+    }
+}
+
+/// Decodes the revert reason of the call, if it reverted
+fn revert_reason(call: &RawCallResult, errors: Option<&Abi>) -> Option<String> {
+    if !call.reverted {
+        return None
+    }
+    foundry_utils::decode_revert(call.result.as_ref(), errors)
+        .ok()
+        .filter(|reason| !reason.is_empty())
+}
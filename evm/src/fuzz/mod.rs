@@ -1,8 +1,10 @@
+pub mod invariant;
 mod strategies;
 
 pub use proptest::test_runner::{Config as FuzzConfig, Reason};
 
 use crate::{
+    coverage::{merge_coverage, HitMaps},
     executor::{Executor, RawCallResult},
     trace::CallTraceArena,
 };
@@ -10,10 +12,10 @@ use ethers::{
     abi::{Abi, Function, RawLog, Token},
     types::{Address, Bytes},
 };
-use proptest::test_runner::{TestCaseError, TestError, TestRunner};
+use proptest::test_runner::{RngAlgorithm, TestCaseError, TestError, TestRng, TestRunner};
 use revm::db::DatabaseRef;
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, collections::BTreeMap, fmt};
+use std::{cell::RefCell, collections::BTreeMap, fmt, time::Instant};
 use strategies::{
     build_initial_state, collect_state_from_call, fuzz_calldata, fuzz_calldata_from_state,
     EvmFuzzState,
@@ -22,6 +24,16 @@ use strategies::{
 /// Magic return code for the `assume` cheatcode
 pub const ASSUME_MAGIC_RETURN_CODE: &[u8] = b"FOUNDRY::ASSUME";
 
+/// Returns a fuzzer whose random number generator is seeded with the given seed
+///
+/// Campaigns run with the same seed generate the same inputs and shrink to the same
+/// counterexamples.
+pub fn seeded_runner(config: FuzzConfig, seed: u64) -> TestRunner {
+    let mut bytes = [0u8; 32];
+    bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    TestRunner::new_with_rng(config, TestRng::from_seed(RngAlgorithm::ChaCha, &bytes))
+}
+
 /// Wrapper around an [`Executor`] which provides fuzzing support using [`proptest`](https://docs.rs/proptest/1.0.0/proptest/).
 ///
 /// After instantiation, calling `fuzz` will proceed to hammer the deployed smart contract with
@@ -34,6 +46,10 @@ pub struct FuzzedExecutor<'a, DB: DatabaseRef> {
     runner: TestRunner,
     /// The account that calls tests
     sender: Address,
+    /// No new cases are run once the deadline has passed
+    deadline: Option<Instant>,
+    /// Inputs that are run before the random campaign, e.g. counterexamples of earlier runs
+    corpus: Vec<Bytes>,
 }
 
 impl<'a, DB> FuzzedExecutor<'a, DB>
@@ -42,7 +58,23 @@ where
 {
     /// Instantiates a fuzzed executor given a testrunner
     pub fn new(executor: &'a Executor<DB>, runner: TestRunner, sender: Address) -> Self {
-        Self { executor, runner, sender }
+        Self { executor, runner, sender, deadline: None, corpus: Vec::new() }
+    }
+
+    /// Stops the campaign once the deadline has passed
+    ///
+    /// Every case started after the deadline is rejected without being executed.
+    #[must_use]
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Runs the inputs before the random campaign, the first failing input is the counterexample
+    #[must_use]
+    pub fn with_corpus(mut self, corpus: Vec<Bytes>) -> Self {
+        self.corpus = corpus;
+        self
     }
 
     /// Fuzzes the provided function, assuming it is available at the contract at `address`
@@ -63,6 +95,15 @@ where
         // Stores the result and calldata of the last failed call, if any.
         let counterexample: RefCell<(Bytes, RawCallResult)> = RefCell::new(Default::default());
 
+        // Stores the coverage of all fuzz calls
+        let mut coverage = None;
+
+        if let Some(result) = self.replay_corpus(func, address, should_fail, errors, &mut coverage)
+        {
+            return result
+        }
+        let coverage = RefCell::new(coverage);
+
         // Stores fuzz state for use with [fuzz_calldata_from_state]
         let state: EvmFuzzState = build_initial_state(&self.executor.db);
 
@@ -74,10 +115,15 @@ where
         ]);
         tracing::debug!(func = ?func.name, should_fail, "fuzzing");
         let run_result = self.runner.clone().run(&strat, |calldata| {
-            let call = self
+            if self.deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                return Err(TestCaseError::reject("timed out"))
+            }
+
+            let mut call = self
                 .executor
                 .call_raw(self.sender, address, calldata.0.clone(), 0.into())
                 .expect("could not make raw evm call");
+            merge_coverage(&mut coverage.borrow_mut(), call.coverage.take());
             let state_changeset =
                 call.state_changeset.as_ref().expect("we should have a state changeset");
 
@@ -130,6 +176,7 @@ where
             logs: call.logs,
             traces: call.traces,
             labeled_addresses: call.labels,
+            coverage: coverage.into_inner(),
         };
 
         match run_result {
@@ -150,6 +197,54 @@ where
 
         result
     }
+
+    /// Runs the inputs of the corpus, returns the result of the first input that fails
+    fn replay_corpus(
+        &self,
+        func: &Function,
+        address: Address,
+        should_fail: bool,
+        errors: Option<&Abi>,
+        coverage: &mut Option<HitMaps>,
+    ) -> Option<FuzzTestResult> {
+        for calldata in &self.corpus {
+            let mut call = self
+                .executor
+                .call_raw(self.sender, address, calldata.0.clone(), 0.into())
+                .expect("could not make raw evm call");
+            merge_coverage(coverage, call.coverage.take());
+            let state_changeset =
+                call.state_changeset.as_ref().expect("we should have a state changeset");
+            if call.result.as_ref() == ASSUME_MAGIC_RETURN_CODE ||
+                self.executor.is_success(
+                    address,
+                    call.reverted,
+                    state_changeset.clone(),
+                    should_fail,
+                )
+            {
+                continue
+            }
+
+            tracing::debug!(func = ?func.name, ?calldata, "stored counterexample failed");
+            let reason = foundry_utils::decode_revert(call.result.as_ref(), errors)
+                .ok()
+                .filter(|reason| !reason.is_empty());
+            let args =
+                func.decode_input(&calldata.as_ref()[4..]).expect("could not decode fuzzer inputs");
+            return Some(FuzzTestResult {
+                cases: FuzzedCases::new(Vec::new()),
+                success: false,
+                reason,
+                counterexample: Some(CounterExample { calldata: calldata.clone(), args }),
+                logs: call.logs,
+                traces: call.traces,
+                labeled_addresses: call.labels,
+                coverage: coverage.take(),
+            })
+        }
+        None
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -194,6 +289,9 @@ pub struct FuzzTestResult {
 
     /// Labeled addresses
     pub labeled_addresses: BTreeMap<Address, String>,
+
+    /// The coverage info collected during all fuzz cases
+    pub coverage: Option<HitMaps>,
 }
 
 /// Container type for all successful test cases
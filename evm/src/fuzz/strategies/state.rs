@@ -10,12 +10,13 @@ use revm::{
     db::{CacheDB, DatabaseRef},
     opcode, spec_opcode_gas, SpecId,
 };
-use std::{cell::RefCell, collections::HashSet, io::Write, rc::Rc};
+use std::{cell::RefCell, collections::BTreeSet, io::Write, rc::Rc};
 
 /// A set of arbitrary 32 byte data from the VM used to generate values for the strategy.
 ///
-/// Wrapped in a shareable container.
-pub type EvmFuzzState = Rc<RefCell<HashSet<[u8; 32]>>>;
+/// Wrapped in a shareable container. The values are ordered, so a seeded fuzzer picks the same
+/// values on every run.
+pub type EvmFuzzState = Rc<RefCell<BTreeSet<[u8; 32]>>>;
 
 /// Given a function and some state, it returns a strategy which generated valid calldata for the
 /// given function's input types, based on state taken from the EVM.
@@ -48,7 +49,7 @@ This is a bug, please open an issue: https://github.com/gakonst/foundry/issues"#
 
 /// Builds the initial [EvmFuzzState] from a database.
 pub fn build_initial_state<DB: DatabaseRef>(db: &CacheDB<DB>) -> EvmFuzzState {
-    let mut state: HashSet<[u8; 32]> = HashSet::new();
+    let mut state: BTreeSet<[u8; 32]> = BTreeSet::new();
     for (address, storage) in db.storage() {
         let info = db.basic(*address);
 
@@ -67,8 +68,8 @@ pub fn build_initial_state<DB: DatabaseRef>(db: &CacheDB<DB>) -> EvmFuzzState {
     // need at least some state data if db is empty otherwise we can't select random data for state
     // fuzzing
     if state.is_empty() {
-        // prefill with a random addresses
-        state.insert(H256::from(Address::random()).into());
+        // prefill with the zero address
+        state.insert(H256::from(Address::zero()).into());
     }
 
     Rc::new(RefCell::new(state))
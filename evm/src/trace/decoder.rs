@@ -207,22 +207,13 @@ impl CallTraceDecoder {
                 if bytes.len() >= 4 {
                     if let Some(funcs) = self.functions.get(&bytes[0..4]) {
                         node.decode_function(funcs, &self.labels, &self.errors);
+                    } else {
+                        // Unknown selectors stay hex encoded, but the revert reason is decoded
+                        self.decode_revert(node);
                     }
                 } else {
                     node.trace.data = RawOrDecodedCall::Decoded("fallback".to_string(), Vec::new());
-
-                    if let RawOrDecodedReturnData::Raw(bytes) = &node.trace.output {
-                        if !node.trace.success {
-                            if let Ok(decoded_error) =
-                                foundry_utils::decode_revert(&bytes[..], Some(&self.errors))
-                            {
-                                node.trace.output = RawOrDecodedReturnData::Decoded(format!(
-                                    r#""{}""#,
-                                    decoded_error
-                                ));
-                            }
-                        }
-                    }
+                    self.decode_revert(node);
                 }
             }
 
@@ -231,6 +222,20 @@ impl CallTraceDecoder {
         }
     }
 
+    /// Decodes the output of the call if it reverted
+    fn decode_revert(&self, node: &mut CallTraceNode) {
+        if let RawOrDecodedReturnData::Raw(bytes) = &node.trace.output {
+            if !node.trace.success {
+                if let Ok(decoded_error) =
+                    foundry_utils::decode_revert(&bytes[..], Some(&self.errors))
+                {
+                    node.trace.output =
+                        RawOrDecodedReturnData::Decoded(format!(r#""{}""#, decoded_error));
+                }
+            }
+        }
+    }
+
     fn decode_events(&self, node: &mut CallTraceNode) {
         node.logs.iter_mut().for_each(|log| {
             self.decode_event(log);
@@ -289,3 +294,41 @@ where
 fn indexed_inputs(event: &Event) -> usize {
     event.inputs.iter().filter(|param| param.indexed).count()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::CallTrace;
+    use ethers::{abi::encode, types::U256};
+
+    #[test]
+    fn decodes_reverts_of_unknown_selectors() {
+        let address = Address::from_low_u64_be(0x1234);
+        let mut revert = vec![0x08, 0xc3, 0x79, 0xa0];
+        revert.extend(encode(&[Token::String("boom".to_string())]));
+
+        let mut arena = CallTraceArena::default();
+        arena.push_trace(
+            0,
+            CallTrace {
+                address,
+                value: U256::from(5),
+                data: RawOrDecodedCall::Raw(vec![0xde, 0xad, 0xbe, 0xef, 0x01]),
+                output: RawOrDecodedReturnData::Raw(revert),
+                ..Default::default()
+            },
+        );
+
+        let decoder = CallTraceDecoder::new_with_labels([(address, "Vault".to_string())].into());
+        decoder.decode(&mut arena);
+
+        let trace = &arena.arena[0].trace;
+        assert!(matches!(trace.data, RawOrDecodedCall::Raw(_)));
+        assert_eq!(trace.output.to_string(), r#""boom""#);
+
+        let display = arena.to_string();
+        assert!(display.contains("Vault"), "{}", display);
+        assert!(display.contains("deadbeef"), "{}", display);
+        assert!(display.contains("{value: 5}"), "{}", display);
+    }
+}
@@ -7,7 +7,8 @@ use std::{borrow::Cow, collections::BTreeMap};
 
 /// A trace identifier that tries to identify addresses using local contracts.
 pub struct LocalTraceIdentifier {
-    local_contracts: BTreeMap<Vec<u8>, (String, Abi)>,
+    /// The identifier, name and ABI of every contract by runtime code
+    local_contracts: BTreeMap<Vec<u8>, (String, String, Abi)>,
 }
 
 impl LocalTraceIdentifier {
@@ -16,7 +17,7 @@ impl LocalTraceIdentifier {
             local_contracts: known_contracts
                 .iter()
                 .map(|(id, (abi, runtime_code))| {
-                    (runtime_code.clone(), (id.name.clone(), abi.clone()))
+                    (runtime_code.clone(), (id.identifier(), id.name.clone(), abi.clone()))
                 })
                 .collect(),
         }
@@ -32,14 +33,14 @@ impl TraceIdentifier for LocalTraceIdentifier {
             .into_iter()
             .filter_map(|(address, code)| {
                 let code = code?;
-                let (_, (name, abi)) = self
+                let (_, (identifier, name, abi)) = self
                     .local_contracts
                     .iter()
                     .find(|(known_code, _)| diff_score(known_code, code) < 0.1)?;
 
                 Some(AddressIdentity {
                     address: *address,
-                    contract: Some(name.clone()),
+                    contract: Some(identifier.clone()),
                     label: Some(name.clone()),
                     abi: Some(Cow::Borrowed(abi)),
                 })
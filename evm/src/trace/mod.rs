@@ -80,6 +80,28 @@ impl CallTraceArena {
             })
             .collect()
     }
+
+    /// Returns the logs of all calls in the order they were emitted, with the address of the
+    /// contract that emitted them
+    pub fn logs(&self) -> Vec<(Address, &RawOrDecodedLog)> {
+        fn inner<'a>(
+            arena: &'a CallTraceArena,
+            idx: usize,
+            logs: &mut Vec<(Address, &'a RawOrDecodedLog)>,
+        ) {
+            let node = &arena.arena[idx];
+            for order in &node.ordering {
+                match order {
+                    LogCallOrder::Log(i) => logs.push((node.trace.address, &node.logs[*i])),
+                    LogCallOrder::Call(i) => inner(arena, node.children[*i], logs),
+                }
+            }
+        }
+
+        let mut logs = Vec::new();
+        inner(self, 0, &mut logs);
+        logs
+    }
 }
 
 const PIPE: &str = "  │ ";
@@ -0,0 +1,155 @@
+use crate::SuiteResult;
+use ethers::{abi::Function, types::Bytes};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+/// The counterexamples found by fuzz tests, replayed before the random campaign of later runs
+///
+/// Stored as JSON, keyed by the identifier of the test contract and the signature of the test:
+///
+/// ```json
+/// {
+///   "src/test/Vault.t.sol:VaultTest": {
+///     "testDeposit(uint256)": ["0x..."]
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FuzzCorpus(BTreeMap<String, BTreeMap<String, Vec<Bytes>>>);
+
+impl FuzzCorpus {
+    /// Reads the corpus from the file, a missing file is an empty corpus
+    pub fn read(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default())
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the corpus to the file
+    pub fn write(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Adds the counterexample of the test, if it isn't stored already
+    pub fn add(&mut self, contract: &str, test: &str, calldata: Bytes) {
+        let inputs =
+            self.0.entry(contract.to_string()).or_default().entry(test.to_string()).or_default();
+        if !inputs.contains(&calldata) {
+            inputs.push(calldata);
+        }
+    }
+
+    /// Adds the counterexamples of all failed fuzz tests
+    pub fn record(&mut self, results: &BTreeMap<String, SuiteResult>) {
+        for (contract, suite) in results {
+            for (test, result) in &suite.test_results {
+                if let (false, Some(counterexample)) = (result.success, &result.counterexample) {
+                    self.add(contract, test, counterexample.calldata.clone());
+                }
+            }
+        }
+    }
+
+    /// Returns the stored counterexamples of the contract's tests
+    pub fn contract(&self, contract: &str) -> ContractCorpus {
+        ContractCorpus(self.0.get(contract).cloned().unwrap_or_default())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.values().all(|tests| tests.values().all(Vec::is_empty))
+    }
+}
+
+/// The stored counterexamples of a single test contract, keyed by test signature
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContractCorpus(BTreeMap<String, Vec<Bytes>>);
+
+impl ContractCorpus {
+    /// Returns the stored counterexamples that can be replayed against the fuzz test
+    ///
+    /// Counterexamples that were stored for an earlier signature of the test, or that can't be
+    /// decoded anymore, are skipped with a warning.
+    pub fn inputs(&self, func: &Function, warnings: &mut Vec<String>) -> Vec<Bytes> {
+        let signature = func.signature();
+        for stale in self.0.keys().filter(|test| {
+            **test != signature && test.split('(').next() == Some(func.name.as_str())
+        }) {
+            warnings.push(format!(
+                "Skipping stored counterexamples of \"{}\", the test's signature changed to \"{}\"",
+                stale, signature
+            ));
+        }
+
+        let selector = func.short_signature();
+        self.0
+            .get(&signature)
+            .into_iter()
+            .flatten()
+            .filter(|calldata| {
+                let valid = calldata.len() >= 4 &&
+                    calldata[..4] == selector &&
+                    func.decode_input(&calldata[4..]).is_ok();
+                if !valid {
+                    warnings.push(format!(
+                        "Skipping stored counterexample {} of \"{}\", it can't be decoded",
+                        calldata, signature
+                    ));
+                }
+                valid
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{Param, ParamType};
+
+    fn func(name: &str, kind: ParamType) -> Function {
+        #[allow(deprecated)]
+        Function {
+            name: name.to_string(),
+            inputs: vec![Param { name: "x".to_string(), kind, internal_type: None }],
+            outputs: vec![],
+            constant: None,
+            state_mutability: ethers::abi::StateMutability::NonPayable,
+        }
+    }
+
+    #[test]
+    fn skips_stale_counterexamples() {
+        let old = func("testDeposit", ParamType::Uint(8));
+        let new = func("testDeposit", ParamType::Uint(256));
+        let calldata = |func: &Function| {
+            Bytes::from(func.encode_input(&[ethers::abi::Token::Uint(5u64.into())]).unwrap())
+        };
+
+        let mut corpus = FuzzCorpus::default();
+        corpus.add("src/A.t.sol:ATest", &old.signature(), calldata(&old));
+        corpus.add("src/A.t.sol:ATest", &new.signature(), calldata(&new));
+        corpus.add("src/A.t.sol:ATest", &new.signature(), calldata(&new));
+        corpus.add("src/A.t.sol:ATest", &new.signature(), Bytes::from(vec![0xde, 0xad]));
+
+        let json = serde_json::to_string(&corpus).unwrap();
+        let corpus: FuzzCorpus = serde_json::from_str(&json).unwrap();
+
+        let mut warnings = Vec::new();
+        let inputs = corpus.contract("src/A.t.sol:ATest").inputs(&new, &mut warnings);
+        assert_eq!(inputs, vec![calldata(&new)]);
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].contains("testDeposit(uint8)"));
+
+        let mut warnings = Vec::new();
+        assert!(corpus.contract("src/B.t.sol:BTest").inputs(&new, &mut warnings).is_empty());
+        assert!(warnings.is_empty());
+    }
+}
@@ -5,11 +5,17 @@ use crate::{
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
 use ethers::types::U256;
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, fmt::Display};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    path::Path,
+};
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct GasReport {
     pub report_for: Vec<String>,
+    /// The gas usage of every contract, keyed by the `<artifact>:<contract>` identifier if the
+    /// contract is a local contract
     pub contracts: BTreeMap<String, ContractInfo>,
 }
 
@@ -54,7 +60,7 @@ impl GasReport {
         }
 
         if let Some(name) = &trace.contract {
-            let report_for = self.report_for.iter().any(|s| s == name);
+            let report_for = self.report_for.iter().any(|s| s == name || s == contract_name(name));
             if report_for || report_for_all {
                 let mut contract_report =
                     self.contracts.entry(name.to_string()).or_insert_with(Default::default);
@@ -108,6 +114,221 @@ impl GasReport {
         });
         self
     }
+
+    /// Renders the finalized report as a JSON object keyed by contract identifier
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        let contracts = self
+            .contracts
+            .iter()
+            .map(|(name, contract)| (name.as_str(), JsonContractReport::from(contract)))
+            .collect::<BTreeMap<_, _>>();
+        serde_json::to_string_pretty(&contracts)
+    }
+
+    /// Renders the finalized report as GitHub flavored markdown, with a table per contract
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        for (name, contract) in self.contracts.iter() {
+            if contract.functions.is_empty() {
+                continue
+            }
+
+            md.push_str(&format!("### `{}`\n\n", name));
+            md.push_str("| Deployment Cost | Deployment Size |\n| ---: | ---: |\n");
+            md.push_str(&format!("| {} | {} |\n\n", contract.gas, contract.size));
+            md.push_str("| Function Name | min | avg | median | max | # calls |\n");
+            md.push_str("| --- | ---: | ---: | ---: | ---: | ---: |\n");
+            for (fname, function) in contract.functions.iter() {
+                md.push_str(&format!(
+                    "| {} | {} | {} | {} | {} | {} |\n",
+                    fname,
+                    function.min,
+                    function.mean,
+                    function.median,
+                    function.max,
+                    function.calls.len()
+                ));
+            }
+            md.push('\n');
+        }
+        md
+    }
+}
+
+/// A gas report as rendered by [GasReport::to_json], keyed by contract identifier
+pub type JsonGasReport = BTreeMap<String, JsonContractReport>;
+
+/// Reads a gas report that was saved as JSON, e.g. to compare against with [GasReportDiff]
+pub fn read_json_report(path: impl AsRef<Path>) -> eyre::Result<JsonGasReport> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| eyre::eyre!("failed to read gas report {}: {}", path.display(), err))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// The median gas of a function in the baseline and the current report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionGasDiff {
+    pub contract: String,
+    pub function: String,
+    /// `None` if the function was added
+    pub baseline: Option<u64>,
+    /// `None` if the function was removed
+    pub current: Option<u64>,
+}
+
+impl FunctionGasDiff {
+    /// The change in gas, `None` if the function only exists in one of the reports
+    pub fn delta(&self) -> Option<i128> {
+        Some(self.current? as i128 - self.baseline? as i128)
+    }
+
+    /// The change in gas in percent of the baseline
+    pub fn delta_pct(&self) -> Option<f64> {
+        Some(self.delta()? as f64 / self.baseline? as f64 * 100.0)
+    }
+
+    /// Whether the function uses more than `tolerance` percent more gas than in the baseline
+    pub fn exceeds_tolerance(&self, tolerance: f64) -> bool {
+        match (self.delta(), self.delta_pct()) {
+            (Some(delta), Some(pct)) => delta > 0 && pct > tolerance,
+            _ => false,
+        }
+    }
+}
+
+/// The per function comparison of a gas report with a baseline
+#[derive(Debug, Clone)]
+pub struct GasReportDiff {
+    pub diffs: Vec<FunctionGasDiff>,
+    /// Functions whose gas increased by more than this percentage are regressions
+    pub tolerance: f64,
+}
+
+impl GasReportDiff {
+    /// Compares the median gas of every function of the report with the baseline
+    pub fn new(report: &GasReport, baseline: &JsonGasReport, tolerance: f64) -> Self {
+        let current = report
+            .contracts
+            .iter()
+            .map(|(name, contract)| (name.clone(), JsonContractReport::from(contract)))
+            .collect::<JsonGasReport>();
+
+        let mut diffs = Vec::new();
+        for contract in current.keys().chain(baseline.keys()).collect::<BTreeSet<_>>() {
+            let functions = |report: &JsonGasReport| {
+                report
+                    .get(contract)
+                    .map(|contract| {
+                        contract
+                            .functions
+                            .iter()
+                            .map(|(name, function)| (name.clone(), function.median))
+                            .collect::<BTreeMap<_, _>>()
+                    })
+                    .unwrap_or_default()
+            };
+            let (baseline, current) = (functions(baseline), functions(&current));
+            for function in current.keys().chain(baseline.keys()).collect::<BTreeSet<_>>() {
+                diffs.push(FunctionGasDiff {
+                    contract: contract.clone(),
+                    function: function.clone(),
+                    baseline: baseline.get(function).copied(),
+                    current: current.get(function).copied(),
+                });
+            }
+        }
+        Self { diffs, tolerance }
+    }
+
+    /// Returns the functions whose gas increased by more than the tolerance
+    pub fn regressions(&self) -> impl Iterator<Item = &FunctionGasDiff> {
+        self.diffs.iter().filter(|diff| diff.exceeds_tolerance(self.tolerance))
+    }
+}
+
+impl Display for GasReportDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
+        table.set_header(
+            ["Contract", "Function", "Baseline", "Current", "Delta"]
+                .map(|header| Cell::new(header).add_attribute(Attribute::Bold)),
+        );
+        for diff in &self.diffs {
+            let gas = |gas: Option<u64>| gas.map(|gas| gas.to_string()).unwrap_or_default();
+            let delta = match (diff.delta(), diff.delta_pct()) {
+                (Some(delta), Some(pct)) => {
+                    let cell = Cell::new(format!("{:+} ({:+.2}%)", delta, pct));
+                    if diff.exceeds_tolerance(self.tolerance) {
+                        cell.fg(Color::Red)
+                    } else if delta < 0 {
+                        cell.fg(Color::Green)
+                    } else {
+                        cell
+                    }
+                }
+                (Some(delta), None) => Cell::new(format!("{:+}", delta)),
+                _ if diff.baseline.is_none() => Cell::new("added").fg(Color::Cyan),
+                _ => Cell::new("removed").fg(Color::Cyan),
+            };
+            table.add_row(vec![
+                Cell::new(contract_name(&diff.contract)),
+                Cell::new(&diff.function),
+                Cell::new(gas(diff.baseline)),
+                Cell::new(gas(diff.current)),
+                delta,
+            ]);
+        }
+        write!(f, "{}", table)
+    }
+}
+
+/// Returns the name of the contract, without the artifact of the `<artifact>:<contract>`
+/// identifier
+fn contract_name(identifier: &str) -> &str {
+    identifier.rsplit(':').next().unwrap_or(identifier)
+}
+
+/// The gas usage of a contract as rendered by [GasReport::to_json]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonContractReport {
+    pub deployment_gas: u64,
+    pub deployment_size: u64,
+    pub functions: BTreeMap<String, JsonFunctionReport>,
+}
+
+/// The gas usage of a function as rendered by [GasReport::to_json]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonFunctionReport {
+    pub min: u64,
+    pub avg: u64,
+    pub median: u64,
+    pub max: u64,
+    pub calls: usize,
+}
+
+impl From<&ContractInfo> for JsonContractReport {
+    fn from(contract: &ContractInfo) -> Self {
+        Self {
+            deployment_gas: contract.gas.low_u64(),
+            deployment_size: contract.size.low_u64(),
+            functions: contract
+                .functions
+                .iter()
+                .map(|(name, function)| {
+                    let report = JsonFunctionReport {
+                        min: function.min.low_u64(),
+                        avg: function.mean.low_u64(),
+                        median: function.median.low_u64(),
+                        max: function.max.low_u64(),
+                        calls: function.calls.len(),
+                    };
+                    (name.clone(), report)
+                })
+                .collect(),
+        }
+    }
 }
 
 impl Display for GasReport {
@@ -119,7 +340,7 @@ impl Display for GasReport {
 
             let mut table = Table::new();
             table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
-            table.set_header(vec![Cell::new(format!("{} contract", name))
+            table.set_header(vec![Cell::new(format!("{} contract", contract_name(name)))
                 .add_attribute(Attribute::Bold)
                 .fg(Color::Green)]);
             table.add_row(vec![
@@ -151,3 +372,91 @@ impl Display for GasReport {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> GasReport {
+        let contract = |calls: &[u64]| {
+            let calls = calls.iter().map(|gas| (*gas).into()).collect();
+            ContractInfo {
+                gas: 1000.into(),
+                size: 500.into(),
+                functions: BTreeMap::from([(
+                    "deposit".to_string(),
+                    GasInfo { calls, ..Default::default() },
+                )]),
+            }
+        };
+        GasReport {
+            report_for: vec![],
+            contracts: BTreeMap::from([
+                ("src/a/Vault.sol:Vault".to_string(), contract(&[300, 100, 200])),
+                ("src/b/Vault.sol:Vault".to_string(), contract(&[50])),
+            ]),
+        }
+        .finalize()
+    }
+
+    #[test]
+    fn renders_json_report() {
+        let json = report().to_json().unwrap();
+        let contracts: BTreeMap<String, JsonContractReport> = serde_json::from_str(&json).unwrap();
+        assert_eq!(contracts.len(), 2);
+        assert_eq!(
+            contracts["src/a/Vault.sol:Vault"],
+            JsonContractReport {
+                deployment_gas: 1000,
+                deployment_size: 500,
+                functions: BTreeMap::from([(
+                    "deposit".to_string(),
+                    JsonFunctionReport { min: 100, avg: 200, median: 200, max: 300, calls: 3 }
+                )]),
+            }
+        );
+        assert_eq!(contracts["src/b/Vault.sol:Vault"].functions["deposit"].calls, 1);
+    }
+
+    #[test]
+    fn diffs_against_baseline() {
+        let mut baseline: JsonGasReport =
+            serde_json::from_str(&report().to_json().unwrap()).unwrap();
+        let vault = baseline.get_mut("src/a/Vault.sol:Vault").unwrap();
+        let deposit = vault.functions.remove("deposit").unwrap();
+        // the median was 180 before, the 200 gas now are an increase of 11.1%
+        let regressed = JsonFunctionReport { median: 180, ..deposit.clone() };
+        vault.functions.insert("deposit".to_string(), regressed);
+        vault.functions.insert("withdraw".to_string(), deposit);
+        baseline.get_mut("src/b/Vault.sol:Vault").unwrap().functions.clear();
+
+        let diff = GasReportDiff::new(&report(), &baseline, 10.0);
+        let find = |contract: &str, function: &str| {
+            diff.diffs
+                .iter()
+                .find(|diff| diff.contract == contract && diff.function == function)
+                .unwrap()
+                .clone()
+        };
+
+        let deposit = find("src/a/Vault.sol:Vault", "deposit");
+        assert_eq!(deposit.delta(), Some(20));
+        assert!(deposit.exceeds_tolerance(10.0));
+        assert!(!deposit.exceeds_tolerance(12.0));
+        assert_eq!(find("src/a/Vault.sol:Vault", "withdraw").current, None);
+        assert_eq!(find("src/b/Vault.sol:Vault", "deposit").baseline, None);
+        assert_eq!(diff.regressions().count(), 1);
+
+        let table = diff.to_string();
+        assert!(table.contains("removed") && table.contains("added"));
+    }
+
+    #[test]
+    fn renders_markdown_report() {
+        let md = report().to_markdown();
+        assert!(md.contains("### `src/a/Vault.sol:Vault`\n"));
+        assert!(md.contains("| 1000 | 500 |\n"));
+        assert!(md.contains("| deposit | 100 | 200 | 200 | 300 | 3 |\n"));
+        assert!(md.contains("| deposit | 50 | 50 | 50 | 50 | 1 |\n"));
+    }
+}
@@ -9,6 +9,10 @@ pub use runner::{ContractRunner, SuiteResult, TestKind, TestKindGas, TestResult}
 mod multi_runner;
 pub use multi_runner::{MultiContractRunner, MultiContractRunnerBuilder};
 
+/// Stored counterexamples of fuzz tests
+mod corpus;
+pub use corpus::{ContractCorpus, FuzzCorpus};
+
 pub trait TestFilter {
     fn matches_test(&self, test_name: impl AsRef<str>) -> bool;
     fn matches_contract(&self, contract_name: impl AsRef<str>) -> bool;
@@ -1,4 +1,4 @@
-use crate::{ContractRunner, SuiteResult, TestFilter};
+use crate::{ContractRunner, FuzzCorpus, SuiteResult, TestFilter};
 use ethers::{
     abi::Abi,
     prelude::{artifacts::CompactContractBytecode, ArtifactId, ArtifactOutput},
@@ -6,19 +6,37 @@ use ethers::{
     types::{Address, Bytes, U256},
 };
 use eyre::Result;
-use foundry_evm::executor::{
-    builder::Backend, opts::EvmOpts, DatabaseRef, Executor, ExecutorBuilder, Fork, SpecId,
+use foundry_evm::{
+    executor::{
+        builder::Backend, fork::MultiForkConfig, opts::EvmOpts, DatabaseRef, Executor,
+        ExecutorBuilder, Fork, SpecId,
+    },
+    fuzz::{invariant::InvariantConfig, seeded_runner, FuzzConfig},
 };
 use foundry_utils::{PostLinkInput, RuntimeOrHandle};
 use proptest::test_runner::TestRunner;
 use rayon::prelude::*;
-use std::{collections::BTreeMap, marker::Sync, path::Path, sync::mpsc::Sender};
+use std::{
+    collections::BTreeMap,
+    marker::Sync,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Mutex,
+    },
+    time::Duration,
+};
 
 /// Builder used for instantiating the multi-contract runner
 #[derive(Debug, Default)]
 pub struct MultiContractRunnerBuilder {
     /// The fuzzer to be used for running fuzz tests
     pub fuzzer: Option<TestRunner>,
+    /// The seed of the fuzzer, if it was seeded
+    pub fuzz_seed: Option<u64>,
+    /// Counterexamples of earlier runs, replayed before the random campaigns
+    pub fuzz_corpus: FuzzCorpus,
     /// The address which will be used to deploy the initial contracts and send all
     /// transactions
     pub sender: Option<Address>,
@@ -28,6 +46,18 @@ pub struct MultiContractRunnerBuilder {
     pub evm_spec: Option<SpecId>,
     /// The fork config
     pub fork: Option<Fork>,
+    /// The settings for forks created by the tests
+    pub fork_config: MultiForkConfig,
+    /// The number of test contracts to run in parallel
+    pub jobs: Option<usize>,
+    /// The maximum duration of a single test
+    pub test_timeout: Option<Duration>,
+    /// The number and length of the call sequences of invariant campaigns
+    pub invariant_config: InvariantConfig,
+    /// Whether or not to collect coverage info
+    pub coverage: bool,
+    /// Whether to skip the remaining tests once a test failed
+    pub fail_fast: bool,
 }
 
 pub type DeployableContracts = BTreeMap<ArtifactId, (Abi, Bytes, Vec<Bytes>)>;
@@ -87,7 +117,9 @@ impl MultiContractRunnerBuilder {
                 let abi = contract.abi.expect("We should have an abi by now");
                 // if its a test, add it to deployable contracts
                 if abi.constructor.as_ref().map(|c| c.inputs.is_empty()).unwrap_or(true) &&
-                    abi.functions().any(|func| func.name.starts_with("test"))
+                    abi.functions().any(|func| {
+                        func.name.starts_with("test") || func.name.starts_with("invariant")
+                    })
                 {
                     deployable_contracts
                         .insert(id.clone(), (abi.clone(), bytecode, dependencies.to_vec()));
@@ -110,9 +142,17 @@ impl MultiContractRunnerBuilder {
             evm_spec: self.evm_spec.unwrap_or(SpecId::LONDON),
             sender: self.sender,
             fuzzer: self.fuzzer,
+            fuzz_seed: self.fuzz_seed,
+            fuzz_corpus: self.fuzz_corpus,
             errors: Some(execution_info.2),
             source_paths,
             fork: self.fork,
+            fork_config: self.fork_config,
+            jobs: self.jobs,
+            test_timeout: self.test_timeout,
+            invariant_config: self.invariant_config,
+            coverage: self.coverage,
+            fail_fast: self.fail_fast,
         })
     }
 
@@ -134,6 +174,21 @@ impl MultiContractRunnerBuilder {
         self
     }
 
+    /// Replays the stored counterexamples before the random campaign of each fuzz test
+    #[must_use]
+    pub fn fuzz_corpus(mut self, corpus: FuzzCorpus) -> Self {
+        self.fuzz_corpus = corpus;
+        self
+    }
+
+    /// Uses a fuzzer seeded with the given seed, the seed is reported with every fuzz test result
+    #[must_use]
+    pub fn seeded_fuzzer(mut self, config: FuzzConfig, seed: u64) -> Self {
+        self.fuzzer = Some(seeded_runner(config, seed));
+        self.fuzz_seed = Some(seed);
+        self
+    }
+
     #[must_use]
     pub fn evm_spec(mut self, spec: SpecId) -> Self {
         self.evm_spec = Some(spec);
@@ -145,6 +200,49 @@ impl MultiContractRunnerBuilder {
         self.fork = fork;
         self
     }
+
+    /// Sets the endpoints and the storage caching of the forks created by the tests with the
+    /// `createFork` cheatcode
+    #[must_use]
+    pub fn with_fork_config(mut self, config: MultiForkConfig) -> Self {
+        self.fork_config = config;
+        self
+    }
+
+    /// Sets the number of test contracts to run in parallel, `0` uses the number of logical CPUs
+    #[must_use]
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Fails every test that runs longer than the timeout, fuzz tests are limited as a whole
+    #[must_use]
+    pub fn test_timeout(mut self, timeout: Duration) -> Self {
+        self.test_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the number and length of the call sequences of invariant campaigns
+    #[must_use]
+    pub fn invariant_config(mut self, config: InvariantConfig) -> Self {
+        self.invariant_config = config;
+        self
+    }
+
+    /// Collects the program counters hit by every test, see [crate::TestResult::coverage]
+    #[must_use]
+    pub fn coverage(mut self, enable: bool) -> Self {
+        self.coverage = enable;
+        self
+    }
+
+    /// Skips the tests and contracts that didn't start yet once a test failed
+    #[must_use]
+    pub fn fail_fast(mut self, enable: bool) -> Self {
+        self.fail_fast = enable;
+        self
+    }
 }
 
 /// A multi contract runner receives a set of contracts deployed in an EVM instance and proceeds
@@ -163,26 +261,57 @@ pub struct MultiContractRunner {
     pub errors: Option<Abi>,
     /// The fuzzer which will be used to run parametric tests (w/ non-0 solidity args)
     fuzzer: Option<TestRunner>,
+    /// The seed of the fuzzer, if it was seeded
+    fuzz_seed: Option<u64>,
+    /// Counterexamples of earlier runs, replayed before the random campaigns
+    fuzz_corpus: FuzzCorpus,
     /// The address which will be used as the `from` field in all EVM calls
     sender: Option<Address>,
     /// A map of contract names to absolute source file paths
     pub source_paths: BTreeMap<String, String>,
     /// The fork config
     pub fork: Option<Fork>,
+    /// The settings for forks created by the tests
+    pub fork_config: MultiForkConfig,
+    /// The number of test contracts to run in parallel, defaults to the number of logical CPUs
+    ///
+    /// With a single job the contracts are run one after another on the calling thread.
+    pub jobs: Option<usize>,
+    /// The maximum duration of a single test, fuzz tests are limited as a whole
+    pub test_timeout: Option<Duration>,
+    /// The number and length of the call sequences of invariant campaigns
+    pub invariant_config: InvariantConfig,
+    /// Whether or not to collect coverage info
+    pub coverage: bool,
+    /// Whether to skip the remaining tests once a test failed
+    ///
+    /// Tests that already started keep running, so with multiple jobs a few more tests than
+    /// necessary may be reported.
+    pub fail_fast: bool,
 }
 
 impl MultiContractRunner {
     pub fn count_filtered_tests(&self, filter: &(impl TestFilter + Send + Sync)) -> usize {
-        self.contracts
+        self.filtered_tests(filter).len()
+    }
+
+    /// Returns the `<identifier>::<signature>` of every test that matches the filter, sorted
+    pub fn filtered_tests(&self, filter: &(impl TestFilter + Send + Sync)) -> Vec<String> {
+        let mut tests = self
+            .contracts
             .iter()
             .filter(|(id, _)| {
                 filter.matches_path(id.source.to_string_lossy()) &&
                     filter.matches_contract(&id.name)
             })
-            .flat_map(|(_, (abi, _, _))| {
-                abi.functions().filter(|func| filter.matches_test(func.signature()))
+            .flat_map(|(id, (abi, _, _))| {
+                abi.functions()
+                    .filter(|func| filter.matches_test(func.signature()))
+                    .map(move |func| format!("{}::{}", id.identifier(), func.signature()))
             })
-            .count()
+            .collect::<Vec<_>>();
+        tests.sort();
+        tests
     }
 
     pub fn test(
@@ -197,63 +326,120 @@ impl MultiContractRunner {
         // the db backend that serves all the data
         let db = runtime.block_on(Backend::new(self.fork.take(), &env));
 
-        let results = self
+        let mut contracts = self
             .contracts
-            .par_iter()
+            .iter()
             .filter(|(id, _)| {
                 filter.matches_path(id.source.to_string_lossy()) &&
                     filter.matches_contract(&id.name)
             })
             .filter(|(_, (abi, _, _))| abi.functions().any(|func| filter.matches_test(&func.name)))
-            .map(|(id, (abi, deploy_code, libs))| {
-                let mut builder = ExecutorBuilder::new()
-                    .with_cheatcodes(self.evm_opts.ffi)
-                    .with_config(env.clone())
-                    .with_spec(self.evm_spec)
-                    .with_gas_limit(self.evm_opts.gas_limit());
-
-                if self.evm_opts.verbosity >= 3 {
-                    builder = builder.with_tracing();
-                }
+            .collect::<Vec<_>>();
+        // results are streamed in the same order in which they're returned
+        contracts.sort_by_key(|(id, _)| id.identifier());
 
-                let executor = builder.build(db.clone());
-                let result = self.run_tests(
-                    &id.identifier(),
-                    abi,
-                    executor,
-                    deploy_code.clone(),
-                    libs,
-                    (filter, include_fuzz_tests),
-                )?;
-                Ok((id.identifier(), result))
-            })
-            .filter_map(Result::<_>::ok)
-            .filter(|(_, results)| !results.is_empty())
-            .map_with(stream_result, |stream_result, (name, result)| {
-                if let Some(stream_result) = stream_result.as_ref() {
-                    stream_result.send((name.clone(), result.clone())).unwrap();
-                }
-                (name, result)
-            })
-            .collect::<BTreeMap<_, _>>();
+        // set by the first failed test if fail fast is enabled
+        let failed = AtomicBool::new(false);
+        let fail_fast = self.fail_fast.then(|| &failed);
+
+        // every contract is tested with its own executor
+        type Contract<'a> = (&'a ArtifactId, &'a (Abi, Bytes, Vec<Bytes>));
+        let run_contract = |(id, (abi, deploy_code, libs)): &Contract| -> Result<_> {
+            // contracts without results are neither streamed nor returned
+            if failed.load(Ordering::Relaxed) {
+                return Ok((
+                    id.identifier(),
+                    SuiteResult::new(Duration::ZERO, BTreeMap::new(), vec![]),
+                ))
+            }
+
+            let mut builder = ExecutorBuilder::new()
+                .with_cheatcodes(self.evm_opts.ffi)
+                .with_config(env.clone())
+                .with_spec(self.evm_spec)
+                .with_gas_limit(self.evm_opts.gas_limit())
+                .with_forks(self.fork_config.clone());
+
+            if let Some(ref errors) = self.errors {
+                builder = builder.with_errors(errors.clone());
+            }
+            if self.evm_opts.verbosity >= 3 {
+                builder = builder.with_tracing();
+            }
+            // halts calls that never return, tests that ran too long are failed by the runner
+            if let Some(timeout) = self.test_timeout {
+                builder = builder.with_timeout(timeout);
+            }
+            if self.coverage {
+                builder = builder.with_coverage();
+            }
+
+            let executor = builder.build(db.clone());
+            let result = self.run_tests(
+                &id.identifier(),
+                abi,
+                executor,
+                deploy_code.clone(),
+                libs,
+                (filter, include_fuzz_tests),
+                fail_fast,
+            )?;
+            Ok((id.identifier(), result))
+        };
+
+        // the results are collected into a map, so their order doesn't depend on the number of
+        // jobs
+        let results = match self.jobs {
+            Some(1) => contracts
+                .iter()
+                .map(&run_contract)
+                .filter_map(Result::<_>::ok)
+                .filter(|(_, results)| !results.is_empty())
+                .map(|(name, result)| {
+                    if let Some(stream_result) = stream_result.as_ref() {
+                        stream_result.send((name.clone(), result.clone())).unwrap();
+                    }
+                    (name, result)
+                })
+                .collect::<BTreeMap<_, _>>(),
+            jobs => {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(jobs.unwrap_or_default())
+                    .build()?;
+                let stream = Mutex::new(OrderedStream::new(stream_result));
+                pool.install(|| {
+                    contracts
+                        .par_iter()
+                        .enumerate()
+                        .filter_map(|(idx, contract)| {
+                            let result = run_contract(contract)
+                                .ok()
+                                .filter(|(_, results)| !results.is_empty());
+                            stream.lock().unwrap().push(idx, result.clone());
+                            result
+                        })
+                        .collect::<BTreeMap<_, _>>()
+                })
+            }
+        };
         Ok(results)
     }
 
-    // The _name field is unused because we only want it for tracing
     #[tracing::instrument(
         name = "contract",
         skip_all,
         err,
-        fields(name = %_name)
+        fields(name = %name)
     )]
-    fn run_tests<DB: DatabaseRef + Send + Sync>(
+    fn run_tests<DB: DatabaseRef + Clone + Send + Sync>(
         &self,
-        _name: &str,
+        name: &str,
         contract: &Abi,
         executor: Executor<DB>,
         deploy_code: Bytes,
         libs: &[Bytes],
         (filter, include_fuzz_tests): (&impl TestFilter, bool),
+        fail_fast: Option<&AtomicBool>,
     ) -> Result<SuiteResult> {
         let mut runner = ContractRunner::new(
             executor,
@@ -264,10 +450,48 @@ impl MultiContractRunner {
             self.errors.as_ref(),
             libs,
         );
+        runner.test_timeout = self.test_timeout;
+        runner.fuzz_seed = self.fuzz_seed;
+        runner.corpus = self.fuzz_corpus.contract(name);
+        runner.invariant_config = self.invariant_config;
+        runner.known_contracts = Some(&self.known_contracts);
+        runner.fail_fast = fail_fast;
         runner.run_tests(filter, self.fuzzer.clone(), include_fuzz_tests)
     }
 }
 
+/// Streams the results of contracts in the order of the contracts, no matter in which order they
+/// complete
+struct OrderedStream {
+    sender: Option<Sender<(String, SuiteResult)>>,
+    /// The index of the next contract to stream
+    next: usize,
+    /// Completed contracts that wait for a contract with a lower index, `None` if there is
+    /// nothing to stream for the contract
+    pending: BTreeMap<usize, Option<(String, SuiteResult)>>,
+}
+
+impl OrderedStream {
+    fn new(sender: Option<Sender<(String, SuiteResult)>>) -> Self {
+        Self { sender, next: 0, pending: BTreeMap::new() }
+    }
+
+    /// Records the result of the contract at the index and streams every result that's next in
+    /// line
+    fn push(&mut self, idx: usize, result: Option<(String, SuiteResult)>) {
+        if self.sender.is_none() {
+            return
+        }
+        self.pending.insert(idx, result);
+        while let Some(result) = self.pending.remove(&self.next) {
+            if let (Some(sender), Some(result)) = (self.sender.as_ref(), result) {
+                sender.send(result).unwrap();
+            }
+            self.next += 1;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -276,7 +500,9 @@ mod tests {
         test_helpers::{
             filter::Filter, COMPILED, COMPILED_WITH_LIBS, EVM_OPTS, LIBS_PROJECT, PROJECT,
         },
+        TestKind,
     };
+    use ethers::abi::ParamType;
     use foundry_evm::trace::TraceKind;
 
     /// Builds a base runner
@@ -302,11 +528,19 @@ mod tests {
 
         opts.env.chain_id = None; // clear chain id so the correct one gets fetched from the RPC
         opts.fork_url = Some(rpc.to_string());
+        opts.rpc_endpoints = BTreeMap::from([("mainnet".to_string(), rpc.to_string())]);
         let chain_id = opts.get_chain_id();
 
-        let fork = Some(Fork { cache_path: None, url: rpc.to_string(), pin_block: None, chain_id });
+        let fork = Some(Fork {
+            cache_path: None,
+            url: rpc.to_string(),
+            pin_block: None,
+            chain_id,
+            retry: opts.fork_retry_config(),
+        });
         base_runner()
             .with_fork(fork)
+            .with_fork_config(MultiForkConfig::new(opts.rpc_endpoints.clone()))
             .build(&(*LIBS_PROJECT).paths.root, (*COMPILED_WITH_LIBS).clone(), opts)
             .unwrap()
     }
@@ -380,6 +614,96 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_jobs() {
+        let filter = Filter::new(".*", ".*", ".*core");
+        let serial = base_runner()
+            .jobs(1)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap()
+            .test(&filter, None, true)
+            .unwrap();
+        let parallel = base_runner()
+            .jobs(4)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap()
+            .test(&filter, None, true)
+            .unwrap();
+
+        let outcome = |results: &BTreeMap<String, SuiteResult>| {
+            results
+                .iter()
+                .flat_map(|(contract, suite)| {
+                    suite.test_results.iter().map(move |(test, result)| {
+                        (contract.clone(), test.clone(), result.success, result.reason.clone())
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        assert!(!serial.is_empty());
+        assert_eq!(outcome(&serial), outcome(&parallel));
+    }
+
+    #[test]
+    fn test_jobs_stream_order() {
+        let filter = Filter::new(".*", ".*", ".*core");
+        let (tx, rx) = std::sync::mpsc::channel();
+        let results = base_runner()
+            .jobs(4)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap()
+            .test(&filter, Some(tx), true)
+            .unwrap();
+
+        let streamed = rx.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert!(streamed.len() > 1);
+        assert_eq!(streamed, results.into_keys().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_filtered_tests() {
+        let runner = runner();
+        let tests = runner.filtered_tests(&Filter::new("testAdd|testMultiply", ".*", ".*core"));
+        assert_eq!(tests.len(), 2, "{:?}", tests);
+        assert!(tests[0].ends_with("SetupConsistency.t.sol:SetupConsistencyCheck::testAdd()"));
+        assert!(tests[1].ends_with("SetupConsistency.t.sol:SetupConsistencyCheck::testMultiply()"));
+        assert_eq!(runner.count_filtered_tests(&Filter::new("testAdd", ".*", ".*core")), 1);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut runner = base_runner()
+            .fuzzer(TestRunner::new(Default::default()))
+            .test_timeout(Duration::from_secs(1))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*timeout"), None, true).unwrap();
+
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "timeout/Timeout.t.sol:TimeoutTest",
+                vec![
+                    (
+                        "testLoopForever()",
+                        false,
+                        Some("timed out after 1s".to_string()),
+                        None,
+                        None,
+                    ),
+                    (
+                        "testFuzzLoopForever(uint256)",
+                        false,
+                        Some("timed out after 1s".to_string()),
+                        None,
+                        None,
+                    ),
+                    ("testReturns()", true, None, None, None),
+                ],
+            )]),
+        );
+    }
+
     #[test]
     fn test_core() {
         let mut runner = runner();
@@ -578,6 +902,117 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fuzz_seed() {
+        let counterexamples = || {
+            let config = FuzzConfig { failure_persistence: None, ..Default::default() };
+            let results = base_runner()
+                .seeded_fuzzer(config, 1337)
+                .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+                .unwrap()
+                .test(&Filter::new("testNegativeHalf", ".*", ".*fuzz"), None, true)
+                .unwrap();
+            results
+                .values()
+                .flat_map(|suite| suite.test_results.values())
+                .map(|result| {
+                    assert!(!result.success);
+                    assert_eq!(result.fuzz_seed, Some(1337));
+                    result.counterexample.as_ref().unwrap().calldata.clone()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = counterexamples();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first, counterexamples());
+    }
+
+    #[test]
+    fn test_fuzz_corpus() {
+        let contract = "fuzz/FuzzNumbers.t.sol:FuzzNumbersTest";
+        let calldata = ethers::abi::short_signature("testNegative0", &[ParamType::Uint(256)])
+            .into_iter()
+            .chain([0u8; 32])
+            .collect::<Vec<_>>();
+        let mut corpus = FuzzCorpus::default();
+        corpus.add(contract, "testNegative0(uint256)", calldata.clone().into());
+
+        let results = base_runner()
+            .fuzzer(TestRunner::new(Default::default()))
+            .fuzz_corpus(corpus)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap()
+            .test(&Filter::new("testNegative0", ".*", ".*fuzz"), None, true)
+            .unwrap();
+
+        // the stored counterexample fails before any random input is run
+        let result = &results[contract].test_results["testNegative0(uint256)"];
+        assert!(!result.success);
+        assert_eq!(result.counterexample.as_ref().unwrap().calldata.to_vec(), calldata);
+        assert!(matches!(&result.kind, TestKind::Fuzz(cases) if cases.cases().is_empty()));
+    }
+
+    #[test]
+    fn test_invariant() {
+        let results = base_runner()
+            .seeded_fuzzer(FuzzConfig { failure_persistence: None, ..Default::default() }, 1337)
+            .invariant_config(InvariantConfig { runs: 64, depth: 10 })
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap()
+            .test(&Filter::new(".*", ".*", ".*invariant"), None, true)
+            .unwrap();
+        let suite = &results["invariant/InvariantBreaker.t.sol:InvariantBreakerTest"];
+
+        let held = &suite.test_results["invariantCountIsReadable()"];
+        assert!(held.success, "{:?}", held.reason);
+        assert!(held.call_sequence.is_none());
+        assert!(matches!(held.kind, TestKind::Invariant { runs: 64, .. }));
+
+        // the shrunk sequence only contains the calls needed to break the invariant
+        let broken = &suite.test_results["invariantBelowThree()"];
+        assert!(!broken.success);
+        let sequence = broken.call_sequence.as_ref().unwrap();
+        assert_eq!(
+            sequence.iter().map(|call| call.decoded.as_str()).collect::<Vec<_>>(),
+            ["increment()"; 3]
+        );
+        assert!(sequence.iter().all(|call| call.contract == "Counter"));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let mut runner = base_runner()
+            .seeded_fuzzer(FuzzConfig { failure_persistence: None, ..Default::default() }, 1337)
+            .invariant_config(InvariantConfig { runs: 16, depth: 10 })
+            .coverage(true)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let counter = runner
+            .known_contracts
+            .iter()
+            .find(|(id, _)| id.name == "Counter")
+            .map(|(_, (_, code))| code.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*invariant"), None, true).unwrap();
+        let suite = &results["invariant/InvariantBreaker.t.sol:InvariantBreakerTest"];
+
+        // the campaign calls every function of the counter, and `decrement` takes both branches
+        let coverage = suite.test_results["invariantCountIsReadable()"].coverage.as_ref().unwrap();
+        let hit_map = coverage
+            .0
+            .values()
+            .find(|hit_map| hit_map.bytecode.starts_with(&counter))
+            .expect("no coverage of the counter");
+        assert!(!hit_map.hits.is_empty());
+        assert!(hit_map.branches.values().any(|(taken, not_taken)| *taken > 0 && *not_taken > 0));
+
+        // coverage is only collected if enabled
+        let results = runner().test(&Filter::new(".*", ".*", ".*invariant"), None, true).unwrap();
+        let suite = &results["invariant/InvariantBreaker.t.sol:InvariantBreakerTest"];
+        assert!(suite.test_results.values().all(|result| result.coverage.is_none()));
+    }
+
     #[test]
     fn test_trace() {
         let mut runner = tracing_runner();
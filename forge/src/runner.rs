@@ -1,21 +1,32 @@
-use crate::TestFilter;
+use crate::{ContractCorpus, TestFilter};
 use ethers::{
     abi::{Abi, Function, RawLog},
+    prelude::ArtifactId,
     types::{Address, Bytes, U256},
 };
 use eyre::Result;
 use foundry_evm::{
+    coverage::{merge_coverage, HitMaps},
     executor::{CallResult, DatabaseRef, DeployResult, EvmError, Executor},
-    fuzz::{CounterExample, FuzzedCases, FuzzedExecutor},
-    trace::{CallTraceArena, TraceKind},
+    fuzz::{
+        invariant::{InvariantCall, InvariantConfig, InvariantExecutor, TargetContract},
+        CounterExample, FuzzedCases, FuzzedExecutor,
+    },
+    trace::{
+        identifier::{LocalTraceIdentifier, TraceIdentifier},
+        CallTraceArena, TraceKind,
+    },
     CALLER,
 };
 use proptest::test_runner::TestRunner;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use std::{
+    any::Any,
     collections::BTreeMap,
     fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::atomic::{AtomicBool, Ordering},
     time::{Duration, Instant},
 };
 
@@ -63,6 +74,10 @@ pub struct TestResult {
     /// Minimal reproduction test case for failing fuzz tests
     pub counterexample: Option<CounterExample>,
 
+    /// The seed of the fuzzer, only set for fuzz tests run with a seeded fuzzer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzz_seed: Option<u64>,
+
     /// Any captured & parsed as strings logs along the test's execution which should
     /// be printed to the user.
     #[serde(skip)]
@@ -76,6 +91,18 @@ pub struct TestResult {
 
     /// Labeled addresses
     pub labeled_addresses: BTreeMap<Address, String>,
+
+    /// How long it took to execute the test, excluding the `setUp` function
+    #[serde(default)]
+    pub duration: Duration,
+
+    /// The shrunk sequence of calls that broke the invariant, only set for failed invariants
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub call_sequence: Option<Vec<InvariantCall>>,
+
+    /// The coverage info collected during the test and its setup, only set if coverage is enabled
+    #[serde(skip)]
+    pub coverage: Option<HitMaps>,
 }
 
 impl TestResult {
@@ -83,6 +110,11 @@ impl TestResult {
     pub fn is_fuzz(&self) -> bool {
         matches!(self.kind, TestKind::Fuzz(_))
     }
+
+    /// Returns `true` if this is the result of an invariant
+    pub fn is_invariant(&self) -> bool {
+        matches!(self.kind, TestKind::Invariant { .. })
+    }
 }
 
 /// Used gas by a test
@@ -90,6 +122,7 @@ impl TestResult {
 pub enum TestKindGas {
     Standard(u64),
     Fuzz { runs: usize, mean: u64, median: u64 },
+    Invariant { runs: usize, calls: usize, reverts: usize },
 }
 
 impl fmt::Display for TestKindGas {
@@ -101,6 +134,9 @@ impl fmt::Display for TestKindGas {
             TestKindGas::Fuzz { runs, mean, median } => {
                 write!(f, "(runs: {}, μ: {}, ~: {})", runs, mean, median)
             }
+            TestKindGas::Invariant { runs, calls, reverts } => {
+                write!(f, "(runs: {}, calls: {}, reverts: {})", runs, calls, reverts)
+            }
         }
     }
 }
@@ -112,6 +148,8 @@ impl TestKindGas {
             TestKindGas::Standard(gas) => *gas,
             // We use the median for comparisons
             TestKindGas::Fuzz { median, .. } => *median,
+            // Invariants span many transactions, there is no single gas value
+            TestKindGas::Invariant { .. } => 0,
         }
     }
 }
@@ -125,6 +163,8 @@ pub enum TestKind {
     Standard(u64),
     /// A solidity fuzz test, that stores all test cases
     Fuzz(FuzzedCases),
+    /// An invariant, checked after every call of random call sequences to the target contracts
+    Invariant { runs: usize, calls: usize, reverts: usize },
 }
 
 impl TestKind {
@@ -137,6 +177,9 @@ impl TestKind {
                 median: fuzzed.median_gas(false),
                 mean: fuzzed.mean_gas(false),
             },
+            TestKind::Invariant { runs, calls, reverts } => {
+                TestKindGas::Invariant { runs: *runs, calls: *calls, reverts: *reverts }
+            }
         }
     }
 }
@@ -155,6 +198,8 @@ pub struct TestSetup {
     pub setup_failed: bool,
     /// The reason the setup failed
     pub reason: Option<String>,
+    /// The coverage info collected during the deployments and setup
+    pub coverage: Option<HitMaps>,
 }
 
 pub struct ContractRunner<'a, DB: DatabaseRef> {
@@ -174,6 +219,18 @@ pub struct ContractRunner<'a, DB: DatabaseRef> {
     pub initial_balance: U256,
     /// The address which will be used as the `from` field in all EVM calls
     pub sender: Address,
+    /// The maximum duration of a single test, fuzz tests are limited as a whole
+    pub test_timeout: Option<Duration>,
+    /// The seed of the fuzzer, reported with the results of fuzz tests
+    pub fuzz_seed: Option<u64>,
+    /// Counterexamples of earlier runs, replayed before the random campaign of each fuzz test
+    pub corpus: ContractCorpus,
+    /// The number and length of the call sequences of invariant campaigns
+    pub invariant_config: InvariantConfig,
+    /// All compiled contracts, used to identify the target contracts of invariants
+    pub known_contracts: Option<&'a BTreeMap<ArtifactId, (Abi, Vec<u8>)>>,
+    /// Set once a test failed, tests that didn't start yet are skipped while it's set
+    pub fail_fast: Option<&'a AtomicBool>,
 }
 
 impl<'a, DB: DatabaseRef> ContractRunner<'a, DB> {
@@ -195,11 +252,29 @@ impl<'a, DB: DatabaseRef> ContractRunner<'a, DB> {
             sender: sender.unwrap_or_default(),
             errors,
             predeploy_libs,
+            test_timeout: None,
+            fuzz_seed: None,
+            corpus: Default::default(),
+            invariant_config: Default::default(),
+            known_contracts: None,
+            fail_fast: None,
+        }
+    }
+
+    /// Whether a test failed and the remaining tests are skipped, see [ContractRunner::fail_fast]
+    fn cancelled(&self) -> bool {
+        self.fail_fast.map(|failed| failed.load(Ordering::Relaxed)).unwrap_or_default()
+    }
+
+    /// Cancels the remaining tests if the test failed and fail fast is enabled
+    fn record_result(&self, result: &TestResult) {
+        if let Some(failed) = self.fail_fast.filter(|_| !result.success) {
+            failed.store(true, Ordering::Relaxed);
         }
     }
 }
 
-impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
+impl<'a, DB: DatabaseRef + Clone + Send + Sync> ContractRunner<'a, DB> {
     /// Deploys the test contract inside the runner from the sending account, and optionally runs
     /// the `setUp` function on the test contract.
     pub fn setup(&mut self, setup: bool) -> Result<TestSetup> {
@@ -211,14 +286,16 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         self.executor.set_nonce(self.sender, 1);
 
         // Deploy libraries
+        let mut coverage = None;
         let mut traces: Vec<(TraceKind, CallTraceArena)> = self
             .predeploy_libs
             .iter()
             .filter_map(|code| {
-                let DeployResult { traces, .. } = self
+                let DeployResult { traces, coverage: lib_coverage, .. } = self
                     .executor
                     .deploy(self.sender, code.0.clone(), 0u32.into())
                     .expect("couldn't deploy library");
+                merge_coverage(&mut coverage, lib_coverage);
 
                 traces
             })
@@ -226,11 +303,18 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             .collect();
 
         // Deploy an instance of the contract
-        let DeployResult { address, mut logs, traces: constructor_traces, .. } = self
+        let DeployResult {
+            address,
+            mut logs,
+            traces: constructor_traces,
+            coverage: constructor_coverage,
+            ..
+        } = self
             .executor
             .deploy(self.sender, self.code.0.clone(), 0u32.into())
             .expect("couldn't deploy");
         traces.extend(constructor_traces.map(|traces| (TraceKind::Deployment, traces)).into_iter());
+        merge_coverage(&mut coverage, constructor_coverage);
 
         // Now we set the contracts initial balance, and we also reset `self.sender`s balance to
         // the initial balance we want
@@ -240,28 +324,35 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         // Optionally call the `setUp` function
         Ok(if setup {
             tracing::trace!("setting up");
-            let (setup_failed, setup_logs, setup_traces, labeled_addresses, reason) = match self
-                .executor
-                .setup(address)
-            {
-                Ok(CallResult { traces, labels, logs, .. }) => (false, logs, traces, labels, None),
-                Err(EvmError::Execution { traces, labels, logs, reason, .. }) => {
-                    (true, logs, traces, labels, Some(format!("Setup failed: {}", reason)))
-                }
-                Err(e) => (
-                    true,
-                    Vec::new(),
-                    None,
-                    BTreeMap::new(),
-                    Some(format!("Setup failed: {}", &e.to_string())),
-                ),
-            };
+            let (setup_failed, setup_logs, setup_traces, labeled_addresses, reason, setup_coverage) =
+                match self.executor.setup(address) {
+                    Ok(CallResult { traces, labels, logs, coverage, .. }) => {
+                        (false, logs, traces, labels, None, coverage)
+                    }
+                    Err(EvmError::Execution { traces, labels, logs, reason, coverage, .. }) => (
+                        true,
+                        logs,
+                        traces,
+                        labels,
+                        Some(format!("Setup failed: {}", reason)),
+                        coverage,
+                    ),
+                    Err(e) => (
+                        true,
+                        Vec::new(),
+                        None,
+                        BTreeMap::new(),
+                        Some(format!("Setup failed: {}", &e.to_string())),
+                        None,
+                    ),
+                };
             traces.extend(setup_traces.map(|traces| (TraceKind::Setup, traces)).into_iter());
             logs.extend_from_slice(&setup_logs);
+            merge_coverage(&mut coverage, setup_coverage);
 
-            TestSetup { address, logs, traces, labeled_addresses, setup_failed, reason }
+            TestSetup { address, logs, traces, labeled_addresses, setup_failed, reason, coverage }
         } else {
-            TestSetup { address, logs, traces, ..Default::default() }
+            TestSetup { address, logs, traces, coverage, ..Default::default() }
         })
     }
 
@@ -293,43 +384,55 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
 
         // There are multiple setUp function, so we return a single test result for `setUp`
         if setup_fns.len() > 1 {
+            let result = TestResult {
+                success: false,
+                reason: Some("Multiple setUp functions".to_string()),
+                counterexample: None,
+                fuzz_seed: None,
+                logs: vec![],
+                kind: TestKind::Standard(0),
+                traces: vec![],
+                labeled_addresses: BTreeMap::new(),
+                duration: Duration::ZERO,
+                call_sequence: None,
+                coverage: None,
+            };
+            self.record_result(&result);
             return Ok(SuiteResult::new(
                 start.elapsed(),
-                [(
-                    "setUp()".to_string(),
-                    TestResult {
-                        success: false,
-                        reason: Some("Multiple setUp functions".to_string()),
-                        counterexample: None,
-                        logs: vec![],
-                        kind: TestKind::Standard(0),
-                        traces: vec![],
-                        labeled_addresses: BTreeMap::new(),
-                    },
-                )]
-                .into(),
+                [("setUp()".to_string(), result)].into(),
                 warnings,
             ))
         }
 
-        let setup = self.setup(needs_setup)?;
+        // a fork that fails for good during the deployment or `setUp` only fails this contract
+        let setup = match panic::catch_unwind(AssertUnwindSafe(|| self.setup(needs_setup))) {
+            Ok(setup) => setup?,
+            Err(payload) => TestSetup {
+                setup_failed: true,
+                reason: Some(format!("Setup failed: {}", panic_reason(payload))),
+                ..Default::default()
+            },
+        };
         if setup.setup_failed {
             // The setup failed, so we return a single test result for `setUp`
+            let result = TestResult {
+                success: false,
+                reason: setup.reason,
+                counterexample: None,
+                fuzz_seed: None,
+                logs: setup.logs,
+                kind: TestKind::Standard(0),
+                traces: setup.traces,
+                labeled_addresses: setup.labeled_addresses,
+                duration: Duration::ZERO,
+                call_sequence: None,
+                coverage: setup.coverage,
+            };
+            self.record_result(&result);
             return Ok(SuiteResult::new(
                 start.elapsed(),
-                [(
-                    "setUp()".to_string(),
-                    TestResult {
-                        success: false,
-                        reason: setup.reason,
-                        counterexample: None,
-                        logs: setup.logs,
-                        kind: TestKind::Standard(0),
-                        traces: setup.traces,
-                        labeled_addresses: setup.labeled_addresses,
-                    },
-                )]
-                .into(),
+                [("setUp()".to_string(), result)].into(),
                 warnings,
             ))
         }
@@ -344,24 +447,71 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
                     filter.matches_test(func.signature()) &&
                     (include_fuzz_tests || func.inputs.is_empty())
             })
-            .map(|func| (func, func.name.starts_with("testFail")))
+            .map(|func| {
+                let corpus = self.corpus.inputs(func, &mut warnings);
+                (func, func.name.starts_with("testFail"), corpus)
+            })
             .collect();
 
-        let test_results = tests
+        let mut test_results = tests
             .par_iter()
-            .filter_map(|(func, should_fail)| {
+            .filter_map(|(func, should_fail, corpus)| {
+                // tests that already started keep running, only the remaining ones are skipped
+                if self.cancelled() {
+                    return None
+                }
                 let result = if func.inputs.is_empty() {
-                    Some(self.run_test(func, *should_fail, setup.clone()))
+                    Some(fail_on_panic(|| self.run_test(func, *should_fail, setup.clone())))
                 } else {
                     fuzzer.as_ref().map(|fuzzer| {
-                        self.run_fuzz_test(func, *should_fail, fuzzer.clone(), setup.clone())
+                        fail_on_panic(|| {
+                            self.run_fuzz_test(
+                                func,
+                                *should_fail,
+                                fuzzer.clone(),
+                                setup.clone(),
+                                corpus.clone(),
+                            )
+                        })
                     })
                 };
 
-                result.map(|result| Ok((func.signature(), result?)))
+                result.map(|result| {
+                    let result = result?;
+                    self.record_result(&result);
+                    Ok((func.signature(), result))
+                })
             })
             .collect::<Result<BTreeMap<_, _>>>()?;
 
+        // Invariants are checked in campaigns of the fuzzer, so they are run along with the fuzz
+        // tests
+        let invariants: Vec<_> = self
+            .contract
+            .functions()
+            .filter(|func| {
+                func.name.starts_with("invariant") &&
+                    func.inputs.is_empty() &&
+                    filter.matches_test(func.signature())
+            })
+            .collect();
+        if let Some(fuzzer) =
+            fuzzer.as_ref().filter(|_| include_fuzz_tests && !invariants.is_empty())
+        {
+            let targets = self.target_contracts(setup.address)?;
+            let invariant_results = invariants
+                .par_iter()
+                .filter(|_| !self.cancelled())
+                .map(|func| {
+                    let result =
+                        self.run_invariant_test(func, fuzzer.clone(), setup.clone(), &targets)?;
+                    self.record_result(&result);
+                    Ok((func.signature(), result))
+                })
+                .collect::<Result<BTreeMap<_, _>>>()?;
+            test_results.extend(invariant_results);
+        }
+
         let duration = start.elapsed();
         if !test_results.is_empty() {
             let successful = test_results.iter().filter(|(_, tst)| tst.success).count();
@@ -382,49 +532,75 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         should_fail: bool,
         setup: TestSetup,
     ) -> Result<TestResult> {
-        let TestSetup { address, mut logs, mut traces, mut labeled_addresses, .. } = setup;
+        let TestSetup {
+            address, mut logs, mut traces, mut labeled_addresses, mut coverage, ..
+        } = setup;
 
         // Run unit test
         let start = Instant::now();
-        let (reverted, reason, gas, stipend, execution_traces, state_changeset) = match self
-            .executor
-            .call::<(), _, _>(self.sender, address, func.clone(), (), 0.into(), self.errors)
-        {
-            Ok(CallResult {
-                reverted,
-                gas,
-                stipend,
-                logs: execution_logs,
-                traces: execution_trace,
-                labels: new_labels,
-                state_changeset,
-                ..
-            }) => {
-                labeled_addresses.extend(new_labels);
-                logs.extend(execution_logs);
-                (reverted, None, gas, stipend, execution_trace, state_changeset)
-            }
-            Err(EvmError::Execution {
-                reverted,
-                reason,
-                gas,
-                stipend,
-                logs: execution_logs,
-                traces: execution_trace,
-                labels: new_labels,
-                state_changeset,
-                ..
-            }) => {
-                labeled_addresses.extend(new_labels);
-                logs.extend(execution_logs);
-                (reverted, Some(reason), gas, stipend, execution_trace, state_changeset)
-            }
-            Err(err) => {
-                tracing::error!(?err);
-                return Err(err.into())
-            }
-        };
+        let (reverted, reason, gas, stipend, execution_traces, execution_coverage, state_changeset) =
+            match self.executor.call::<(), _, _>(
+                self.sender,
+                address,
+                func.clone(),
+                (),
+                0.into(),
+                self.errors,
+            ) {
+                Ok(CallResult {
+                    reverted,
+                    gas,
+                    stipend,
+                    logs: execution_logs,
+                    traces: execution_trace,
+                    coverage: execution_coverage,
+                    labels: new_labels,
+                    state_changeset,
+                    ..
+                }) => {
+                    labeled_addresses.extend(new_labels);
+                    logs.extend(execution_logs);
+                    (
+                        reverted,
+                        None,
+                        gas,
+                        stipend,
+                        execution_trace,
+                        execution_coverage,
+                        state_changeset,
+                    )
+                }
+                Err(EvmError::Execution {
+                    reverted,
+                    reason,
+                    gas,
+                    stipend,
+                    logs: execution_logs,
+                    traces: execution_trace,
+                    coverage: execution_coverage,
+                    labels: new_labels,
+                    state_changeset,
+                    ..
+                }) => {
+                    labeled_addresses.extend(new_labels);
+                    logs.extend(execution_logs);
+                    (
+                        reverted,
+                        Some(reason),
+                        gas,
+                        stipend,
+                        execution_trace,
+                        execution_coverage,
+                        state_changeset,
+                    )
+                }
+                Err(err) => {
+                    tracing::error!(?err);
+                    return Err(err.into())
+                }
+            };
         traces.extend(execution_traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
+        merge_coverage(&mut coverage, execution_coverage);
 
         let success = self.executor.is_success(
             setup.address,
@@ -434,20 +610,30 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         );
 
         // Record test execution time
+        let duration = start.elapsed();
         tracing::debug!(
-            duration = ?start.elapsed(),
+            ?duration,
             %success,
             %gas
         );
 
+        let (success, reason) = match self.timed_out(duration) {
+            Some(reason) => (false, Some(reason)),
+            None => (success, reason),
+        };
+
         Ok(TestResult {
             success,
             reason,
             counterexample: None,
+            fuzz_seed: None,
             logs,
             kind: TestKind::Standard(gas.overflowing_sub(stipend).0),
             traces,
             labeled_addresses,
+            duration,
+            call_sequence: None,
+            coverage,
         })
     }
 
@@ -458,37 +644,199 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         should_fail: bool,
         runner: TestRunner,
         setup: TestSetup,
+        corpus: Vec<Bytes>,
     ) -> Result<TestResult> {
-        let TestSetup { address, mut logs, mut traces, mut labeled_addresses, .. } = setup;
+        let TestSetup {
+            address, mut logs, mut traces, mut labeled_addresses, mut coverage, ..
+        } = setup;
 
         // Run fuzz test
         let start = Instant::now();
-        let mut result = FuzzedExecutor::new(&self.executor, runner, self.sender).fuzz(
-            func,
-            address,
-            should_fail,
-            self.errors,
-        );
+        let mut fuzzer =
+            FuzzedExecutor::new(&self.executor, runner, self.sender).with_corpus(corpus);
+        if let Some(timeout) = self.test_timeout {
+            fuzzer = fuzzer.with_deadline(start + timeout);
+        }
+        let mut result = fuzzer.fuzz(func, address, should_fail, self.errors);
 
-        // Record logs, labels and traces
+        // Record logs, labels, traces and coverage
         logs.append(&mut result.logs);
         labeled_addresses.append(&mut result.labeled_addresses);
         traces.extend(result.traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
+        merge_coverage(&mut coverage, result.coverage.take());
 
         // Record test execution time
+        let duration = start.elapsed();
         tracing::debug!(
-            duration = ?start.elapsed(),
+            ?duration,
             success = %result.success
         );
 
+        if let Some(reason) = self.timed_out(duration) {
+            result.success = false;
+            result.reason = Some(reason);
+            result.counterexample = None;
+        }
+
         Ok(TestResult {
             success: result.success,
             reason: result.reason,
             counterexample: result.counterexample,
+            fuzz_seed: self.fuzz_seed,
             logs,
             kind: TestKind::Fuzz(result.cases),
             traces,
             labeled_addresses,
+            duration,
+            call_sequence: None,
+            coverage,
         })
     }
+
+    #[tracing::instrument(name = "invariant", skip_all, fields(name = %func.signature()))]
+    pub fn run_invariant_test(
+        &self,
+        func: &Function,
+        runner: TestRunner,
+        setup: TestSetup,
+        targets: &[TargetContract],
+    ) -> Result<TestResult> {
+        let TestSetup {
+            address, mut logs, mut traces, mut labeled_addresses, mut coverage, ..
+        } = setup;
+
+        // Run the invariant campaign
+        let start = Instant::now();
+        let mut executor =
+            InvariantExecutor::new(&self.executor, runner, self.sender, self.invariant_config);
+        if let Some(timeout) = self.test_timeout {
+            executor = executor.with_deadline(start + timeout);
+        }
+        let mut result = executor.invariant_fuzz(func, address, targets, self.errors);
+
+        // Record logs, labels, traces and coverage
+        logs.append(&mut result.logs);
+        labeled_addresses.append(&mut result.labeled_addresses);
+        traces.extend(result.traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
+        merge_coverage(&mut coverage, result.coverage.take());
+
+        // Record test execution time
+        let duration = start.elapsed();
+        tracing::debug!(
+            ?duration,
+            success = %result.success
+        );
+
+        if let Some(reason) = self.timed_out(duration) {
+            result.success = false;
+            result.reason = Some(reason);
+            result.call_sequence = None;
+        }
+
+        Ok(TestResult {
+            success: result.success,
+            reason: result.reason,
+            counterexample: None,
+            fuzz_seed: self.fuzz_seed,
+            logs,
+            kind: TestKind::Invariant {
+                runs: result.runs,
+                calls: result.calls,
+                reverts: result.reverts,
+            },
+            traces,
+            labeled_addresses,
+            duration,
+            call_sequence: result.call_sequence,
+            coverage,
+        })
+    }
+
+    /// Returns the contracts whose functions are called by the invariant campaigns of the test
+    /// contract at `address`
+    ///
+    /// These are the contracts returned by `targetContracts()`, if the test contract defines it,
+    /// or else every contract the test contract deployed, e.g. in `setUp()`. Contracts that
+    /// can't be matched with a compiled contract are skipped.
+    fn target_contracts(&self, address: Address) -> Result<Vec<TargetContract>> {
+        let target_fn = self
+            .contract
+            .functions()
+            .find(|func| func.name == "targetContracts" && func.inputs.is_empty());
+        let contracts = match target_fn {
+            Some(func) => self
+                .executor
+                .call::<Vec<Address>, _, _>(
+                    self.sender,
+                    address,
+                    func.clone(),
+                    (),
+                    0.into(),
+                    self.errors,
+                )?
+                .result
+                .into_iter()
+                .filter_map(|target| {
+                    let code = self.executor.db.basic(target).code?;
+                    Some((target, code.to_vec()))
+                })
+                .collect(),
+            None => self.executor.created_contracts(address),
+        };
+
+        let empty = BTreeMap::new();
+        let identifier = LocalTraceIdentifier::new(self.known_contracts.unwrap_or(&empty));
+        let identities = identifier.identify_addresses(
+            contracts
+                .iter()
+                .filter(|(target, _)| *target != address)
+                .map(|(target, code)| (target, Some(code)))
+                .collect(),
+        );
+        Ok(identities
+            .into_iter()
+            .filter_map(|identity| {
+                Some(TargetContract {
+                    address: identity.address,
+                    name: identity.label?,
+                    abi: identity.abi?.into_owned(),
+                })
+            })
+            .collect())
+    }
+
+    /// Returns the failure reason if the test ran longer than the configured timeout
+    fn timed_out(&self, duration: Duration) -> Option<String> {
+        let timeout = self.test_timeout?;
+        (duration >= timeout).then(|| format!("timed out after {}s", timeout.as_secs()))
+    }
+}
+
+/// Runs the test and turns a panic during its execution, like an RPC request of a fork that failed
+/// for good, into a failed test instead of aborting the whole run
+fn fail_on_panic(run: impl FnOnce() -> Result<TestResult>) -> Result<TestResult> {
+    panic::catch_unwind(AssertUnwindSafe(run)).or_else(|payload| {
+        Ok(TestResult {
+            success: false,
+            reason: Some(panic_reason(payload)),
+            counterexample: None,
+            fuzz_seed: None,
+            logs: vec![],
+            kind: TestKind::Standard(0),
+            traces: vec![],
+            labeled_addresses: BTreeMap::new(),
+            duration: Duration::ZERO,
+            call_sequence: None,
+            coverage: None,
+        })
+    })
+}
+
+/// Returns the message of a caught panic
+fn panic_reason(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|reason| reason.to_string()))
+        .unwrap_or_else(|| "the test panicked".to_string())
 }
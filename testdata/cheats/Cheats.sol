@@ -64,4 +64,13 @@ interface Cheats {
     function setNonce(address,uint64) external;
     // Get nonce for an account
     function getNonce(address) external returns(uint64);
+    // Creates a fork of an endpoint at the latest block and returns its id, the endpoint is
+    // either one of the `rpc_endpoints` of the config or a url
+    function createFork(string calldata) external returns(uint256);
+    // Creates a fork of an endpoint at a block and returns its id
+    function createFork(string calldata,uint256) external returns(uint256);
+    // Selects a fork by id, local contracts like the test contract are available on every fork
+    function selectFork(uint256) external;
+    // Returns the id of the selected fork
+    function activeFork() external returns(uint256);
 }
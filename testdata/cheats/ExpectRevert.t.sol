@@ -6,6 +6,7 @@ import "./Cheats.sol";
 
 contract Reverter {
     error CustomError();
+    error CustomErrorWithArgs(uint256 value, address account);
 
     function revertWithMessage(string memory message) public pure {
         require(false, message);
@@ -21,6 +22,16 @@ contract Reverter {
         revert CustomError();
     }
 
+    function revertWithCustomErrorWithArgs(uint256 value) public view {
+        revert CustomErrorWithArgs(value, msg.sender);
+    }
+
+    function catchAndRevert(Reverter inner) public view {
+        try inner.revertWithCustomErrorWithArgs(1) {} catch {
+            revert CustomError();
+        }
+    }
+
     function nestedRevert(Reverter inner, string memory message) public pure {
         inner.revertWithMessage(message);
     }
@@ -73,6 +84,41 @@ contract ExpectRevertTest is DSTest {
         reverter.revertWithCustomError();
     }
 
+    function testExpectRevertCustomErrorWithArgs() public {
+        Reverter reverter = new Reverter();
+        cheats.expectRevert(
+            abi.encodeWithSelector(Reverter.CustomErrorWithArgs.selector, 1, address(this))
+        );
+        reverter.revertWithCustomErrorWithArgs(1);
+    }
+
+    function testFailExpectRevertCustomErrorArgsDoNotMatch() public {
+        Reverter reverter = new Reverter();
+        cheats.expectRevert(
+            abi.encodeWithSelector(Reverter.CustomErrorWithArgs.selector, 2, address(this))
+        );
+        reverter.revertWithCustomErrorWithArgs(1);
+    }
+
+    function testFailExpectRevertCustomErrorSelectorOnly() public {
+        Reverter reverter = new Reverter();
+        cheats.expectRevert(abi.encodePacked(Reverter.CustomErrorWithArgs.selector));
+        reverter.revertWithCustomErrorWithArgs(1);
+    }
+
+    function testExpectRevertStringData() public {
+        Reverter reverter = new Reverter();
+        cheats.expectRevert(abi.encodeWithSignature("Error(string)", "revert"));
+        reverter.revertWithMessage("revert");
+    }
+
+    function testExpectRevertCaughtNestedRevert() public {
+        Reverter reverter = new Reverter();
+        Reverter inner = new Reverter();
+        cheats.expectRevert(abi.encodePacked(Reverter.CustomError.selector));
+        reverter.catchAndRevert(inner);
+    }
+
     function testExpectRevertNested() public {
         Reverter reverter = new Reverter();
         Reverter inner = new Reverter();
@@ -0,0 +1,18 @@
+pragma solidity ^0.8.10;
+
+interface Errors {}
+
+contract Token {
+    uint256 public totalSupply;
+    mapping(address => uint256) public balanceOf;
+
+
+    function mint(address to, uint256 amount) external {
+        balanceOf[to] += amount;
+        totalSupply += amount;
+    }
+}
+
+contract Vault is Token, Errors {
+    function deposit() external payable {}
+}
@@ -0,0 +1,9 @@
+pragma solidity ^0.8.10;
+
+enum Empty {}
+enum Status { Active, Paused,
+    Stopped }
+
+contract Enums {
+        enum Side { Buy, Sell }
+}
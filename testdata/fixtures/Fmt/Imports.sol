@@ -0,0 +1,6 @@
+pragma solidity ^0.8.10;
+import "./Enums.sol";
+import {Token as T, Errors} from "./Contracts.sol";
+
+
+import "./Contracts.sol" as Contracts;
@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+interface Cheats {
+    function createFork(string calldata) external returns (uint256);
+    function createFork(string calldata, uint256) external returns (uint256);
+    function selectFork(uint256) external;
+    function activeFork() external returns (uint256);
+}
+
+interface IWETH {
+    function deposit() external payable;
+    function balanceOf(address) external view returns (uint256);
+}
+
+// A local contract that should be available on every fork
+contract LocalCounter {
+    uint256 public count;
+
+    function increment() public {
+        count += 1;
+    }
+}
+
+contract MultiForkTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+    IWETH constant WETH = IWETH(0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2);
+
+    LocalCounter counter;
+
+    function setUp() public {
+        counter = new LocalCounter();
+    }
+
+    function testCanSelectForkAtBlock() public {
+        uint256 fork = cheats.createFork("mainnet", 14_000_000);
+        cheats.selectFork(fork);
+        assertEq(cheats.activeFork(), fork);
+        assertEq(block.number, 14_000_000);
+    }
+
+    function testCanSwitchForks() public {
+        uint256 latest = cheats.createFork("mainnet");
+        uint256 pinned = cheats.createFork("mainnet", 14_000_000);
+        assertTrue(latest != pinned);
+
+        cheats.selectFork(pinned);
+        assertEq(block.number, 14_000_000);
+        cheats.selectFork(latest);
+        assertEq(cheats.activeFork(), latest);
+        assertGt(block.number, 14_000_000);
+    }
+
+    function testForkStateIsIsolated() public {
+        uint256 first = cheats.createFork("mainnet", 14_000_000);
+        uint256 second = cheats.createFork("mainnet", 14_000_000);
+
+        cheats.selectFork(first);
+        WETH.deposit{value: 1 ether}();
+        assertEq(WETH.balanceOf(address(this)), 1 ether);
+
+        cheats.selectFork(second);
+        assertEq(WETH.balanceOf(address(this)), 0);
+
+        cheats.selectFork(first);
+        assertEq(WETH.balanceOf(address(this)), 1 ether);
+    }
+
+    function testLocalContractsAreAvailableOnEveryFork() public {
+        cheats.selectFork(cheats.createFork("mainnet", 14_000_000));
+        counter.increment();
+        assertEq(counter.count(), 1);
+
+        cheats.selectFork(cheats.createFork("mainnet"));
+        counter.increment();
+        assertEq(counter.count(), 2);
+    }
+}
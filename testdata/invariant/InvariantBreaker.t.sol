@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract Counter {
+    uint256 public count;
+
+    function increment() public {
+        count++;
+    }
+
+    function decrement() public {
+        if (count > 0) count--;
+    }
+}
+
+contract InvariantBreakerTest is DSTest {
+    Counter counter;
+
+    function setUp() public {
+        counter = new Counter();
+    }
+
+    function invariantBelowThree() public returns (bool) {
+        return counter.count() < 3;
+    }
+
+    function invariantCountIsReadable() public {
+        counter.count();
+    }
+}
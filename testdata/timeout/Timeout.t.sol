@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract TimeoutTest is DSTest {
+    uint256 counter;
+
+    function loop() internal {
+        while (true) {
+            counter++;
+        }
+    }
+
+    function testLoopForever() public {
+        loop();
+    }
+
+    function testFuzzLoopForever(uint256 x) public {
+        if (x != 0) {
+            loop();
+        }
+    }
+
+    function testReturns() public {
+        assertTrue(true);
+    }
+}
@@ -14,7 +14,7 @@ use forge::{
 };
 use std::{
     cmp::{max, min},
-    collections::{BTreeMap, HashMap, VecDeque},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     io,
     sync::mpsc,
     thread,
@@ -47,6 +47,15 @@ pub enum TUIExitReason {
 mod op_effects;
 use op_effects::stack_indices_affected;
 
+/// A line of a source file, used to set breakpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct SourceLine {
+    /// The index of the source file
+    index: u32,
+    /// The 1-based line number
+    line: usize,
+}
+
 pub struct Tui {
     debug_arena: Vec<(Address, Vec<DebugStep>, CallKind)>,
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
@@ -57,6 +66,8 @@ pub struct Tui {
     identified_contracts: HashMap<Address, String>,
     known_contracts: HashMap<String, ContractBytecodeSome>,
     source_code: BTreeMap<u32, String>,
+    /// Source lines on which the debugger stops when jumping between breakpoints
+    breakpoints: HashSet<SourceLine>,
 }
 
 impl Tui {
@@ -83,9 +94,76 @@ impl Tui {
             identified_contracts,
             known_contracts,
             source_code,
+            breakpoints: HashSet::new(),
         })
     }
 
+    /// Returns the source line executed by every step of every call, if it is known
+    fn source_lines(&self) -> Vec<Vec<Option<SourceLine>>> {
+        let newlines = self
+            .source_code
+            .iter()
+            .map(|(index, source)| {
+                (*index, source.match_indices('\n').map(|(pos, _)| pos).collect::<Vec<_>>())
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut sourcemaps = HashMap::new();
+        self.debug_arena
+            .iter()
+            .map(|(address, steps, call_kind)| {
+                let is_create = matches!(call_kind, CallKind::Create);
+                let sourcemap = sourcemaps.entry((*address, is_create)).or_insert_with(|| {
+                    let known =
+                        self.known_contracts.get(self.identified_contracts.get(address)?)?;
+                    let bytecode = if is_create {
+                        &known.bytecode
+                    } else {
+                        known.deployed_bytecode.bytecode.as_ref()?
+                    };
+                    bytecode.source_map()?.ok()
+                });
+                steps
+                    .iter()
+                    .map(|step| {
+                        let element = sourcemap.as_ref()?.get(step.ic)?;
+                        let index = element.index?;
+                        let line =
+                            newlines.get(&index)?.partition_point(|pos| *pos < element.offset) + 1;
+                        Some(SourceLine { index, line })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the `(call, step)` of the next, or previous, step after `from` that enters a line
+    /// with a breakpoint
+    ///
+    /// A step enters a line if the step before it in the same call executed a different line.
+    fn find_breakpoint(
+        lines: &[Vec<Option<SourceLine>>],
+        breakpoints: &HashSet<SourceLine>,
+        from: (usize, usize),
+        forward: bool,
+    ) -> Option<(usize, usize)> {
+        let enters = |(call, step): (usize, usize)| match lines[call][step] {
+            Some(line) if breakpoints.contains(&line) => {
+                step == 0 || lines[call][step - 1] != Some(line)
+            }
+            _ => false,
+        };
+        let mut steps = lines
+            .iter()
+            .enumerate()
+            .flat_map(|(call, steps)| (0..steps.len()).map(move |step| (call, step)));
+        if forward {
+            steps.find(|pos| *pos > from && enters(*pos))
+        } else {
+            steps.filter(|pos| *pos < from && enters(*pos)).last()
+        }
+    }
+
     /// Grab number from buffer. Used for something like '10k' to move up 10 operations
     fn buffer_as_number(buffer: &str, default_value: usize) -> usize {
         if let Ok(num) = buffer.parse() {
@@ -312,7 +390,7 @@ impl Tui {
         let block_controls = Block::default();
 
         let text_output = Text::from(Span::styled(
-            "[q]: quit | [k/j]: prev/next op | [a/s]: prev/next jump | [c/C]: prev/next call | [g/G]: start/end | [t]: toggle stack labels | [m]: toggle memory decoding | [shift + j/k]: scroll stack | [ctrl + j/k]: scroll memory",
+            "[q]: quit | [k/j]: prev/next op | [a/s]: prev/next jump | [c/C]: prev/next call | [g/G]: start/end | [b]: toggle breakpoint | [n/N]: next/prev breakpoint | [t]: toggle stack labels | [m]: toggle memory decoding | [shift + j/k]: scroll stack | [ctrl + j/k]: scroll memory",
             Style::default().add_modifier(Modifier::DIM)
         ));
         let paragraph = Paragraph::new(text_output)
@@ -958,6 +1036,8 @@ impl Ui for Tui {
             debug_call[0].1.iter().map(|step| step.pretty_opcode()).collect();
         let mut last_index = 0;
 
+        let source_lines = self.source_lines();
+
         let mut stack_labels = false;
         let mut mem_utf = false;
         // UI thread that manages drawing
@@ -1123,6 +1203,36 @@ impl Ui for Tui {
                         }
                         self.key_buffer.clear();
                     }
+                    // Toggle a breakpoint on the current source line
+                    KeyCode::Char('b') => {
+                        if let Some(line) =
+                            source_lines[draw_memory.inner_call_index][self.current_step]
+                        {
+                            if !self.breakpoints.remove(&line) {
+                                self.breakpoints.insert(line);
+                            }
+                        }
+                        self.key_buffer.clear();
+                    }
+                    // Go to next/previous breakpoint
+                    KeyCode::Char(c @ ('n' | 'N')) => {
+                        for _ in 0..Tui::buffer_as_number(&self.key_buffer, 1) {
+                            if let Some((call, step)) = Tui::find_breakpoint(
+                                &source_lines,
+                                &self.breakpoints,
+                                (draw_memory.inner_call_index, self.current_step),
+                                c == 'n',
+                            ) {
+                                if call != draw_memory.inner_call_index {
+                                    draw_memory.current_mem_startline = 0;
+                                    draw_memory.current_stack_startline = 0;
+                                }
+                                draw_memory.inner_call_index = call;
+                                self.current_step = step;
+                            }
+                        }
+                        self.key_buffer.clear();
+                    }
                     // toggle stack labels
                     KeyCode::Char('t') => {
                         stack_labels = !stack_labels;
@@ -1218,3 +1328,33 @@ impl DrawMemory {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_breakpoints() {
+        let line = |line| Some(SourceLine { index: 0, line });
+        let lines = vec![
+            vec![line(1), line(2), line(2), None, line(2)],
+            vec![line(2), line(3)],
+            vec![line(3), line(2)],
+        ];
+        let breakpoints = HashSet::from([SourceLine { index: 0, line: 2 }]);
+
+        let next = |from| Tui::find_breakpoint(&lines, &breakpoints, from, true);
+        assert_eq!(next((0, 0)), Some((0, 1)));
+        assert_eq!(next((0, 1)), Some((0, 4)));
+        assert_eq!(next((0, 4)), Some((1, 0)));
+        assert_eq!(next((1, 0)), Some((2, 1)));
+        assert_eq!(next((2, 1)), None);
+
+        let prev = |from| Tui::find_breakpoint(&lines, &breakpoints, from, false);
+        assert_eq!(prev((2, 1)), Some((1, 0)));
+        assert_eq!(prev((0, 4)), Some((0, 1)));
+        assert_eq!(prev((0, 1)), None);
+
+        assert_eq!(Tui::find_breakpoint(&lines, &HashSet::new(), (0, 0), true), None);
+    }
+}